@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+/// None of these settings is pushed to us on every platform, so they are
+/// polled like the keyboard layout.
+const POLL: Duration = Duration::from_secs(2);
+
+/// Accessibility settings the frontend adapts to.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct A11yState {
+    screen_reader: bool,
+    reduced_motion: bool,
+}
+
+/// How urgently an announcement should be read, as for ARIA live regions.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Politeness {
+    /// Read once the screen reader is idle.
+    #[default]
+    Polite,
+    /// Interrupt whatever is being read.
+    Assertive,
+}
+
+/// Payload of `spectrus://a11y-announce`.
+#[derive(Clone, Serialize)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct Announcement {
+    message: String,
+    politeness: Politeness,
+}
+
+fn state(app: &AppHandle) -> A11yState {
+    A11yState {
+        screen_reader: platform::screen_reader(app),
+        reduced_motion: platform::reduced_motion(),
+    }
+}
+
+/// Watch the settings, emitting `spectrus://a11y-changed` with the new
+/// `A11yState` when the user turns a screen reader or reduced motion on or off.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut last = state(&app);
+        loop {
+            std::thread::sleep(POLL);
+            let now = state(&app);
+            if now != last {
+                let _ = app.emit("spectrus://a11y-changed", now);
+                last = now;
+            }
+        }
+    });
+}
+
+/// Whether a screen reader is running and the user asked for less motion.
+#[tauri::command]
+pub async fn a11y_state(app: AppHandle) -> Result<A11yState, String> {
+    tauri::async_runtime::spawn_blocking(move || state(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Have the screen reader speak `message`, for things that happen outside
+/// the focused control such as an export finishing in the background.
+#[tauri::command]
+pub fn a11y_announce(
+    app: AppHandle,
+    message: String,
+    politeness: Option<Politeness>,
+) -> Result<(), String> {
+    if message.trim().is_empty() {
+        return Err("nothing to announce".into());
+    }
+    platform::announce(&app, message, politeness.unwrap_or_default())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::{AppHandle, Emitter, Manager};
+
+    use super::{Announcement, Politeness};
+
+    fn gsettings(schema: &str, key: &str) -> Option<bool> {
+        let out = Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()?;
+        match String::from_utf8_lossy(&out.stdout).trim() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Orca sets `ScreenReaderEnabled` on the accessibility bus; older
+    /// setups only have GNOME's setting.
+    pub fn screen_reader(app: &AppHandle) -> bool {
+        app.try_state::<crate::linux_dbus::DbusState>()
+            .and_then(|dbus| dbus.screen_reader_enabled())
+            .or_else(|| {
+                gsettings(
+                    "org.gnome.desktop.a11y.applications",
+                    "screen-reader-enabled",
+                )
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn reduced_motion() -> bool {
+        gsettings("org.gnome.desktop.interface", "enable-animations") == Some(false)
+    }
+
+    /// AT-SPI has no announcement call a client can make without owning
+    /// the accessible tree, which WebKitGTK does. The frontend puts the
+    /// message in an ARIA live region instead, and WebKit forwards that
+    /// to Orca.
+    pub fn announce(
+        app: &AppHandle,
+        message: String,
+        politeness: Politeness,
+    ) -> Result<(), String> {
+        app.emit(
+            "spectrus://a11y-announce",
+            Announcement {
+                message,
+                politeness,
+            },
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::{AppHandle, Manager};
+    use windows::core::BSTR;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Accessibility::{
+        NotificationKind_ActionCompleted, NotificationProcessing_ImportantAll,
+        NotificationProcessing_ImportantMostRecent, UiaHostProviderFromHwnd,
+        UiaRaiseNotificationEvent,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETSCREENREADER,
+        SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    use super::Politeness;
+
+    fn flag(action: SYSTEM_PARAMETERS_INFO_ACTION) -> Option<bool> {
+        let mut value = windows::core::BOOL(0);
+        // SAFETY: both actions write a BOOL through the pointer.
+        unsafe {
+            SystemParametersInfoW(
+                action,
+                0,
+                Some(&mut value as *mut _ as _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+        }
+        .ok()?;
+        Some(value.as_bool())
+    }
+
+    /// Set by Narrator, NVDA and JAWS while they run.
+    pub fn screen_reader(_app: &AppHandle) -> bool {
+        flag(SPI_GETSCREENREADER).unwrap_or(false)
+    }
+
+    /// "Show animations in Windows" off.
+    pub fn reduced_motion() -> bool {
+        flag(SPI_GETCLIENTAREAANIMATION) == Some(false)
+    }
+
+    /// Raise a UI Automation notification on the focused window, which
+    /// Narrator and NVDA read without moving focus.
+    pub fn announce(
+        app: &AppHandle,
+        message: String,
+        politeness: Politeness,
+    ) -> Result<(), String> {
+        let windows = app.webview_windows();
+        let window = windows
+            .values()
+            .find(|w| w.is_focused().unwrap_or(false))
+            .or_else(|| windows.values().next())
+            .ok_or("no window to announce from")?;
+        let hwnd = HWND(window.hwnd().map_err(|e| e.to_string())?.0);
+        let processing = match politeness {
+            Politeness::Polite => NotificationProcessing_ImportantAll,
+            Politeness::Assertive => NotificationProcessing_ImportantMostRecent,
+        };
+        // SAFETY: `hwnd` is a live top-level window of this process.
+        unsafe {
+            let provider = UiaHostProviderFromHwnd(hwnd).map_err(|e| e.to_string())?;
+            UiaRaiseNotificationEvent(
+                &provider,
+                NotificationKind_ActionCompleted,
+                processing,
+                &BSTR::from(message),
+                &BSTR::from("spectrus.announcement"),
+            )
+            .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::runtime::AnyObject;
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::{
+        NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+        NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityKey,
+        NSAccessibilityPriorityLevel, NSApplication, NSWorkspace,
+    };
+    use objc2_foundation::{NSDictionary, NSNumber, NSString};
+    use tauri::AppHandle;
+
+    use super::Politeness;
+
+    pub fn screen_reader(_app: &AppHandle) -> bool {
+        NSWorkspace::sharedWorkspace().isVoiceOverEnabled()
+    }
+
+    pub fn reduced_motion() -> bool {
+        NSWorkspace::sharedWorkspace().accessibilityDisplayShouldReduceMotion()
+    }
+
+    /// Post an announcement request on the application, which VoiceOver
+    /// reads regardless of where its cursor is.
+    pub fn announce(
+        app: &AppHandle,
+        message: String,
+        politeness: Politeness,
+    ) -> Result<(), String> {
+        let priority = match politeness {
+            Politeness::Polite => NSAccessibilityPriorityLevel::Medium,
+            Politeness::Assertive => NSAccessibilityPriorityLevel::High,
+        };
+        app.run_on_main_thread(move || {
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            let message = NSString::from_str(&message);
+            let priority = NSNumber::new_isize(priority.0);
+            let values: [&AnyObject; 2] = [message.as_ref(), priority.as_ref()];
+            // SAFETY: the keys are AppKit's own constants and the values
+            // have the types the announcement notification documents.
+            unsafe {
+                let info = NSDictionary::from_slices(
+                    &[NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey],
+                    &values,
+                );
+                NSAccessibilityPostNotificationWithUserInfo(
+                    NSApplication::sharedApplication(mtm).as_ref(),
+                    NSAccessibilityAnnouncementRequestedNotification,
+                    Some(&info),
+                );
+            }
+        })
+        .map_err(|e| e.to_string())
+    }
+}
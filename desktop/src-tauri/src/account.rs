@@ -0,0 +1,79 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Settings key remembering the signed-in account per profile.
+const SETTING: &str = "account.active";
+
+/// Payload of `spectrus://account-changed`.
+#[derive(Clone, Serialize)]
+struct AccountChanged {
+    account: Option<String>,
+}
+
+/// Account remembered in the profile's settings, if any.
+pub fn saved(profile: &Profile) -> Option<String> {
+    settings::get(profile, SETTING)
+        .and_then(|v| v.as_str().map(str::to_string))
+        .filter(|id| validate_id(id).is_ok())
+}
+
+/// Account ids become part of the keychain service name.
+fn validate_id(id: &str) -> Result<(), String> {
+    let ok = !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid account id {id:?}"))
+    }
+}
+
+/// Return the active account id, or `null` for the legacy single-account
+/// namespace.
+#[tauri::command]
+pub fn account_current(profiles: State<'_, ProfileState>) -> Option<String> {
+    profiles.current().account
+}
+
+/// Switch to another account without restarting. The keychain namespace is
+/// swapped under the profile lock and the choice is persisted. Backend state
+/// tied to the old account goes with it: remote asset servers and pooled
+/// connections are dropped, sync exchanges under way are stopped and the
+/// new account's device key is advertised. Tokens themselves aren't cached;
+/// every read goes through the new namespace. A single
+/// `spectrus://account-changed` event then tells every window to drop its
+/// own state, read the new account's tokens and reconnect its realtime
+/// socket.
+#[tauri::command]
+pub fn account_switch(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    account_id: Option<String>,
+) -> Result<(), String> {
+    if let Some(id) = &account_id {
+        validate_id(id)?;
+    }
+    if profiles.current().account == account_id {
+        return Ok(());
+    }
+    // Saved first, so a failed write leaves the old account in force.
+    let saved = account_id.clone().map(Value::String).unwrap_or(Value::Null);
+    settings::set(&profiles.current(), SETTING, saved)?;
+    profiles.set_account(account_id.clone());
+    app.state::<crate::remote_assets::RemoteAssets>().reset();
+    crate::sync::account_changed(&app);
+    app.emit(
+        "spectrus://account-changed",
+        AccountChanged {
+            account: account_id,
+        },
+    )
+    .map_err(|e| e.to_string())
+}
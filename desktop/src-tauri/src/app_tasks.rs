@@ -0,0 +1,231 @@
+use serde::Deserialize;
+use tauri::AppHandle;
+
+/// A shortcut in the Windows jump list / macOS Dock menu. Activating it routes
+/// `url` exactly like an OS-opened deep link.
+#[derive(Clone, Deserialize)]
+pub struct Task {
+    // Linux desktops only read actions from the installed .desktop file.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    title: String,
+    url: String,
+}
+
+fn defaults() -> Vec<Task> {
+    [
+        ("New Recording", "spectrus://recording/new"),
+        ("Quick Import", "spectrus://import"),
+        ("Open Last Project", "spectrus://project/last"),
+    ]
+    .into_iter()
+    .map(|(title, url)| Task {
+        title: title.into(),
+        url: url.into(),
+    })
+    .collect()
+}
+
+/// Install the default tasks at startup.
+pub fn init(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    dock::install(app);
+    apply(app, defaults());
+}
+
+fn apply(app: &AppHandle, tasks: Vec<Task>) {
+    #[cfg(target_os = "macos")]
+    let _ = app.run_on_main_thread(move || dock::set(tasks));
+
+    #[cfg(windows)]
+    let _ = app.run_on_main_thread(move || {
+        // SAFETY: COM calls on the main thread, which the event loop has
+        // already initialised for COM.
+        if let Err(e) = unsafe { jump_list::write(&tasks) } {
+            eprintln!("app tasks: jump list update failed: {e}");
+        }
+    });
+
+    #[cfg(target_os = "linux")]
+    let _ = (app, tasks);
+}
+
+/// Replace the jump list / Dock menu tasks, e.g. to name the last project.
+/// `null` restores the defaults; an empty list removes them all.
+#[tauri::command]
+pub fn app_tasks_set(app: AppHandle, tasks: Option<Vec<Task>>) -> Result<(), String> {
+    let tasks = tasks.unwrap_or_else(defaults);
+    if let Some(bad) = tasks.iter().find(|t| !t.url.starts_with("spectrus://")) {
+        return Err(format!("{}: tasks must use spectrus:// links", bad.url));
+    }
+    apply(&app, tasks);
+    Ok(())
+}
+
+#[cfg(windows)]
+mod jump_list {
+    use windows::core::{Interface, HSTRING};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+    };
+
+    use super::Task;
+
+    /// Rewrite the "Tasks" section of the taskbar jump list. Each entry
+    /// relaunches the executable with the task's URL as its only argument,
+    /// which is how Windows delivers deep links too.
+    pub unsafe fn write(tasks: &[Task]) -> windows::core::Result<()> {
+        let exe = HSTRING::from(std::env::current_exe()?.as_os_str());
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut slots = 0;
+        let _removed: IObjectArray = list.BeginList(&mut slots)?;
+        if !tasks.is_empty() {
+            let collection: IObjectCollection =
+                CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            for task in tasks {
+                let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+                link.SetPath(&exe)?;
+                link.SetArguments(&HSTRING::from(format!("\"{}\"", task.url)))?;
+                link.SetIconLocation(&exe, 0)?;
+                // Jump list entries take their label from the link's title
+                // property, not its description.
+                let store: IPropertyStore = link.cast()?;
+                store.SetValue(&PKEY_Title, &PROPVARIANT::from(task.title.as_str()))?;
+                store.Commit()?;
+                collection.AddObject(&link)?;
+            }
+            list.AddUserTasks(&collection)?;
+        }
+        list.CommitList()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod dock {
+    use std::cell::RefCell;
+    use std::sync::OnceLock;
+
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject, Imp, NSObject, Sel};
+    use objc2::{define_class, msg_send, sel, MainThreadMarker, MainThreadOnly};
+    use objc2_app_kit::{NSApplication, NSMenu, NSMenuItem};
+    use objc2_foundation::NSString;
+    use tauri::{AppHandle, Emitter};
+
+    use super::Task;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    struct DockMenu {
+        menu: Retained<NSMenu>,
+        // Menu items only hold a weak reference to their target.
+        _target: Retained<Target>,
+        urls: Vec<String>,
+    }
+
+    thread_local! {
+        static MENU: RefCell<Option<DockMenu>> = const { RefCell::new(None) };
+    }
+
+    define_class!(
+        #[unsafe(super(NSObject))]
+        #[thread_kind = MainThreadOnly]
+        #[name = "SpectrusDockTaskTarget"]
+        struct Target;
+
+        impl Target {
+            #[unsafe(method(runTask:))]
+            fn run_task(&self, item: &NSMenuItem) {
+                let url = MENU.with_borrow(|m| {
+                    m.as_ref()
+                        .and_then(|m| m.urls.get(item.tag() as usize).cloned())
+                });
+                if let (Some(app), Some(url)) = (APP.get(), url) {
+                    crate::tray::show_main(app);
+                    if let Err(e) = app.emit("spectrus://deep-link", url) {
+                        eprintln!("app tasks: deep-link emit error: {e}");
+                    }
+                }
+            }
+        }
+    );
+
+    extern "C-unwind" fn dock_menu(
+        _this: &AnyObject,
+        _cmd: Sel,
+        _sender: &AnyObject,
+    ) -> *mut NSMenu {
+        MENU.with_borrow(|m| {
+            m.as_ref().map_or(std::ptr::null_mut(), |m| {
+                Retained::as_ptr(&m.menu).cast_mut()
+            })
+        })
+    }
+
+    /// Teach the app delegate (owned by the windowing layer) to answer
+    /// `applicationDockMenu:`.
+    pub fn install(app: &AppHandle) {
+        let _ = APP.set(app.clone());
+        let _ = app.run_on_main_thread(|| {
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            let Some(delegate) = NSApplication::sharedApplication(mtm).delegate() else {
+                return;
+            };
+            let delegate: &AnyObject = delegate.as_ref();
+            let class: &AnyClass = delegate.class();
+            // SAFETY: the function matches the `@@:@` signature AppKit calls
+            // `applicationDockMenu:` with, and returns a menu kept alive by
+            // `MENU`. Adding fails harmlessly if the method already exists.
+            unsafe {
+                let imp: Imp = std::mem::transmute(
+                    dock_menu as extern "C-unwind" fn(&AnyObject, Sel, &AnyObject) -> *mut NSMenu,
+                );
+                objc2::ffi::class_addMethod(
+                    (class as *const AnyClass).cast_mut(),
+                    sel!(applicationDockMenu:),
+                    imp,
+                    c"@@:@".as_ptr(),
+                );
+            }
+        });
+    }
+
+    /// Rebuild the Dock menu; runs on the main thread.
+    pub fn set(tasks: Vec<Task>) {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        // SAFETY: `init` on a freshly allocated NSObject subclass.
+        let target: Retained<Target> = unsafe { msg_send![Target::alloc(mtm), init] };
+        let menu = NSMenu::new(mtm);
+        for (i, task) in tasks.iter().enumerate() {
+            // SAFETY: `runTask:` is implemented by `Target` with the
+            // `(id)sender` signature menu actions use.
+            let item = unsafe {
+                NSMenuItem::initWithTitle_action_keyEquivalent(
+                    NSMenuItem::alloc(mtm),
+                    &NSString::from_str(&task.title),
+                    Some(sel!(runTask:)),
+                    &NSString::new(),
+                )
+            };
+            // SAFETY: `target` outlives the item; both are kept in `MENU`.
+            unsafe { item.setTarget(Some(&target)) };
+            item.setTag(i as isize);
+            menu.addItem(&item);
+        }
+        let urls = tasks.into_iter().map(|t| t.url).collect();
+        MENU.set(Some(DockMenu {
+            menu,
+            _target: target,
+            urls,
+        }));
+    }
+}
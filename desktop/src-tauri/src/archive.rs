@@ -0,0 +1,208 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State, Window};
+use walkdir::WalkDir;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, ZipArchive, ZipWriter};
+
+use crate::fs_scope::FsScope;
+use crate::jobs::Job;
+use crate::watchdog::Call;
+
+/// Most an archive may expand to. Entry sizes in the archive can lie, so
+/// what is actually written counts.
+const MAX_EXTRACTED: u64 = 32 * 1024 * 1024 * 1024;
+
+/// Copy `reader` into `writer`, advancing the job's progress and stopping
+/// promptly on cancellation.
+fn copy_with_progress(
+    job: &mut Job,
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    done: &mut u64,
+    total: u64,
+) -> Result<(), String> {
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        job.check()?;
+        let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+        *done += n as u64;
+        job.progress(*done, total);
+    }
+}
+
+/// Expand `paths` into (file on disk, entry name) pairs. A directory keeps its
+/// own name as the top-level folder in the archive.
+fn collect_entries(paths: &[PathBuf]) -> Result<Vec<(PathBuf, String)>, String> {
+    let mut entries = Vec::new();
+    for root in paths {
+        let base = root.parent().unwrap_or(Path::new(""));
+        for entry in WalkDir::new(root).follow_links(false) {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let rel = entry.path().strip_prefix(base).map_err(|e| e.to_string())?;
+            // Zip entries always use forward slashes.
+            let name = rel.to_string_lossy().replace('\\', "/");
+            entries.push((entry.into_path(), name));
+        }
+    }
+    Ok(entries)
+}
+
+fn create(
+    job: &mut Job,
+    paths: &[PathBuf],
+    dest: &Path,
+    password: Option<&str>,
+) -> Result<(), String> {
+    let entries = collect_entries(paths)?;
+    let total = entries
+        .iter()
+        .map(|(p, _)| fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+        .sum();
+
+    let mut zip = ZipWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    let mut options = SimpleFileOptions::default().large_file(total > u32::MAX as u64);
+    if let Some(p) = password {
+        options = options.with_aes_encryption(AesMode::Aes256, p);
+    }
+    let mut done = 0;
+    for (path, name) in entries {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        let mut file = File::open(&path).map_err(|e| e.to_string())?;
+        copy_with_progress(job, &mut file, &mut zip, &mut done, total)?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn extract(job: &mut Job, src: &Path, dest: &Path, password: Option<&str>) -> Result<(), String> {
+    let mut archive =
+        ZipArchive::new(File::open(src).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    extract_archive(job, &mut archive, dest, password, &[])
+}
+
+/// Unpack `archive` into `dest`, leaving out the entries named in `skip`.
+/// Fails once more than `MAX_EXTRACTED` bytes come out, leaving what was
+/// written for the caller to remove.
+pub(crate) fn extract_archive(
+    job: &mut Job,
+    archive: &mut ZipArchive<File>,
+    dest: &Path,
+    password: Option<&str>,
+    skip: &[&str],
+) -> Result<(), String> {
+    let total = archive.decompressed_size().unwrap_or(0) as u64;
+    if total > MAX_EXTRACTED {
+        return Err("the archive expands to more than 32 GiB".into());
+    }
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+
+    let mut done = 0;
+    for i in 0..archive.len() {
+        let mut entry = match password {
+            Some(p) => archive.by_index_decrypt(i, p.as_bytes()),
+            None => archive.by_index(i),
+        }
+        .map_err(|e| match e {
+            ZipError::InvalidPassword => "wrong password".to_string(),
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                "this archive is encrypted; a password is required".to_string()
+            }
+            e => e.to_string(),
+        })?;
+        if skip.contains(&entry.name()) {
+            continue;
+        }
+        // Zip-slip: refuse absolute paths and `..` components instead of
+        // silently writing outside `dest`.
+        let rel = entry
+            .enclosed_name()
+            .ok_or_else(|| format!("unsafe path in archive: {}", entry.name()))?;
+        let out = dest.join(rel);
+        if entry.is_dir() {
+            fs::create_dir_all(&out).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Symlink entries are written as plain files holding the link target,
+        // so a crafted archive can't plant a link that later writes follow.
+        let mut file = File::create(&out).map_err(|e| e.to_string())?;
+        let mut capped = (&mut entry).take(MAX_EXTRACTED - done + 1);
+        copy_with_progress(job, &mut capped, &mut file, &mut done, total)?;
+        if done > MAX_EXTRACTED {
+            return Err("the archive expands to more than 32 GiB".into());
+        }
+    }
+    Ok(())
+}
+
+/// Zip `paths` (files or directories) into `dest`, optionally AES-256
+/// encrypted. Progress is reported as `spectrus://job-progress` in bytes; a
+/// cancelled or failed run removes the partial archive.
+#[tauri::command]
+pub async fn archive_create(
+    app: AppHandle,
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    paths: Vec<String>,
+    dest: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    let sources = paths
+        .iter()
+        .map(|p| scope.check(window.label(), p))
+        .collect::<Result<Vec<_>, _>>()?;
+    let dest = scope.check_new(window.label(), &dest)?;
+    crate::audit::record(
+        &app,
+        crate::audit::Action::Export,
+        serde_json::json!({ "kind": "archive", "path": dest, "sources": sources }),
+    );
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut job = Job::start(&app, "archive-create").within(call);
+        let result = create(&mut job, &sources, &dest, password.as_deref());
+        if result.is_err() {
+            let _ = fs::remove_file(&dest);
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Extract the zip at `src` into the directory `dest` (created if missing).
+/// Progress is reported as `spectrus://job-progress` in uncompressed bytes.
+#[tauri::command]
+pub async fn archive_extract(
+    app: AppHandle,
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    src: String,
+    dest: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    let src = scope.check(window.label(), &src)?;
+    let dest = scope.check_new(window.label(), &dest)?;
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut job = Job::start(&app, "archive-extract").within(call);
+        extract(&mut job, &src, &dest, password.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
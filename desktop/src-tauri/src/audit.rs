@@ -0,0 +1,507 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::fs_scope::FsScope;
+use crate::profile::ProfileState;
+use crate::watchdog::Call;
+
+/// Kept next to the profile directories rather than in one, so it covers
+/// every profile, isn't carried in profile bundles and isn't removed with a
+/// profile.
+const LOG_FILE: &str = "audit.log";
+
+/// The log is read backwards in chunks this big to find the last entry.
+const TAIL: u64 = 64 * 1024;
+
+/// Past this size the log is moved aside as `audit.1.log`, older ones
+/// moving up a number; the new log's first entry chains to the old one's
+/// last.
+const MAX_LOG: u64 = 16 * 1024 * 1024;
+
+/// Rotated logs kept; the oldest goes when another is moved aside.
+const KEEP: usize = 4;
+
+/// Installation-wide keychain entry holding the chain key, as
+/// `<base64url key>:<seq>`: entries from `seq` on are hashed with the key.
+/// Logs written before the key existed keep their plain hashes up to there.
+const KEY_ENTRY: &str = "spectrus:audit-key";
+
+/// Hash the first entry chains from.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What happened.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    KeychainRead,
+    KeychainWrite,
+    KeychainDelete,
+    /// A table export or a profile bundle.
+    Export,
+    PermissionGrant,
+    PermissionRevoke,
+    ProfileSwitch,
+    UpdateInstall,
+    /// The log was moved aside for size; `prev` is the old log's last hash.
+    LogRotated,
+    /// The last entry was found damaged, so the chain couldn't go on from
+    /// it. Starts a new chain segment, from `GENESIS`.
+    Tampered,
+}
+
+/// The hashed part of an entry.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Body {
+    seq: u64,
+    /// Unix ms.
+    at: i64,
+    action: Action,
+    /// The profile active at the time.
+    profile: String,
+    /// Action-specific: the keychain key (never the value), the export
+    /// path, the capability granted and so on.
+    detail: Value,
+    /// `hash` of the entry before.
+    prev: String,
+}
+
+/// One line of `audit.log`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(flatten)]
+    body: Body,
+    /// Keyed BLAKE3 of the body's JSON, so the chain can't be rebuilt
+    /// without the key from the keychain.
+    hash: String,
+}
+
+#[derive(Clone, Copy)]
+struct ChainKey {
+    key: [u8; 32],
+    /// First entry hashed with the key.
+    since: u64,
+}
+
+/// Managed state: the chain key once read from the keychain.
+#[derive(Default)]
+pub struct Audit(Mutex<Option<ChainKey>>);
+
+impl Body {
+    fn hash(&self, key: Option<ChainKey>) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        match key {
+            Some(k) if self.seq >= k.since => blake3::keyed_hash(&k.key, &json),
+            _ => blake3::hash(&json),
+        }
+        .to_hex()
+        .to_string()
+    }
+}
+
+fn parse_key(stored: &str) -> Option<ChainKey> {
+    let (key, since) = stored.split_once(':')?;
+    let key = <[u8; 32]>::try_from(URL_SAFE_NO_PAD.decode(key).ok()?).ok()?;
+    Some(ChainKey {
+        key,
+        since: since.parse().ok()?,
+    })
+}
+
+/// The chain key, if one has been made.
+fn stored_key(app: &AppHandle) -> Result<Option<ChainKey>, String> {
+    let state = app.state::<Audit>();
+    let mut cached = state.0.lock().unwrap();
+    if cached.is_none() {
+        *cached = match crate::keychain::read_app_secret(app, KEY_ENTRY)? {
+            Some(stored) => Some(parse_key(&stored).ok_or("the audit key is malformed")?),
+            None => None,
+        };
+    }
+    Ok(*cached)
+}
+
+/// The chain key, made on first use to cover entries from `next` on.
+fn chain_key(app: &AppHandle, next: u64) -> Result<ChainKey, String> {
+    if let Some(key) = stored_key(app)? {
+        return Ok(key);
+    }
+    let mut key = [0u8; 32];
+    getrandom::fill(&mut key).map_err(|e| e.to_string())?;
+    let stored = format!("{}:{next}", URL_SAFE_NO_PAD.encode(key));
+    crate::keychain::write_app_secret(app, KEY_ENTRY, stored)?;
+    let key = ChainKey { key, since: next };
+    *app.state::<Audit>().0.lock().unwrap() = Some(key);
+    Ok(key)
+}
+
+/// Which entries `audit_query` returns; fields left out match everything.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AuditFilter {
+    action: Option<Action>,
+    profile: Option<String>,
+    /// Unix ms, inclusive.
+    since: Option<i64>,
+    until: Option<i64>,
+    limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, body: &Body) -> bool {
+        self.action.is_none_or(|a| a == body.action)
+            && self.profile.as_ref().is_none_or(|p| *p == body.profile)
+            && self.since.is_none_or(|t| body.at >= t)
+            && self.until.is_none_or(|t| body.at <= t)
+    }
+}
+
+/// Result of walking the chain through the kept logs, oldest first.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    entries: u64,
+    intact: bool,
+    /// Line number (from 1, across the kept logs) of the first entry that
+    /// was altered, removed, inserted or can't be read.
+    broken_at: Option<u64>,
+    /// Times the chain was started over after finding the log damaged.
+    restarts: u64,
+    /// First entry covered by the chain key; entries before it (from
+    /// older versions) could be rewritten by anyone with the file.
+    keyed_from: Option<u64>,
+}
+
+fn log_path(app: &AppHandle) -> PathBuf {
+    app.state::<ProfileState>().root().join(LOG_FILE)
+}
+
+/// `audit.<n>.log`, the `n`th newest rotated log.
+fn rotated_path(log: &Path, n: usize) -> PathBuf {
+    log.with_extension(format!("{n}.log"))
+}
+
+/// The kept logs that exist, oldest first.
+fn logs(app: &AppHandle) -> Vec<PathBuf> {
+    let log = log_path(app);
+    let mut all: Vec<PathBuf> = (1..=KEEP).rev().map(|n| rotated_path(&log, n)).collect();
+    all.push(log);
+    all.retain(|p| p.is_file());
+    all
+}
+
+fn rotate(log: &Path) -> Result<(), String> {
+    for n in (1..KEEP).rev() {
+        let from = rotated_path(log, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(log, n + 1)).map_err(|e| e.to_string())?;
+        }
+    }
+    fs::rename(log, rotated_path(log, 1)).map_err(|e| e.to_string())
+}
+
+fn open_log(log: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(log)
+        .map_err(|e| e.to_string())
+}
+
+/// The last complete line of the log.
+enum Last {
+    Empty,
+    Entry(Entry),
+    /// Not an entry: damaged or tampered with.
+    Damaged,
+}
+
+/// The last complete entry, reading back from the end of the file as far
+/// as it takes, and whether the file ends in a partly written line (which
+/// is skipped).
+fn last(file: &mut File) -> Result<(Last, bool), String> {
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut tail = Vec::new();
+    let mut pos = len;
+    loop {
+        if pos > 0 {
+            let start = pos.saturating_sub(TAIL);
+            let mut chunk = vec![0u8; (pos - start) as usize];
+            file.seek(SeekFrom::Start(start))
+                .and_then(|_| file.read_exact(&mut chunk))
+                .map_err(|e| e.to_string())?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+            pos = start;
+        }
+        let partial = tail.last().is_some_and(|b| *b != b'\n');
+        let complete = match tail.iter().rposition(|b| *b == b'\n') {
+            Some(end) => tail[..end].trim_ascii_end(),
+            None if pos == 0 => return Ok((Last::Empty, partial)),
+            None => continue,
+        };
+        if complete.is_empty() && pos == 0 {
+            return Ok((Last::Empty, partial));
+        }
+        let line = match complete.iter().rposition(|b| *b == b'\n') {
+            Some(start) => &complete[start + 1..],
+            None if pos == 0 => complete,
+            None => continue,
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let last = match serde_json::from_slice(line) {
+            Ok(entry) => Last::Entry(entry),
+            Err(_) => Last::Damaged,
+        };
+        return Ok((last, partial));
+    }
+}
+
+/// Hash a new entry and add its line to `lines`; returns its hash.
+fn push(
+    app: &AppHandle,
+    lines: &mut Vec<u8>,
+    (seq, prev): (u64, String),
+    action: Action,
+    detail: Value,
+) -> Result<String, String> {
+    let key = chain_key(app, seq)?;
+    let body = Body {
+        seq,
+        at: crate::time::now_ms(app),
+        action,
+        profile: app.state::<ProfileState>().current().name,
+        detail,
+        prev,
+    };
+    let hash = body.hash(Some(key));
+    let entry = Entry {
+        body,
+        hash: hash.clone(),
+    };
+    serde_json::to_writer(&mut *lines, &entry).map_err(|e| e.to_string())?;
+    lines.push(b'\n');
+    Ok(hash)
+}
+
+fn append(app: &AppHandle, action: Action, detail: Value) -> Result<(), String> {
+    write(app, Some((action, detail)), MAX_LOG).map(drop)
+}
+
+/// Append `new`, if given, moving the log aside first once it is
+/// `rotate_at` bytes or more. Returns the size of the oldest kept log when
+/// moving aside deleted it.
+fn write(
+    app: &AppHandle,
+    new: Option<(Action, Value)>,
+    rotate_at: u64,
+) -> Result<Option<u64>, String> {
+    let log = log_path(app);
+    // Other Spectrus processes append to the same log. The lock is a file
+    // of its own so the log can be moved aside under it.
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(log.with_extension("lock"))
+        .map_err(|e| e.to_string())?;
+    lock.lock().map_err(|e| e.to_string())?;
+    let mut file = open_log(&log)?;
+    let (last, partial) = last(&mut file)?;
+    // A line cut off by a crash stays, for `audit_verify` to flag, but the
+    // new entry starts on a line of its own.
+    let mut lines = if partial { vec![b'\n'] } else { Vec::new() };
+    let mut dropped = None;
+    let next = match last {
+        Last::Empty => (1, GENESIS.to_string()),
+        Last::Entry(entry) if file.metadata().map_err(|e| e.to_string())?.len() >= rotate_at => {
+            drop(file);
+            dropped = Some(fs::metadata(rotated_path(&log, KEEP)).map_or(0, |m| m.len()));
+            rotate(&log)?;
+            file = open_log(&log)?;
+            lines.clear();
+            let seq = entry.body.seq + 1;
+            let hash = push(
+                app,
+                &mut lines,
+                (seq, entry.hash),
+                Action::LogRotated,
+                serde_json::json!({}),
+            )?;
+            (seq + 1, hash)
+        }
+        Last::Entry(entry) => (entry.body.seq + 1, entry.hash),
+        Last::Damaged => {
+            // Failing every later entry would leave nothing audited; say so
+            // in the log and start over, which `audit_verify` reports.
+            eprintln!("audit: the last entry is damaged; starting a new chain");
+            let after = entries(app).into_iter().flatten().map(|e| e.body.seq).max();
+            let seq = after.unwrap_or(0) + 1;
+            let seq = stored_key(app)?.map_or(seq, |k| seq.max(k.since));
+            let hash = push(
+                app,
+                &mut lines,
+                (seq, GENESIS.to_string()),
+                Action::Tampered,
+                serde_json::json!({ "reason": "the last entry was unreadable" }),
+            )?;
+            (seq + 1, hash)
+        }
+    };
+    if let Some((action, detail)) = new {
+        push(app, &mut lines, next, action, detail)?;
+    } else if lines.len() <= 1 {
+        // Nothing to record, and the log wasn't moved aside.
+        return Ok(None);
+    }
+    file.write_all(&lines).map_err(|e| e.to_string())?;
+    file.sync_data().map_err(|e| e.to_string())?;
+    Ok(dropped.filter(|&size| size > 0))
+}
+
+/// Move the log aside if it is past half its limit, so that maintenance
+/// does it while the machine is idle rather than an entry being recorded
+/// having to. Returns the size of the oldest kept log if that went.
+pub(crate) fn rotate_early(app: &AppHandle) -> Result<Option<u64>, String> {
+    write(app, None, MAX_LOG / 2)
+}
+
+/// Append an entry to the audit log. Never fails the action being audited;
+/// a write error is reported on stderr.
+pub(crate) fn record(app: &AppHandle, action: Action, detail: Value) {
+    if let Err(e) = append(app, action, detail) {
+        eprintln!("audit: could not record an entry: {e}");
+    }
+}
+
+/// Every line of the kept logs, oldest first: `None` for one that isn't a
+/// UTF-8 JSON entry (damaged, cut off or tampered with).
+fn entries(app: &AppHandle) -> Vec<Option<Entry>> {
+    let mut all = Vec::new();
+    for path in logs(app) {
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        for line in BufReader::new(file).split(b'\n') {
+            let Ok(line) = line else {
+                all.push(None);
+                break;
+            };
+            if !line.trim_ascii().is_empty() {
+                all.push(serde_json::from_slice(&line).ok());
+            }
+        }
+    }
+    all
+}
+
+fn verify(app: &AppHandle) -> Result<Verification, String> {
+    let key = stored_key(app)?;
+    let mut prev: Option<String> = None;
+    let mut seq = 0;
+    let mut count = 0;
+    let mut broken_at = None;
+    let mut restarts = 0;
+    for entry in entries(app) {
+        count += 1;
+        let ok = match &entry {
+            None => false,
+            Some(e) if e.body.hash(key) != e.hash => false,
+            Some(e) if e.body.action == Action::Tampered => {
+                restarts += 1;
+                e.body.prev == GENESIS && e.body.seq > seq
+            }
+            // The oldest kept log may begin where a deleted one ended.
+            Some(e) if prev.is_none() && e.body.action == Action::LogRotated => true,
+            Some(e) => e.body.prev == prev.as_deref().unwrap_or(GENESIS) && e.body.seq == seq + 1,
+        };
+        if !ok && broken_at.is_none() {
+            broken_at = Some(count);
+        }
+        if let Some(e) = entry {
+            seq = e.body.seq;
+            prev = Some(e.hash);
+        }
+    }
+    Ok(Verification {
+        entries: count,
+        intact: broken_at.is_none() && restarts == 0,
+        broken_at,
+        restarts,
+        keyed_from: key.map(|k| k.since),
+    })
+}
+
+/// Audit entries matching `filter` from the kept logs, newest first. Lines
+/// that don't parse are skipped; `audit_verify` reports them.
+#[tauri::command]
+pub async fn audit_query(
+    app: AppHandle,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<Entry>, String> {
+    let filter = filter.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut found: Vec<Entry> = entries(&app)
+            .into_iter()
+            .flatten()
+            .filter(|e| filter.matches(&e.body))
+            .collect();
+        found.reverse();
+        found.truncate(filter.limit.unwrap_or(usize::MAX));
+        found
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Check that no entry was changed, removed or inserted since it was
+/// written. Removing entries from the end can't be detected from the log
+/// alone; compare `entries` with an earlier export. Fails if the chain
+/// key can't be read.
+#[tauri::command]
+pub async fn audit_verify(app: AppHandle) -> Result<Verification, String> {
+    tauri::async_runtime::spawn_blocking(move || verify(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Copy the kept logs, oldest first and one JSON entry per line, to `dest`
+/// for review, and return their verification.
+#[tauri::command]
+pub async fn audit_export(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    dest: String,
+) -> Result<Verification, String> {
+    let dest = scope.check_new(call.window(), &dest)?;
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut out = File::create(&dest).map_err(|e| e.to_string())?;
+        for src in logs(&app) {
+            scope.check()?;
+            let mut log = File::open(&src).map_err(|e| e.to_string())?;
+            std::io::copy(&mut log, &mut out).map_err(|e| e.to_string())?;
+        }
+        record(
+            &app,
+            Action::Export,
+            serde_json::json!({ "kind": "audit", "path": dest }),
+        );
+        verify(&app)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Listener, Manager, State, WebviewWindow};
+
+use crate::consent::{self, Capability};
+use crate::fs_scope::FsScope;
+use crate::import::{self, ImportResult};
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watcher::{self, Change, ChangeKind, Watch, WatcherState};
+
+/// Where rules were kept before they got their own file. The renderer can
+/// write any setting, so they are carried over once and then checked like
+/// any other rule.
+const LEGACY_SETTING: &str = "import.rules";
+
+/// Instruments tend to write their output in bursts; wait this long after
+/// the last change before picking a file up.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// What happens to a file once it has been imported.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum After {
+    #[default]
+    Keep,
+    /// Move it into `to`, keeping its name.
+    Move { to: PathBuf },
+    /// Rename it in place. `name` may use `{stem}`, `{ext}`, `{date}` and
+    /// `{import}` (the new import's id).
+    Rename { name: String },
+}
+
+/// Import files matching `pattern` that appear in `folder` into `project`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    id: String,
+    folder: PathBuf,
+    /// Glob matched against the path relative to `folder`.
+    #[serde(default = "any_file")]
+    pattern: String,
+    #[serde(default)]
+    recursive: bool,
+    project: String,
+    #[serde(default)]
+    after: After,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn any_file() -> String {
+    "*".into()
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Payload of `spectrus://auto-imported`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AutoImported<'a> {
+    rule: &'a str,
+    result: &'a ImportResult,
+    /// Set when the import worked but the post-action didn't.
+    after_error: Option<String>,
+}
+
+/// Managed state: a watch per enabled rule, the paths this module moved or
+/// renamed files to (so they aren't picked up again), and the size and
+/// mtime of files already imported in place.
+#[derive(Default)]
+pub struct AutoImport {
+    watches: Mutex<HashMap<String, Watch>>,
+    ours: Mutex<HashSet<PathBuf>>,
+    done: Mutex<HashMap<PathBuf, (u64, SystemTime)>>,
+}
+
+/// The rules, as an array of `Rule`, written only by `import_rule_set` and
+/// `import_rule_delete`.
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("import-rules.json")
+}
+
+fn rules(profile: &Profile) -> Vec<Rule> {
+    if let Ok(bytes) = fs::read(store_path(profile)) {
+        return serde_json::from_slice(&bytes).unwrap_or_default();
+    }
+    let legacy: Vec<Rule> = settings::get(profile, LEGACY_SETTING)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    if !legacy.is_empty() && save(profile, &legacy).is_ok() {
+        let _ = settings::set(profile, LEGACY_SETTING, Value::Null);
+    }
+    legacy
+}
+
+fn save(profile: &Profile, rules: &[Rule]) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(rules).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(profile), &bytes)
+}
+
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+fn renamed(template: &str, path: &Path, import: &str, app: &AppHandle) -> Result<PathBuf, String> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().unwrap_or_default().to_string_lossy();
+    let date = jiff::Timestamp::from_millisecond(crate::time::now_ms(app))
+        .map_err(|e| e.to_string())?
+        .to_zoned(jiff::tz::TimeZone::system())
+        .date();
+    let name = template
+        .replace("{stem}", &stem)
+        .replace("{ext}", &ext)
+        .replace("{date}", &date.to_string())
+        .replace("{import}", import);
+    let plain = Path::new(&name)
+        .file_name()
+        .is_some_and(|n| n == name.as_str());
+    if !plain {
+        return Err(format!("not a file name: {name}"));
+    }
+    Ok(path.with_file_name(name))
+}
+
+/// `dir/name`, or `dir/stem (n).ext` if that is taken.
+fn free_path(dir: &Path, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|x| format!(".{}", x.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| dir.join(format!("{stem} ({n}){ext}")))
+        .find(|p| !p.exists())
+        .expect("some free name")
+}
+
+fn apply_after(app: &AppHandle, rule: &Rule, path: &Path, import: &str) -> Result<(), String> {
+    let dest = match &rule.after {
+        After::Keep => {
+            if let Some(stamp) = stamp(path) {
+                let state = app.state::<AutoImport>();
+                state.done.lock().unwrap().insert(path.to_path_buf(), stamp);
+            }
+            return Ok(());
+        }
+        After::Move { to } => {
+            fs::create_dir_all(to).map_err(|e| e.to_string())?;
+            free_path(to, path)
+        }
+        After::Rename { name } => {
+            let dest = renamed(name, path, import, app)?;
+            if dest.exists() {
+                return Err(format!("{} already exists", dest.display()));
+            }
+            dest
+        }
+    };
+    let state = app.state::<AutoImport>();
+    state.ours.lock().unwrap().insert(dest.clone());
+    fs::rename(path, &dest).map_err(|e| {
+        state.ours.lock().unwrap().remove(&dest);
+        format!("{}: {e}", path.display())
+    })
+}
+
+/// Whether a change is a new or updated file this rule hasn't dealt with.
+fn wanted(app: &AppHandle, change: &Change) -> bool {
+    if change.kind == ChangeKind::Removed || !change.path.is_file() {
+        return false;
+    }
+    let state = app.state::<AutoImport>();
+    if state.ours.lock().unwrap().remove(&change.path) {
+        return false;
+    }
+    let done = state.done.lock().unwrap();
+    done.get(&change.path)
+        .is_none_or(|s| stamp(&change.path) != Some(*s))
+}
+
+fn on_changes(app: &AppHandle, rule: &Rule, changes: Vec<Change>) {
+    let imports = match import::imports_dir(&app.state::<ProfileState>().current(), &rule.project) {
+        Ok(dir) => dir,
+        Err(e) => return eprintln!("auto-import {}: {e}", rule.id),
+    };
+    for change in changes.into_iter().filter(|c| wanted(app, c)) {
+        let path = change.path.to_string_lossy().into_owned();
+        let result = import::import_file(app, &imports, path, Ok(change.path.clone()), None);
+        let after_error = result
+            .import_id()
+            .and_then(|id| apply_after(app, rule, &change.path, id).err());
+        let _ = app.emit(
+            "spectrus://auto-imported",
+            AutoImported {
+                rule: &rule.id,
+                result: &result,
+                after_error,
+            },
+        );
+    }
+}
+
+/// Replace the running watches with one per enabled rule of the active
+/// profile. A rule's folder is only watched while the user's leave to watch
+/// it stands (`Capability::Watch`).
+pub(crate) fn reload(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    let state = app.state::<AutoImport>();
+    let mut watches = state.watches.lock().unwrap();
+    watches.clear();
+    for rule in rules(&profile) {
+        if !rule.enabled {
+            continue;
+        }
+        let folder = rule.folder.to_string_lossy();
+        if !consent::granted_anywhere(&profile, Capability::Watch, &folder) {
+            eprintln!("auto-import {}: {folder}: not allowed to watch", rule.id);
+            continue;
+        }
+        let id = app.state::<WatcherState>().next_id();
+        let handle = app.clone();
+        let watching = rule.clone();
+        let watch = watcher::spawn(
+            id,
+            &rule.folder,
+            rule.recursive,
+            vec![rule.pattern.clone()],
+            SETTLE,
+            move |changes| on_changes(&handle, &watching, changes),
+        );
+        match watch {
+            Ok(watch) => {
+                watches.insert(rule.id, watch);
+            }
+            Err(e) => eprintln!("auto-import {}: {}: {e}", rule.id, rule.folder.display()),
+        }
+    }
+}
+
+/// Start watching for the active profile's rules, and follow profile
+/// switches. Runs for as long as the app does, window or not.
+pub fn start(app: &AppHandle) {
+    reload(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| reload(&handle));
+}
+
+/// The active profile's rules.
+#[tauri::command]
+pub fn import_rules_list(profiles: State<'_, ProfileState>) -> Vec<Rule> {
+    rules(&profiles.current())
+}
+
+/// Add a rule, or replace the one with the same id. An empty id makes a new
+/// rule. The folder, and a move target, must be ones the calling window was
+/// given access to. Rules keep watching with no window open, so the user is
+/// also asked to let the folder be watched.
+#[tauri::command]
+pub async fn import_rule_set(
+    app: AppHandle,
+    window: WebviewWindow,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    mut rule: Rule,
+) -> Result<Rule, String> {
+    rule.folder = scope.check(window.label(), &rule.folder.to_string_lossy())?;
+    if let After::Move { to } = &mut rule.after {
+        *to = scope.check_new(window.label(), &to.to_string_lossy())?;
+    }
+    let folder = rule.folder.to_string_lossy().into_owned();
+    consent::require(&app, &window, Capability::Watch, Some(&folder)).await?;
+    globset::Glob::new(&rule.pattern).map_err(|e| format!("bad pattern: {e}"))?;
+    let profile = profiles.current();
+    crate::sync::project_dir(&profile, &rule.project)?;
+    if rule.id.is_empty() {
+        let mut bytes = [0u8; 8];
+        getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+        rule.id = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    }
+    let mut all = rules(&profile);
+    match all.iter_mut().find(|r| r.id == rule.id) {
+        Some(existing) => *existing = rule.clone(),
+        None => all.push(rule.clone()),
+    }
+    save(&profile, &all)?;
+    reload(&app);
+    Ok(rule)
+}
+
+/// Remove a rule and stop watching its folder.
+#[tauri::command]
+pub fn import_rule_delete(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    id: String,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    let mut all = rules(&profile);
+    all.retain(|r| r.id != id);
+    save(&profile, &all)?;
+    reload(&app);
+    Ok(())
+}
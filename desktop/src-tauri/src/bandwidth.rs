@@ -0,0 +1,415 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+
+use crate::profile::ProfileState;
+use crate::settings;
+
+/// Setting that defers large transfers while the connection is metered.
+/// On unless turned off.
+const DEFER_SETTING: &str = "bandwidth.deferOnMetered";
+
+/// How often a deferred transfer looks at the connection again.
+const DEFER_POLL: Duration = Duration::from_secs(2);
+
+/// Where transfers are counted, each with its own cap under the total one.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Subsystem {
+    /// Remote assets, update packages and translation catalogs.
+    Download,
+    /// Webhook deliveries.
+    Upload,
+    /// Project sync with other devices, both directions.
+    Sync,
+}
+
+impl Subsystem {
+    fn index(self) -> usize {
+        match self {
+            Self::Download => 1,
+            Self::Upload => 2,
+            Self::Sync => 3,
+        }
+    }
+}
+
+/// Caps in bytes per second; `None` is unlimited. Stored per profile as
+/// `bandwidth.total`, `bandwidth.download` and so on.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Limits {
+    total: Option<u64>,
+    download: Option<u64>,
+    upload: Option<u64>,
+    sync: Option<u64>,
+}
+
+const KEYS: [&str; 4] = [
+    "bandwidth.total",
+    "bandwidth.download",
+    "bandwidth.upload",
+    "bandwidth.sync",
+];
+
+impl Limits {
+    fn rates(&self) -> [Option<u64>; 4] {
+        [self.total, self.download, self.upload, self.sync]
+    }
+
+    fn load(app: &AppHandle) -> Self {
+        let profile = app.state::<ProfileState>().current();
+        let [total, download, upload, sync] =
+            KEYS.map(|key| settings::get(&profile, key).and_then(|v| v.as_u64()));
+        Self {
+            total,
+            download,
+            upload,
+            sync,
+        }
+    }
+}
+
+/// Token bucket allowing a second's worth of burst. Transfers take what
+/// they send and wait off any debt.
+#[derive(Default)]
+struct Bucket {
+    rate: Option<u64>,
+    tokens: f64,
+    at: Option<Instant>,
+}
+
+impl Bucket {
+    /// Take `bytes`; returns how long to wait before sending them.
+    fn take(&mut self, bytes: u64) -> Duration {
+        let Some(rate) = self.rate.filter(|&r| r > 0) else {
+            return Duration::ZERO;
+        };
+        let rate = rate as f64;
+        let now = Instant::now();
+        let elapsed = self.at.map_or(1.0, |at| (now - at).as_secs_f64());
+        self.at = Some(now);
+        self.tokens = (self.tokens + elapsed * rate).min(rate) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    limits: Limits,
+    buckets: [Bucket; 4],
+    defer: bool,
+    /// `None` until the OS has said, and where it can't.
+    metered: Option<bool>,
+    /// Transfers waiting for an unmetered connection.
+    deferred: Vec<Subsystem>,
+}
+
+/// Managed state: caps, buckets and what is known about the connection.
+#[derive(Default)]
+pub struct Bandwidth(Mutex<Inner>);
+
+/// Payload of `spectrus://bandwidth-changed` and `bandwidth_status`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    limits: Limits,
+    metered: Option<bool>,
+    defer_on_metered: bool,
+    /// Subsystems with transfers held back by a metered connection.
+    deferred: Vec<Subsystem>,
+}
+
+impl Bandwidth {
+    fn status(&self) -> Status {
+        let inner = self.0.lock().unwrap();
+        let mut deferred = Vec::new();
+        for subsystem in &inner.deferred {
+            if !deferred.contains(subsystem) {
+                deferred.push(*subsystem);
+            }
+        }
+        Status {
+            limits: inner.limits,
+            metered: inner.metered,
+            defer_on_metered: inner.defer,
+            deferred,
+        }
+    }
+
+    fn wait(&self, subsystem: Subsystem, bytes: u64) -> Duration {
+        let mut inner = self.0.lock().unwrap();
+        let total = inner.buckets[0].take(bytes);
+        total.max(inner.buckets[subsystem.index()].take(bytes))
+    }
+
+    fn holding_back(&self) -> bool {
+        let inner = self.0.lock().unwrap();
+        inner.defer && inner.metered == Some(true)
+    }
+}
+
+fn changed(app: &AppHandle) {
+    let _ = app.emit(
+        "spectrus://bandwidth-changed",
+        app.state::<Bandwidth>().status(),
+    );
+}
+
+/// Apply the active profile's settings.
+fn reload(app: &AppHandle) {
+    let limits = Limits::load(app);
+    let profile = app.state::<ProfileState>().current();
+    let defer = settings::get(&profile, DEFER_SETTING)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    {
+        let bandwidth = app.state::<Bandwidth>();
+        let mut inner = bandwidth.0.lock().unwrap();
+        inner.limits = limits;
+        inner.defer = defer;
+        for (bucket, rate) in inner.buckets.iter_mut().zip(limits.rates()) {
+            bucket.rate = rate;
+        }
+    }
+    changed(app);
+}
+
+/// Record what the OS reports about the connection. Called from the
+/// platform watchers.
+fn set_metered(app: &AppHandle, metered: bool) {
+    let previous = app
+        .state::<Bandwidth>()
+        .0
+        .lock()
+        .unwrap()
+        .metered
+        .replace(metered);
+    if previous != Some(metered) {
+        changed(app);
+    }
+}
+
+/// Load the caps, follow profile switches and start watching whether the
+/// connection is metered.
+pub fn start(app: &AppHandle) {
+    reload(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| reload(&handle));
+    platform::start(app);
+}
+
+/// Wait until `bytes` may go out under the caps. For transfers on their own
+/// thread.
+pub(crate) fn throttle(app: &AppHandle, subsystem: Subsystem, bytes: u64) {
+    let wait = app.state::<Bandwidth>().wait(subsystem, bytes);
+    if !wait.is_zero() {
+        std::thread::sleep(wait);
+    }
+}
+
+/// `throttle` for async transfers.
+pub(crate) async fn throttle_async(app: &AppHandle, subsystem: Subsystem, bytes: u64) {
+    let wait = app.state::<Bandwidth>().wait(subsystem, bytes);
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Whether large transfers are held back right now: the connection is
+/// metered and deferring isn't turned off.
+pub(crate) fn holding_back(app: &AppHandle) -> bool {
+    app.state::<Bandwidth>().holding_back()
+}
+
+/// A transfer listed in `Status::deferred` while it exists.
+struct Deferred<'a> {
+    app: &'a AppHandle,
+    subsystem: Subsystem,
+}
+
+impl Drop for Deferred<'_> {
+    fn drop(&mut self) {
+        {
+            let bandwidth = self.app.state::<Bandwidth>();
+            let mut inner = bandwidth.0.lock().unwrap();
+            if let Some(i) = inner.deferred.iter().position(|s| *s == self.subsystem) {
+                inner.deferred.remove(i);
+            }
+        }
+        changed(self.app);
+    }
+}
+
+/// Hold a large transfer back while the connection is metered, unless
+/// deferring is turned off. Gives up once `check` fails, e.g. with a job's
+/// or a call's `check`, or when the future is dropped.
+pub(crate) async fn defer_while_metered(
+    app: &AppHandle,
+    subsystem: Subsystem,
+    check: impl Fn() -> Result<(), String>,
+) -> Result<(), String> {
+    let bandwidth = app.state::<Bandwidth>();
+    if !bandwidth.holding_back() {
+        return Ok(());
+    }
+    bandwidth.0.lock().unwrap().deferred.push(subsystem);
+    changed(app);
+    let _deferred = Deferred { app, subsystem };
+    while bandwidth.holding_back() {
+        check()?;
+        tokio::time::sleep(DEFER_POLL).await;
+    }
+    Ok(())
+}
+
+/// Caps, whether the connection is metered and what is being held back.
+/// Changes come as `spectrus://bandwidth-changed`.
+#[tauri::command]
+pub fn bandwidth_status(bandwidth: State<'_, Bandwidth>) -> Status {
+    bandwidth.status()
+}
+
+/// Set the caps, taking effect at once for transfers under way, and
+/// optionally whether large transfers wait out metered connections.
+#[tauri::command]
+pub fn bandwidth_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    limits: Limits,
+    defer_on_metered: Option<bool>,
+) -> Result<Status, String> {
+    let profile = profiles.current();
+    for (key, rate) in KEYS.iter().zip(limits.rates()) {
+        let value = rate.filter(|&r| r > 0).map_or(Value::Null, Value::from);
+        settings::set(&profile, key, value)?;
+    }
+    if let Some(defer) = defer_on_metered {
+        settings::set(&profile, DEFER_SETTING, Value::Bool(defer))?;
+    }
+    reload(&app);
+    Ok(app.state::<Bandwidth>().status())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use tauri::AppHandle;
+    use windows::Networking::Connectivity::{
+        NetworkCostType, NetworkInformation, NetworkStatusChangedEventHandler,
+    };
+
+    /// Metered when the internet connection has a fixed or per-use cost, or
+    /// is roaming or over its data limit.
+    fn metered() -> windows::core::Result<bool> {
+        let cost = NetworkInformation::GetInternetConnectionProfile()?.GetConnectionCost()?;
+        let kind = cost.NetworkCostType()?;
+        Ok(kind == NetworkCostType::Fixed
+            || kind == NetworkCostType::Variable
+            || cost.Roaming()?
+            || cost.OverDataLimit()?)
+    }
+
+    fn check(app: &AppHandle) {
+        // No internet connection profile means nothing is being paid for.
+        super::set_metered(app, metered().unwrap_or(false));
+    }
+
+    pub fn start(app: &AppHandle) {
+        check(app);
+        let app = app.clone();
+        let handler = NetworkStatusChangedEventHandler::new(move |_| {
+            check(&app);
+            Ok(())
+        });
+        if let Err(e) = NetworkInformation::NetworkStatusChanged(&handler) {
+            eprintln!("bandwidth: network changes unavailable: {e}");
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+
+    use block2::RcBlock;
+    use dispatch2::DispatchQueue;
+    use tauri::AppHandle;
+
+    #[link(name = "Network", kind = "framework")]
+    extern "C" {
+        fn nw_path_monitor_create() -> *mut c_void;
+        fn nw_path_monitor_set_queue(monitor: *mut c_void, queue: *const DispatchQueue);
+        fn nw_path_monitor_set_update_handler(
+            monitor: *mut c_void,
+            handler: &block2::Block<dyn Fn(*mut c_void)>,
+        );
+        fn nw_path_monitor_start(monitor: *mut c_void);
+        fn nw_path_is_expensive(path: *mut c_void) -> bool;
+        fn nw_path_is_constrained(path: *mut c_void) -> bool;
+    }
+
+    /// Metered when the path is expensive (cellular, a personal hotspot) or
+    /// constrained (Low Data Mode). The monitor reports the current path
+    /// right away and every change after; it lives as long as the app.
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        let handler = RcBlock::new(move |path: *mut c_void| {
+            // SAFETY: `path` is the nw_path_t the monitor hands the handler.
+            let metered = unsafe { nw_path_is_expensive(path) || nw_path_is_constrained(path) };
+            super::set_metered(&app, metered);
+        });
+        let queue = DispatchQueue::new("com.spectrus.bandwidth", None);
+        // SAFETY: plain Network.framework calls; the monitor retains the
+        // handler and the queue, and is never released.
+        unsafe {
+            let monitor = nw_path_monitor_create();
+            nw_path_monitor_set_queue(monitor, &*queue);
+            nw_path_monitor_set_update_handler(monitor, &handler);
+            nw_path_monitor_start(monitor);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::AppHandle;
+    use zbus::blocking::{Connection, Proxy};
+
+    /// NetworkManager's `NMMetered`: yes, and guessed yes.
+    fn metered(value: u32) -> bool {
+        value == 1 || value == 3
+    }
+
+    fn watch(app: &AppHandle) -> zbus::Result<()> {
+        let conn = Connection::system()?;
+        let proxy = Proxy::new(
+            &conn,
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+        )?;
+        super::set_metered(app, metered(proxy.get_property("Metered")?));
+        for change in proxy.receive_property_changed::<u32>("Metered") {
+            if let Ok(value) = change.get() {
+                super::set_metered(app, metered(value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = watch(&app) {
+                eprintln!("bandwidth: NetworkManager unavailable: {e}");
+            }
+        });
+    }
+}
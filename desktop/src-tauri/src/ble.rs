@@ -0,0 +1,370 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use btleplug::api::bleuuid::uuid_from_u16;
+use btleplug::api::{
+    Central, CentralEvent, CharPropFlags, Characteristic, Manager as _, Peripheral as _,
+    ScanFilter, WriteType,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tauri::async_runtime::{JoinHandle, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::watchdog::Call;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BleDevice {
+    /// Platform peripheral id (a MAC address on Linux and Windows, a
+    /// per-host UUID on macOS); pass to the other commands.
+    id: String,
+    name: Option<String>,
+    rssi: Option<i16>,
+    services: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BleCharacteristic {
+    uuid: String,
+    /// Any of "read", "write", "writeWithoutResponse", "notify", "indicate".
+    properties: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct BleService {
+    uuid: String,
+    characteristics: Vec<BleCharacteristic>,
+}
+
+/// Payload of `spectrus://ble-notification`.
+#[derive(Clone, Serialize)]
+struct Notification {
+    device: String,
+    service: String,
+    characteristic: String,
+    value: Vec<u8>,
+}
+
+/// Managed state: the adapter (opened on first use), the task relaying its
+/// events, and one notification relay per connected device.
+#[derive(Default)]
+pub struct Ble {
+    adapter: Mutex<Option<Adapter>>,
+    events: Mutex<Option<JoinHandle<()>>>,
+    relays: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+/// Accept full UUIDs and the 16-bit short form ("180d") of assigned numbers.
+fn parse_uuid(s: &str) -> Result<Uuid, String> {
+    if s.len() == 4 {
+        if let Ok(short) = u16::from_str_radix(s, 16) {
+            return Ok(uuid_from_u16(short));
+        }
+    }
+    Uuid::parse_str(s).map_err(|e| format!("{s}: {e}"))
+}
+
+async fn adapter(ble: &Ble) -> Result<Adapter, String> {
+    let mut adapter = ble.adapter.lock().await;
+    if let Some(adapter) = adapter.as_ref() {
+        return Ok(adapter.clone());
+    }
+    let manager = Manager::new().await.map_err(|e| e.to_string())?;
+    let first = manager
+        .adapters()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or("no Bluetooth adapter")?;
+    *adapter = Some(first.clone());
+    Ok(first)
+}
+
+async fn peripheral(ble: &Ble, id: &str) -> Result<Peripheral, String> {
+    adapter(ble)
+        .await?
+        .peripherals()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id().to_string() == id)
+        .ok_or_else(|| format!("unknown device {id}; scan first"))
+}
+
+async fn describe(peripheral: &Peripheral) -> Option<BleDevice> {
+    let properties = peripheral.properties().await.ok()??;
+    Some(BleDevice {
+        id: peripheral.id().to_string(),
+        name: properties.local_name.or(properties.advertisement_name),
+        rssi: properties.rssi,
+        services: properties.services.iter().map(Uuid::to_string).collect(),
+    })
+}
+
+async fn characteristic(
+    peripheral: &Peripheral,
+    service: &str,
+    uuid: &str,
+) -> Result<Characteristic, String> {
+    let (service, uuid) = (parse_uuid(service)?, parse_uuid(uuid)?);
+    peripheral
+        .characteristics()
+        .into_iter()
+        .find(|c| c.service_uuid == service && c.uuid == uuid)
+        .ok_or_else(|| format!("no characteristic {uuid} in service {service}"))
+}
+
+/// Forward discovery and disconnect events from the adapter as
+/// `spectrus://ble-device` and `spectrus://ble-disconnected`.
+async fn relay_events(app: AppHandle, adapter: Adapter) {
+    let Ok(mut events) = adapter.events().await else {
+        return;
+    };
+    while let Some(event) = events.next().await {
+        match event {
+            CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                if let Ok(peripheral) = adapter.peripheral(&id).await {
+                    if let Some(device) = describe(&peripheral).await {
+                        let _ = app.emit("spectrus://ble-device", device);
+                    }
+                }
+            }
+            CentralEvent::DeviceDisconnected(id) => {
+                let _ = app.emit("spectrus://ble-disconnected", id.to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Scan for devices advertising any of `services` (all devices if empty).
+/// Sightings are emitted as `spectrus://ble-device` until `ble_scan_stop`.
+#[tauri::command]
+pub async fn ble_scan_start(
+    app: AppHandle,
+    call: Call,
+    ble: State<'_, Ble>,
+    services: Option<Vec<String>>,
+) -> Result<(), String> {
+    call.run(async {
+        let services = services
+            .unwrap_or_default()
+            .iter()
+            .map(|s| parse_uuid(s))
+            .collect::<Result<_, _>>()?;
+        let adapter = adapter(&ble).await?;
+        let mut events = ble.events.lock().await;
+        if events.is_none() {
+            let task = relay_events(app, adapter.clone());
+            *events = Some(tauri::async_runtime::spawn(task));
+        }
+        adapter
+            .start_scan(ScanFilter { services })
+            .await
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Stop scanning. Devices already seen stay known for `ble_connect`.
+#[tauri::command]
+pub async fn ble_scan_stop(call: Call, ble: State<'_, Ble>) -> Result<(), String> {
+    call.run(async {
+        adapter(&ble)
+            .await?
+            .stop_scan()
+            .await
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Devices seen so far.
+#[tauri::command]
+pub async fn ble_devices(call: Call, ble: State<'_, Ble>) -> Result<Vec<BleDevice>, String> {
+    call.run(async {
+        let peripherals = adapter(&ble)
+            .await?
+            .peripherals()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut devices = Vec::new();
+        for peripheral in &peripherals {
+            devices.extend(describe(peripheral).await);
+        }
+        Ok(devices)
+    })
+    .await
+}
+
+/// Connect to device `id` and discover its GATT services. Values of
+/// subscribed characteristics arrive as `spectrus://ble-notification`.
+#[tauri::command]
+pub async fn ble_connect(
+    app: AppHandle,
+    call: Call,
+    ble: State<'_, Ble>,
+    id: String,
+) -> Result<Vec<BleService>, String> {
+    call.run(async {
+        let peripheral = peripheral(&ble, &id).await?;
+        if !peripheral.is_connected().await.unwrap_or(false) {
+            peripheral.connect().await.map_err(|e| e.to_string())?;
+        }
+        peripheral
+            .discover_services()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Entry::Vacant(slot) = ble.relays.lock().await.entry(id.clone()) {
+            let mut stream = peripheral
+                .notifications()
+                .await
+                .map_err(|e| e.to_string())?;
+            let device = id.clone();
+            let task = tauri::async_runtime::spawn(async move {
+                while let Some(n) = stream.next().await {
+                    let _ = app.emit(
+                        "spectrus://ble-notification",
+                        Notification {
+                            device: device.clone(),
+                            service: n.service_uuid.to_string(),
+                            characteristic: n.uuid.to_string(),
+                            value: n.value,
+                        },
+                    );
+                }
+            });
+            slot.insert(task);
+        }
+
+        Ok(peripheral
+            .services()
+            .into_iter()
+            .map(|s| BleService {
+                uuid: s.uuid.to_string(),
+                characteristics: s
+                    .characteristics
+                    .iter()
+                    .map(|c| BleCharacteristic {
+                        uuid: c.uuid.to_string(),
+                        properties: [
+                            (CharPropFlags::READ, "read"),
+                            (CharPropFlags::WRITE, "write"),
+                            (
+                                CharPropFlags::WRITE_WITHOUT_RESPONSE,
+                                "writeWithoutResponse",
+                            ),
+                            (CharPropFlags::NOTIFY, "notify"),
+                            (CharPropFlags::INDICATE, "indicate"),
+                        ]
+                        .into_iter()
+                        .filter(|(flag, _)| c.properties.contains(*flag))
+                        .map(|(_, name)| name)
+                        .collect(),
+                    })
+                    .collect(),
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Disconnect from device `id`.
+#[tauri::command]
+pub async fn ble_disconnect(call: Call, ble: State<'_, Ble>, id: String) -> Result<(), String> {
+    call.run(async {
+        if let Some(task) = ble.relays.lock().await.remove(&id) {
+            task.abort();
+        }
+        peripheral(&ble, &id)
+            .await?
+            .disconnect()
+            .await
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Turn on notifications (or indications) for a characteristic.
+#[tauri::command]
+pub async fn ble_subscribe(
+    call: Call,
+    ble: State<'_, Ble>,
+    id: String,
+    service: String,
+    characteristic: String,
+) -> Result<(), String> {
+    call.run(async {
+        let peripheral = peripheral(&ble, &id).await?;
+        let c = self::characteristic(&peripheral, &service, &characteristic).await?;
+        peripheral.subscribe(&c).await.map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Turn notifications for a characteristic back off.
+#[tauri::command]
+pub async fn ble_unsubscribe(
+    call: Call,
+    ble: State<'_, Ble>,
+    id: String,
+    service: String,
+    characteristic: String,
+) -> Result<(), String> {
+    call.run(async {
+        let peripheral = peripheral(&ble, &id).await?;
+        let c = self::characteristic(&peripheral, &service, &characteristic).await?;
+        peripheral.unsubscribe(&c).await.map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Read a characteristic's current value.
+#[tauri::command]
+pub async fn ble_read(
+    call: Call,
+    ble: State<'_, Ble>,
+    id: String,
+    service: String,
+    characteristic: String,
+) -> Result<Vec<u8>, String> {
+    call.run(async {
+        let peripheral = peripheral(&ble, &id).await?;
+        let c = self::characteristic(&peripheral, &service, &characteristic).await?;
+        peripheral.read(&c).await.map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Write `value` to a characteristic. Writes wait for the device's
+/// acknowledgement unless `without_response` is set.
+#[tauri::command]
+pub async fn ble_write(
+    call: Call,
+    ble: State<'_, Ble>,
+    id: String,
+    service: String,
+    characteristic: String,
+    value: Vec<u8>,
+    without_response: Option<bool>,
+) -> Result<(), String> {
+    call.run(async {
+        let peripheral = peripheral(&ble, &id).await?;
+        let c = self::characteristic(&peripheral, &service, &characteristic).await?;
+        let kind = if without_response.unwrap_or(false) {
+            WriteType::WithoutResponse
+        } else {
+            WriteType::WithResponse
+        };
+        peripheral
+            .write(&c, &value, kind)
+            .await
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
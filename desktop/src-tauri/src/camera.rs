@@ -0,0 +1,573 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::consent::{self, Capability};
+use crate::qr;
+
+/// Capture size asked of the camera. QR codes held up to a webcam read fine
+/// at VGA, and decoding stays fast enough to keep up with the frame rate.
+/// macOS takes a session preset instead.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+const WIDTH: u32 = 640;
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+const HEIGHT: u32 = 480;
+
+/// A code still in view is reported again after this long, so a pairing
+/// screen that missed the first event doesn't wait forever.
+const REPEAT_AFTER: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Serialize)]
+pub struct CameraDevice {
+    /// Pass to `camera_scan_start`. A /dev path on Linux, the device
+    /// symbolic link on Windows, the AVCaptureDevice unique id on macOS.
+    id: String,
+    name: String,
+}
+
+/// Payload of `spectrus://camera-qr`.
+#[derive(Clone, Serialize)]
+struct Scanned {
+    device: Option<String>,
+    data: String,
+}
+
+/// Payload of `spectrus://camera-scan-stopped`.
+#[derive(Clone, Serialize)]
+struct Stopped {
+    /// Set when the camera failed or went away rather than being stopped.
+    error: Option<String>,
+}
+
+/// Managed state: the stop flag of the running scan, if any.
+#[derive(Default)]
+pub struct Camera {
+    active: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl Camera {
+    pub(crate) fn stop(&self) {
+        if let Some(stop) = self.active.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn scan(app: &AppHandle, device: Option<String>, stop: &AtomicBool) -> Result<(), String> {
+    let mut last: Option<(String, Instant)> = None;
+    platform::capture(device.as_deref(), stop, &mut |frame| {
+        for data in qr::decode_image(frame) {
+            let repeat = last
+                .as_ref()
+                .is_some_and(|(seen, at)| *seen == data && at.elapsed() < REPEAT_AFTER);
+            if !repeat {
+                let _ = app.emit(
+                    "spectrus://camera-qr",
+                    Scanned {
+                        device: device.clone(),
+                        data: data.clone(),
+                    },
+                );
+                last = Some((data, Instant::now()));
+            }
+        }
+    })
+}
+
+/// Video capture devices.
+#[tauri::command]
+pub async fn camera_list() -> Result<Vec<CameraDevice>, String> {
+    tauri::async_runtime::spawn_blocking(platform::devices)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Start scanning `device` (default: the first camera) for QR codes. Each
+/// code is emitted as `spectrus://camera-qr`; `spectrus://camera-scan-stopped`
+/// follows when the scan ends. Starting a scan stops the previous one. Needs
+/// the user's consent the first time for each window, and is refused while
+/// capture is paused for privacy.
+#[tauri::command]
+pub async fn camera_scan_start(
+    app: AppHandle,
+    window: WebviewWindow,
+    camera: State<'_, Camera>,
+    device: Option<String>,
+) -> Result<(), String> {
+    crate::privacy::check_capture(&app)?;
+    consent::require(&app, &window, Capability::Camera, None).await?;
+    // The pause may have begun while the prompt was up.
+    crate::privacy::check_capture(&app)?;
+    camera.stop();
+    let stop = Arc::new(AtomicBool::new(false));
+    *camera.active.lock().unwrap() = Some(stop.clone());
+    std::thread::spawn(move || {
+        let error = scan(&app, device, &stop).err();
+        let camera = app.state::<Camera>();
+        let mut active = camera.active.lock().unwrap();
+        if active.as_ref().is_some_and(|a| Arc::ptr_eq(a, &stop)) {
+            *active = None;
+        }
+        let _ = app.emit("spectrus://camera-scan-stopped", Stopped { error });
+    });
+    Ok(())
+}
+
+/// Stop the running scan and release the camera. A no-op if none is running.
+#[tauri::command]
+pub fn camera_scan_stop(camera: State<'_, Camera>) {
+    camera.stop();
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use image::GrayImage;
+    use linuxvideo::format::{PixFormat, PixelFormat};
+    use linuxvideo::{BufType, BufTypes, Device};
+
+    use super::{CameraDevice, HEIGHT, WIDTH};
+
+    pub fn devices() -> Result<Vec<CameraDevice>, String> {
+        let list = linuxvideo::list().map_err(|e| e.to_string())?;
+        let mut devices: Vec<_> = list
+            .flatten()
+            .filter(|d| d.supported_buf_types().contains(BufTypes::VIDEO_CAPTURE))
+            .filter_map(|d| {
+                Some(CameraDevice {
+                    id: d.path().ok()?.to_string_lossy().into_owned(),
+                    name: d.capabilities().ok()?.card().to_string(),
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(devices)
+    }
+
+    /// Grab frames until `stop` is set. YUYV frames are used as-is (every
+    /// other byte is luma); cameras that only offer MJPEG are decoded.
+    pub fn capture(
+        id: Option<&str>,
+        stop: &AtomicBool,
+        on_frame: &mut dyn FnMut(GrayImage),
+    ) -> Result<(), String> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                devices()?
+                    .into_iter()
+                    .next()
+                    .ok_or("no camera connected")?
+                    .id
+            }
+        };
+        let device = Device::open(Path::new(&id)).map_err(|e| format!("{id}: {e}"))?;
+        let formats: Vec<_> = device
+            .formats(BufType::VIDEO_CAPTURE)
+            .flatten()
+            .map(|f| f.pixel_format())
+            .collect();
+        let format = [PixelFormat::YUYV, PixelFormat::MJPG, PixelFormat::JPEG]
+            .into_iter()
+            .find(|f| formats.contains(f))
+            .ok_or_else(|| format!("{id}: no supported pixel format"))?;
+        let capture = device
+            .video_capture(PixFormat::new(WIDTH, HEIGHT, format))
+            .map_err(|e| e.to_string())?;
+        let negotiated = capture.format();
+        let (width, height) = (negotiated.width(), negotiated.height());
+        let stride = negotiated.bytes_per_line() as usize;
+        let format = negotiated.pixel_format();
+        let mut stream = capture.into_stream().map_err(|e| e.to_string())?;
+        while !stop.load(Ordering::Relaxed) {
+            let frame = stream
+                .dequeue(|buf| {
+                    if buf.is_error() {
+                        return Ok(None);
+                    }
+                    Ok(if format == PixelFormat::YUYV {
+                        GrayImage::from_fn(width, height, |x, y| {
+                            let i = y as usize * stride + x as usize * 2;
+                            image::Luma([buf.get(i).copied().unwrap_or(0)])
+                        })
+                        .into()
+                    } else {
+                        image::load_from_memory(&buf).ok().map(|i| i.to_luma8())
+                    })
+                })
+                .map_err(|e| e.to_string())?;
+            if let Some(frame) = frame {
+                on_frame(frame);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use image::GrayImage;
+    use windows::core::{Interface, GUID, PWSTR};
+    use windows::Win32::Media::MediaFoundation::{
+        IMF2DBuffer, IMFActivate, IMFAttributes, IMFMediaSource, MFCreateAttributes,
+        MFCreateMediaType, MFCreateSourceReaderFromMediaSource, MFEnumDeviceSources,
+        MFMediaType_Video, MFShutdown, MFStartup, MFVideoFormat_NV12, MFSTARTUP_NOSOCKET,
+        MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME, MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
+        MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
+        MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK, MF_MT_FRAME_SIZE,
+        MF_MT_MAJOR_TYPE, MF_MT_SUBTYPE, MF_SOURCE_READERF_ENDOFSTREAM,
+        MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING, MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+        MF_VERSION,
+    };
+    use windows::Win32::System::Com::{
+        CoInitializeEx, CoTaskMemFree, CoUninitialize, COINIT_MULTITHREADED,
+    };
+
+    use super::{CameraDevice, HEIGHT, WIDTH};
+
+    /// COM and Media Foundation for the calling thread, torn down on drop.
+    struct Session;
+
+    impl Session {
+        fn start() -> Result<Self, String> {
+            // SAFETY: balanced by `drop` on the same thread.
+            unsafe {
+                CoInitializeEx(None, COINIT_MULTITHREADED)
+                    .ok()
+                    .map_err(|e| e.to_string())?;
+                if let Err(e) = MFStartup(MF_VERSION, MFSTARTUP_NOSOCKET) {
+                    CoUninitialize();
+                    return Err(e.to_string());
+                }
+            }
+            Ok(Session)
+        }
+    }
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            // SAFETY: paired with `start`.
+            unsafe {
+                let _ = MFShutdown();
+                CoUninitialize();
+            }
+        }
+    }
+
+    fn string(activate: &IMFActivate, key: &GUID) -> Option<String> {
+        let mut value = PWSTR::null();
+        let mut len = 0;
+        // SAFETY: the string is allocated by MF and freed here.
+        unsafe {
+            activate
+                .GetAllocatedString(key, &mut value, &mut len)
+                .ok()?;
+            let s = value.to_string().ok();
+            CoTaskMemFree(Some(value.0 as _));
+            s
+        }
+    }
+
+    fn activates() -> Result<Vec<IMFActivate>, String> {
+        let mut attributes: Option<IMFAttributes> = None;
+        let mut list = std::ptr::null_mut();
+        let mut count = 0;
+        // SAFETY: MF fills `list` with `count` owned activates; each is
+        // moved out once and the array itself is freed.
+        unsafe {
+            MFCreateAttributes(&mut attributes, 1).map_err(|e| e.to_string())?;
+            let attributes = attributes.ok_or("no attributes")?;
+            attributes
+                .SetGUID(
+                    &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
+                    &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
+                )
+                .map_err(|e| e.to_string())?;
+            MFEnumDeviceSources(&attributes, &mut list, &mut count).map_err(|e| e.to_string())?;
+            let found = (0..count as usize)
+                .filter_map(|i| list.add(i).read())
+                .collect();
+            CoTaskMemFree(Some(list as _));
+            Ok(found)
+        }
+    }
+
+    fn describe(activate: &IMFActivate) -> Option<CameraDevice> {
+        Some(CameraDevice {
+            id: string(
+                activate,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )?,
+            name: string(activate, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME).unwrap_or_default(),
+        })
+    }
+
+    pub fn devices() -> Result<Vec<CameraDevice>, String> {
+        let _session = Session::start()?;
+        Ok(activates()?.iter().filter_map(describe).collect())
+    }
+
+    /// Read frames through a source reader that converts to NV12, whose
+    /// first plane is the luma image.
+    pub fn capture(
+        id: Option<&str>,
+        stop: &AtomicBool,
+        on_frame: &mut dyn FnMut(GrayImage),
+    ) -> Result<(), String> {
+        let _session = Session::start()?;
+        let activate = activates()?
+            .into_iter()
+            .find(|a| id.is_none() || describe(a).is_some_and(|d| Some(d.id.as_str()) == id))
+            .ok_or("camera not found")?;
+        let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        // SAFETY: plain COM calls on objects owned by this function; the
+        // locked buffer is only read within its lock.
+        unsafe {
+            let source: IMFMediaSource = activate.ActivateObject().map_err(|e| e.to_string())?;
+            let mut attributes: Option<IMFAttributes> = None;
+            MFCreateAttributes(&mut attributes, 1).map_err(|e| e.to_string())?;
+            let attributes = attributes.ok_or("no attributes")?;
+            attributes
+                .SetUINT32(&MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING, 1)
+                .map_err(|e| e.to_string())?;
+            let reader = MFCreateSourceReaderFromMediaSource(&source, &attributes)
+                .map_err(|e| e.to_string())?;
+            let wanted = MFCreateMediaType().map_err(|e| e.to_string())?;
+            wanted
+                .SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)
+                .map_err(|e| e.to_string())?;
+            wanted
+                .SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_NV12)
+                .map_err(|e| e.to_string())?;
+            wanted
+                .SetUINT64(
+                    &MF_MT_FRAME_SIZE,
+                    (u64::from(WIDTH) << 32) | u64::from(HEIGHT),
+                )
+                .map_err(|e| e.to_string())?;
+            reader
+                .SetCurrentMediaType(stream, None, &wanted)
+                .map_err(|e| e.to_string())?;
+            let size = reader
+                .GetCurrentMediaType(stream)
+                .and_then(|t| t.GetUINT64(&MF_MT_FRAME_SIZE))
+                .map_err(|e| e.to_string())?;
+            let (width, height) = ((size >> 32) as u32, size as u32);
+
+            let result = loop {
+                if stop.load(Ordering::Relaxed) {
+                    break Ok(());
+                }
+                let mut flags = 0;
+                let mut sample = None;
+                if let Err(e) =
+                    reader.ReadSample(stream, 0, None, Some(&mut flags), None, Some(&mut sample))
+                {
+                    break Err(e.to_string());
+                }
+                if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+                    break Err("camera disconnected".into());
+                }
+                // Stream ticks and format changes come without a sample.
+                let Some(buffer) = sample.and_then(|s| s.ConvertToContiguousBuffer().ok()) else {
+                    continue;
+                };
+                let mut data = std::ptr::null_mut();
+                let mut pitch = width as i32;
+                let two_d = buffer.cast::<IMF2DBuffer>().ok();
+                let locked = match &two_d {
+                    Some(b) => b.Lock2D(&mut data, &mut pitch).is_ok(),
+                    None => buffer.Lock(&mut data, None, None).is_ok(),
+                };
+                if !locked {
+                    continue;
+                }
+                let frame = GrayImage::from_fn(width, height, |x, y| {
+                    image::Luma([*data.offset(y as isize * pitch as isize + x as isize)])
+                });
+                let _ = match &two_d {
+                    Some(b) => b.Unlock2D(),
+                    None => buffer.Unlock(),
+                };
+                on_frame(frame);
+            };
+            let _ = source.Shutdown();
+            result
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, SyncSender};
+    use std::time::Duration;
+
+    use dispatch2::DispatchQueue;
+    use image::GrayImage;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyObject, NSObject, NSObjectProtocol, ProtocolObject};
+    use objc2::{define_class, msg_send, AllocAnyThread, DeclaredClass};
+    use objc2_av_foundation::{
+        AVAuthorizationStatus, AVCaptureConnection, AVCaptureDevice, AVCaptureDeviceInput,
+        AVCaptureOutput, AVCaptureSession, AVCaptureSessionPreset640x480, AVCaptureVideoDataOutput,
+        AVCaptureVideoDataOutputSampleBufferDelegate, AVMediaTypeVideo,
+    };
+    use objc2_core_media::CMSampleBuffer;
+    use objc2_core_video::{
+        kCVPixelBufferPixelFormatTypeKey, kCVPixelFormatType_420YpCbCr8BiPlanarFullRange,
+        CVPixelBufferGetBaseAddressOfPlane, CVPixelBufferGetBytesPerRowOfPlane,
+        CVPixelBufferGetHeightOfPlane, CVPixelBufferGetWidthOfPlane, CVPixelBufferLockBaseAddress,
+        CVPixelBufferLockFlags, CVPixelBufferUnlockBaseAddress,
+    };
+    use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSString};
+
+    use super::CameraDevice;
+
+    /// How long to wait for a frame before checking the stop flag again.
+    const FRAME_WAIT: Duration = Duration::from_millis(250);
+
+    define_class!(
+        #[unsafe(super(NSObject))]
+        #[name = "SpectrusCameraSink"]
+        #[ivars = SyncSender<GrayImage>]
+        struct FrameSink;
+
+        unsafe impl NSObjectProtocol for FrameSink {}
+
+        unsafe impl AVCaptureVideoDataOutputSampleBufferDelegate for FrameSink {
+            #[unsafe(method(captureOutput:didOutputSampleBuffer:fromConnection:))]
+            fn did_output(
+                &self,
+                _output: &AVCaptureOutput,
+                sample: &CMSampleBuffer,
+                _connection: &AVCaptureConnection,
+            ) {
+                if let Some(frame) = luma(sample) {
+                    // A full channel means the decoder is busy; drop the frame.
+                    let _ = self.ivars().try_send(frame);
+                }
+            }
+        }
+    );
+
+    impl FrameSink {
+        fn new(tx: SyncSender<GrayImage>) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(tx);
+            unsafe { msg_send![super(this), init] }
+        }
+    }
+
+    /// Copy the Y plane of a 420f pixel buffer.
+    fn luma(sample: &CMSampleBuffer) -> Option<GrayImage> {
+        // SAFETY: the buffer is locked read-only while the plane is copied.
+        unsafe {
+            let pixels = sample.image_buffer()?;
+            if CVPixelBufferLockBaseAddress(&pixels, CVPixelBufferLockFlags::ReadOnly) != 0 {
+                return None;
+            }
+            let base = CVPixelBufferGetBaseAddressOfPlane(&pixels, 0) as *const u8;
+            let stride = CVPixelBufferGetBytesPerRowOfPlane(&pixels, 0);
+            let width = CVPixelBufferGetWidthOfPlane(&pixels, 0) as u32;
+            let height = CVPixelBufferGetHeightOfPlane(&pixels, 0) as u32;
+            let frame = (!base.is_null()).then(|| {
+                GrayImage::from_fn(width, height, |x, y| {
+                    image::Luma([*base.add(y as usize * stride + x as usize)])
+                })
+            });
+            CVPixelBufferUnlockBaseAddress(&pixels, CVPixelBufferLockFlags::ReadOnly);
+            frame
+        }
+    }
+
+    fn video_devices() -> Retained<NSArray<AVCaptureDevice>> {
+        // SAFETY: AVMediaTypeVideo is always present; the discovery-session
+        // replacement needs device-type constants missing on older macOS.
+        #[allow(deprecated)]
+        unsafe {
+            AVCaptureDevice::devicesWithMediaType(AVMediaTypeVideo.unwrap())
+        }
+    }
+
+    pub fn devices() -> Result<Vec<CameraDevice>, String> {
+        Ok(video_devices()
+            .iter()
+            .map(|d| unsafe {
+                CameraDevice {
+                    id: d.uniqueID().to_string(),
+                    name: d.localizedName().to_string(),
+                }
+            })
+            .collect())
+    }
+
+    pub fn capture(
+        id: Option<&str>,
+        stop: &AtomicBool,
+        on_frame: &mut dyn FnMut(GrayImage),
+    ) -> Result<(), String> {
+        // SAFETY: AVFoundation objects are created and torn down on this
+        // thread; only the sink is touched from the capture queue.
+        unsafe {
+            let video = AVMediaTypeVideo.unwrap();
+            match AVCaptureDevice::authorizationStatusForMediaType(video) {
+                AVAuthorizationStatus::Denied | AVAuthorizationStatus::Restricted => {
+                    return Err("camera access is turned off in System Settings".into())
+                }
+                // Creating the input below shows the permission prompt.
+                _ => {}
+            }
+            let device = video_devices()
+                .iter()
+                .find(|d| id.is_none_or(|id| d.uniqueID().to_string() == id))
+                .ok_or("camera not found")?;
+            let input = AVCaptureDeviceInput::deviceInputWithDevice_error(&device)
+                .map_err(|e| e.localizedDescription().to_string())?;
+            let session = AVCaptureSession::new();
+            session.setSessionPreset(AVCaptureSessionPreset640x480);
+            if !session.canAddInput(&input) {
+                return Err("camera is in use".into());
+            }
+            session.addInput(&input);
+
+            let output = AVCaptureVideoDataOutput::new();
+            let key: &NSString =
+                &*(kCVPixelBufferPixelFormatTypeKey as *const _ as *const NSString);
+            let format =
+                NSNumber::numberWithUnsignedInt(kCVPixelFormatType_420YpCbCr8BiPlanarFullRange);
+            let settings = NSDictionary::<NSString, AnyObject>::from_slices(&[key], &[&*format]);
+            output.setVideoSettings(Some(&settings));
+            output.setAlwaysDiscardsLateVideoFrames(true);
+            let (tx, rx): (_, Receiver<GrayImage>) = mpsc::sync_channel(1);
+            let sink = FrameSink::new(tx);
+            let queue = DispatchQueue::new("com.spectrus.camera", None);
+            output.setSampleBufferDelegate_queue(
+                Some(ProtocolObject::from_ref(&*sink)),
+                Some(&queue),
+            );
+            session.addOutput(&output);
+            session.startRunning();
+
+            let result = loop {
+                if stop.load(Ordering::Relaxed) {
+                    break Ok(());
+                }
+                match rx.recv_timeout(FRAME_WAIT) {
+                    Ok(frame) => on_frame(frame),
+                    Err(mpsc::RecvTimeoutError::Timeout) if session.isRunning() => {}
+                    Err(_) => break Err("camera disconnected".to_string()),
+                }
+            };
+            session.stopRunning();
+            output.setSampleBufferDelegate_queue(None, None);
+            result
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, State, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, WindowEvent,
+};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::jobs::Job;
+use crate::profile::ProfileState;
+use crate::settings;
+
+/// Label of the popover window.
+pub const LABEL: &str = "quick-capture";
+
+/// Frontend route the popover loads.
+const ROUTE: &str = "quick-capture";
+
+/// Accelerator to summon the popover; `null` turns the shortcut off.
+const SHORTCUT_SETTING: &str = "capture.shortcut";
+const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+/// Logical size of the popover.
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 220.0;
+
+/// Managed state: the accelerator currently registered, if any.
+#[derive(Default)]
+pub struct Capture {
+    shortcut: Mutex<Option<String>>,
+}
+
+/// What the popover hands over.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Submission {
+    Note { text: String },
+    Recording,
+}
+
+/// Payload of `spectrus://capture-added`, and an entry of `capture_list`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedNote {
+    path: String,
+    text: String,
+    created_at: u64,
+}
+
+fn popover(app: &AppHandle) -> Option<WebviewWindow> {
+    app.get_webview_window(LABEL)
+}
+
+/// Create the popover hidden, so summoning it later doesn't wait for a
+/// webview to start, and register the global shortcut.
+pub fn init(app: &AppHandle) {
+    let window = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App(ROUTE.into()))
+        .title("Quick Capture")
+        .inner_size(WIDTH, HEIGHT)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .focused(false)
+        .build();
+    match window {
+        Ok(window) => {
+            let handle = window.clone();
+            // Kept around for instant reopen: closing or clicking away
+            // only hides it.
+            window.on_window_event(move |event| match event {
+                WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    let _ = handle.hide();
+                }
+                WindowEvent::Focused(false) => {
+                    let _ = handle.hide();
+                }
+                _ => {}
+            });
+        }
+        Err(e) => eprintln!("capture: could not create popover: {e}"),
+    }
+
+    let profile = app.state::<ProfileState>().current();
+    let shortcut = match settings::get(&profile, SHORTCUT_SETTING) {
+        Some(Value::String(s)) => Some(s),
+        Some(_) => None,
+        None => Some(DEFAULT_SHORTCUT.to_string()),
+    };
+    if let Some(shortcut) = shortcut {
+        if let Err(e) = register(app, &shortcut) {
+            eprintln!("capture: shortcut {shortcut}: {e}");
+        }
+    }
+}
+
+fn register(app: &AppHandle, shortcut: &str) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, |app, _, event| {
+            if event.state == ShortcutState::Pressed {
+                toggle(app);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    *app.state::<Capture>().shortcut.lock().unwrap() = Some(shortcut.to_string());
+    Ok(())
+}
+
+/// Center the popover in the upper third of the display under the pointer.
+pub(crate) fn place(app: &AppHandle, window: &WebviewWindow) {
+    let monitor = app
+        .cursor_position()
+        .ok()
+        .and_then(|p| app.monitor_from_point(p.x, p.y).ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten());
+    let (Some(monitor), Ok(size)) = (monitor, window.outer_size()) else {
+        return;
+    };
+    let area = monitor.work_area();
+    let x = area.position.x + (area.size.width as i32 - size.width as i32) / 2;
+    let y = area.position.y + (area.size.height as i32 - size.height as i32) / 3;
+    let _ = window.set_position(PhysicalPosition::new(x, y));
+}
+
+/// Show the popover over whatever the user is doing, or hide it if it is
+/// already up. The main window is left as it is.
+pub fn toggle(app: &AppHandle) {
+    let Some(window) = popover(app) else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+        return;
+    }
+    place(app, &window);
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// Change the global shortcut (an accelerator like `"Alt+Space"`), or turn
+/// it off with `null`. The old one stays if the new one can't be registered.
+#[tauri::command]
+pub fn capture_shortcut_set(
+    app: AppHandle,
+    capture: State<'_, Capture>,
+    profiles: State<'_, ProfileState>,
+    shortcut: Option<String>,
+) -> Result<(), String> {
+    let old = capture.shortcut.lock().unwrap().clone();
+    if old == shortcut {
+        return Ok(());
+    }
+    if let Some(new) = &shortcut {
+        register(&app, new)?;
+    }
+    if let Some(old) = old {
+        let _ = app.global_shortcut().unregister(old.as_str());
+    }
+    if shortcut.is_none() {
+        *capture.shortcut.lock().unwrap() = None;
+    }
+    let value = shortcut.map_or(Value::Null, Value::String);
+    settings::set(&profiles.current(), SHORTCUT_SETTING, value)
+}
+
+/// The registered shortcut, `null` when off.
+#[tauri::command]
+pub fn capture_shortcut(capture: State<'_, Capture>) -> Option<String> {
+    capture.shortcut.lock().unwrap().clone()
+}
+
+/// Notes in the profile's `captures/` inbox, newest first, decrypted when
+/// encryption at rest is on. Notes that can't be read (keychain locked)
+/// are left out.
+#[tauri::command]
+pub fn capture_list(app: AppHandle, profiles: State<'_, ProfileState>) -> Vec<CapturedNote> {
+    let dir = profiles.current().dir.join("captures");
+    let mut notes: Vec<CapturedNote> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|x| x == "json"))
+        .filter_map(|path| crate::encryption::read(&app, &path))
+        .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+        .collect();
+    notes.sort_by_key(|n| std::cmp::Reverse(n.created_at));
+    notes
+}
+
+/// Take what the popover captured and hide it. Notes are filed in the
+/// profile's `captures/` inbox as a "capture-note" job and announced to the
+/// main window with `spectrus://capture-added`; a recording request goes to
+/// the main window as the `spectrus://recording/new` deep link.
+#[tauri::command]
+pub fn capture_submit(app: AppHandle, submission: Submission) -> Result<(), String> {
+    if let Some(window) = popover(&app) {
+        let _ = window.hide();
+    }
+    match submission {
+        Submission::Note { text } => {
+            if text.trim().is_empty() {
+                return Err("empty note".into());
+            }
+            let mut job = Job::start(&app, "capture-note");
+            let created_at = crate::time::now_ms(&app).max(0) as u64;
+            let dir = app.state::<ProfileState>().current().dir.join("captures");
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let path = dir.join(format!("{created_at}-{}.json", job.id()));
+            let note = CapturedNote {
+                path: path.display().to_string(),
+                text,
+                created_at,
+            };
+            let json = serde_json::to_vec_pretty(&note).map_err(|e| e.to_string())?;
+            crate::encryption::write(&app, &path, &json)?;
+            job.progress(1, 1);
+            let _ = app.emit_to("main", "spectrus://capture-added", note);
+            Ok(())
+        }
+        Submission::Recording => app
+            .emit_to(
+                "main",
+                "spectrus://deep-link",
+                "spectrus://recording/new?source=capture",
+            )
+            .map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Command line accepted by the executable. Also the payload forwarded to an
+/// already-running instance (see `instance`).
+#[derive(Clone, Parser, Serialize, Deserialize)]
+#[command(name = "spectrus", version, about = "Spectrus desktop client")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Frontend route to show once the window has loaded, e.g. "/settings".
+    #[arg(long, global = true)]
+    pub route: Option<String>,
+
+    /// Profile to open instead of the last one used.
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Start with the main window minimized.
+    #[arg(long, global = true)]
+    pub minimized: bool,
+
+    /// Verbosity for the frontend logger.
+    #[arg(long, value_enum, default_value_t = LogLevel::Info, global = true)]
+    pub log_level: LogLevel,
+
+    /// Run the webviews without GPU acceleration for this launch (see `gpu`).
+    #[arg(long)]
+    pub disable_gpu: bool,
+
+    /// Keep all data next to the executable (see `portable`).
+    #[arg(long)]
+    pub portable: bool,
+
+    /// Internal: set on the child spawned by `incognito_start`.
+    #[arg(long, hide = true)]
+    pub incognito: bool,
+
+    /// A spectrus:// link; how Windows and Linux deliver deep links. Any
+    /// other value is a file opened through its file association.
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Subcommand, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Command {
+    /// Open a file in Spectrus.
+    Open { path: PathBuf },
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Managed state: the arguments this process was started with.
+pub struct Startup(pub Args);
+
+/// Parse the process arguments, exiting with usage on error. Relative paths
+/// are resolved now, while the working directory is still the caller's.
+pub fn parse() -> Args {
+    // Finder adds a `-psn_...` process serial number on older macOS.
+    let raw = std::env::args_os().filter(|a| !a.to_string_lossy().starts_with("-psn_"));
+    let mut args = Args::parse_from(raw);
+    if let Some(Command::Open { path }) = &mut args.command {
+        if let Ok(abs) = std::path::absolute(&*path) {
+            *path = abs;
+        }
+    }
+    if let Some(url) = args.url.as_mut().filter(|u| !u.starts_with("spectrus://")) {
+        if let Ok(abs) = std::path::absolute(&*url) {
+            *url = abs.to_string_lossy().into_owned();
+        }
+    }
+    args
+}
+
+/// Act on the parts of `args` the backend handles itself: grant the main
+/// window the file given to `open` or by file association, and pass a deep
+/// link along. Routes are
+/// left to the frontend.
+pub fn apply(app: &AppHandle, args: &Args) {
+    if let Some(Command::Open { path }) = &args.command {
+        crate::recent::open(app, "main", path);
+    }
+    match args.url.as_deref() {
+        Some(url) if url.starts_with("spectrus://") => crate::deep_link::open(app, url),
+        Some(path) if std::path::Path::new(path).is_file() => {
+            crate::recent::open(app, "main", std::path::Path::new(path));
+        }
+        _ => {}
+    }
+}
+
+/// Handle a launch that was forwarded from a second process: bring the window
+/// forward and tell the frontend via `spectrus://second-instance`.
+pub fn forwarded(app: &AppHandle, args: Args) {
+    if !args.minimized {
+        crate::tray::show_main(app);
+    }
+    apply(app, &args);
+    if let Err(e) = app.emit("spectrus://second-instance", &args) {
+        eprintln!("cli: forward emit error: {e}");
+    }
+}
+
+/// Minimize the main window if `--minimized` was given.
+pub fn apply_window_state(app: &AppHandle, args: &Args) {
+    if args.minimized {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.minimize();
+        }
+    }
+}
+
+/// Arguments this process was launched with, for the frontend to pick up
+/// `route`, `log_level` and the file to open once it has loaded.
+#[tauri::command]
+pub fn startup_args(startup: State<'_, Startup>) -> Args {
+    startup.0.clone()
+}
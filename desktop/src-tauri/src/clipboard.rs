@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{
+    AppHandle, Listener, Manager, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder, Window,
+    WindowEvent,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Label of the picker window.
+pub const LABEL: &str = "clipboard-picker";
+
+/// Frontend route the picker loads.
+const ROUTE: &str = "clipboard-picker";
+
+/// `true` turns the history on. Off by default.
+const ENABLED_SETTING: &str = "clipboard.history";
+
+/// Accelerator to summon the picker; `null` turns the shortcut off.
+const SHORTCUT_SETTING: &str = "clipboard.shortcut";
+const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+V";
+
+/// Oldest items are dropped past this many.
+const MAX_ITEMS: usize = 50;
+
+/// Larger copies go to the clipboard but not into the history.
+const MAX_ITEM_BYTES: usize = 64 * 1024;
+
+/// Secret copies are wiped from the clipboard after this long, unless
+/// something else has been copied since.
+const SECRET_TTL: Duration = Duration::from_secs(45);
+
+/// Logical size of the picker.
+const WIDTH: f64 = 520.0;
+const HEIGHT: f64 = 420.0;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipItem {
+    id: u64,
+    text: String,
+    /// Unix ms.
+    copied_at: i64,
+    /// Label of the window that copied it.
+    source: String,
+}
+
+/// Managed state: recent copies made through `clipboard_copy`, newest
+/// first. Kept in memory only, so it ends with the process, and forgotten
+/// when the profile changes.
+#[derive(Default)]
+pub struct ClipHistory {
+    items: Mutex<VecDeque<ClipItem>>,
+    next_id: AtomicU64,
+    shortcut: Mutex<Option<String>>,
+}
+
+fn enabled(profile: &Profile) -> bool {
+    settings::get(profile, ENABLED_SETTING) == Some(Value::Bool(true))
+}
+
+fn picker(app: &AppHandle) -> Option<WebviewWindow> {
+    app.get_webview_window(LABEL)
+}
+
+/// Set up the picker and its shortcut if the history is on, and again
+/// for each profile switched to, whose settings may differ.
+pub fn init(app: &AppHandle) {
+    apply(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| {
+        // Copies made in one profile stay out of the next one's picker.
+        turn_off(&handle);
+        apply(&handle);
+    });
+}
+
+fn apply(app: &AppHandle) {
+    if enabled(&app.state::<ProfileState>().current()) {
+        turn_on(app);
+    }
+}
+
+fn turn_on(app: &AppHandle) {
+    create_picker(app);
+    let state = app.state::<ClipHistory>();
+    if state.shortcut.lock().unwrap().is_some() {
+        return;
+    }
+    let profile = app.state::<ProfileState>().current();
+    let shortcut = match settings::get(&profile, SHORTCUT_SETTING) {
+        Some(Value::String(s)) => Some(s),
+        Some(_) => None,
+        None => Some(DEFAULT_SHORTCUT.to_string()),
+    };
+    if let Some(shortcut) = shortcut {
+        let registered = app
+            .global_shortcut()
+            .on_shortcut(shortcut.as_str(), |app, _, event| {
+                if event.state == ShortcutState::Pressed {
+                    toggle(app);
+                }
+            });
+        match registered {
+            Ok(()) => *state.shortcut.lock().unwrap() = Some(shortcut),
+            Err(e) => eprintln!("clipboard: shortcut {shortcut}: {e}"),
+        }
+    }
+}
+
+fn turn_off(app: &AppHandle) {
+    let state = app.state::<ClipHistory>();
+    if let Some(shortcut) = state.shortcut.lock().unwrap().take() {
+        let _ = app.global_shortcut().unregister(shortcut.as_str());
+    }
+    state.items.lock().unwrap().clear();
+    if let Some(window) = picker(app) {
+        let _ = window.destroy();
+    }
+}
+
+/// Create the picker hidden, like the quick-capture popover, so the
+/// shortcut shows it without waiting for a webview.
+fn create_picker(app: &AppHandle) {
+    if picker(app).is_some() {
+        return;
+    }
+    let window = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App(ROUTE.into()))
+        .title("Clipboard History")
+        .inner_size(WIDTH, HEIGHT)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .focused(false)
+        .build();
+    match window {
+        Ok(window) => {
+            let handle = window.clone();
+            window.on_window_event(move |event| match event {
+                WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    let _ = handle.hide();
+                }
+                WindowEvent::Focused(false) => {
+                    let _ = handle.hide();
+                }
+                _ => {}
+            });
+        }
+        Err(e) => eprintln!("clipboard: could not create picker: {e}"),
+    }
+}
+
+/// Show the picker on the display under the pointer, or hide it.
+pub fn toggle(app: &AppHandle) {
+    let Some(window) = picker(app) else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+        return;
+    }
+    crate::capture::place(app, &window);
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+fn record(app: &AppHandle, text: &str, source: &str) {
+    if text.len() > MAX_ITEM_BYTES || text.trim().is_empty() {
+        return;
+    }
+    let state = app.state::<ClipHistory>();
+    let item = ClipItem {
+        id: state.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+        text: text.to_string(),
+        copied_at: crate::time::now_ms(app),
+        source: source.to_string(),
+    };
+    let mut items = state.items.lock().unwrap();
+    // Copying the same text again moves it to the top.
+    items.retain(|i| i.text != text);
+    items.push_front(item);
+    items.truncate(MAX_ITEMS);
+}
+
+/// Wipe a secret after `SECRET_TTL` if it is still what's on the clipboard.
+fn expire_secret(app: &AppHandle, text: String) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(SECRET_TTL);
+        if app
+            .clipboard()
+            .read_text()
+            .is_ok_and(|current| current == text)
+        {
+            let _ = app.clipboard().clear();
+        }
+    });
+}
+
+/// Put `text` on the system clipboard. With `secret` (passwords, tokens)
+/// it stays out of the history and is cleared again after 45 seconds;
+/// otherwise it's remembered while the history is on.
+#[tauri::command]
+pub fn clipboard_copy(
+    app: AppHandle,
+    window: Window,
+    profiles: State<'_, ProfileState>,
+    text: String,
+    secret: Option<bool>,
+) -> Result<(), String> {
+    app.clipboard()
+        .write_text(text.as_str())
+        .map_err(|e| e.to_string())?;
+    if secret.unwrap_or(false) {
+        expire_secret(&app, text);
+    } else if enabled(&profiles.current()) {
+        record(&app, &text, window.label());
+    }
+    Ok(())
+}
+
+/// Past copies, newest first, optionally only those containing `query`
+/// (case-insensitive). Empty while the history is off.
+#[tauri::command]
+pub fn clipboard_history(history: State<'_, ClipHistory>, query: Option<String>) -> Vec<ClipItem> {
+    let query = query.map(|q| q.to_lowercase()).filter(|q| !q.is_empty());
+    history
+        .items
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|i| {
+            query
+                .as_ref()
+                .is_none_or(|q| i.text.to_lowercase().contains(q))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Copy a past item again, move it to the top and hide the picker.
+#[tauri::command]
+pub fn clipboard_recopy(app: AppHandle, id: u64) -> Result<(), String> {
+    let state = app.state::<ClipHistory>();
+    let item = {
+        let mut items = state.items.lock().unwrap();
+        let index = items
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or("no such clipboard item")?;
+        let mut item = items.remove(index).expect("index from position");
+        item.copied_at = crate::time::now_ms(&app);
+        items.push_front(item.clone());
+        item
+    };
+    app.clipboard()
+        .write_text(item.text)
+        .map_err(|e| e.to_string())?;
+    if let Some(window) = picker(&app) {
+        let _ = window.hide();
+    }
+    Ok(())
+}
+
+/// Forget every recorded copy.
+#[tauri::command]
+pub fn clipboard_history_clear(history: State<'_, ClipHistory>) {
+    history.items.lock().unwrap().clear();
+}
+
+/// Turn the history (and the picker's shortcut) on or off. Turning it off
+/// forgets everything recorded so far.
+#[tauri::command]
+pub async fn clipboard_history_enable(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    settings::set(&profile, ENABLED_SETTING, Value::Bool(enabled))?;
+    if enabled {
+        turn_on(&app);
+    } else {
+        turn_off(&app);
+    }
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Manager, State};
+
+use crate::jobs::Job;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::Call;
+
+/// Peak files are capped at this many buckets per channel.
+const MAX_BUCKETS: u32 = 1 << 16;
+
+/// Stored alongside each cached artifact as `<key>.json`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    key: String,
+    kind: String,
+    /// Content hash of what the artifact was derived from.
+    input: String,
+    params: Value,
+    size: u64,
+    /// Unix ms.
+    created_at: i64,
+}
+
+/// Which entries `compute_cache_query` and `compute_cache_invalidate` act
+/// on; fields left out match everything.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct CacheFilter {
+    kind: Option<String>,
+    input: Option<String>,
+    key: Option<String>,
+}
+
+impl CacheFilter {
+    fn matches(&self, entry: &CacheEntry) -> bool {
+        self.kind.as_ref().is_none_or(|k| *k == entry.kind)
+            && self.input.as_ref().is_none_or(|i| *i == entry.input)
+            && self.key.as_ref().is_none_or(|k| *k == entry.key)
+    }
+}
+
+/// Managed state: the compute pool. Limits how many artifacts are computed
+/// at once, makes a second request for an artifact being computed wait for
+/// the first instead of repeating it, and remembers content hashes of files
+/// by size and mtime.
+pub struct Compute {
+    running: Mutex<(usize, HashSet<String>)>,
+    changed: Condvar,
+    limit: usize,
+    hashes: Mutex<HashMap<PathBuf, (u64, SystemTime, String)>>,
+}
+
+impl Default for Compute {
+    fn default() -> Self {
+        Self {
+            running: Mutex::default(),
+            changed: Condvar::new(),
+            limit: std::thread::available_parallelism().map_or(2, |n| n.get()),
+            hashes: Mutex::default(),
+        }
+    }
+}
+
+fn cache_root(profile: &Profile) -> PathBuf {
+    profile.cache_dir().join("compute")
+}
+
+/// Sorted keys all the way down, so equal parameters always hash the same.
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(
+                keys.into_iter()
+                    .map(|k| (k.clone(), canonical(&map[k])))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+fn cache_key(kind: &str, input: &str, params: &Value) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in [kind, input, &canonical(params).to_string()] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+fn entry_paths(profile: &Profile, kind: &str, key: &str) -> (PathBuf, PathBuf) {
+    let dir = cache_root(profile).join(kind);
+    (
+        dir.join(format!("{key}.bin")),
+        dir.join(format!("{key}.json")),
+    )
+}
+
+/// A cached artifact, with its mtime bumped so housekeeping trims the
+/// least recently used first.
+fn lookup(profile: &Profile, kind: &str, key: &str) -> Option<Vec<u8>> {
+    let (data, _) = entry_paths(profile, kind, key);
+    let bytes = fs::read(&data).ok()?;
+    if let Ok(file) = File::options().append(true).open(&data) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(bytes)
+}
+
+fn store(profile: &Profile, entry: &CacheEntry, data: &[u8]) {
+    let (data_path, meta_path) = entry_paths(profile, &entry.kind, &entry.key);
+    let stored = data_path
+        .parent()
+        .map_or(Ok(()), |dir| {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())
+        })
+        .and_then(|()| settings::write_atomic(&data_path, data))
+        .and_then(|()| serde_json::to_vec(entry).map_err(|e| e.to_string()))
+        .and_then(|meta| settings::write_atomic(&meta_path, &meta));
+    if let Err(e) = stored {
+        eprintln!("compute: could not cache {}: {e}", entry.key);
+        let _ = fs::remove_file(&data_path);
+    }
+}
+
+impl Compute {
+    /// Block until `key` isn't being computed elsewhere and a slot is free,
+    /// then claim both.
+    fn acquire(&self, key: &str) {
+        let mut running = self.running.lock().unwrap();
+        while running.0 >= self.limit || running.1.contains(key) {
+            running = self.changed.wait(running).unwrap();
+        }
+        running.0 += 1;
+        running.1.insert(key.to_string());
+    }
+
+    fn release(&self, key: &str) {
+        let mut running = self.running.lock().unwrap();
+        running.0 -= 1;
+        running.1.remove(key);
+        self.changed.notify_all();
+    }
+}
+
+/// The artifact of `kind` derived from content hash `input` with `params`:
+/// from the cache when it's there, otherwise computed on the pool and
+/// cached. Meant to be called off the main thread.
+pub(crate) fn cached(
+    app: &AppHandle,
+    kind: &str,
+    input: &str,
+    params: &Value,
+    compute: impl FnOnce() -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, String> {
+    let profile = app.state::<ProfileState>().current();
+    let key = cache_key(kind, input, params);
+    if let Some(hit) = lookup(&profile, kind, &key) {
+        return Ok(hit);
+    }
+    let pool = app.state::<Compute>();
+    pool.acquire(&key);
+    // Whoever held the key before us may have just cached it.
+    let result = match lookup(&profile, kind, &key) {
+        Some(hit) => Ok(hit),
+        None => compute().inspect(|data| {
+            let entry = CacheEntry {
+                key: key.clone(),
+                kind: kind.to_string(),
+                input: input.to_string(),
+                params: canonical(params),
+                size: data.len() as u64,
+                created_at: crate::time::now_ms(app),
+            };
+            store(&profile, &entry, data);
+        }),
+    };
+    pool.release(&key);
+    result
+}
+
+/// BLAKE3 of a file's contents, remembered until its size or mtime changes.
+pub(crate) fn content_hash(app: &AppHandle, path: &Path) -> Result<String, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let modified = meta.modified().map_err(|e| e.to_string())?;
+    let pool = app.state::<Compute>();
+    if let Some((size, mtime, hash)) = pool.hashes.lock().unwrap().get(path) {
+        if *size == meta.len() && *mtime == modified {
+            return Ok(hash.clone());
+        }
+    }
+    let mut hasher = blake3::Hasher::new();
+    hasher
+        .update_reader(File::open(path).map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())?;
+    let hash = hasher.finalize().to_hex().to_string();
+    pool.hashes
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (meta.len(), modified, hash.clone()));
+    Ok(hash)
+}
+
+fn entries(profile: &Profile) -> Vec<(CacheEntry, PathBuf, PathBuf)> {
+    walkdir::WalkDir::new(cache_root(profile))
+        .into_iter()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| {
+            let entry: CacheEntry = serde_json::from_slice(&fs::read(e.path()).ok()?).ok()?;
+            Some((entry, e.path().with_extension("bin"), e.into_path()))
+        })
+        .filter(|(_, data, _)| data.is_file())
+        .collect()
+}
+
+/// Min/max per bucket of each channel of an import's samples, as
+/// little-endian `f32` pairs, channel after channel.
+fn peaks(
+    samples: &Path,
+    channels: usize,
+    frames: u64,
+    buckets: u32,
+    job: &mut Job,
+) -> Result<Vec<u8>, String> {
+    let buckets = buckets as usize;
+    let mut out = vec![(f32::INFINITY, f32::NEG_INFINITY); channels * buckets];
+    let mut reader = BufReader::new(File::open(samples).map_err(|e| e.to_string())?);
+    let mut frame = vec![0u8; channels * 4];
+    for index in 0..frames {
+        reader.read_exact(&mut frame).map_err(|e| e.to_string())?;
+        let bucket = (index as u128 * buckets as u128 / frames.max(1) as u128) as usize;
+        for (channel, bytes) in frame.chunks_exact(4).enumerate() {
+            let value = f32::from_le_bytes(bytes.try_into().expect("4-byte chunk"));
+            if value.is_nan() {
+                continue;
+            }
+            let (min, max) = &mut out[channel * buckets + bucket];
+            *min = min.min(value);
+            *max = max.max(value);
+        }
+        if index.is_multiple_of(65536) {
+            job.check()?;
+            job.progress(index, frames);
+        }
+    }
+    Ok(out
+        .into_iter()
+        .flat_map(|(min, max)| {
+            // Buckets with no readings come out as NaN.
+            let (min, max) = if min > max {
+                (f32::NAN, f32::NAN)
+            } else {
+                (min, max)
+            };
+            [min.to_le_bytes(), max.to_le_bytes()]
+        })
+        .flatten()
+        .collect())
+}
+
+/// Waveform peaks of an import, `buckets` min/max pairs per channel (see
+/// `peaks`). Computed once per content and bucket count, then served from
+/// the cache.
+#[tauri::command]
+pub async fn compute_peaks(
+    app: AppHandle,
+    call: Call,
+    project: String,
+    import: String,
+    buckets: u32,
+) -> Result<Response, String> {
+    if import.is_empty() || !import.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid import id: {import}"));
+    }
+    let profile = app.state::<ProfileState>().current();
+    let dir = crate::sync::project_dir(&profile, &project)?
+        .join("imports")
+        .join(&import);
+    let buckets = buckets.clamp(1, MAX_BUCKETS);
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let meta: Value = serde_json::from_slice(
+            &fs::read(dir.join("meta.json")).map_err(|e| format!("{import}: {e}"))?,
+        )
+        .map_err(|e| e.to_string())?;
+        let channels = meta["channels"].as_array().map_or(0, Vec::len);
+        let frames = meta["frames"].as_u64().unwrap_or(0);
+        if channels == 0 {
+            return Err("the import has no channels".to_string());
+        }
+        let samples = dir.join("samples.f32");
+        let input = content_hash(&app, &samples)?;
+        let params = serde_json::json!({ "buckets": buckets, "channels": channels });
+        cached(&app, "peaks", &input, &params, || {
+            let mut job = Job::start(&app, "compute-peaks").within(call);
+            peaks(&samples, channels, frames, buckets, &mut job)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map(Response::new)
+}
+
+/// Cached artifacts matching `filter`, newest first.
+#[tauri::command]
+pub fn compute_cache_query(
+    profiles: State<'_, ProfileState>,
+    filter: Option<CacheFilter>,
+) -> Vec<CacheEntry> {
+    let filter = filter.unwrap_or_default();
+    let mut found: Vec<CacheEntry> = entries(&profiles.current())
+        .into_iter()
+        .map(|(entry, _, _)| entry)
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    found.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    found
+}
+
+/// Drop cached artifacts matching `filter` (all of them without one) so
+/// they are computed again next time. Returns how many were removed.
+#[tauri::command]
+pub fn compute_cache_invalidate(
+    profiles: State<'_, ProfileState>,
+    filter: Option<CacheFilter>,
+) -> u64 {
+    let filter = filter.unwrap_or_default();
+    let mut removed = 0;
+    for (entry, data, meta) in entries(&profiles.current()) {
+        if filter.matches(&entry) && fs::remove_file(&data).is_ok() {
+            let _ = fs::remove_file(&meta);
+            removed += 1;
+        }
+    }
+    removed
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::audit::{self, Action};
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Capabilities the user is asked about before a window may use them.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    /// Watching a folder the user didn't pick through a dialog.
+    Watch,
+    /// Opening a serial port.
+    Serial,
+    /// Reading frames from a camera.
+    Camera,
+    /// Accepting connections from the local network.
+    Server,
+}
+
+impl Capability {
+    fn describe(self, subject: Option<&str>) -> String {
+        let subject = subject.unwrap_or_default();
+        match self {
+            Capability::Watch => format!("watch the folder {subject} for changes"),
+            Capability::Serial => format!("open the serial port {subject}"),
+            Capability::Camera => "use the camera".into(),
+            Capability::Server => {
+                "start a server that devices on your network can connect to".into()
+            }
+        }
+    }
+}
+
+/// A capability the user allowed, for one window of one origin, kept in the
+/// profile's `consent.json`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Grant {
+    id: String,
+    capability: Capability,
+    /// The folder or port the grant covers; `None` for the camera and server.
+    subject: Option<String>,
+    origin: String,
+    window: String,
+    /// Unix ms.
+    granted_at: i64,
+}
+
+impl Grant {
+    fn covers(&self, request: &Request) -> bool {
+        self.capability == request.capability
+            && self.subject == request.subject
+            && self.origin == request.origin
+            && self.window == request.window
+    }
+}
+
+/// What a window is asking to do.
+struct Request {
+    capability: Capability,
+    subject: Option<String>,
+    origin: String,
+    window: String,
+}
+
+impl Request {
+    fn granted(&self, profile: &Profile) -> bool {
+        grants(profile).iter().any(|g| g.covers(self))
+    }
+}
+
+/// Managed state: held while a prompt is up, so one question is asked at a
+/// time and a second request for the same thing sees the first's answer.
+#[derive(Default)]
+pub struct Consent(Mutex<()>);
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("consent.json")
+}
+
+fn grants(profile: &Profile) -> Vec<Grant> {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save(profile: &Profile, grants: &[Grant]) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(grants).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(profile), &bytes)
+}
+
+/// Whether some window was allowed `capability` on `subject` in `profile`,
+/// for work that goes on with no window asking, like auto-import rules.
+pub(crate) fn granted_anywhere(profile: &Profile, capability: Capability, subject: &str) -> bool {
+    grants(profile)
+        .iter()
+        .any(|g| g.capability == capability && g.subject.as_deref() == Some(subject))
+}
+
+/// Scheme and host of the page loaded in `window`, e.g. `tauri://localhost`.
+fn origin(window: &WebviewWindow) -> Result<String, String> {
+    let url = window.url().map_err(|e| e.to_string())?;
+    Ok(match url.port() {
+        Some(port) => format!(
+            "{}://{}:{port}",
+            url.scheme(),
+            url.host_str().unwrap_or_default()
+        ),
+        None => format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()),
+    })
+}
+
+fn ask(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    capability: Capability,
+    subject: Option<&str>,
+) -> bool {
+    let title = window.title().unwrap_or_else(|_| "Spectrus".into());
+    app.dialog()
+        .message(format!(
+            "“{title}” wants to {}. You can revoke this later in Settings.",
+            capability.describe(subject)
+        ))
+        .title("Allow access?")
+        .kind(MessageDialogKind::Warning)
+        .parent(window)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Allow".into(),
+            "Don't Allow".into(),
+        ))
+        .blocking_show()
+}
+
+/// Succeed if `window` may use `capability` on `subject`, asking the user
+/// with a native prompt the first time. An allowed request is remembered
+/// for that window, origin and profile; a refused one is asked again next
+/// time.
+pub async fn require(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    capability: Capability,
+    subject: Option<&str>,
+) -> Result<(), String> {
+    let request = Request {
+        capability,
+        subject: subject.map(str::to_string),
+        origin: origin(window)?,
+        window: window.label().to_string(),
+    };
+    if request.granted(&app.state::<ProfileState>().current()) {
+        return Ok(());
+    }
+
+    let (app, window) = (app.clone(), window.clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        let consent = app.state::<Consent>();
+        let _asking = consent.0.lock().unwrap();
+        let profile = app.state::<ProfileState>().current();
+        if request.granted(&profile) {
+            return Ok(());
+        }
+        let Request {
+            capability,
+            subject,
+            origin,
+            window: label,
+        } = request;
+        if !ask(&app, &window, capability, subject.as_deref()) {
+            return Err(format!(
+                "permission denied: {}",
+                capability.describe(subject.as_deref())
+            ));
+        }
+        let mut bytes = [0u8; 8];
+        getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+        let grant = Grant {
+            id: bytes.iter().map(|b| format!("{b:02x}")).collect(),
+            capability,
+            subject,
+            origin,
+            window: label,
+            granted_at: crate::time::now_ms(&app),
+        };
+        let mut all = grants(&profile);
+        all.push(grant.clone());
+        save(&profile, &all)?;
+        audit::record(&app, Action::PermissionGrant, serde_json::json!(grant));
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Everything the user has allowed in the active profile, newest first.
+#[tauri::command]
+pub fn consent_grants(profiles: State<'_, ProfileState>) -> Vec<Grant> {
+    let mut all = grants(&profiles.current());
+    all.sort_by_key(|g| std::cmp::Reverse(g.granted_at));
+    all
+}
+
+/// Withdraw a grant. The capability is asked about again the next time it
+/// is used; anything already running (an open port, a watch) keeps going
+/// until it is stopped, except auto-import rules, which stop watching
+/// folders no longer granted.
+#[tauri::command]
+pub fn consent_revoke(app: AppHandle, id: String) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    let mut all = grants(&profile);
+    let index = all.iter().position(|g| g.id == id).ok_or("no such grant")?;
+    let grant = all.remove(index);
+    save(&profile, &all)?;
+    audit::record(&app, Action::PermissionRevoke, serde_json::json!(grant));
+    if grant.capability == Capability::Watch {
+        crate::auto_import::reload(&app);
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+use crate::audit::Action;
+use crate::keychain;
+use crate::profile::ProfileState;
+use crate::settings;
+
+/// Keychain entry holding this device's PKCS#8 Ed25519 key, base64url.
+const DEVICE_KEY: &str = "spectrus:device-key";
+
+/// Path of a signed link under either scheme:
+/// `spectrus://link?p=…` or `<web origin>/link?p=…`.
+const LINK_PATH: &str = "link";
+
+/// The web page at the fallback URL hands the link to the installed app, or
+/// offers the download.
+const DEFAULT_WEB_ORIGIN: &str = "https://open.spectrus.app";
+
+const DEFAULT_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+const MAX_TTL_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// What a link signs. Kept short since it ends up in the URL.
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    #[serde(rename = "r")]
+    route: String,
+    #[serde(rename = "p", default)]
+    params: BTreeMap<String, String>,
+    /// Unix seconds.
+    #[serde(rename = "e")]
+    expires: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedLink {
+    url: String,
+    /// For opening the link on machines without the app (or where the
+    /// scheme isn't clickable, like most chat apps).
+    web_url: String,
+    expires_at: i64,
+}
+
+/// A link that passed `verify`, also the payload of
+/// `spectrus://deep-link-verified`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedLink {
+    /// The plain `spectrus://` route, as the router takes it.
+    url: String,
+    route: String,
+    params: BTreeMap<String, String>,
+    expires_at: i64,
+    /// base64url public key of the device that created the link.
+    signer: String,
+    /// What the signer is called among the trusted devices; `None` for
+    /// this device's own links.
+    signer_name: Option<String>,
+    /// Created on this device, as opposed to a teammate's.
+    own: bool,
+}
+
+/// The plain `spectrus://` link the frontend router understands.
+fn route_url(route: &str, params: &BTreeMap<String, String>) -> Result<Url, String> {
+    let mut url = Url::parse(&format!("spectrus://{route}")).map_err(|e| e.to_string())?;
+    if !params.is_empty() {
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    Ok(url)
+}
+
+/// Optional `deepLink.webOrigin` setting, for self-hosted deployments.
+fn web_origin(app: &AppHandle) -> String {
+    let profile = app.state::<ProfileState>().current();
+    settings::get(&profile, "deepLink.webOrigin")
+        .and_then(|v| v.as_str().map(|s| s.trim_end_matches('/').to_string()))
+        .unwrap_or_else(|| DEFAULT_WEB_ORIGIN.into())
+}
+
+/// This device's signing key, created on first use.
+pub(crate) fn device_key(app: &AppHandle) -> Result<Ed25519KeyPair, String> {
+    keychain::audit_backend(app, Action::KeychainRead, DEVICE_KEY);
+    let pkcs8 = match keychain::read(app, DEVICE_KEY)? {
+        Some(stored) => URL_SAFE_NO_PAD.decode(stored).map_err(|e| e.to_string())?,
+        None => {
+            keychain::audit_backend(app, Action::KeychainWrite, DEVICE_KEY);
+            let generated =
+                Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|e| e.to_string())?;
+            keychain::write(app, DEVICE_KEY, URL_SAFE_NO_PAD.encode(generated.as_ref()))?;
+            generated.as_ref().to_vec()
+        }
+    };
+    Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|e| e.to_string())
+}
+
+fn is_signed(url: &Url, web_origin: &str) -> bool {
+    match url.scheme() {
+        "spectrus" => url.host_str() == Some(LINK_PATH),
+        "https" => {
+            url.origin().ascii_serialization() == web_origin
+                && url.path().trim_matches('/') == LINK_PATH
+        }
+        _ => false,
+    }
+}
+
+/// Check a signed link's signature and expiry, and that it was signed by
+/// this device or one the profile trusts for sync. The key travels in the
+/// link, so the signature alone proves nothing about who made it.
+fn verify(app: &AppHandle, url: &Url) -> Result<VerifiedLink, String> {
+    let query: BTreeMap<_, _> = url.query_pairs().collect();
+    let field = |name: &str| {
+        query
+            .get(name)
+            .ok_or_else(|| format!("link is missing `{name}`"))
+    };
+    let payload = field("p")?;
+    let key = URL_SAFE_NO_PAD
+        .decode(field("k")?.as_bytes())
+        .map_err(|_| "malformed link key")?;
+    let signer = URL_SAFE_NO_PAD.encode(&key);
+    let own = device_key(app).is_ok_and(|k| k.public_key().as_ref() == key.as_slice());
+    let signer_name = if own {
+        None
+    } else {
+        Some(
+            crate::sync::trusted_name(app, &signer)
+                .ok_or("link was signed by a device this profile doesn't trust")?,
+        )
+    };
+    let signature = URL_SAFE_NO_PAD
+        .decode(field("s")?.as_bytes())
+        .map_err(|_| "malformed link signature")?;
+    UnparsedPublicKey::new(&ED25519, &key)
+        .verify(payload.as_bytes(), &signature)
+        .map_err(|_| "link signature does not match")?;
+
+    let decoded = URL_SAFE_NO_PAD
+        .decode(payload.as_bytes())
+        .map_err(|_| "malformed link payload")?;
+    let payload: Payload = serde_json::from_slice(&decoded).map_err(|e| e.to_string())?;
+    // Use the corrected clock so a machine set to the past can't revive links.
+    if crate::time::now_ms(app) / 1000 > payload.expires {
+        return Err("link has expired".into());
+    }
+    Ok(VerifiedLink {
+        url: route_url(&payload.route, &payload.params)?.to_string(),
+        route: payload.route,
+        params: payload.params,
+        expires_at: payload.expires,
+        signer,
+        signer_name,
+        own,
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct Rejected {
+    url: String,
+    reason: String,
+}
+
+/// Entry point for every link the OS or command line hands us. Signed links
+/// are verified and forwarded as `spectrus://deep-link-verified`, with their
+/// plain route and who signed them; bad ones go to
+/// `spectrus://deep-link-rejected` instead. Anything else passes through
+/// unchanged as `spectrus://deep-link`.
+pub fn open(app: &AppHandle, raw: &str) {
+    let url = match Url::parse(raw) {
+        Ok(url) if is_signed(&url, &web_origin(app)) => url,
+        _ => {
+            if let Err(e) = app.emit("spectrus://deep-link", raw) {
+                eprintln!("deep-link emit error: {e}");
+            }
+            return;
+        }
+    };
+    let emitted = match verify(app, &url) {
+        Ok(link) => app.emit("spectrus://deep-link-verified", link),
+        Err(reason) => app.emit(
+            "spectrus://deep-link-rejected",
+            Rejected {
+                url: raw.to_string(),
+                reason,
+            },
+        ),
+    };
+    if let Err(e) = emitted {
+        eprintln!("deep-link emit error: {e}");
+    }
+}
+
+/// Create a shareable link to `route` (e.g. `project/42/timeline`) with
+/// `params` as its query, signed with this device's key and valid for `ttl`
+/// seconds (default a week, at most 90 days).
+#[tauri::command]
+pub fn deep_link_create(
+    app: AppHandle,
+    route: String,
+    params: Option<BTreeMap<String, String>>,
+    ttl: Option<u64>,
+) -> Result<SignedLink, String> {
+    let route = route.trim_matches('/').to_string();
+    if route.is_empty()
+        || route.contains(['?', '#', ':'])
+        || route.split('/').next() == Some(LINK_PATH)
+    {
+        return Err(format!("{route:?}: not a route"));
+    }
+    let ttl = ttl.unwrap_or(DEFAULT_TTL_SECS);
+    if ttl == 0 || ttl > MAX_TTL_SECS {
+        return Err(format!("ttl must be between 1 and {MAX_TTL_SECS} seconds"));
+    }
+    let expires = crate::time::now_ms(&app) / 1000 + ttl as i64;
+    let payload = Payload {
+        route,
+        params: params.unwrap_or_default(),
+        expires,
+    };
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&payload).map_err(|e| e.to_string())?);
+    let key = device_key(&app)?;
+    let signature = key.sign(payload.as_bytes());
+
+    let query = [
+        ("p", payload),
+        ("k", URL_SAFE_NO_PAD.encode(key.public_key())),
+        ("s", URL_SAFE_NO_PAD.encode(signature)),
+    ];
+    let mut url = Url::parse(&format!("spectrus://{LINK_PATH}")).expect("static URL");
+    url.query_pairs_mut().extend_pairs(&query);
+    let mut web_url =
+        Url::parse(&format!("{}/{LINK_PATH}", web_origin(&app))).map_err(|e| e.to_string())?;
+    web_url.query_pairs_mut().extend_pairs(&query);
+    Ok(SignedLink {
+        url: url.to_string(),
+        web_url: web_url.to_string(),
+        expires_at: expires,
+    })
+}
+
+/// Verify a signed link without opening it, e.g. one pasted into the app.
+#[tauri::command]
+pub fn deep_link_verify(app: AppHandle, url: String) -> Result<VerifiedLink, String> {
+    let url = Url::parse(&url).map_err(|e| e.to_string())?;
+    if !is_signed(&url, &web_origin(&app)) {
+        return Err("not a signed Spectrus link".into());
+    }
+    verify(&app, &url)
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, State, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+use crate::fs_scope::FsScope;
+use crate::profile::ProfileState;
+use crate::settings;
+
+/// Why a dialog is being shown. Each purpose remembers its own last folder and
+/// carries its own extension filters.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Purpose {
+    Import,
+    Export,
+    Attach,
+}
+
+type Filter = (&'static str, &'static [&'static str]);
+
+impl Purpose {
+    fn name(self) -> &'static str {
+        match self {
+            Purpose::Import => "import",
+            Purpose::Export => "export",
+            Purpose::Attach => "attach",
+        }
+    }
+
+    /// Filters shown in the dialog; the first one's first extension is the
+    /// default for save targets typed without one.
+    fn filters(self) -> &'static [Filter] {
+        match self {
+            Purpose::Import => &[
+                ("Recordings", &["wav", "flac", "mp3", "ogg", "opus"]),
+                ("Data", &["csv", "json", "zip"]),
+            ],
+            Purpose::Export => &[
+                ("Zip archive", &["zip"]),
+                ("CSV", &["csv"]),
+                ("PDF", &["pdf"]),
+                ("JSON", &["json"]),
+            ],
+            Purpose::Attach => &[
+                ("Images", &["png", "jpg", "jpeg", "gif", "webp"]),
+                ("Documents", &["pdf", "txt", "md"]),
+                ("Audio", &["wav", "flac", "mp3", "ogg", "opus"]),
+            ],
+        }
+    }
+
+    fn setting_key(self) -> String {
+        format!("dialog.last_dir.{}", self.name())
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct OpenOptions {
+    multiple: bool,
+    directory: bool,
+    title: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct SaveOptions {
+    default_name: Option<String>,
+    min_free_bytes: Option<u64>,
+    create_dirs: bool,
+    title: Option<String>,
+}
+
+fn allowed_extension(purpose: Purpose, path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    purpose
+        .filters()
+        .iter()
+        .flat_map(|(_, exts)| exts.iter())
+        .any(|e| e.eq_ignore_ascii_case(ext))
+}
+
+fn builder(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    profiles: &ProfileState,
+    purpose: Purpose,
+    title: Option<String>,
+) -> FileDialogBuilder<tauri::Wry> {
+    let mut dialog = app.dialog().file().set_parent(window);
+    for (name, exts) in purpose.filters() {
+        dialog = dialog.add_filter(*name, exts);
+    }
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    let last = settings::get(&profiles.current(), &purpose.setting_key());
+    if let Some(dir) = last.as_ref().and_then(Value::as_str).map(Path::new) {
+        if dir.is_dir() {
+            dialog = dialog.set_directory(dir);
+        }
+    }
+    dialog
+}
+
+fn remember_dir(profiles: &ProfileState, purpose: Purpose, dir: Option<&Path>) {
+    let Some(dir) = dir else {
+        return;
+    };
+    let value = Value::String(dir.to_string_lossy().into_owned());
+    if let Err(e) = settings::set(&profiles.current(), &purpose.setting_key(), value) {
+        eprintln!("dialogs: could not remember folder: {e}");
+    }
+}
+
+/// Fail unless something can actually be written into `dir`.
+fn check_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(".spectrus-write-test");
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {e}", dir.display()))
+}
+
+fn check_free_space(dir: &Path, needed: u64) -> Result<(), String> {
+    let free = fs4::available_space(dir).map_err(|e| e.to_string())?;
+    if free < needed {
+        return Err(format!(
+            "not enough disk space in {}: {} MB free, {} MB needed",
+            dir.display(),
+            free / 1_000_000,
+            needed.div_ceil(1_000_000)
+        ));
+    }
+    Ok(())
+}
+
+/// Show a native open dialog. Selected paths are granted to the calling
+/// window's file scope and returned; `null` means the user cancelled.
+#[tauri::command]
+pub async fn dialog_open(
+    app: AppHandle,
+    window: WebviewWindow,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    purpose: Purpose,
+    options: Option<OpenOptions>,
+) -> Result<Option<Vec<PathBuf>>, String> {
+    let OpenOptions {
+        multiple,
+        directory,
+        title,
+    } = options.unwrap_or_default();
+    let dialog = builder(&app, &window, &profiles, purpose, title);
+    let picked = tauri::async_runtime::spawn_blocking(move || match (directory, multiple) {
+        (true, true) => dialog.blocking_pick_folders(),
+        (true, false) => dialog.blocking_pick_folder().map(|p| vec![p]),
+        (false, true) => dialog.blocking_pick_files(),
+        (false, false) => dialog.blocking_pick_file().map(|p| vec![p]),
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let paths = picked
+        .into_iter()
+        .map(|p| p.into_path().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let dir = paths.first().and_then(|p| {
+        if directory {
+            Some(p.as_path())
+        } else {
+            p.parent()
+        }
+    });
+    remember_dir(&profiles, purpose, dir);
+    for path in &paths {
+        scope.grant(window.label(), path);
+    }
+    Ok(Some(paths))
+}
+
+/// Show a native save dialog and validate the choice before returning it: the
+/// extension must match the purpose's filters (the default one is appended if
+/// missing), the folder must be writable, and at least `min_free_bytes` must be
+/// free. With `create_dirs`, missing parent folders are created. The target is
+/// granted to the calling window's file scope.
+#[tauri::command]
+pub async fn dialog_save(
+    app: AppHandle,
+    window: WebviewWindow,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    purpose: Purpose,
+    options: Option<SaveOptions>,
+) -> Result<Option<PathBuf>, String> {
+    let SaveOptions {
+        default_name,
+        min_free_bytes,
+        create_dirs,
+        title,
+    } = options.unwrap_or_default();
+    let mut dialog =
+        builder(&app, &window, &profiles, purpose, title).set_can_create_directories(true);
+    if let Some(name) = default_name {
+        dialog = dialog.set_file_name(name);
+    }
+    let picked = tauri::async_runtime::spawn_blocking(move || dialog.blocking_save_file())
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let mut path = picked.into_path().map_err(|e| e.to_string())?;
+
+    if path.extension().is_none() {
+        path.set_extension(purpose.filters()[0].1[0]);
+    }
+    if !allowed_extension(purpose, &path) {
+        return Err(format!(
+            "{} has an unsupported extension for this export",
+            path.display()
+        ));
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("{} has no parent folder", path.display()))?
+        .to_path_buf();
+    if !parent.is_dir() {
+        if create_dirs {
+            fs::create_dir_all(&parent).map_err(|e| e.to_string())?;
+        } else {
+            return Err(format!("{} does not exist", parent.display()));
+        }
+    }
+    check_writable(&parent)?;
+    if let Some(needed) = min_free_bytes {
+        check_free_space(&parent, needed)?;
+    }
+
+    remember_dir(&profiles, purpose, Some(&parent));
+    scope.grant(window.label(), &path);
+    Ok(Some(path))
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// DNS-SD service type every instance advertises and browses for.
+const SERVICE_TYPE: &str = "_spectrus._tcp.local.";
+
+/// `false` keeps this instance off the network: no advertising, no browsing.
+const ENABLED_SETTING: &str = "discovery.enabled";
+
+/// Another instance seen on the local network.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Peer {
+    /// Stable per-profile id from the peer's TXT record.
+    pub(crate) id: String,
+    /// The peer's host name, for display.
+    pub(crate) name: String,
+    version: String,
+    pub(crate) addresses: Vec<IpAddr>,
+    /// Port of the peer's handoff server, `None` while it is off.
+    handoff_port: Option<u16>,
+    /// Port of the peer's sync listener.
+    pub(crate) sync_port: Option<u16>,
+    /// The peer's base64url Ed25519 device key, which sync authenticates.
+    pub(crate) key: Option<String>,
+}
+
+/// Payload of `spectrus://peer-disappeared`.
+#[derive(Clone, Serialize)]
+struct PeerGone<'a> {
+    id: &'a str,
+}
+
+/// What this instance puts in its advertisement.
+#[derive(Clone, Default)]
+struct Advert {
+    id: String,
+    handoff_port: Option<u16>,
+    sync_port: Option<u16>,
+    key: Option<String>,
+}
+
+/// Managed state: the mDNS daemon once started, what this instance
+/// advertises, and peers by their DNS-SD full name.
+#[derive(Default)]
+pub struct Discovery {
+    daemon: Mutex<Option<ServiceDaemon>>,
+    advertised: Mutex<Option<Advert>>,
+    peers: Mutex<HashMap<String, Peer>>,
+}
+
+fn id_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("device-id")
+}
+
+/// Random id kept with the profile, so peers can tell instances on the same
+/// host apart and recognise them across restarts.
+fn device_id(profile: &Profile) -> Result<String, String> {
+    if let Ok(id) = fs::read_to_string(id_path(profile)) {
+        if !id.trim().is_empty() {
+            return Ok(id.trim().to_string());
+        }
+    }
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    let id: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    settings::write_atomic(&id_path(profile), id.as_bytes())?;
+    Ok(id)
+}
+
+/// Host name reduced to what an mDNS label allows.
+fn host_label() -> String {
+    let host = gethostname::gethostname().to_string_lossy().into_owned();
+    let label: String = host
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if label.is_empty() {
+        "spectrus".into()
+    } else {
+        label
+    }
+}
+
+fn service(advert: &Advert) -> Result<ServiceInfo, String> {
+    let host = host_label();
+    let id = advert.id.as_str();
+    let mut properties = vec![
+        ("id", id.to_string()),
+        ("name", host.clone()),
+        ("version", env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let (Some(port), Some(key)) = (advert.sync_port, &advert.key) {
+        properties.push(("sync", port.to_string()));
+        properties.push(("key", key.clone()));
+    }
+    // Port 0 in the SRV record means the handoff server is off.
+    ServiceInfo::new(
+        SERVICE_TYPE,
+        &format!("{host}-{}", id.get(..8).unwrap_or(id)),
+        &format!("{host}.local."),
+        "",
+        advert.handoff_port.unwrap_or(0),
+        &properties[..],
+    )
+    .map(ServiceInfo::enable_addr_auto)
+    .map_err(|e| e.to_string())
+}
+
+fn peer(info: &ServiceInfo) -> Option<Peer> {
+    let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+    addresses.sort();
+    Some(Peer {
+        id: info.get_property_val_str("id")?.to_string(),
+        name: info
+            .get_property_val_str("name")
+            .unwrap_or(info.get_hostname())
+            .to_string(),
+        version: info
+            .get_property_val_str("version")
+            .unwrap_or_default()
+            .to_string(),
+        addresses,
+        handoff_port: Some(info.get_port()).filter(|p| *p != 0),
+        sync_port: info
+            .get_property_val_str("sync")
+            .and_then(|p| p.parse().ok()),
+        key: info.get_property_val_str("key").map(str::to_string),
+    })
+}
+
+fn on_event(app: &AppHandle, own_id: &str, event: ServiceEvent) {
+    let discovery = app.state::<Discovery>();
+    match event {
+        ServiceEvent::ServiceResolved(info) => {
+            let Some(peer) = peer(&info).filter(|p| p.id != own_id) else {
+                return;
+            };
+            discovery
+                .peers
+                .lock()
+                .unwrap()
+                .insert(info.get_fullname().to_string(), peer.clone());
+            let _ = app.emit("spectrus://peer-appeared", peer);
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            let gone = discovery.peers.lock().unwrap().remove(&fullname);
+            if let Some(peer) = gone {
+                let _ = app.emit("spectrus://peer-disappeared", PeerGone { id: &peer.id });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Advertise this instance and watch for others. Peers coming and going
+/// emit `spectrus://peer-appeared` and `spectrus://peer-disappeared`.
+/// Skipped for throwaway profiles and when `discovery.enabled` is `false`.
+pub fn start(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    if !enabled(&profile) {
+        return;
+    }
+    if let Err(e) = try_start(app, &profile) {
+        eprintln!("discovery: {e}");
+    }
+}
+
+/// Whether this profile takes part in discovery at all.
+pub fn enabled(profile: &Profile) -> bool {
+    !profile.ephemeral && settings::get(profile, ENABLED_SETTING) != Some(Value::Bool(false))
+}
+
+fn try_start(app: &AppHandle, profile: &Profile) -> Result<(), String> {
+    let advert = Advert {
+        id: device_id(profile)?,
+        ..Default::default()
+    };
+    let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    daemon
+        .register(service(&advert)?)
+        .map_err(|e| e.to_string())?;
+    let events = daemon.browse(SERVICE_TYPE).map_err(|e| e.to_string())?;
+
+    let id = advert.id.clone();
+    let discovery = app.state::<Discovery>();
+    *discovery.advertised.lock().unwrap() = Some(advert);
+    *discovery.daemon.lock().unwrap() = Some(daemon);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        while let Ok(event) = events.recv() {
+            on_event(&app, &id, event);
+        }
+    });
+    Ok(())
+}
+
+fn readvertise(app: &AppHandle, change: impl FnOnce(&mut Advert)) {
+    let discovery = app.state::<Discovery>();
+    let daemon = discovery.daemon.lock().unwrap();
+    let mut advertised = discovery.advertised.lock().unwrap();
+    let (Some(daemon), Some(advert)) = (daemon.as_ref(), advertised.as_mut()) else {
+        return;
+    };
+    change(advert);
+    let registered =
+        service(advert).and_then(|info| daemon.register(info).map_err(|e| e.to_string()));
+    if let Err(e) = registered {
+        eprintln!("discovery: re-advertise failed: {e}");
+    }
+}
+
+/// Re-advertise with the handoff server's port, or without one when it
+/// stops, so peers know where to send files.
+pub fn set_handoff_port(app: &AppHandle, port: Option<u16>) {
+    readvertise(app, |advert| advert.handoff_port = port);
+}
+
+/// Advertise the sync listener and the device key it authenticates with.
+pub fn set_sync(app: &AppHandle, port: u16, key: String) {
+    readvertise(app, |advert| {
+        advert.sync_port = Some(port);
+        advert.key = Some(key);
+    });
+}
+
+/// Re-advertise with another device key, after an account switch.
+pub fn set_key(app: &AppHandle, key: String) {
+    readvertise(app, |advert| advert.key = Some(key));
+}
+
+/// A peer currently visible, by its id.
+pub(crate) fn peer_by_id(app: &AppHandle, id: &str) -> Option<Peer> {
+    let discovery = app.state::<Discovery>();
+    let peers = discovery.peers.lock().unwrap();
+    peers.values().find(|p| p.id == id).cloned()
+}
+
+/// Instances currently visible on the local network, excluding this one.
+#[tauri::command]
+pub fn peers_list(discovery: State<'_, Discovery>) -> Vec<Peer> {
+    let mut peers: Vec<Peer> = discovery.peers.lock().unwrap().values().cloned().collect();
+    peers.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    peers
+}
@@ -0,0 +1,374 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+use walkdir::WalkDir;
+
+use crate::jobs::Job;
+use crate::keychain;
+use crate::portable::Portable;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// `true` once the user has turned encryption at rest on.
+const ENABLED_SETTING: &str = "encryption.atRest";
+
+/// Keychain entry, in the profile's namespace, holding the 256-bit data key
+/// as base64url.
+const DATA_KEY: &str = "spectrus:data-key";
+
+/// Prefix of every encrypted file, followed by the nonce and the AES-GCM
+/// ciphertext. Anything else is read as plaintext.
+const MAGIC: &[u8] = b"SPXENC1\0";
+
+/// Managed state: the active profile's data key once read from the
+/// keychain, and a write lock so migration and regular writes never
+/// interleave on the same file.
+#[derive(Default)]
+pub struct Encryption {
+    key: Mutex<Option<(PathBuf, [u8; 32])>>,
+    writing: Mutex<()>,
+    migrating: AtomicBool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    enabled: bool,
+    migrating: bool,
+    encrypted_files: usize,
+    /// Files still waiting for the migration to reach them (or, with
+    /// encryption off, already decrypted).
+    plain_files: usize,
+}
+
+/// Payload of `spectrus://encryption-migrated`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Migrated {
+    enabled: bool,
+    files: usize,
+}
+
+fn enabled(profile: &Profile) -> bool {
+    settings::get(profile, ENABLED_SETTING) == Some(Value::Bool(true))
+}
+
+/// Stores that hold what the user wrote: session drafts, reminders, the UI
+/// snapshot and captured notes (read back with `capture_list`). Files the
+/// frontend opens itself (imports, projects) are left as they are.
+fn covered(profile: &Profile) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = ["session.json", "reminders.json", "ui-snapshot.json"]
+        .iter()
+        .map(|name| profile.dir.join(name))
+        .filter(|path| path.is_file())
+        .collect();
+    files.extend(
+        WalkDir::new(profile.dir.join("captures"))
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .map(|e| e.into_path()),
+    );
+    files
+}
+
+/// Whether `path` holds encrypted data.
+pub fn is_sealed(path: &Path) -> bool {
+    let mut head = [0u8; MAGIC.len()];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut head))
+        .is_ok_and(|()| head == MAGIC)
+}
+
+fn cached_key(app: &AppHandle, profile: &Profile) -> Option<[u8; 32]> {
+    let state = app.state::<Encryption>();
+    let cached = state.key.lock().unwrap();
+    cached
+        .as_ref()
+        .filter(|(dir, _)| *dir == profile.dir)
+        .map(|(_, key)| *key)
+}
+
+fn remember_key(app: &AppHandle, profile: &Profile, key: [u8; 32]) -> [u8; 32] {
+    *app.state::<Encryption>().key.lock().unwrap() = Some((profile.dir.clone(), key));
+    key
+}
+
+fn data_key(app: &AppHandle, profile: &Profile) -> Result<[u8; 32], String> {
+    if let Some(key) = cached_key(app, profile) {
+        return Ok(key);
+    }
+    let stored = keychain::read_profile_secret(app, DATA_KEY)?.ok_or("the data key is missing")?;
+    let key = URL_SAFE_NO_PAD
+        .decode(stored)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .ok_or("the stored data key is malformed")?;
+    Ok(remember_key(app, profile, key))
+}
+
+/// The data key, generated and stored if this profile has none yet.
+fn ensure_key(app: &AppHandle, profile: &Profile) -> Result<[u8; 32], String> {
+    match data_key(app, profile) {
+        Ok(key) => Ok(key),
+        Err(_) if keychain::read_profile_secret(app, DATA_KEY)?.is_none() => {
+            let mut key = [0u8; 32];
+            getrandom::fill(&mut key).map_err(|e| e.to_string())?;
+            keychain::write_profile_secret(app, DATA_KEY, &URL_SAFE_NO_PAD.encode(key))?;
+            Ok(remember_key(app, profile, key))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn cipher(key: &[u8; 32]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("32-byte AES key"))
+}
+
+fn seal(key: &[u8; 32], plain: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|e| e.to_string())?;
+    let mut body = plain.to_vec();
+    cipher(key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut body,
+        )
+        .map_err(|_| "encryption failed")?;
+    Ok([MAGIC, &nonce, &body].concat())
+}
+
+fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, String> {
+    let rest = &sealed[MAGIC.len()..];
+    if rest.len() < NONCE_LEN {
+        return Err("truncated file".into());
+    }
+    let (nonce, body) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "truncated file")?;
+    let mut body = body.to_vec();
+    let len = cipher(key)
+        .open_in_place(nonce, Aad::from(MAGIC), &mut body)
+        .map_err(|_| "wrong key or damaged file")?
+        .len();
+    body.truncate(len);
+    Ok(body)
+}
+
+/// Contents of a covered file, decrypted if it is encrypted. Plain files
+/// read as they are, so data not yet migrated stays usable. `None` if the
+/// file is missing or can't be decrypted, e.g. while the keychain is locked.
+pub fn read(app: &AppHandle, path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    if !bytes.starts_with(MAGIC) {
+        return Some(bytes);
+    }
+    let profile = app.state::<ProfileState>().current();
+    match data_key(app, &profile).and_then(|key| open(&key, &bytes)) {
+        Ok(plain) => Some(plain),
+        Err(e) => {
+            eprintln!("encryption: cannot read {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// `settings::write_atomic` for covered files, encrypting first while
+/// encryption is on. Fails rather than fall back to plaintext when the key
+/// can't be had.
+pub fn write(app: &AppHandle, path: &Path, data: &[u8]) -> Result<(), String> {
+    let state = app.state::<Encryption>();
+    let _guard = state.writing.lock().unwrap();
+    write_locked(app, path, data)
+}
+
+fn write_locked(app: &AppHandle, path: &Path, data: &[u8]) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    if enabled(&profile) {
+        let key = data_key(app, &profile)?;
+        settings::write_atomic(path, &seal(&key, data)?)
+    } else {
+        settings::write_atomic(path, data)
+    }
+}
+
+fn is_current(app: &AppHandle, profile: &Profile) -> bool {
+    app.state::<ProfileState>().current().dir == profile.dir
+}
+
+/// Rewrite every covered file of `profile` in mode `on`, one at a time so
+/// the app keeps working meanwhile. The key is looked up once, up front:
+/// the keychain answers for the active profile, and a switch midway must
+/// not seal the rest with the next profile's key.
+fn convert(
+    app: &AppHandle,
+    job: &mut Job,
+    profile: &Profile,
+    files: &[PathBuf],
+    on: bool,
+) -> Result<(), String> {
+    let state = app.state::<Encryption>();
+    let key = if files.iter().any(|path| is_sealed(path) != on) {
+        let key = if on {
+            ensure_key(app, profile)?
+        } else {
+            data_key(app, profile)?
+        };
+        if !is_current(app, profile) {
+            return Ok(());
+        }
+        Some(key)
+    } else {
+        None
+    };
+    for (i, path) in files.iter().enumerate() {
+        job.check()?;
+        let guard = state.writing.lock().unwrap();
+        if let Some(key) = key.as_ref().filter(|_| is_sealed(path) != on) {
+            let bytes = fs::read(path).map_err(|e| e.to_string())?;
+            let plain = if bytes.starts_with(MAGIC) {
+                open(key, &bytes)?
+            } else {
+                bytes
+            };
+            let out = if on { seal(key, &plain)? } else { plain };
+            settings::write_atomic(path, &out)?;
+        }
+        drop(guard);
+        job.progress(i as u64 + 1, files.len() as u64);
+    }
+    // Under the write lock, so `encryption_enable` can't slip in between
+    // the check and the delete, and only while the secret is this
+    // profile's.
+    let _guard = state.writing.lock().unwrap();
+    if !on && is_current(app, profile) && !enabled(profile) {
+        keychain::delete_profile_secret(app, DATA_KEY)?;
+        *state.key.lock().unwrap() = None;
+    }
+    Ok(())
+}
+
+/// Bring existing data in line with the setting as an "encryption-migrate"
+/// job, starting over if the setting flips or the profile switches midway.
+/// Emits `spectrus://encryption-migrated` when done.
+fn migrate(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<Encryption>();
+    if state.migrating.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let mut job = Job::start(app, "encryption-migrate");
+    let result = loop {
+        let profile = app.state::<ProfileState>().current();
+        let on = enabled(&profile);
+        let files = covered(&profile);
+        if let Err(e) = convert(app, &mut job, &profile, &files, on) {
+            break Err(e);
+        }
+        if is_current(app, &profile) && enabled(&profile) == on {
+            let _ = app.emit(
+                "spectrus://encryption-migrated",
+                Migrated {
+                    enabled: on,
+                    files: files.len(),
+                },
+            );
+            break Ok(());
+        }
+    };
+    state.migrating.store(false, Ordering::SeqCst);
+    result
+}
+
+fn migrate_in_background(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = migrate(&app) {
+            eprintln!("encryption: migration stopped: {e}");
+        }
+    });
+}
+
+/// Finish a migration the last run didn't get to complete, such as after
+/// quitting midway or importing plaintext data into an encrypted profile;
+/// also for each profile switched to later.
+pub fn start(app: &AppHandle) {
+    catch_up(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| catch_up(&handle));
+}
+
+fn catch_up(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    if profile.ephemeral {
+        return;
+    }
+    let on = enabled(&profile);
+    if covered(&profile).iter().any(|path| is_sealed(path) != on) {
+        if on {
+            if let Err(e) = ensure_key(app, &profile) {
+                return eprintln!("encryption: no data key: {e}");
+            }
+        }
+        migrate_in_background(app);
+    }
+}
+
+/// Encrypt the profile's stores with a key kept in the OS keychain. Returns
+/// once new writes are encrypted; existing data is converted in the
+/// background (see `encryption_status`). Not available in portable mode,
+/// where the key would sit next to the data, or in incognito sessions.
+#[tauri::command]
+pub fn encryption_enable(
+    app: AppHandle,
+    encryption: State<'_, Encryption>,
+    profiles: State<'_, ProfileState>,
+    portable: State<'_, Portable>,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    if portable.0.is_some() || profile.ephemeral {
+        return Err("encryption at rest needs the OS keychain".into());
+    }
+    {
+        let _guard = encryption.writing.lock().unwrap();
+        ensure_key(&app, &profile)?;
+        settings::set(&profile, ENABLED_SETTING, Value::Bool(true))?;
+    }
+    migrate_in_background(&app);
+    Ok(())
+}
+
+/// Turn encryption off: new writes are plaintext at once, existing data is
+/// decrypted in the background, and the key is deleted when that finishes.
+#[tauri::command]
+pub fn encryption_disable(app: AppHandle, profiles: State<'_, ProfileState>) -> Result<(), String> {
+    settings::set(&profiles.current(), ENABLED_SETTING, Value::Bool(false))?;
+    migrate_in_background(&app);
+    Ok(())
+}
+
+/// Whether encryption is on and how far converting existing data has got.
+#[tauri::command]
+pub fn encryption_status(
+    encryption: State<'_, Encryption>,
+    profiles: State<'_, ProfileState>,
+) -> EncryptionStatus {
+    let profile = profiles.current();
+    let files = covered(&profile);
+    let encrypted_files = files.iter().filter(|path| is_sealed(path)).count();
+    EncryptionStatus {
+        enabled: enabled(&profile),
+        migrating: encryption.migrating.load(Ordering::SeqCst),
+        encrypted_files,
+        plain_files: files.len() - encrypted_files,
+    }
+}
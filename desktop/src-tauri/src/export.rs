@@ -0,0 +1,269 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::fs_scope::FsScope;
+use crate::jobs::Job;
+use crate::profile::ProfileState;
+use crate::watchdog::{Call, Scope};
+
+/// Sheet rows available below the header row.
+const XLSX_MAX_ROWS: u64 = 1_048_575;
+
+/// Rows written between cancellation checks.
+const CHECK_EVERY: u64 = 4096;
+
+enum Writer {
+    Csv(Box<csv::Writer<File>>),
+    /// Constant-memory mode: rows are flushed to a temp file as they are
+    /// written, so the workbook never holds the whole table.
+    Xlsx(Box<Workbook>),
+}
+
+/// Which part of an import to export; all of it by default.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFilter {
+    /// Channel indices, in the order wanted as columns.
+    channels: Option<Vec<usize>>,
+    /// First frame to export.
+    from_frame: Option<u64>,
+    /// Frame to stop before.
+    to_frame: Option<u64>,
+}
+
+/// What `meta.json` says about an import's layout.
+struct Layout {
+    frames: u64,
+    /// Channel names, with the unit in brackets when there is one.
+    channels: Vec<String>,
+    timed: bool,
+}
+
+impl Layout {
+    fn read(dir: &Path, import: &str) -> Result<Self, String> {
+        let meta: Value = serde_json::from_slice(
+            &fs::read(dir.join("meta.json")).map_err(|e| format!("{import}: {e}"))?,
+        )
+        .map_err(|e| e.to_string())?;
+        let channels: Vec<String> = meta["channels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|c| {
+                let name = c["name"].as_str().unwrap_or_default();
+                match c["unit"].as_str() {
+                    Some(unit) => format!("{name} ({unit})"),
+                    None => name.to_string(),
+                }
+            })
+            .collect();
+        if channels.is_empty() {
+            return Err("the import has no channels".into());
+        }
+        Ok(Self {
+            frames: meta["frames"].as_u64().unwrap_or(0),
+            channels,
+            timed: meta["timed"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+impl Writer {
+    /// Create `path` with a header row; the format follows its extension.
+    fn create(path: &Path, columns: &[String]) -> Result<Self, String> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("csv") => {
+                let mut csv = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+                csv.write_record(columns).map_err(|e| e.to_string())?;
+                Ok(Writer::Csv(Box::new(csv)))
+            }
+            Some("xlsx") => {
+                if columns.len() > 16_384 {
+                    return Err("XLSX sheets hold at most 16,384 columns".into());
+                }
+                let mut workbook = Workbook::new();
+                let sheet = workbook.add_worksheet_with_constant_memory();
+                let bold = Format::new().set_bold();
+                for (c, name) in columns.iter().enumerate() {
+                    sheet
+                        .write_string_with_format(0, c as u16, name, &bold)
+                        .map_err(|e| e.to_string())?;
+                }
+                Ok(Writer::Xlsx(Box::new(workbook)))
+            }
+            _ => Err(format!("{}: export to .csv or .xlsx", path.display())),
+        }
+    }
+
+    /// Write data row `row` (0 is the first below the header): `first` in
+    /// the first column, then `values`.
+    fn write(&mut self, row: u64, first: f64, values: &[f32]) -> Result<(), String> {
+        match self {
+            Writer::Csv(csv) => csv
+                .write_record(
+                    std::iter::once(first.to_string()).chain(values.iter().map(f32::to_string)),
+                )
+                .map_err(|e| e.to_string()),
+            Writer::Xlsx(workbook) => {
+                if row >= XLSX_MAX_ROWS {
+                    return Err("XLSX sheets hold at most 1,048,575 data rows".into());
+                }
+                let sheet = workbook
+                    .worksheet_from_index(0)
+                    .map_err(|e| e.to_string())?;
+                let cells = std::iter::once(first).chain(values.iter().map(|&v| f64::from(v)));
+                for (c, value) in cells.enumerate() {
+                    // NaN and infinities have no number cell; leave them blank.
+                    if value.is_finite() {
+                        sheet
+                            .write_number(row as u32 + 1, c as u16, value)
+                            .map_err(|e| e.to_string())?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self, path: &Path) -> Result<(), String> {
+        match self {
+            Writer::Csv(mut csv) => csv.flush().map_err(|e| e.to_string()),
+            Writer::Xlsx(mut workbook) => workbook.save(path).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Stream frames `from..to` of the import in `dir` into `writer`: a time
+/// column (or the frame number for untimed imports), then the chosen
+/// channels. Returns the number of rows written.
+fn write_rows(
+    dir: &Path,
+    layout: &Layout,
+    channels: &[usize],
+    (from, to): (u64, u64),
+    writer: &mut Writer,
+    job: &mut Job,
+) -> Result<u64, String> {
+    let width = layout.channels.len();
+    let mut samples =
+        BufReader::new(File::open(dir.join("samples.f32")).map_err(|e| e.to_string())?);
+    samples
+        .seek(SeekFrom::Start(from * width as u64 * 4))
+        .map_err(|e| e.to_string())?;
+    let mut times = if layout.timed {
+        let mut file = BufReader::new(File::open(dir.join("time.f64")).map_err(|e| e.to_string())?);
+        file.seek(SeekFrom::Start(from * 8))
+            .map_err(|e| e.to_string())?;
+        Some(file)
+    } else {
+        None
+    };
+    let mut frame = vec![0u8; width * 4];
+    let mut values = Vec::with_capacity(channels.len());
+    let total = to - from;
+    for row in 0..total {
+        if row % CHECK_EVERY == 0 {
+            job.check()?;
+            job.progress(row, total);
+        }
+        samples.read_exact(&mut frame).map_err(|e| e.to_string())?;
+        let first = match times.as_mut() {
+            Some(times) => {
+                let mut time = [0u8; 8];
+                times.read_exact(&mut time).map_err(|e| e.to_string())?;
+                f64::from_le_bytes(time)
+            }
+            None => (from + row) as f64,
+        };
+        values.clear();
+        values.extend(
+            channels
+                .iter()
+                .map(|&c| f32::from_le_bytes(frame[c * 4..c * 4 + 4].try_into().unwrap())),
+        );
+        writer.write(row, first, &values)?;
+    }
+    job.progress(total, total);
+    Ok(total)
+}
+
+/// Export the import in `dir` to `path`, removing the partial file on
+/// failure or cancellation.
+fn export(
+    app: &AppHandle,
+    dir: &Path,
+    import: &str,
+    path: PathBuf,
+    filter: ExportFilter,
+    scope: Scope,
+) -> Result<u64, String> {
+    let layout = Layout::read(dir, import)?;
+    let channels = filter
+        .channels
+        .unwrap_or_else(|| (0..layout.channels.len()).collect());
+    if let Some(c) = channels.iter().find(|&&c| c >= layout.channels.len()) {
+        return Err(format!("the import has no channel {c}"));
+    }
+    let to = filter
+        .to_frame
+        .map_or(layout.frames, |t| t.min(layout.frames));
+    let from = filter.from_frame.unwrap_or(0).min(to);
+    let mut columns = vec![if layout.timed { "time" } else { "frame" }.to_string()];
+    columns.extend(channels.iter().map(|&c| layout.channels[c].clone()));
+
+    let mut writer = Writer::create(&path, &columns)?;
+    crate::audit::record(
+        app,
+        crate::audit::Action::Export,
+        serde_json::json!({ "kind": "table", "path": path, "import": import, "rows": to - from }),
+    );
+    let mut job = Job::start(app, "export").within(scope);
+    let written = write_rows(dir, &layout, &channels, (from, to), &mut writer, &mut job)
+        .and_then(|rows| writer.finish(&path).map(|()| rows));
+    if written.is_err() {
+        let _ = fs::remove_file(&path);
+    }
+    written
+}
+
+/// Export an import of `project` into `path` as a table, read straight from
+/// the project's store; the format follows the extension (`.csv` or
+/// `.xlsx`). `filter` picks channels and a frame range. Runs as an "export"
+/// job: progress arrives as `spectrus://job-progress`, and `job_cancel` or
+/// the window going away stops it and removes the partial file. Returns the
+/// number of rows written.
+#[tauri::command]
+pub async fn export_table(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    path: String,
+    project: String,
+    import: String,
+    filter: Option<ExportFilter>,
+) -> Result<u64, String> {
+    if import.is_empty() || !import.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid import id: {import}"));
+    }
+    let path = scope.check_new(call.window(), &path)?;
+    let profile = app.state::<ProfileState>().current();
+    let dir = crate::sync::project_dir(&profile, &project)?
+        .join("imports")
+        .join(&import);
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        export(&app, &dir, &import, path, filter.unwrap_or_default(), scope)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use tauri::ipc::{Channel, InvokeResponseBody, Response};
+use tauri::{State, Window};
+
+use crate::fs_scope::FsScope;
+use crate::watchdog::Call;
+
+/// Upper bound for a single `file_read_range` call. Larger previews should use
+/// `file_read_stream` so the renderer never holds one huge buffer.
+const MAX_RANGE: u64 = 16 * 1024 * 1024;
+
+/// Chunk size for `file_read_stream` when the caller doesn't pick one.
+const DEFAULT_CHUNK: u64 = 1024 * 1024;
+
+fn open_at(path: &PathBuf, offset: u64) -> Result<File, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+/// Read up to `len` bytes into a fresh buffer. Short reads only happen at EOF.
+fn read_up_to(file: &mut File, len: u64) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Size in bytes of a file the window has access to.
+#[tauri::command]
+pub fn file_size(window: Window, scope: State<'_, FsScope>, path: String) -> Result<u64, String> {
+    let path = scope.check(window.label(), &path)?;
+    std::fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| e.to_string())
+}
+
+/// Read `len` bytes starting at `offset`, returned as a raw `ArrayBuffer`.
+/// Reading past EOF returns fewer bytes (possibly none) rather than failing.
+#[tauri::command]
+pub async fn file_read_range(
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    path: String,
+    offset: u64,
+    len: u64,
+) -> Result<Response, String> {
+    if len > MAX_RANGE {
+        return Err(format!(
+            "range of {len} bytes exceeds the {MAX_RANGE}-byte limit; use file_read_stream"
+        ));
+    }
+    let path = scope.check(window.label(), &path)?;
+    let read = tauri::async_runtime::spawn_blocking(move || {
+        let mut file = open_at(&path, offset)?;
+        read_up_to(&mut file, len).map(Response::new)
+    });
+    call.run(async { read.await.map_err(|e| e.to_string())? })
+        .await
+}
+
+/// Stream `len` bytes (default: to EOF) from `offset` over `channel` in raw
+/// chunks of `chunk_size`. Resolves once the last chunk has been sent, so the
+/// frontend can pace itself by awaiting successive calls. Stops when the
+/// window reloads or closes.
+#[tauri::command]
+pub async fn file_read_stream(
+    call: Call,
+    scope: State<'_, FsScope>,
+    path: String,
+    offset: u64,
+    len: Option<u64>,
+    chunk_size: Option<u64>,
+    channel: Channel,
+) -> Result<u64, String> {
+    let path = scope.check(call.window(), &path)?;
+    let chunk = chunk_size.unwrap_or(DEFAULT_CHUNK).clamp(1, MAX_RANGE);
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut file = open_at(&path, offset)?;
+        let mut left = len.unwrap_or(u64::MAX);
+        let mut sent = 0;
+        while left > 0 {
+            call.check()?;
+            let buf = read_up_to(&mut file, chunk.min(left))?;
+            if buf.is_empty() {
+                break;
+            }
+            let n = buf.len() as u64;
+            channel
+                .send(InvokeResponseBody::Raw(buf))
+                .map_err(|e| e.to_string())?;
+            sent += n;
+            left -= n;
+        }
+        Ok(sent)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,163 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How often the OS state is polled; none of the platforms push changes.
+const POLL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+pub enum FocusState {
+    /// Notifications are shown normally.
+    Off,
+    /// Do Not Disturb, Focus Assist, a presentation or a full-screen app.
+    On,
+    /// The state can't be read (always the case on macOS).
+    Unknown,
+}
+
+/// An alert held back while focus mode was on.
+struct Deferred {
+    title: String,
+    body: String,
+}
+
+/// Managed state: last polled focus state and the alerts waiting for it to end.
+pub struct Focus {
+    state: Mutex<FocusState>,
+    deferred: Mutex<Vec<Deferred>>,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(FocusState::Unknown),
+            deferred: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Focus {
+    /// Whether non-critical alerts should be held back right now.
+    pub fn active(&self) -> bool {
+        *self.state.lock().unwrap() == FocusState::On
+    }
+
+    /// Hold an alert until focus mode ends; see `start`.
+    pub fn defer(&self, title: &str, body: &str) {
+        self.deferred.lock().unwrap().push(Deferred {
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+    }
+}
+
+/// Deliver what piled up during focus mode: a single alert as it was, several
+/// as one digest notification.
+fn deliver_digest(app: &AppHandle, deferred: Vec<Deferred>) {
+    let (title, body) = match deferred.as_slice() {
+        [] => return,
+        [only] => (only.title.clone(), only.body.clone()),
+        many => (
+            format!("{} alerts while Focus was on", many.len()),
+            many.iter()
+                .map(|d| d.title.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    };
+    crate::reminders::notify(app, &title, &body);
+}
+
+/// Poll the OS focus state, emitting `spectrus://focus-changed` with the new
+/// `FocusState` whenever it changes, and flushing deferred alerts as a digest
+/// once it turns off.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let now = platform::query(&app);
+        let focus = app.state::<Focus>();
+        let before = std::mem::replace(&mut *focus.state.lock().unwrap(), now);
+        if before != now {
+            let _ = app.emit("spectrus://focus-changed", now);
+            if now != FocusState::On {
+                let deferred = std::mem::take(&mut *focus.deferred.lock().unwrap());
+                deliver_digest(&app, deferred);
+            }
+        }
+        std::thread::sleep(POLL);
+    });
+}
+
+/// Current OS focus / Do Not Disturb state.
+#[tauri::command]
+pub fn focus_state(focus: State<'_, Focus>) -> FocusState {
+    *focus.state.lock().unwrap()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::{AppHandle, Manager};
+
+    use super::FocusState;
+
+    /// KDE publishes `Inhibited` on the notification service; GNOME keeps it
+    /// as the inverse of the `show-banners` setting.
+    pub fn query(app: &AppHandle) -> FocusState {
+        let kde = app
+            .try_state::<crate::linux_dbus::DbusState>()
+            .and_then(|dbus| dbus.notifications_inhibited());
+        if let Some(inhibited) = kde {
+            return if inhibited {
+                FocusState::On
+            } else {
+                FocusState::Off
+            };
+        }
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+            .output();
+        match output.ok().filter(|o| o.status.success()) {
+            Some(o) if o.stdout.starts_with(b"false") => FocusState::On,
+            Some(o) if o.stdout.starts_with(b"true") => FocusState::Off,
+            _ => FocusState::Unknown,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_ACCEPTS_NOTIFICATIONS};
+
+    use super::FocusState;
+
+    /// Quiet hours (Focus Assist), presentations, full-screen games and a
+    /// locked session all count as "on".
+    pub fn query(_app: &AppHandle) -> FocusState {
+        // SAFETY: no arguments; returns a plain enum value.
+        match unsafe { SHQueryUserNotificationState() } {
+            Ok(QUNS_ACCEPTS_NOTIFICATIONS) => FocusState::Off,
+            Ok(_) => FocusState::On,
+            Err(_) => FocusState::Unknown,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use tauri::AppHandle;
+
+    use super::FocusState;
+
+    /// Reading Focus needs `INFocusStatusCenter` and the Communication
+    /// Notifications entitlement, which this build doesn't carry.
+    pub fn query(_app: &AppHandle) -> FocusState {
+        FocusState::Unknown
+    }
+}
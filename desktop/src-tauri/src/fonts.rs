@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::io::Cursor;
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use base64::Engine;
+use fontdb::{Database, FaceInfo, Style};
+use image::{GrayAlphaImage, ImageFormat, LumaA};
+use serde::{Deserialize, Serialize};
+
+/// Drawn when the caller doesn't pass its own text.
+const DEFAULT_SAMPLE: &str = "The quick brown fox jumps over the lazy dog";
+const DEFAULT_SIZE: f32 = 24.0;
+
+/// Previews are cut off at this width, in pixels.
+const MAX_WIDTH: u32 = 640;
+const MAX_SIZE: f32 = 96.0;
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FontListOptions {
+    /// Render a preview of each face.
+    preview: bool,
+    /// Text to render, instead of a pangram.
+    sample: Option<String>,
+    /// Pixel size of the preview text.
+    size: Option<f32>,
+    /// Only these families, matched case-insensitively.
+    families: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFace {
+    family: String,
+    /// "normal", "italic" or "oblique".
+    style: &'static str,
+    /// CSS weight, 100–900.
+    weight: u16,
+    monospace: bool,
+    postscript_name: String,
+    /// Black-on-transparent PNG as a `data:` URL, usable as an image or a CSS
+    /// mask. `None` unless asked for, or when the face can't draw the sample
+    /// (symbol and icon fonts).
+    preview: Option<String>,
+}
+
+fn style_name(style: Style) -> &'static str {
+    match style {
+        Style::Normal => "normal",
+        Style::Italic => "italic",
+        Style::Oblique => "oblique",
+    }
+}
+
+/// Draw `text` on one line, or `None` if the face lacks most of its glyphs.
+fn render(data: &[u8], index: u32, text: &str, size: f32) -> Option<GrayAlphaImage> {
+    let font = FontRef::try_from_slice_and_index(data, index).ok()?;
+    let scaled = font.as_scaled(PxScale::from(size));
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_control()).collect();
+    let missing = chars
+        .iter()
+        .filter(|c| !c.is_whitespace() && font.glyph_id(**c).0 == 0)
+        .count();
+    if chars.is_empty() || missing * 2 > chars.len() {
+        return None;
+    }
+
+    let mut glyphs = Vec::with_capacity(chars.len());
+    let mut x = 0.0;
+    let mut previous = None;
+    for c in chars {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            x += scaled.kern(previous, id);
+        }
+        glyphs.push(id.with_scale_and_position(size, point(x, scaled.ascent())));
+        x += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    let width = (x.ceil() as u32).clamp(1, MAX_WIDTH);
+    let height = ((scaled.ascent() - scaled.descent()).ceil() as u32).max(1);
+
+    let mut image = GrayAlphaImage::new(width, height);
+    for glyph in glyphs {
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+            *pixel = LumaA([0, pixel.0[1].max(alpha)]);
+        });
+    }
+    Some(image)
+}
+
+fn preview(db: &Database, face: &FaceInfo, text: &str, size: f32) -> Option<String> {
+    let image = db
+        .with_face_data(face.id, |data, index| render(data, index, text, size))
+        .flatten()?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .ok()?;
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    Some(format!("data:image/png;base64,{data}"))
+}
+
+fn list(options: FontListOptions) -> Vec<FontFace> {
+    let mut db = Database::new();
+    db.load_system_fonts();
+
+    let wanted: Option<HashSet<String>> = options
+        .families
+        .map(|families| families.iter().map(|f| f.to_lowercase()).collect());
+    let sample = options
+        .sample
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_SAMPLE.to_string());
+    let size = options.size.unwrap_or(DEFAULT_SIZE).clamp(6.0, MAX_SIZE);
+
+    // The same face is often installed more than once (user and system
+    // folders, or several formats); list it once.
+    let mut seen = HashSet::new();
+    let mut faces: Vec<FontFace> = db
+        .faces()
+        .filter_map(|face| {
+            // fontdb puts the English family name first.
+            let family = face.families.first()?.0.clone();
+            if wanted
+                .as_ref()
+                .is_some_and(|w| !w.contains(&family.to_lowercase()))
+            {
+                return None;
+            }
+            if !seen.insert((family.clone(), face.style, face.weight.0)) {
+                return None;
+            }
+            Some(FontFace {
+                preview: options
+                    .preview
+                    .then(|| preview(&db, face, &sample, size))
+                    .flatten(),
+                family,
+                style: style_name(face.style),
+                weight: face.weight.0,
+                monospace: face.monospaced,
+                postscript_name: face.post_script_name.clone(),
+            })
+        })
+        .collect();
+    faces.sort_by(|a, b| {
+        a.family
+            .to_lowercase()
+            .cmp(&b.family.to_lowercase())
+            .then(a.weight.cmp(&b.weight))
+            .then(a.style.cmp(b.style))
+    });
+    faces
+}
+
+/// Installed font faces, sorted by family, with optional preview images so
+/// a font picker can show each face without loading it in the webview.
+/// Rendering every face's preview takes a while on systems with many fonts;
+/// pass `families` to preview only those on screen.
+#[tauri::command]
+pub async fn fonts_list(options: Option<FontListOptions>) -> Result<Vec<FontFace>, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || list(options))
+        .await
+        .map_err(|e| e.to_string())
+}
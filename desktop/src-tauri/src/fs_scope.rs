@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Per-window allowlist of filesystem paths the frontend may touch through
+/// file commands. Paths only get here from user actions the backend observes
+/// (drag-and-drop, native dialogs), never from a plain command argument, so a
+/// compromised renderer can't read arbitrary files.
+#[derive(Default)]
+pub struct FsScope(Mutex<HashMap<String, Vec<PathBuf>>>);
+
+/// Canonical form of `path`. A path that doesn't exist yet (an output file)
+/// resolves through its parent, which must exist. Symlinks and `..` are
+/// resolved so they can't be used to step outside a grant.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if path.exists() {
+        return path
+            .canonicalize()
+            .map_err(|e| format!("{}: {e}", path.display()));
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("{}: not a file path", path.display()))?;
+    let parent = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let parent = parent
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", parent.display()))?;
+    Ok(parent.join(name))
+}
+
+impl FsScope {
+    /// Allow `window` to access `path` (and everything below it if it is a dir).
+    pub fn grant(&self, window: &str, path: &Path) {
+        let Ok(path) = resolve(path) else {
+            return;
+        };
+        let mut scopes = self.0.lock().unwrap();
+        let roots = scopes.entry(window.to_string()).or_default();
+        if !roots.iter().any(|r| path.starts_with(r)) {
+            roots.push(path);
+        }
+    }
+
+    /// Forget every grant for a window, e.g. when it is destroyed.
+    pub fn revoke_window(&self, window: &str) {
+        self.0.lock().unwrap().remove(window);
+    }
+
+    fn allow(&self, window: &str, resolved: PathBuf, path: &str) -> Result<PathBuf, String> {
+        let scopes = self.0.lock().unwrap();
+        let allowed = scopes
+            .get(window)
+            .is_some_and(|roots| roots.iter().any(|r| resolved.starts_with(r)));
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(format!("{path}: access denied for this window"))
+        }
+    }
+
+    /// Resolve an existing `path` and confirm `window` may access it.
+    pub fn check(&self, window: &str, path: &str) -> Result<PathBuf, String> {
+        let resolved = Path::new(path)
+            .canonicalize()
+            .map_err(|e| format!("{path}: {e}"))?;
+        self.allow(window, resolved, path)
+    }
+
+    /// Like `check`, for a path that may not exist yet (an output file). Either
+    /// the path itself or a directory above it must have been granted.
+    pub fn check_new(&self, window: &str, path: &str) -> Result<PathBuf, String> {
+        let resolved = resolve(Path::new(path))?;
+        self.allow(window, resolved, path)
+    }
+}
@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Context, Manager, State};
+
+/// File in the data root whose presence turns hardware acceleration off at
+/// the next launch. A file rather than a setting, because it has to be read
+/// before the webview (and the profile) exists.
+const MARKER: &str = "disable-gpu";
+
+/// Renderer strings that mean WebGL is running on the CPU.
+const SOFTWARE_RENDERERS: [&str; 5] = [
+    "swiftshader",
+    "llvmpipe",
+    "softpipe",
+    "software",
+    "microsoft basic render",
+];
+
+/// Ask WebGL which GPU it ended up on. The unmasked strings need
+/// `WEBGL_debug_renderer_info`; without it only the generic ones are known.
+const PROBE_JS: &str = r#"(() => {
+  const gl = document.createElement("canvas").getContext("webgl");
+  if (!gl) return { webgl: false, vendor: null, renderer: null };
+  const ext = gl.getExtension("WEBGL_debug_renderer_info");
+  return {
+    webgl: true,
+    vendor: gl.getParameter(ext ? ext.UNMASKED_VENDOR_WEBGL : gl.VENDOR),
+    renderer: gl.getParameter(ext ? ext.UNMASKED_RENDERER_WEBGL : gl.RENDERER),
+  };
+})()"#;
+
+/// Managed state: whether this launch runs without GPU acceleration, and
+/// where the persisted toggle lives.
+pub struct Gpu {
+    disabled: bool,
+    root: Option<PathBuf>,
+}
+
+impl Gpu {
+    pub fn new(disabled: bool, root: Option<PathBuf>) -> Self {
+        Self { disabled, root }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Adapter {
+    vendor: Option<String>,
+    model: Option<String>,
+    /// Kernel module on Linux, driver version on Windows.
+    driver: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WebviewGpu {
+    webgl: bool,
+    vendor: Option<String>,
+    renderer: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuInfo {
+    adapters: Vec<Adapter>,
+    /// `None` when the main window couldn't be asked.
+    webview: Option<WebviewGpu>,
+    /// WebGL runs on a GPU rather than a software rasteriser.
+    hardware_accelerated: Option<bool>,
+    /// Acceleration was turned off for this launch.
+    gpu_disabled: bool,
+    /// The fallback is persisted and applies from the next launch.
+    fallback_next_launch: bool,
+    /// Known trouble spots for the detected setup, in plain words.
+    hints: Vec<String>,
+}
+
+/// Where the toggle is kept: the portable directory, or the same per-user
+/// data directory Tauri resolves as `app_data_dir`.
+pub fn root(context: &Context, portable: Option<&Path>) -> Option<PathBuf> {
+    match portable {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => dirs::data_dir().map(|d| d.join(&context.config().identifier)),
+    }
+}
+
+/// Whether the persisted fallback is on.
+pub fn fallback_enabled(root: Option<&Path>) -> bool {
+    root.is_some_and(|r| r.join(MARKER).is_file())
+}
+
+/// Start the webviews without GPU compositing. Must run before the builder
+/// creates any window. WKWebView offers no such switch, so this does nothing
+/// on macOS.
+#[cfg_attr(not(windows), allow(unused_variables))]
+pub fn configure(context: &mut Context) {
+    // WebKitGTK's DMA-BUF renderer and accelerated compositing are what leave
+    // canvases blank on some driver/compositor combinations.
+    #[cfg(target_os = "linux")]
+    {
+        std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
+        std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
+    }
+    // Setting browser args replaces wry's defaults, so those are kept.
+    #[cfg(windows)]
+    for window in &mut context.config_mut().app.windows {
+        let args = window.additional_browser_args.take().unwrap_or_else(|| {
+            "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection".into()
+        });
+        window.additional_browser_args = Some(format!("{args} --disable-gpu"));
+    }
+}
+
+fn probe_webview(app: &AppHandle) -> Option<WebviewGpu> {
+    let window = app.get_webview_window("main")?;
+    let json = crate::pdf::eval(&window, PROBE_JS).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn hints(adapters: &[Adapter], webview: Option<&WebviewGpu>, disabled: bool) -> Vec<String> {
+    let mut hints = Vec::new();
+    let software = webview
+        .and_then(|w| w.renderer.as_deref())
+        .is_some_and(is_software);
+    if software && !disabled {
+        hints
+            .push("The webview is rendering on the CPU; the GPU driver may be blocklisted.".into());
+    }
+    if webview.is_some_and(|w| !w.webgl) {
+        hints.push("WebGL is unavailable; canvas-based views will not render.".into());
+    }
+    let wayland = std::env::var("XDG_SESSION_TYPE").is_ok_and(|s| s == "wayland");
+    let nvidia = adapters
+        .iter()
+        .any(|a| a.driver.as_deref() == Some("nvidia"));
+    if cfg!(target_os = "linux") && wayland && nvidia && !disabled {
+        hints.push(
+            "The NVIDIA driver on Wayland is known to leave WebKitGTK canvases blank; \
+             turn on the GPU fallback if views stay empty."
+                .into(),
+        );
+    }
+    hints
+}
+
+fn is_software(renderer: &str) -> bool {
+    let renderer = renderer.to_ascii_lowercase();
+    SOFTWARE_RENDERERS.iter().any(|s| renderer.contains(s))
+}
+
+/// Graphics adapters, what the webview is actually rendering with, and
+/// whether the disable-GPU fallback is on now or from the next launch.
+#[tauri::command]
+pub async fn gpu_info(app: AppHandle, gpu: State<'_, Gpu>) -> Result<GpuInfo, String> {
+    let disabled = gpu.disabled;
+    let fallback_next_launch = fallback_enabled(gpu.root.as_deref());
+    let probe_app = app.clone();
+    let (adapters, webview) = tauri::async_runtime::spawn_blocking(move || {
+        (platform::adapters(), probe_webview(&probe_app))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let hardware_accelerated = webview
+        .as_ref()
+        .map(|w| w.webgl && !w.renderer.as_deref().is_some_and(is_software));
+    Ok(GpuInfo {
+        hints: hints(&adapters, webview.as_ref(), disabled),
+        adapters,
+        webview,
+        hardware_accelerated,
+        gpu_disabled: disabled,
+        fallback_next_launch,
+    })
+}
+
+/// Persist (or clear) the disable-GPU fallback. It takes effect at the next
+/// launch; `--disable-gpu` does the same for a single run.
+#[tauri::command]
+pub fn gpu_fallback_set(gpu: State<'_, Gpu>, enabled: bool) -> Result<(), String> {
+    let root = gpu.root.as_ref().ok_or("no data directory")?;
+    let marker = root.join(MARKER);
+    if enabled {
+        std::fs::create_dir_all(root).map_err(|e| e.to_string())?;
+        std::fs::write(marker, b"").map_err(|e| e.to_string())
+    } else {
+        match std::fs::remove_file(marker) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn run(command: &mut Command) -> Option<Vec<u8>> {
+    command
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| o.stdout)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::{run, Adapter};
+
+    fn vendor_name(id: &str) -> String {
+        match id {
+            "0x8086" => "Intel".into(),
+            "0x1002" => "AMD".into(),
+            "0x10de" => "NVIDIA".into(),
+            other => other.into(),
+        }
+    }
+
+    /// The quoted device name from `lspci -mm`, e.g. `"Alder Lake-P GT2"`.
+    fn lspci_model(slot: &str) -> Option<String> {
+        let out = run(Command::new("lspci").args(["-mm", "-s", slot]))?;
+        let line = String::from_utf8_lossy(&out);
+        line.split('"').nth(5).map(str::to_string)
+    }
+
+    /// One entry per DRM card (not per connector) under `/sys/class/drm`.
+    pub fn adapters() -> Vec<Adapter> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+            return Vec::new();
+        };
+        let mut cards: Vec<_> = entries
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|n| n.starts_with("card") && !n.contains('-'))
+            .collect();
+        cards.sort();
+        cards
+            .iter()
+            .map(|card| {
+                let device = Path::new("/sys/class/drm").join(card).join("device");
+                let read = |f: &str| {
+                    std::fs::read_to_string(device.join(f))
+                        .ok()
+                        .map(|s| s.trim().to_string())
+                };
+                let link_name = |f: &str| {
+                    std::fs::read_link(device.join(f))
+                        .ok()
+                        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                };
+                let model = std::fs::canonicalize(&device)
+                    .ok()
+                    .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                    .and_then(|slot| lspci_model(&slot))
+                    .or_else(|| read("device"));
+                Adapter {
+                    vendor: read("vendor").map(|v| vendor_name(&v)),
+                    model,
+                    driver: link_name("driver"),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use serde::Deserialize;
+
+    use super::{run, Adapter};
+    use crate::print::powershell;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct VideoController {
+        name: Option<String>,
+        adapter_compatibility: Option<String>,
+        driver_version: Option<String>,
+    }
+
+    pub fn adapters() -> Vec<Adapter> {
+        let script = "Get-CimInstance Win32_VideoController | \
+                      Select-Object Name,AdapterCompatibility,DriverVersion | ConvertTo-Json";
+        let Some(out) = run(&mut powershell(script)) else {
+            return Vec::new();
+        };
+        // A single adapter comes back as an object rather than an array.
+        let list = match serde_json::from_slice::<serde_json::Value>(&out) {
+            Ok(serde_json::Value::Array(list)) => list,
+            Ok(serde_json::Value::Null) | Err(_) => Vec::new(),
+            Ok(other) => vec![other],
+        };
+        list.into_iter()
+            .filter_map(|v| serde_json::from_value::<VideoController>(v).ok())
+            .map(|c| Adapter {
+                vendor: c.adapter_compatibility,
+                model: c.name,
+                driver: c.driver_version,
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    use super::{run, Adapter};
+
+    /// macOS ships the GPU driver with the OS, so no driver is reported.
+    pub fn adapters() -> Vec<Adapter> {
+        let Some(out) = run(Command::new("system_profiler").args(["SPDisplaysDataType", "-json"]))
+        else {
+            return Vec::new();
+        };
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap_or_default();
+        let Some(list) = value["SPDisplaysDataType"].as_array() else {
+            return Vec::new();
+        };
+        list.iter()
+            .map(|gpu| Adapter {
+                // e.g. "sppci_vendor_Apple" or "Intel (0x8086)".
+                vendor: gpu["spdisplays_vendor"]
+                    .as_str()
+                    .map(|v| v.trim_start_matches("sppci_vendor_").to_string()),
+                model: gpu["sppci_model"].as_str().map(str::to_string),
+                driver: None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use qrcode::QrCode;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+use crate::consent::{self, Capability};
+use crate::fs_scope::FsScope;
+use crate::profile::ProfileState;
+
+/// Largest file accepted in one upload.
+const MAX_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Largest JSON payload accepted.
+const MAX_JSON_BYTES: u64 = 64 * 1024;
+
+/// Upload page served to phones that scan the pairing code. Files are sent
+/// one request each as raw bodies, so the server needs no multipart parser.
+const UPLOAD_PAGE: &str = r#"<!doctype html>
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Send to Spectrus</title>
+<input type="file" id="files" multiple>
+<p id="status"></p>
+<script>
+const token = new URLSearchParams(location.search).get("token");
+document.getElementById("files").onchange = async (e) => {
+  const status = document.getElementById("status");
+  for (const file of e.target.files) {
+    status.textContent = "Sending " + file.name + "…";
+    const url = "/upload?token=" + encodeURIComponent(token) + "&name=" + encodeURIComponent(file.name);
+    const res = await fetch(url, { method: "POST", body: file });
+    status.textContent = res.ok ? "Sent " + file.name : "Failed: " + (await res.text());
+  }
+};
+</script>
+"#;
+
+/// What `handoff_start` hands the UI for pairing.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandoffInfo {
+    /// Base URL other devices use, e.g. `http://192.168.1.20:49152`.
+    url: String,
+    /// Bearer token every request must carry, as `Authorization: Bearer` or
+    /// a `token` query parameter.
+    token: String,
+    /// `url` with the token, for the QR code.
+    pairing_url: String,
+    /// SVG markup of a QR code for `pairing_url`.
+    qr_svg: String,
+}
+
+/// Payload of `spectrus://handoff-received`, one per item.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+enum Received {
+    File {
+        name: String,
+        path: PathBuf,
+        size: u64,
+        from: Option<IpAddr>,
+    },
+    Json {
+        data: Value,
+        from: Option<IpAddr>,
+    },
+}
+
+struct Running {
+    server: Arc<Server>,
+    info: HandoffInfo,
+}
+
+/// Managed state: the server while it is on. Off by default; only
+/// `handoff_start` opens the port.
+#[derive(Default)]
+pub struct Handoff(Mutex<Option<Running>>);
+
+/// Address of the interface that routes to the outside, which is the one
+/// LAN peers can reach. Connecting a UDP socket sends nothing.
+pub(crate) fn lan_ip() -> IpAddr {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|s| s.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).map(|()| s))
+        .and_then(|s| s.local_addr())
+        .map(|a| a.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+fn new_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Compare without stopping at the first differing byte.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn authorized(request: &Request, url: &Url, token: &str) -> bool {
+    let bearer = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(str::to_string);
+    let query = url
+        .query_pairs()
+        .find(|(k, _)| k == "token")
+        .map(|(_, v)| v.into_owned());
+    bearer.or(query).is_some_and(|t| token_matches(&t, token))
+}
+
+/// Keep only the final component and characters safe in any filesystem.
+fn safe_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(120)
+        .collect();
+    let cleaned = cleaned.trim_matches(['.', ' ']).to_string();
+    if cleaned.is_empty() {
+        "upload".into()
+    } else {
+        cleaned
+    }
+}
+
+/// Hand a received file to the main window the way OS shares arrive: grant
+/// it access, then a `spectrus://import?path=...` deep link.
+fn import(app: &AppHandle, path: &std::path::Path) {
+    app.state::<FsScope>().grant("main", path);
+    let mut link = Url::parse("spectrus://import").expect("static URL");
+    link.query_pairs_mut()
+        .append_pair("path", &path.to_string_lossy())
+        .append_pair("source", "handoff");
+    if let Err(e) = app.emit("spectrus://deep-link", link.to_string()) {
+        eprintln!("handoff: deep-link emit error: {e}");
+    }
+}
+
+fn receive_file(app: &AppHandle, request: &mut Request, url: &Url) -> Result<Received, String> {
+    let name = safe_name(
+        &url.query_pairs()
+            .find(|(k, _)| k == "name")
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_default(),
+    );
+    let dir = app
+        .state::<ProfileState>()
+        .current()
+        .dir
+        .join("inbox")
+        .join("handoff");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let stamp = crate::time::now_ms(app);
+    let path = dir.join(format!("{stamp}-{name}"));
+    let mut file = File::create(&path).map_err(|e| e.to_string())?;
+    let size = io::copy(&mut request.as_reader().take(MAX_FILE_BYTES + 1), &mut file)
+        .map_err(|e| e.to_string())?;
+    if size > MAX_FILE_BYTES {
+        drop(file);
+        let _ = fs::remove_file(&path);
+        return Err("file too large".into());
+    }
+    import(app, &path);
+    Ok(Received::File {
+        name,
+        path,
+        size,
+        from: request.remote_addr().map(SocketAddr::ip),
+    })
+}
+
+fn receive_json(request: &mut Request) -> Result<Received, String> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_JSON_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    if body.len() as u64 > MAX_JSON_BYTES {
+        return Err("payload too large".into());
+    }
+    let data = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+    Ok(Received::Json {
+        data,
+        from: request.remote_addr().map(SocketAddr::ip),
+    })
+}
+
+fn handle(app: &AppHandle, mut request: Request, token: &str) {
+    let Ok(url) = Url::parse(&format!("http://handoff{}", request.url())) else {
+        let _ = request.respond(Response::from_string("bad request").with_status_code(400));
+        return;
+    };
+    if !authorized(&request, &url, token) {
+        let _ = request.respond(Response::from_string("unauthorized").with_status_code(401));
+        return;
+    }
+    let received = match (request.method(), url.path()) {
+        (Method::Get, "/") => {
+            let html = Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+                .expect("static header");
+            let _ = request.respond(Response::from_string(UPLOAD_PAGE).with_header(html));
+            return;
+        }
+        (Method::Post, "/upload") => receive_file(app, &mut request, &url),
+        (Method::Post, "/json") => receive_json(&mut request),
+        _ => {
+            let _ = request.respond(Response::from_string("not found").with_status_code(404));
+            return;
+        }
+    };
+    let response = match received {
+        Ok(item) => {
+            let _ = app.emit("spectrus://handoff-received", &item);
+            Response::from_string("ok")
+        }
+        Err(e) => Response::from_string(e).with_status_code(400),
+    };
+    let _ = request.respond(response);
+}
+
+/// Start accepting uploads from the LAN on `port` (any free port if
+/// omitted), with a fresh token. Files are saved to the profile's
+/// `inbox/handoff/` and imported into the main window; each file or JSON
+/// payload also emits `spectrus://handoff-received`. The port is included
+/// in the discovery advertisement while running. Calling it while running
+/// returns the current pairing info. The user is asked before a window
+/// starts the server for the first time.
+#[tauri::command]
+pub async fn handoff_start(
+    app: AppHandle,
+    window: WebviewWindow,
+    handoff: State<'_, Handoff>,
+    port: Option<u16>,
+) -> Result<HandoffInfo, String> {
+    consent::require(&app, &window, Capability::Server, None).await?;
+    let mut running = handoff.0.lock().unwrap();
+    if let Some(running) = running.as_ref() {
+        return Ok(running.info.clone());
+    }
+    let server =
+        Server::http((Ipv4Addr::UNSPECIFIED, port.unwrap_or(0))).map_err(|e| e.to_string())?;
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|a| a.port())
+        .ok_or("no TCP address")?;
+    let token = new_token()?;
+    let url = format!("http://{}:{port}", lan_ip());
+    let pairing_url = format!("{url}/?token={token}");
+    let qr_svg = QrCode::new(&pairing_url)
+        .map_err(|e| e.to_string())?
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(256, 256)
+        .build();
+    let info = HandoffInfo {
+        url,
+        token: token.clone(),
+        pairing_url,
+        qr_svg,
+    };
+
+    crate::discovery::set_handoff_port(&app, Some(port));
+    let server = Arc::new(server);
+    let accept = server.clone();
+    std::thread::spawn(move || {
+        for request in accept.incoming_requests() {
+            let app = app.clone();
+            let token = token.clone();
+            std::thread::spawn(move || handle(&app, request, &token));
+        }
+    });
+    *running = Some(Running {
+        server,
+        info: info.clone(),
+    });
+    Ok(info)
+}
+
+/// Close the port. The token stops working; pairing again issues a new one.
+#[tauri::command]
+pub fn handoff_stop(app: AppHandle, handoff: State<'_, Handoff>) {
+    if let Some(running) = handoff.0.lock().unwrap().take() {
+        running.server.unblock();
+        crate::discovery::set_handoff_port(&app, None);
+    }
+}
+
+/// Pairing info while the server runs, `null` when it is off.
+#[tauri::command]
+pub fn handoff_info(handoff: State<'_, Handoff>) -> Option<HandoffInfo> {
+    handoff.0.lock().unwrap().as_ref().map(|r| r.info.clone())
+}
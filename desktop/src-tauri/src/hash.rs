@@ -0,0 +1,148 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State, Window};
+use walkdir::WalkDir;
+
+use crate::fs_scope::FsScope;
+use crate::jobs::Job;
+use crate::watchdog::Call;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algo {
+    Sha256,
+    Blake3,
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algo: Algo) -> Self {
+        match algo {
+            Algo::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Hash result for one file in a batch.
+#[derive(Serialize)]
+pub struct FileDigest {
+    /// Relative to the directory that was hashed, with `/` separators, so it
+    /// can be compared directly against manifest entries.
+    path: String,
+    hash: String,
+}
+
+/// Hash one file, folding its bytes into the job's running `done` count.
+fn hash_file(
+    job: &mut Job,
+    path: &Path,
+    algo: Algo,
+    done: &mut u64,
+    total: u64,
+) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut hasher = Hasher::new(algo);
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        job.check()?;
+        let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        *done += n as u64;
+        job.progress(*done, total);
+    }
+    Ok(hasher.finalize_hex())
+}
+
+/// Hash a file on a background thread and return the lowercase hex digest.
+/// Progress is reported as `spectrus://job-progress` in bytes.
+#[tauri::command]
+pub async fn file_hash(
+    app: AppHandle,
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    path: String,
+    algo: Algo,
+) -> Result<String, String> {
+    let path = scope.check(window.label(), &path)?;
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut job = Job::start(&app, "file-hash").within(call);
+        let total = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        hash_file(&mut job, &path, algo, &mut 0, total)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Hash every file under `dir` (recursively) as one job, sorted by path.
+#[tauri::command]
+pub async fn file_hash_dir(
+    app: AppHandle,
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    dir: String,
+    algo: Algo,
+) -> Result<Vec<FileDigest>, String> {
+    let dir = scope.check(window.label(), &dir)?;
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut job = Job::start(&app, "file-hash").within(call);
+        let mut files: Vec<(PathBuf, u64)> = WalkDir::new(&dir)
+            .follow_links(false)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let len = e.metadata().map(|m| m.len()).unwrap_or(0);
+                (e.into_path(), len)
+            })
+            .collect();
+        files.sort();
+        let total = files.iter().map(|(_, len)| len).sum();
+
+        let mut done = 0;
+        files
+            .into_iter()
+            .map(|(path, _)| {
+                let hash = hash_file(&mut job, &path, algo, &mut done, total)?;
+                let rel = path.strip_prefix(&dir).map_err(|e| e.to_string())?;
+                Ok(FileDigest {
+                    path: rel.to_string_lossy().replace('\\', "/"),
+                    hash,
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
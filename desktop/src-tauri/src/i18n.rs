@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State};
+use url::Url;
+
+use crate::portable::Portable;
+use crate::profile::ProfileState;
+use crate::settings;
+use crate::watchdog::Call;
+
+/// Settings key holding the UI language, when the user picked one other
+/// than the OS language.
+const SETTING: &str = "i18n.language";
+
+/// Every catalog falls back to this one for keys it lacks.
+const BASE: &str = "en";
+
+/// Catalogs built into the app.
+const BUNDLED: &[(&str, &str)] = &[
+    ("de", include_str!("../locales/de.json")),
+    ("en", include_str!("../locales/en.json")),
+];
+
+/// Published next to the update manifest: `{"<tag>": <version>}` for the
+/// catalogs available, each at `<tag>.json` with a detached `<tag>.json.sig`
+/// made with the updater's signing key.
+const INDEX: &str = "locales/index.json";
+
+/// A translation catalog, as shipped and as published.
+#[derive(Clone, Deserialize)]
+struct Catalog {
+    name: String,
+    /// Updates replace a catalog only with a higher version.
+    version: u32,
+    #[serde(default)]
+    messages: BTreeMap<String, String>,
+}
+
+/// The catalog in force: the chosen language laid over its parent language
+/// and English, so every key the app ships has some text.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Active {
+    /// The best available match for what was asked for.
+    language: String,
+    requested: String,
+    version: u32,
+    messages: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct Language {
+    tag: String,
+    name: String,
+    version: u32,
+}
+
+/// Managed state: the merged catalog for the language last asked for, and
+/// keys already reported missing.
+#[derive(Default)]
+pub struct I18n {
+    active: Mutex<Option<Arc<Active>>>,
+    reported: Mutex<HashSet<(String, String)>>,
+}
+
+/// Updated catalogs, shared by all profiles. Dot-named so it isn't taken
+/// for a profile.
+fn updates_dir(app: &AppHandle) -> PathBuf {
+    app.state::<ProfileState>().root().join(".locales")
+}
+
+fn bundled(tag: &str) -> Option<Catalog> {
+    let (_, json) = BUNDLED.iter().find(|(t, _)| *t == tag)?;
+    serde_json::from_str(json).ok()
+}
+
+fn downloaded(app: &AppHandle, tag: &str) -> Option<Catalog> {
+    let bytes = fs::read(updates_dir(app).join(format!("{tag}.json"))).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// The newer of the bundled and downloaded catalogs for `tag`.
+fn catalog(app: &AppHandle, tag: &str) -> Option<Catalog> {
+    match (bundled(tag), downloaded(app, tag)) {
+        (Some(b), Some(d)) if d.version > b.version => Some(d),
+        (Some(b), _) => Some(b),
+        (None, d) => d,
+    }
+}
+
+fn tags(app: &AppHandle) -> Vec<String> {
+    let mut tags: Vec<String> = BUNDLED.iter().map(|(t, _)| t.to_string()).collect();
+    let updated = fs::read_dir(updates_dir(app))
+        .into_iter()
+        .flatten()
+        .flatten();
+    for entry in updated {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(tag) = name.strip_suffix(".json") {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+    tags.sort();
+    tags
+}
+
+/// The profile's choice, else the OS language.
+fn requested(app: &AppHandle) -> String {
+    let profile = app.state::<ProfileState>().current();
+    settings::get(&profile, SETTING)
+        .and_then(|v| v.as_str().map(str::to_string))
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_else(|| BASE.to_string())
+}
+
+/// `de-AT` → `de-AT`, `de`.
+fn prefixes(tag: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut tag = tag.replace('_', "-");
+    loop {
+        chain.push(tag.clone());
+        match tag.rfind('-') {
+            Some(i) => tag.truncate(i),
+            None => return chain,
+        }
+    }
+}
+
+/// The prefixes, then English.
+fn fallbacks(tag: &str) -> Vec<String> {
+    let mut chain = prefixes(tag);
+    if !chain.iter().any(|t| t == BASE) {
+        chain.push(BASE.to_string());
+    }
+    chain
+}
+
+fn build(app: &AppHandle, requested: &str) -> Active {
+    let mut language = None;
+    let mut version = 0;
+    let mut messages = BTreeMap::new();
+    for tag in fallbacks(requested).iter().rev() {
+        let Some(catalog) = catalog(app, tag) else {
+            continue;
+        };
+        language = Some(tag.clone());
+        version = catalog.version;
+        messages.extend(catalog.messages);
+    }
+    Active {
+        language: language.unwrap_or_else(|| BASE.to_string()),
+        requested: requested.to_string(),
+        version,
+        messages,
+    }
+}
+
+fn active(app: &AppHandle) -> Arc<Active> {
+    let requested = requested(app);
+    let state = app.state::<I18n>();
+    let mut cached = state.active.lock().unwrap();
+    match &*cached {
+        Some(active) if active.requested == requested => active.clone(),
+        _ => {
+            let active = Arc::new(build(app, &requested));
+            *cached = Some(active.clone());
+            active
+        }
+    }
+}
+
+fn report(app: &AppHandle, language: &str, key: &str) {
+    let fresh = app
+        .state::<I18n>()
+        .reported
+        .lock()
+        .unwrap()
+        .insert((language.to_string(), key.to_string()));
+    if fresh {
+        eprintln!("i18n: missing {language} translation for {key:?}");
+    }
+}
+
+/// Text for `key` in the UI language, for strings the backend shows itself
+/// (the tray menu). Falls back to the key.
+pub(crate) fn text(app: &AppHandle, key: &str) -> String {
+    let active = active(app);
+    match active.messages.get(key) {
+        Some(text) => text.clone(),
+        None => {
+            report(app, &active.language, key);
+            key.to_string()
+        }
+    }
+}
+
+/// Drop the merged catalog and tell every window about the new one.
+fn changed(app: &AppHandle) {
+    *app.state::<I18n>().active.lock().unwrap() = None;
+    let _ = app.emit("spectrus://i18n-changed", &*active(app));
+    crate::tray::refresh(app);
+}
+
+/// Where catalog updates are published: beside the update manifest of the
+/// channel the updater follows.
+fn index_url(app: &AppHandle) -> Result<Url, String> {
+    let endpoint = match &crate::policy::current().update_endpoint {
+        Some(url) => url.clone(),
+        None => app
+            .config()
+            .plugins
+            .0
+            .get("updater")
+            .and_then(|u| u["endpoints"][0].as_str())
+            .ok_or("no update endpoint is configured")?
+            .parse()
+            .map_err(|e: url::ParseError| e.to_string())?,
+    };
+    endpoint.join(INDEX).map_err(|e| e.to_string())
+}
+
+fn public_key(app: &AppHandle) -> Result<PublicKey, String> {
+    let encoded = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|u| u["pubkey"].as_str())
+        .filter(|k| !k.is_empty())
+        .ok_or("catalog updates need the updater's signing key")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| e.to_string())?;
+    PublicKey::decode(&String::from_utf8_lossy(&decoded)).map_err(|e| e.to_string())
+}
+
+fn verify(key: &PublicKey, data: &[u8], signature: &str) -> Result<(), String> {
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(signature.trim())
+        .map_err(|e| e.to_string())?;
+    let signature =
+        Signature::decode(&String::from_utf8_lossy(&decoded)).map_err(|e| e.to_string())?;
+    key.verify(data, &signature, false)
+        .map_err(|e| e.to_string())
+}
+
+async fn fetch(client: &reqwest::Client, url: Url) -> Result<Vec<u8>, String> {
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("{url}: {e}"))?;
+    Ok(response.bytes().await.map_err(|e| e.to_string())?.to_vec())
+}
+
+/// The catalog in force for the active profile.
+#[tauri::command]
+pub fn i18n_catalog(app: AppHandle) -> Active {
+    (*active(&app)).clone()
+}
+
+/// Languages a catalog is available for, bundled or downloaded.
+#[tauri::command]
+pub fn i18n_languages(app: AppHandle) -> Vec<Language> {
+    tags(&app)
+        .into_iter()
+        .filter_map(|tag| {
+            let catalog = catalog(&app, &tag)?;
+            Some(Language {
+                tag,
+                name: catalog.name,
+                version: catalog.version,
+            })
+        })
+        .collect()
+}
+
+/// Switch the UI language, or follow the OS again with `None`. Every window
+/// gets the new catalog in `spectrus://i18n-changed`.
+#[tauri::command]
+pub fn i18n_language_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    language: Option<String>,
+) -> Result<(), String> {
+    let value = match language {
+        Some(tag) => {
+            let known = tags(&app);
+            if !prefixes(&tag).iter().any(|t| known.contains(t)) {
+                return Err(format!("no catalog for {tag}"));
+            }
+            Value::String(tag)
+        }
+        None => Value::Null,
+    };
+    settings::set(&profiles.current(), SETTING, value)?;
+    changed(&app);
+    Ok(())
+}
+
+/// Log keys the frontend looked up and found in no catalog, once each per
+/// run.
+#[tauri::command]
+pub fn i18n_report_missing(app: AppHandle, keys: Vec<String>) {
+    let language = active(&app).language.clone();
+    for key in keys {
+        report(&app, &language, &key);
+    }
+}
+
+async fn update(app: AppHandle) -> Result<Vec<String>, String> {
+    if app.state::<Portable>().0.is_some() || crate::policy::current().disable_updates {
+        return Err("updates are not available".into());
+    }
+    let key = public_key(&app)?;
+    let index_url = index_url(&app)?;
+    // The caller's `Call` gives up the wait by dropping this future.
+    crate::bandwidth::defer_while_metered(&app, crate::bandwidth::Subsystem::Download, || Ok(()))
+        .await?;
+    let client = app.state::<crate::remote_assets::RemoteAssets>().client();
+    let index: BTreeMap<String, u32> =
+        serde_json::from_slice(&fetch(&client, index_url.clone()).await?)
+            .map_err(|e| format!("{index_url}: {e}"))?;
+
+    let dir = updates_dir(&app);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut updated = Vec::new();
+    for (tag, version) in index {
+        let valid = !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid || catalog(&app, &tag).is_some_and(|c| c.version >= version) {
+            continue;
+        }
+        let url = index_url
+            .join(&format!("{tag}.json"))
+            .map_err(|e| e.to_string())?;
+        let sig_url = index_url
+            .join(&format!("{tag}.json.sig"))
+            .map_err(|e| e.to_string())?;
+        let data = fetch(&client, url.clone()).await?;
+        let signature = String::from_utf8_lossy(&fetch(&client, sig_url).await?).into_owned();
+        verify(&key, &data, &signature).map_err(|e| format!("{url}: {e}"))?;
+        let parsed: Catalog = serde_json::from_slice(&data).map_err(|e| format!("{url}: {e}"))?;
+        if parsed.version < version {
+            continue;
+        }
+        settings::write_atomic(&dir.join(format!("{tag}.json")), &data)?;
+        updated.push(tag);
+    }
+
+    if fallbacks(&requested(&app))
+        .iter()
+        .any(|t| updated.contains(t))
+    {
+        changed(&app);
+    }
+    Ok(updated)
+}
+
+/// Download catalogs newer than the ones in use from the update channel,
+/// checking each against the updater's signing key. Returns the languages
+/// updated; if the active one is among them, `spectrus://i18n-changed`
+/// follows. Waits while the connection is metered.
+#[tauri::command]
+pub async fn i18n_update(app: AppHandle, call: Call) -> Result<Vec<String>, String> {
+    call.run(update(app)).await
+}
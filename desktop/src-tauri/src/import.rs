@@ -0,0 +1,664 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use crate::fs_scope::FsScope;
+use crate::jobs::{self, Job};
+use crate::profile::{Profile, ProfileState};
+use crate::watchdog::{Call, Scope};
+
+/// What a parser turns a file into.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatasetKind {
+    /// Channels sampled at a fixed rate.
+    Audio,
+    /// Columns of readings, optionally timestamped.
+    Series,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatInfo {
+    id: &'static str,
+    name: &'static str,
+    extensions: &'static [&'static str],
+    kind: DatasetKind,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Channel {
+    name: String,
+    unit: Option<String>,
+}
+
+/// Why a file wasn't imported. `line` is set for text formats when the
+/// problem is on a particular line.
+#[derive(Clone, Serialize)]
+pub struct ImportError {
+    /// "denied", "unsupported", "unreadable", "malformed" or "cancelled".
+    code: &'static str,
+    message: String,
+    line: Option<u64>,
+}
+
+impl ImportError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    fn malformed(message: impl Into<String>) -> Self {
+        Self::new("malformed", message)
+    }
+
+    fn at_line(mut self, line: u64) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new("unreadable", e.to_string())
+    }
+}
+
+/// `meta.json` of an import, also returned to the caller.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMeta {
+    id: String,
+    format: &'static str,
+    /// File name of the original.
+    source: String,
+    /// Unix ms.
+    imported_at: i64,
+    kind: DatasetKind,
+    /// Frames per second, for audio.
+    sample_rate: Option<u32>,
+    frames: u64,
+    channels: Vec<Channel>,
+    /// Whether `time.f64` holds a timestamp (Unix ms or the file's own
+    /// unit) per frame.
+    timed: bool,
+}
+
+/// Outcome for one file: `import` on success, `error` otherwise.
+#[derive(Clone, Serialize)]
+pub struct ImportResult {
+    path: String,
+    format: Option<&'static str>,
+    import: Option<ImportMeta>,
+    error: Option<ImportError>,
+}
+
+impl ImportResult {
+    /// Id of the new import, if the file was imported.
+    pub(crate) fn import_id(&self) -> Option<&str> {
+        self.import.as_ref().map(|m| m.id.as_str())
+    }
+}
+
+/// Payload of `spectrus://import-started`, linking a file to the job that
+/// reports its progress.
+#[derive(Clone, Serialize)]
+struct Started<'a> {
+    job: u64,
+    path: &'a str,
+    format: &'static str,
+}
+
+/// What a parser hands back besides the samples.
+pub struct Parsed {
+    sample_rate: Option<u32>,
+    channels: Vec<Channel>,
+}
+
+/// Normalised output: interleaved little-endian `f32` frames in
+/// `samples.f32` and, for timed series, one little-endian `f64` per frame
+/// in `time.f64`.
+pub struct Output {
+    samples: BufWriter<File>,
+    time: Option<BufWriter<File>>,
+    dir: PathBuf,
+    frames: u64,
+}
+
+impl Output {
+    fn create(dir: &Path) -> Result<Self, ImportError> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            samples: BufWriter::new(File::create(dir.join("samples.f32"))?),
+            time: None,
+            dir: dir.to_path_buf(),
+            frames: 0,
+        })
+    }
+
+    /// Append one frame, a value per channel.
+    fn frame(&mut self, values: &[f32]) -> Result<(), ImportError> {
+        for v in values {
+            self.samples.write_all(&v.to_le_bytes())?;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Like `frame`, with the frame's timestamp.
+    fn timed_frame(&mut self, time: f64, values: &[f32]) -> Result<(), ImportError> {
+        if self.time.is_none() {
+            self.time = Some(BufWriter::new(File::create(self.dir.join("time.f64"))?));
+        }
+        if let Some(out) = self.time.as_mut() {
+            out.write_all(&time.to_le_bytes())?;
+        }
+        self.frame(values)
+    }
+
+    fn finish(mut self) -> Result<(u64, bool), ImportError> {
+        self.samples.flush()?;
+        if let Some(time) = self.time.as_mut() {
+            time.flush()?;
+        }
+        Ok((self.frames, self.time.is_some()))
+    }
+}
+
+/// A file format the importer understands. Register new ones in `PARSERS`.
+pub trait Parser: Sync {
+    fn info(&self) -> FormatInfo;
+
+    /// Whether the first bytes of a file are this format, for files whose
+    /// extension doesn't say. Text formats without a signature return false.
+    fn sniff(&self, _head: &[u8]) -> bool {
+        false
+    }
+
+    /// Read `path` into `out`, reporting progress on `job` and stopping
+    /// early once `job.check()` fails.
+    fn parse(&self, path: &Path, out: &mut Output, job: &mut Job) -> Result<Parsed, ImportError>;
+}
+
+static PARSERS: &[&dyn Parser] = &[&Wav, &Flac, &SensorCsv];
+
+fn cancelled(job: &Job) -> Result<(), ImportError> {
+    job.check()
+        .map_err(|_| ImportError::new("cancelled", jobs::CANCELLED))
+}
+
+/// Integer PCM scaled to -1.0..1.0.
+fn pcm_scale(bits: u32) -> f32 {
+    1.0 / (1u64 << bits.saturating_sub(1).min(62)) as f32
+}
+
+fn malformed(e: impl ToString) -> ImportError {
+    ImportError::malformed(e.to_string())
+}
+
+/// Group interleaved audio samples into frames of `channels`.
+fn interleaved(
+    out: &mut Output,
+    job: &mut Job,
+    channels: usize,
+    total: u64,
+    samples: impl Iterator<Item = Result<f32, ImportError>>,
+) -> Result<(), ImportError> {
+    let mut frame = Vec::with_capacity(channels);
+    for sample in samples {
+        frame.push(sample?);
+        if frame.len() == channels {
+            out.frame(&frame)?;
+            frame.clear();
+            if out.frames.is_multiple_of(4096) {
+                cancelled(job)?;
+                job.progress(out.frames, total);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn numbered_channels(count: u16) -> Vec<Channel> {
+    (1..=count)
+        .map(|n| Channel {
+            name: format!("Channel {n}"),
+            unit: None,
+        })
+        .collect()
+}
+
+struct Wav;
+
+impl Parser for Wav {
+    fn info(&self) -> FormatInfo {
+        FormatInfo {
+            id: "wav",
+            name: "WAV audio",
+            extensions: &["wav", "wave"],
+            kind: DatasetKind::Audio,
+        }
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WAVE"
+    }
+
+    fn parse(&self, path: &Path, out: &mut Output, job: &mut Job) -> Result<Parsed, ImportError> {
+        let mut reader = hound::WavReader::open(path).map_err(|e| match e {
+            hound::Error::IoError(e) => e.into(),
+            e => malformed(e),
+        })?;
+        let spec = reader.spec();
+        let channels = spec.channels as usize;
+        if channels == 0 {
+            return Err(ImportError::malformed("no channels"));
+        }
+        let total = reader.duration() as u64;
+        let scale = pcm_scale(spec.bits_per_sample as u32);
+        match spec.sample_format {
+            hound::SampleFormat::Float => {
+                let samples = reader.samples::<f32>().map(|s| s.map_err(malformed));
+                interleaved(out, job, channels, total, samples)?;
+            }
+            hound::SampleFormat::Int => {
+                let samples = reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale).map_err(malformed));
+                interleaved(out, job, channels, total, samples)?;
+            }
+        }
+        Ok(Parsed {
+            sample_rate: Some(spec.sample_rate),
+            channels: numbered_channels(spec.channels),
+        })
+    }
+}
+
+struct Flac;
+
+impl Parser for Flac {
+    fn info(&self) -> FormatInfo {
+        FormatInfo {
+            id: "flac",
+            name: "FLAC audio",
+            extensions: &["flac"],
+            kind: DatasetKind::Audio,
+        }
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(b"fLaC")
+    }
+
+    fn parse(&self, path: &Path, out: &mut Output, job: &mut Job) -> Result<Parsed, ImportError> {
+        let mut reader = claxon::FlacReader::open(path).map_err(|e| match e {
+            claxon::Error::IoError(e) => e.into(),
+            e => malformed(e),
+        })?;
+        let info = reader.streaminfo();
+        let channels = info.channels as usize;
+        let total = info.samples.unwrap_or(0);
+        let scale = pcm_scale(info.bits_per_sample);
+        let samples = reader
+            .samples()
+            .map(|s| s.map(|s| s as f32 * scale).map_err(malformed));
+        interleaved(out, job, channels, total, samples)?;
+        Ok(Parsed {
+            sample_rate: Some(info.sample_rate),
+            channels: numbered_channels(info.channels as u16),
+        })
+    }
+}
+
+/// Sensor logs exported as CSV or TSV: a header row, then one reading per
+/// row. A first column called time, timestamp, t or date becomes the
+/// frame's timestamp; every other column is a channel, with a unit taken
+/// from a trailing "(unit)" or "[unit]" in its header. Empty cells are NaN.
+struct SensorCsv;
+
+const TIME_COLUMNS: &[&str] = &["time", "timestamp", "t", "date", "datetime"];
+
+fn channel(header: &str) -> Channel {
+    let header = header.trim();
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(rest) = header.strip_suffix(close) {
+            if let Some((name, unit)) = rest.rsplit_once(open) {
+                return Channel {
+                    name: name.trim().to_string(),
+                    unit: Some(unit.trim().to_string()).filter(|u| !u.is_empty()),
+                };
+            }
+        }
+    }
+    Channel {
+        name: header.to_string(),
+        unit: None,
+    }
+}
+
+/// A number as-is, or an RFC 3339 date as Unix ms.
+fn timestamp(cell: &str) -> Option<f64> {
+    let cell = cell.trim();
+    cell.parse::<f64>().ok().or_else(|| {
+        cell.parse::<jiff::Timestamp>()
+            .ok()
+            .map(|t| t.as_millisecond() as f64)
+    })
+}
+
+impl Parser for SensorCsv {
+    fn info(&self) -> FormatInfo {
+        FormatInfo {
+            id: "sensor-csv",
+            name: "Sensor log (CSV)",
+            extensions: &["csv", "tsv"],
+            kind: DatasetKind::Series,
+        }
+    }
+
+    fn parse(&self, path: &Path, out: &mut Output, job: &mut Job) -> Result<Parsed, ImportError> {
+        let total = fs::metadata(path)?.len();
+        let tab = path.extension().is_some_and(|x| x == "tsv");
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(if tab { b'\t' } else { b',' })
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(|e| ImportError::new("unreadable", e.to_string()))?;
+        let headers = reader
+            .headers()
+            .map_err(|e| ImportError::malformed(e.to_string()).at_line(1))?
+            .clone();
+        let timed = headers
+            .get(0)
+            .is_some_and(|h| TIME_COLUMNS.contains(&channel(h).name.to_lowercase().as_str()));
+        let channels: Vec<Channel> = headers
+            .iter()
+            .skip(usize::from(timed))
+            .map(channel)
+            .collect();
+        if channels.is_empty() {
+            return Err(ImportError::malformed("no data columns").at_line(1));
+        }
+
+        let mut frame = Vec::with_capacity(channels.len());
+        for record in reader.records() {
+            let record = record.map_err(|e| {
+                let line = e.position().map(|p| p.line());
+                let mut error = ImportError::malformed(e.to_string());
+                error.line = line;
+                error
+            })?;
+            let line = record.position().map_or(0, |p| p.line());
+            let mut cells = record.iter();
+            let time = if timed {
+                let cell = cells.next().unwrap_or_default();
+                Some(timestamp(cell).ok_or_else(|| {
+                    ImportError::malformed(format!("not a time: {cell:?}")).at_line(line)
+                })?)
+            } else {
+                None
+            };
+            frame.clear();
+            for (i, cell) in cells.enumerate().take(channels.len()) {
+                let value = if cell.is_empty() {
+                    f32::NAN
+                } else {
+                    cell.parse().map_err(|_| {
+                        let column = &channels[i].name;
+                        ImportError::malformed(format!("{column}: not a number: {cell:?}"))
+                            .at_line(line)
+                    })?
+                };
+                frame.push(value);
+            }
+            frame.resize(channels.len(), f32::NAN);
+            match time {
+                Some(time) => out.timed_frame(time, &frame)?,
+                None => out.frame(&frame)?,
+            }
+            if out.frames.is_multiple_of(1024) {
+                cancelled(job)?;
+                job.progress(record.position().map_or(0, |p| p.byte()), total);
+            }
+        }
+        Ok(Parsed {
+            sample_rate: None,
+            channels,
+        })
+    }
+}
+
+/// A recording session's segments, stitched in order. Not offered for
+/// files; see `recording`.
+struct Recording;
+
+impl Parser for Recording {
+    fn info(&self) -> FormatInfo {
+        FormatInfo {
+            id: "recording",
+            name: "Recording",
+            extensions: &[],
+            kind: DatasetKind::Audio,
+        }
+    }
+
+    fn parse(&self, path: &Path, out: &mut Output, job: &mut Job) -> Result<Parsed, ImportError> {
+        let stored = crate::recording::Stored::read(path).map_err(ImportError::malformed)?;
+        let channels = usize::from(stored.channels);
+        let total = stored.frames();
+        let mut bytes = vec![0u8; channels * 4];
+        let mut frame = vec![0f32; channels];
+        for segment in &stored.segments {
+            let mut reader = BufReader::new(File::open(&segment.path)?);
+            for _ in 0..segment.frames {
+                reader.read_exact(&mut bytes)?;
+                for (value, chunk) in frame.iter_mut().zip(bytes.chunks_exact(4)) {
+                    *value = f32::from_le_bytes(chunk.try_into().expect("4-byte chunk"));
+                }
+                out.frame(&frame)?;
+                if out.frames.is_multiple_of(4096) {
+                    cancelled(job)?;
+                    job.progress(out.frames, total);
+                }
+            }
+        }
+        Ok(Parsed {
+            sample_rate: Some(stored.sample_rate),
+            channels: numbered_channels(stored.channels),
+        })
+    }
+}
+
+/// The parser for `path`, by extension and then by signature.
+fn parser_for(path: &Path) -> Result<&'static dyn Parser, ImportError> {
+    let extension = path
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if let Some(parser) = PARSERS
+        .iter()
+        .find(|p| p.info().extensions.contains(&extension.as_str()))
+    {
+        return Ok(*parser);
+    }
+    let mut head = [0u8; 16];
+    let read = File::open(path)?.read(&mut head)?;
+    PARSERS
+        .iter()
+        .find(|p| p.sniff(&head[..read]))
+        .copied()
+        .ok_or_else(|| ImportError::new("unsupported", "not a format that can be imported"))
+}
+
+fn new_id() -> Result<String, ImportError> {
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|e| ImportError::new("unreadable", e.to_string()))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Parse one file into `imports/<id>/` under the project. Output goes to a
+/// hidden directory first, so a failed or cancelled import leaves nothing
+/// behind.
+fn import_one(
+    app: &AppHandle,
+    imports: &Path,
+    path: &Path,
+    parser: &dyn Parser,
+    scope: Option<Scope>,
+) -> Result<ImportMeta, ImportError> {
+    let info = parser.info();
+    let id = new_id()?;
+    let partial = imports.join(format!(".{id}.partial"));
+    let mut job = Job::start(app, "import").within(scope);
+    let _ = app.emit(
+        "spectrus://import-started",
+        Started {
+            job: job.id(),
+            path: &path.to_string_lossy(),
+            format: info.id,
+        },
+    );
+
+    let result = (|| {
+        let mut out = Output::create(&partial)?;
+        let parsed = parser.parse(path, &mut out, &mut job)?;
+        let (frames, timed) = out.finish()?;
+        let meta = ImportMeta {
+            id: id.clone(),
+            format: info.id,
+            source: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            imported_at: crate::time::now_ms(app),
+            kind: info.kind,
+            sample_rate: parsed.sample_rate,
+            frames,
+            channels: parsed.channels,
+            timed,
+        };
+        let json = serde_json::to_vec_pretty(&meta)
+            .map_err(|e| ImportError::new("unreadable", e.to_string()))?;
+        crate::settings::write_atomic(&partial.join("meta.json"), &json)
+            .and_then(|()| fs::rename(&partial, imports.join(&id)).map_err(|e| e.to_string()))
+            .map_err(|e| ImportError::new("unreadable", e))?;
+        job.progress(frames, frames);
+        Ok(meta)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&partial);
+    }
+    result
+}
+
+/// Where a project's imports go, created if needed.
+pub(crate) fn imports_dir(profile: &Profile, project: &str) -> Result<PathBuf, String> {
+    let dir = crate::sync::project_dir(profile, project)?.join("imports");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Import `file` into `imports`, reporting the outcome under `path` and
+/// emitting it as `spectrus://import-finished`. With a `scope`, the import
+/// stops when the calling command is cancelled.
+pub(crate) fn import_file(
+    app: &AppHandle,
+    imports: &Path,
+    path: String,
+    file: Result<PathBuf, ImportError>,
+    scope: Option<Scope>,
+) -> ImportResult {
+    let mut format = None;
+    let outcome = file.and_then(|file| {
+        let parser = parser_for(&file)?;
+        format = Some(parser.info().id);
+        import_one(app, imports, &file, parser, scope)
+    });
+    finished(app, path, format, outcome)
+}
+
+fn finished(
+    app: &AppHandle,
+    path: String,
+    format: Option<&'static str>,
+    outcome: Result<ImportMeta, ImportError>,
+) -> ImportResult {
+    let (import, error) = match outcome {
+        Ok(meta) => (Some(meta), None),
+        Err(e) => (None, Some(e)),
+    };
+    let result = ImportResult {
+        path,
+        format,
+        import,
+        error,
+    };
+    let _ = app.emit("spectrus://import-finished", &result);
+    result
+}
+
+/// Stitch the recording session in `dir` into `imports`, reported like a
+/// file import.
+pub(crate) fn import_recording(app: &AppHandle, imports: &Path, dir: &Path) -> ImportResult {
+    let outcome = import_one(app, imports, dir, &Recording, None);
+    finished(app, dir.display().to_string(), Some("recording"), outcome)
+}
+
+/// The result for a recording that couldn't be stitched at all.
+pub(crate) fn failed(dir: &Path, message: String) -> ImportResult {
+    ImportResult {
+        path: dir.display().to_string(),
+        format: Some("recording"),
+        import: None,
+        error: Some(ImportError::new("unreadable", message)),
+    }
+}
+
+/// Formats `import_files` accepts.
+#[tauri::command]
+pub fn import_formats() -> Vec<FormatInfo> {
+    PARSERS.iter().map(|p| p.info()).collect()
+}
+
+/// Import dropped or opened files into a project, one "import" job per file
+/// (announced with `spectrus://import-started`). Each file is normalised
+/// into `imports/<id>/` in the project: `meta.json`, the samples and, for
+/// timestamped logs, the times. One file failing doesn't stop the rest;
+/// its result carries the error instead.
+#[tauri::command]
+pub async fn import_files(
+    app: AppHandle,
+    window: Window,
+    call: Call,
+    scope: State<'_, FsScope>,
+    project: String,
+    paths: Vec<String>,
+) -> Result<Vec<ImportResult>, String> {
+    let imports = imports_dir(&app.state::<ProfileState>().current(), &project)?;
+    let checked: Vec<(String, Result<PathBuf, String>)> = paths
+        .into_iter()
+        .map(|p| {
+            let checked = scope.check(window.label(), &p);
+            (p, checked)
+        })
+        .collect();
+    let call = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        checked
+            .into_iter()
+            .map(|(path, checked)| {
+                let file = checked.map_err(|e| ImportError::new("denied", e));
+                import_file(&app, &imports, path, file, Some(call.clone()))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use tauri::{AppHandle, Context, Manager};
+
+use crate::profile::{self, ProfileState};
+use crate::shred;
+
+/// Flag passed to the child process spawned by `incognito_start`.
+const FLAG: &str = "--incognito";
+
+/// Parent of every incognito session dir; one child per process id.
+fn sessions_root() -> PathBuf {
+    std::env::temp_dir().join("spectrus-incognito")
+}
+
+/// Mark every configured window incognito so the webview keeps cookies,
+/// localStorage and its HTTP cache in memory only.
+pub fn configure(context: &mut Context) {
+    for window in &mut context.config_mut().app.windows {
+        window.incognito = true;
+        window.title = format!("{} (Incognito)", window.title);
+    }
+}
+
+/// Create the throwaway profile for this process. Sessions left behind by a
+/// crash are wiped first.
+pub fn open_profile() -> Result<ProfileState, String> {
+    sweep_stale();
+    let root = sessions_root().join(std::process::id().to_string());
+    ProfileState::open_ephemeral(root)
+}
+
+/// Shred the session directory. Called once the last window has closed.
+pub fn wipe(app: &AppHandle) {
+    let profiles = app.state::<ProfileState>();
+    profiles.release();
+    if let Err(e) = shred::shred_dir(profiles.root()) {
+        eprintln!("incognito: wipe failed: {e}");
+    }
+}
+
+/// Remove session dirs whose owning process is gone (its lock is free).
+fn sweep_stale() {
+    let Ok(entries) = fs::read_dir(sessions_root()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !profile::is_locked_elsewhere(&dir.join("incognito")) {
+            let _ = shred::shred_dir(&dir);
+        }
+    }
+}
+
+/// Launch a separate incognito Spectrus process. Its data lives in a temp dir
+/// and is wiped when its window closes; this process is unaffected.
+#[tauri::command]
+pub fn incognito_start() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    Command::new(exe)
+        .arg(FLAG)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
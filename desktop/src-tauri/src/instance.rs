@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::cli::{self, Args};
+
+/// Written into the open profile's directory: "<port> <token>". A second
+/// launch for the same profile (a deep link, `spectrus open <file>`) reads it
+/// and hands its arguments over instead of failing on the profile lock.
+const FILE: &str = ".instance";
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Managed state: where this process accepts forwarded launches.
+pub struct Instance {
+    port: u16,
+    token: String,
+}
+
+impl Instance {
+    /// Record this process as the owner of `dir`'s profile. Called at startup
+    /// and after every profile switch.
+    pub fn announce(&self, dir: &Path) {
+        if let Err(e) = fs::write(dir.join(FILE), format!("{} {}", self.port, self.token)) {
+            eprintln!("instance: could not announce: {e}");
+        }
+    }
+}
+
+/// Try to hand `args` to the process that has `profile_dir` open. Returns
+/// `true` when it accepted them and this process should exit.
+pub fn forward(profile_dir: &Path, args: &Args) -> bool {
+    match try_forward(profile_dir, args) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("instance: forwarding failed: {e}");
+            false
+        }
+    }
+}
+
+fn try_forward(profile_dir: &Path, args: &Args) -> Result<(), String> {
+    let text = fs::read_to_string(profile_dir.join(FILE)).map_err(|e| e.to_string())?;
+    let (port, token) = text
+        .trim()
+        .split_once(' ')
+        .ok_or("malformed instance file")?;
+    let port: u16 = port.parse().map_err(|_| "malformed instance file")?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(args).map_err(|e| e.to_string())?;
+    writeln!(stream, "{token}\n{json}").map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err("the running instance refused the request".into())
+    }
+}
+
+/// Start accepting forwarded launches on a loopback port. The random token
+/// keeps other local users' processes from driving this one.
+pub fn listen(app: &AppHandle) -> Result<Instance, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+    let app = app.clone();
+    let expected = token.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve(&app, stream, &expected) {
+                eprintln!("instance: bad forward: {e}");
+            }
+        }
+    });
+    Ok(Instance { port, token })
+}
+
+fn serve(app: &AppHandle, mut stream: TcpStream, token: &str) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    if line.trim_end() != token {
+        return Err("wrong token".into());
+    }
+    line.clear();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let args: Args = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+    stream.write_all(b"ok\n").map_err(|e| e.to_string())?;
+    cli::forwarded(app, args);
+    Ok(())
+}
+
+/// Re-announce after a profile switch so launches for the new profile find us.
+pub fn announce(app: &AppHandle, dir: &Path) {
+    if let Some(instance) = app.try_state::<Instance>() {
+        instance.announce(dir);
+    }
+}
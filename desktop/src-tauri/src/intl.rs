@@ -0,0 +1,411 @@
+use std::cmp::Ordering;
+
+use icu::collator::options::{CaseLevel, CollatorOptions, Strength};
+use icu::collator::preferences::CollationNumericOrdering;
+use icu::collator::{Collator, CollatorPreferences};
+use icu::datetime::fieldsets::builder::{DateFields, FieldSetBuilder};
+use icu::datetime::options::{Length, TimePrecision};
+use icu::datetime::DateTimeFormatter;
+use icu::decimal::input::Decimal;
+use icu::decimal::options::{DecimalFormatterOptions, GroupingStrategy};
+use icu::decimal::DecimalFormatter;
+use icu::locale::Locale;
+use icu::time::{DateTime, Time};
+use icu_experimental::dimension::units::categorized_formatter::CategorizedFormatter;
+use icu_experimental::dimension::units::options::{UnitsFormatterOptions, Width};
+use icu_experimental::measure::category::{Area, Duration, Length as Len, Mass, Volume};
+use icu_experimental::relativetime::options::Numeric;
+use icu_experimental::relativetime::{RelativeTimeFormatter, RelativeTimeFormatterOptions};
+use jiff::tz::TimeZone;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Settings key holding the formatting locale, when the user picked one
+/// other than the OS locale.
+const SETTING: &str = "intl.locale";
+
+/// Used when neither the profile nor the OS names a locale we can parse.
+const FALLBACK: &str = "en-US";
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntlWidth {
+    Long,
+    #[default]
+    Short,
+    Narrow,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntlStyle {
+    Full,
+    Long,
+    Medium,
+    Short,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NumberOptions {
+    minimum_fraction_digits: Option<u8>,
+    maximum_fraction_digits: Option<u8>,
+    /// Leave out thousands separators.
+    no_grouping: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DateTimeOptions {
+    date_style: Option<IntlStyle>,
+    time_style: Option<IntlStyle>,
+    /// IANA zone such as `Europe/Berlin`; the system zone if unset.
+    time_zone: Option<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelativeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RelativeOptions {
+    width: IntlWidth,
+    /// Use words like "yesterday" where the locale has them instead of
+    /// always writing "1 day ago".
+    auto: bool,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sensitivity {
+    /// Only base letters differ: a = á = A.
+    Base,
+    /// Accents differ too: a ≠ á, a = A.
+    Accent,
+    /// Case differs too: a ≠ A, a = á.
+    Case,
+    #[default]
+    Variant,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SortOptions {
+    sensitivity: Sensitivity,
+    /// Compare digit runs by value, so "file2" sorts before "file10".
+    numeric: bool,
+}
+
+fn parse_locale(tag: &str) -> Result<Locale, String> {
+    Locale::try_from_str(tag).map_err(|e| format!("{tag}: {e}"))
+}
+
+/// The locale chosen for the profile, else the OS locale.
+fn default_locale(profile: &Profile) -> Locale {
+    settings::get(profile, SETTING)
+        .and_then(|v| v.as_str().and_then(|s| parse_locale(s).ok()))
+        .or_else(|| sys_locale::get_locale().and_then(|s| parse_locale(&s).ok()))
+        .unwrap_or_else(|| parse_locale(FALLBACK).unwrap())
+}
+
+/// A per-call override, else the profile default.
+fn locale(profiles: &ProfileState, tag: Option<String>) -> Result<Locale, String> {
+    match tag {
+        Some(tag) => parse_locale(&tag),
+        None => Ok(default_locale(&profiles.current())),
+    }
+}
+
+fn decimal(value: f64) -> Result<Decimal, String> {
+    if !value.is_finite() {
+        return Err(format!("cannot format {value}"));
+    }
+    // f64's Display is the shortest exact decimal form, never exponential.
+    Decimal::try_from_str(&value.to_string()).map_err(|e| e.to_string())
+}
+
+fn width(width: IntlWidth) -> Width {
+    match width {
+        IntlWidth::Long => Width::Long,
+        IntlWidth::Short => Width::Short,
+        IntlWidth::Narrow => Width::Narrow,
+    }
+}
+
+/// Format `value` in `unit`, given as a CLDR unit id (`kilogram`, `hour`).
+/// ICU4X has display data for a limited set of units so far.
+fn unit(locale: &Locale, value: &Decimal, unit: &str, width: Width) -> Result<String, String> {
+    // Each unit category is its own type, so this can't be a generic fn
+    // without naming ICU's provider bounds.
+    macro_rules! run {
+        ($unit:expr) => {
+            CategorizedFormatter::try_new_core(
+                locale.into(),
+                $unit,
+                UnitsFormatterOptions::from(width),
+            )
+            .map(|f| f.format_fixed_decimal(value).to_string())
+            .map_err(|e| e.to_string())
+        };
+    }
+
+    match unit {
+        "nanosecond" => run!(Duration::nanosecond()),
+        "microsecond" => run!(Duration::microsecond()),
+        "millisecond" => run!(Duration::millisecond()),
+        "second" => run!(Duration::second()),
+        "minute" => run!(Duration::minute()),
+        "hour" => run!(Duration::hour()),
+        "day" => run!(Duration::day()),
+        "week" => run!(Duration::week()),
+        "month" => run!(Duration::month()),
+        "year" => run!(Duration::year()),
+        "meter" => run!(Len::meter()),
+        "gram" => run!(Mass::gram()),
+        "kilogram" => run!(Mass::kilogram()),
+        "liter" => run!(Volume::liter()),
+        "cubic-meter" => run!(Volume::cubic_meter()),
+        "square-meter" => run!(Area::square_meter()),
+        other => Err(format!("unsupported unit {other:?}")),
+    }
+}
+
+fn relative_formatter(
+    locale: &Locale,
+    unit: RelativeUnit,
+    width: IntlWidth,
+    options: RelativeTimeFormatterOptions,
+) -> Result<RelativeTimeFormatter, String> {
+    use IntlWidth::*;
+    use RelativeUnit::*;
+    let prefs = locale.into();
+    let formatter = match (width, unit) {
+        (Long, Second) => RelativeTimeFormatter::try_new_long_second(prefs, options),
+        (Long, Minute) => RelativeTimeFormatter::try_new_long_minute(prefs, options),
+        (Long, Hour) => RelativeTimeFormatter::try_new_long_hour(prefs, options),
+        (Long, Day) => RelativeTimeFormatter::try_new_long_day(prefs, options),
+        (Long, Week) => RelativeTimeFormatter::try_new_long_week(prefs, options),
+        (Long, Month) => RelativeTimeFormatter::try_new_long_month(prefs, options),
+        (Long, Quarter) => RelativeTimeFormatter::try_new_long_quarter(prefs, options),
+        (Long, Year) => RelativeTimeFormatter::try_new_long_year(prefs, options),
+        (Short, Second) => RelativeTimeFormatter::try_new_short_second(prefs, options),
+        (Short, Minute) => RelativeTimeFormatter::try_new_short_minute(prefs, options),
+        (Short, Hour) => RelativeTimeFormatter::try_new_short_hour(prefs, options),
+        (Short, Day) => RelativeTimeFormatter::try_new_short_day(prefs, options),
+        (Short, Week) => RelativeTimeFormatter::try_new_short_week(prefs, options),
+        (Short, Month) => RelativeTimeFormatter::try_new_short_month(prefs, options),
+        (Short, Quarter) => RelativeTimeFormatter::try_new_short_quarter(prefs, options),
+        (Short, Year) => RelativeTimeFormatter::try_new_short_year(prefs, options),
+        (Narrow, Second) => RelativeTimeFormatter::try_new_narrow_second(prefs, options),
+        (Narrow, Minute) => RelativeTimeFormatter::try_new_narrow_minute(prefs, options),
+        (Narrow, Hour) => RelativeTimeFormatter::try_new_narrow_hour(prefs, options),
+        (Narrow, Day) => RelativeTimeFormatter::try_new_narrow_day(prefs, options),
+        (Narrow, Week) => RelativeTimeFormatter::try_new_narrow_week(prefs, options),
+        (Narrow, Month) => RelativeTimeFormatter::try_new_narrow_month(prefs, options),
+        (Narrow, Quarter) => RelativeTimeFormatter::try_new_narrow_quarter(prefs, options),
+        (Narrow, Year) => RelativeTimeFormatter::try_new_narrow_year(prefs, options),
+    };
+    formatter.map_err(|e| e.to_string())
+}
+
+/// Locale the `intl_*` commands use when not given one: the profile's
+/// choice, else the OS locale.
+#[tauri::command]
+pub fn intl_locale(profiles: State<'_, ProfileState>) -> String {
+    default_locale(&profiles.current()).to_string()
+}
+
+/// Format in `locale` (a BCP 47 tag) regardless of the OS locale, or follow
+/// the OS again with `None`. Windows are told via
+/// `spectrus://intl-locale-changed` so they can re-render.
+#[tauri::command]
+pub fn intl_locale_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    locale: Option<String>,
+) -> Result<(), String> {
+    let value = match &locale {
+        Some(tag) => Value::String(parse_locale(tag)?.to_string()),
+        None => Value::Null,
+    };
+    let profile = profiles.current();
+    settings::set(&profile, SETTING, value)?;
+    let _ = app.emit(
+        "spectrus://intl-locale-changed",
+        default_locale(&profile).to_string(),
+    );
+    Ok(())
+}
+
+/// Format a number with the locale's digits and separators.
+#[tauri::command]
+pub fn intl_number(
+    profiles: State<'_, ProfileState>,
+    value: f64,
+    options: Option<NumberOptions>,
+    locale: Option<String>,
+) -> Result<String, String> {
+    let locale = self::locale(&profiles, locale)?;
+    let options = options.unwrap_or_default();
+    let mut value = decimal(value)?;
+    if let Some(max) = options.maximum_fraction_digits {
+        value.round(-i16::from(max));
+    }
+    if let Some(min) = options.minimum_fraction_digits {
+        value.absolute.pad_end(-i16::from(min));
+    }
+    let mut decimal_options = DecimalFormatterOptions::default();
+    if options.no_grouping {
+        decimal_options.grouping_strategy = Some(GroupingStrategy::Never);
+    }
+    let formatter =
+        DecimalFormatter::try_new((&locale).into(), decimal_options).map_err(|e| e.to_string())?;
+    Ok(formatter.format(&value).to_string())
+}
+
+/// Format a measurement such as "3 hr" or "2,5 Kilogramm". `unit` is a CLDR
+/// unit id; durations, meter, gram, kilogram, liter, cubic-meter and
+/// square-meter are supported.
+#[tauri::command]
+pub fn intl_unit(
+    profiles: State<'_, ProfileState>,
+    value: f64,
+    unit: String,
+    width: Option<IntlWidth>,
+    locale: Option<String>,
+) -> Result<String, String> {
+    let locale = self::locale(&profiles, locale)?;
+    self::unit(
+        &locale,
+        &decimal(value)?,
+        &unit,
+        self::width(width.unwrap_or_default()),
+    )
+}
+
+/// Format `timestamp` (milliseconds since the Unix epoch). Without styles
+/// both date and time are shown at medium length.
+#[tauri::command]
+pub fn intl_datetime(
+    profiles: State<'_, ProfileState>,
+    timestamp: i64,
+    options: Option<DateTimeOptions>,
+    locale: Option<String>,
+) -> Result<String, String> {
+    let locale = self::locale(&profiles, locale)?;
+    let options = options.unwrap_or_default();
+    let zone = match &options.time_zone {
+        Some(name) => TimeZone::get(name).map_err(|e| e.to_string())?,
+        None => TimeZone::system(),
+    };
+    let local = jiff::Timestamp::from_millisecond(timestamp)
+        .map_err(|e| e.to_string())?
+        .to_zoned(zone)
+        .datetime();
+
+    let (date_style, time_style) = match (options.date_style, options.time_style) {
+        (None, None) => (Some(IntlStyle::Medium), Some(IntlStyle::Medium)),
+        styles => styles,
+    };
+    let mut builder = FieldSetBuilder::new();
+    builder.length = Some(match date_style.or(time_style) {
+        Some(IntlStyle::Full | IntlStyle::Long) => Length::Long,
+        Some(IntlStyle::Short) => Length::Short,
+        _ => Length::Medium,
+    });
+    builder.date_fields = date_style.map(|style| match style {
+        IntlStyle::Full => DateFields::YMDE,
+        _ => DateFields::YMD,
+    });
+    builder.time_precision = time_style.map(|style| match style {
+        IntlStyle::Short => TimePrecision::Minute,
+        _ => TimePrecision::Second,
+    });
+    let fields = builder
+        .build_composite_datetime()
+        .map_err(|e| e.to_string())?;
+    let formatter =
+        DateTimeFormatter::try_new((&locale).into(), fields).map_err(|e| e.to_string())?;
+
+    let input = DateTime {
+        date: icu::calendar::Date::try_new_iso(
+            i32::from(local.year()),
+            local.month() as u8,
+            local.day() as u8,
+        )
+        .map_err(|e| e.to_string())?,
+        time: Time::try_new(
+            local.hour() as u8,
+            local.minute() as u8,
+            local.second() as u8,
+            local.subsec_nanosecond() as u32,
+        )
+        .map_err(|e| e.to_string())?,
+    };
+    Ok(formatter.format(&input).to_string())
+}
+
+/// Format an offset from now, such as "in 3 days" or "2 hr. ago". Negative
+/// values are in the past.
+#[tauri::command]
+pub fn intl_relative_time(
+    profiles: State<'_, ProfileState>,
+    value: f64,
+    unit: RelativeUnit,
+    options: Option<RelativeOptions>,
+    locale: Option<String>,
+) -> Result<String, String> {
+    let locale = self::locale(&profiles, locale)?;
+    let options = options.unwrap_or_default();
+    let mut formatter_options = RelativeTimeFormatterOptions::default();
+    if options.auto {
+        formatter_options.numeric = Numeric::Auto;
+    }
+    let formatter = relative_formatter(&locale, unit, options.width, formatter_options)?;
+    Ok(formatter.format(decimal(value)?).to_string())
+}
+
+/// Sort `items` in the locale's alphabetical order.
+#[tauri::command]
+pub fn intl_sort(
+    profiles: State<'_, ProfileState>,
+    mut items: Vec<String>,
+    options: Option<SortOptions>,
+    locale: Option<String>,
+) -> Result<Vec<String>, String> {
+    let locale = self::locale(&profiles, locale)?;
+    let options = options.unwrap_or_default();
+    let mut prefs = CollatorPreferences::from(&locale);
+    if options.numeric {
+        prefs.numeric_ordering = Some(CollationNumericOrdering::True);
+    }
+    let mut collator_options = CollatorOptions::default();
+    let (strength, case_level) = match options.sensitivity {
+        Sensitivity::Base => (Strength::Primary, CaseLevel::Off),
+        Sensitivity::Accent => (Strength::Secondary, CaseLevel::Off),
+        Sensitivity::Case => (Strength::Primary, CaseLevel::On),
+        Sensitivity::Variant => (Strength::Tertiary, CaseLevel::Off),
+    };
+    collator_options.strength = Some(strength);
+    collator_options.case_level = Some(case_level);
+    let collator = Collator::try_new(prefs, collator_options).map_err(|e| e.to_string())?;
+    items.sort_by(|a, b| match collator.compare(a, b) {
+        Ordering::Equal => a.cmp(b),
+        order => order,
+    });
+    Ok(items)
+}
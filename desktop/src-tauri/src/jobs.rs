@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::watchdog::Scope;
+
+/// Progress events are throttled to this interval, except for the first and
+/// last, so a tight copy loop doesn't flood the IPC bridge.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Error string returned by a job that stopped because of `job_cancel`.
+pub const CANCELLED: &str = "cancelled";
+
+/// Payload of `spectrus://job-progress`.
+#[derive(Clone, Serialize)]
+struct Progress<'a> {
+    id: u64,
+    kind: &'a str,
+    done: u64,
+    total: u64,
+}
+
+/// Payload of `spectrus://job-finished`.
+#[derive(Clone, Serialize)]
+struct Finished<'a> {
+    id: u64,
+    kind: &'a str,
+    cancelled: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct JobInfo {
+    id: u64,
+    kind: &'static str,
+}
+
+struct Entry {
+    kind: &'static str,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Registry of in-flight background jobs.
+#[derive(Default)]
+pub struct Jobs {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, Entry>>,
+}
+
+/// Handle held by the code doing the work. Dropping it unregisters the job.
+pub struct Job {
+    id: u64,
+    kind: &'static str,
+    app: AppHandle,
+    cancel: Arc<AtomicBool>,
+    last_emit: Option<Instant>,
+    scope: Option<Scope>,
+}
+
+impl Jobs {
+    /// Running jobs, oldest first.
+    pub(crate) fn list(&self) -> Vec<JobInfo> {
+        let active = self.active.lock().unwrap();
+        let mut list: Vec<JobInfo> = active
+            .iter()
+            .map(|(&id, e)| JobInfo { id, kind: e.kind })
+            .collect();
+        list.sort_by_key(|j| j.id);
+        list
+    }
+
+    /// Cancel every running job of one of `kinds`.
+    pub(crate) fn cancel_kinds(&self, kinds: &[&str]) {
+        for entry in self.active.lock().unwrap().values() {
+            if kinds.contains(&entry.kind) {
+                entry.cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Job {
+    /// Register a new job of `kind` (e.g. "archive-create") and announce it
+    /// with a zero-progress event so the frontend learns its id.
+    pub fn start(app: &AppHandle, kind: &'static str) -> Job {
+        let jobs = app.state::<Jobs>();
+        let id = jobs.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        jobs.active.lock().unwrap().insert(
+            id,
+            Entry {
+                kind,
+                cancel: cancel.clone(),
+            },
+        );
+        let mut job = Job {
+            id,
+            kind,
+            app: app.clone(),
+            cancel,
+            last_emit: None,
+            scope: None,
+        };
+        job.progress(0, 0);
+        job
+    }
+
+    /// Also stop once the command that started the job is cancelled or
+    /// times out (see `watchdog`). `None` for work no call is waiting on.
+    pub fn within(mut self, scope: impl Into<Option<Scope>>) -> Job {
+        self.scope = scope.into();
+        self
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Report progress; `total` may be 0 when unknown.
+    pub fn progress(&mut self, done: u64, total: u64) {
+        let now = Instant::now();
+        let due = self
+            .last_emit
+            .is_none_or(|at| now.duration_since(at) >= PROGRESS_INTERVAL);
+        if !due && done != total {
+            return;
+        }
+        self.last_emit = Some(now);
+        let payload = Progress {
+            id: self.id,
+            kind: self.kind,
+            done,
+            total,
+        };
+        if let Err(e) = self.app.emit("spectrus://job-progress", payload) {
+            eprintln!("job {}: progress emit error: {e}", self.id);
+        }
+    }
+
+    /// `Err(CANCELLED)` once `job_cancel` has been called for this job, or
+    /// the scope's error once it ends; meant to be `?`-ed between units of
+    /// work.
+    pub fn check(&self) -> Result<(), String> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(CANCELLED.into());
+        }
+        match &self.scope {
+            Some(scope) => scope.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Job {
+    /// Unregister and emit `spectrus://job-finished`.
+    fn drop(&mut self) {
+        self.app
+            .state::<Jobs>()
+            .active
+            .lock()
+            .unwrap()
+            .remove(&self.id);
+        let payload = Finished {
+            id: self.id,
+            kind: self.kind,
+            cancelled: self.cancel.load(Ordering::Relaxed),
+        };
+        let _ = self.app.emit("spectrus://job-finished", payload);
+    }
+}
+
+/// Ask a running job to stop. It finishes with the error `"cancelled"`.
+/// Unknown ids are ignored — the job may already have finished.
+#[tauri::command]
+pub fn job_cancel(jobs: State<'_, Jobs>, id: u64) {
+    if let Some(entry) = jobs.active.lock().unwrap().get(&id) {
+        entry.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// List running jobs.
+#[tauri::command]
+pub fn job_list(jobs: State<'_, Jobs>) -> Vec<JobInfo> {
+    jobs.list()
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Layout switches aren't pushed to us on any platform in a way that works
+/// without a focused window, so the active layout is polled.
+const POLL: Duration = Duration::from_secs(1);
+
+/// The active keyboard layout.
+#[derive(Clone, PartialEq, Serialize)]
+pub struct KeyboardLayout {
+    /// Platform identifier: an XKB layout such as `de+nodeadkeys` on Linux,
+    /// the HKL in hex on Windows, the input source id such as
+    /// `com.apple.keylayout.German` on macOS.
+    id: String,
+    /// BCP 47 tag of the input language, when the platform reports one.
+    language: Option<String>,
+    /// Human-readable name, when the platform reports one.
+    name: Option<String>,
+}
+
+/// Watch the active layout, emitting `spectrus://keyboard-layout-changed` with
+/// the new `KeyboardLayout` whenever the user switches.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut last = platform::current(&app);
+        loop {
+            std::thread::sleep(POLL);
+            let now = platform::current(&app);
+            if now != last {
+                if let Some(layout) = &now {
+                    let _ = app.emit("spectrus://keyboard-layout-changed", layout);
+                }
+                last = now;
+            }
+        }
+    });
+}
+
+/// Current keyboard layout, or `None` if it can't be determined (e.g. a
+/// Wayland compositor other than GNOME or KDE).
+#[tauri::command]
+pub async fn keyboard_layout(app: AppHandle) -> Option<KeyboardLayout> {
+    tauri::async_runtime::spawn_blocking(move || platform::current(&app))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Run `f` on the UI thread and wait for its result. Windows keeps layouts
+/// per thread and macOS' Text Input Sources API is main-thread only.
+#[cfg(any(windows, target_os = "macos"))]
+pub(crate) fn on_main<T: Send + 'static>(
+    app: &AppHandle,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    app.run_on_main_thread(move || {
+        let _ = tx.send(f());
+    })
+    .ok()?;
+    rx.recv_timeout(Duration::from_secs(5)).ok()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::{AppHandle, Manager};
+
+    use super::KeyboardLayout;
+
+    fn xkb(id: &str) -> KeyboardLayout {
+        KeyboardLayout {
+            id: id.to_string(),
+            language: None,
+            name: None,
+        }
+    }
+
+    fn output(program: &str, args: &[&str]) -> Option<String> {
+        let out = Command::new(program).args(args).output().ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    /// GNOME lists input sources most-recently-used first, e.g.
+    /// `[('xkb', 'de+nodeadkeys'), ('ibus', 'anthy')]`.
+    fn gnome() -> Option<KeyboardLayout> {
+        let out = output(
+            "gsettings",
+            &["get", "org.gnome.desktop.input-sources", "mru-sources"],
+        )?;
+        let first = out.split("('").nth(1)?;
+        let mut parts = first.split('\'');
+        let kind = parts.next()?;
+        let id = parts.nth(1)?;
+        Some(if kind == "xkb" {
+            xkb(id)
+        } else {
+            KeyboardLayout {
+                id: format!("{kind}:{id}"),
+                language: None,
+                name: None,
+            }
+        })
+    }
+
+    /// X11 without a desktop that tracks sources: the first configured
+    /// layout, which is the active one unless the user switches groups.
+    fn x11() -> Option<KeyboardLayout> {
+        let out = output("setxkbmap", &["-query"])?;
+        let line = out.lines().find(|l| l.starts_with("layout:"))?;
+        let layout = line["layout:".len()..].trim().split(',').next()?;
+        (!layout.is_empty()).then(|| xkb(layout))
+    }
+
+    pub fn current(app: &AppHandle) -> Option<KeyboardLayout> {
+        let kde = app
+            .try_state::<crate::linux_dbus::DbusState>()
+            .and_then(|dbus| dbus.keyboard_layout());
+        if let Some((id, name)) = kde {
+            return Some(KeyboardLayout {
+                id,
+                language: None,
+                name: Some(name),
+            });
+        }
+        gnome().or_else(x11)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use windows::core::PCWSTR;
+    use windows::Win32::Globalization::{
+        GetLocaleInfoEx, LCIDToLocaleName, LOCALE_SLOCALIZEDDISPLAYNAME,
+    };
+    use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyboardLayout;
+
+    use super::{on_main, KeyboardLayout};
+
+    const LOCALE_NAME_MAX_LENGTH: usize = 85;
+
+    pub fn current(app: &AppHandle) -> Option<KeyboardLayout> {
+        // SAFETY: 0 means the calling thread, which owns the app's windows.
+        let hkl = on_main(app, || unsafe { GetKeyboardLayout(0) }.0 as usize)?;
+        // The low word of an HKL is the input language's LANGID.
+        let langid = (hkl & 0xFFFF) as u32;
+        let mut locale = [0u16; LOCALE_NAME_MAX_LENGTH];
+        // SAFETY: the buffer is sized to the documented maximum.
+        let len = unsafe { LCIDToLocaleName(langid, Some(&mut locale), 0) };
+        let language = (len > 1).then(|| String::from_utf16_lossy(&locale[..len as usize - 1]));
+        let mut display = [0u16; 256];
+        // SAFETY: `locale` was NUL-terminated by LCIDToLocaleName.
+        let len = unsafe {
+            GetLocaleInfoEx(
+                PCWSTR(locale.as_ptr()),
+                LOCALE_SLOCALIZEDDISPLAYNAME,
+                Some(&mut display),
+            )
+        };
+        let name = (language.is_some() && len > 1)
+            .then(|| String::from_utf16_lossy(&display[..len as usize - 1]));
+        Some(KeyboardLayout {
+            id: format!("{hkl:08x}"),
+            language,
+            name,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+
+    use objc2_foundation::{NSArray, NSString};
+    use tauri::AppHandle;
+
+    use super::{on_main, KeyboardLayout};
+
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        fn TISCopyCurrentKeyboardLayoutInputSource() -> *mut c_void;
+        fn TISGetInputSourceProperty(source: *mut c_void, key: *const c_void) -> *const c_void;
+        static kTISPropertyInputSourceID: *const c_void;
+        static kTISPropertyLocalizedName: *const c_void;
+        static kTISPropertyInputSourceLanguages: *const c_void;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// SAFETY: `value` must be null or a CFStringRef, which is toll-free
+    /// bridged to NSString.
+    unsafe fn string(value: *const c_void) -> Option<String> {
+        (!value.is_null()).then(|| (*value.cast::<NSString>()).to_string())
+    }
+
+    fn query() -> Option<KeyboardLayout> {
+        // SAFETY: called on the main thread; the copied source is released
+        // below and the properties are only borrowed from it.
+        unsafe {
+            let source = TISCopyCurrentKeyboardLayoutInputSource();
+            if source.is_null() {
+                return None;
+            }
+            let id = string(TISGetInputSourceProperty(source, kTISPropertyInputSourceID));
+            let name = string(TISGetInputSourceProperty(source, kTISPropertyLocalizedName));
+            let languages = TISGetInputSourceProperty(source, kTISPropertyInputSourceLanguages);
+            let language = (!languages.is_null())
+                .then(|| (*languages.cast::<NSArray<NSString>>()).firstObject())
+                .flatten()
+                .map(|l| l.to_string());
+            CFRelease(source);
+            Some(KeyboardLayout {
+                id: id?,
+                language,
+                name,
+            })
+        }
+    }
+
+    pub fn current(app: &AppHandle) -> Option<KeyboardLayout> {
+        on_main(app, query).flatten()
+    }
+}
@@ -1,33 +1,283 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
 use keyring::Entry;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::{self, Action};
+use crate::portable::Portable;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
 
 /// Service name used as the keychain namespace for all Spectrus entries.
-const SERVICE: &str = "com.spectrus.app";
+/// Non-default profiles append their name (see `Profile::keychain_service`).
+pub const SERVICE: &str = "com.spectrus.app";
 
-/// Store `value` under `key` in the OS credential store.
-#[tauri::command]
-pub fn keychain_set(key: String, value: String) -> Result<(), String> {
-    Entry::new(SERVICE, &key)
+/// Error string returned while the OS store is locked, after emitting
+/// `spectrus://keychain-locked`. `keychain_unlock` prompts to unlock it.
+pub const LOCKED: &str = "keychain locked";
+
+/// In-memory stand-in for the OS store used by incognito sessions, so their
+/// secrets never reach disk and vanish with the process. Keyed by
+/// (service, key) so account namespaces stay separate.
+#[derive(Default)]
+pub struct MemoryStore(Mutex<HashMap<(String, String), String>>);
+
+/// Fallback store for portable mode: a JSON file in the profile directory, so
+/// tokens travel with the data instead of staying in the host's keychain.
+/// Unencrypted — anyone holding the drive can read it. Keyed like
+/// `MemoryStore`, as "service/key".
+fn file_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("keychain.json")
+}
+
+fn file_key(profile: &Profile, key: &str) -> String {
+    format!("{}/{key}", profile.keychain_service())
+}
+
+fn file_update(profile: &Profile, key: &str, value: Option<String>) -> Result<(), String> {
+    let path = file_path(profile);
+    let mut store = settings::read_file(&path);
+    match value {
+        Some(v) => store.insert(file_key(profile, key), Value::String(v)),
+        None => store.remove(&file_key(profile, key)),
+    };
+    let json = serde_json::to_vec_pretty(&store).map_err(|e| e.to_string())?;
+    settings::write_atomic(&path, &json)
+}
+
+fn entry(profile: &Profile, key: &str) -> keyring::Result<Entry> {
+    Entry::new(&profile.keychain_service(), key)
+}
+
+/// keyring-rs reports a locked Secret Service collection as a generic
+/// "no storage access", indistinguishable from a missing daemon; ask the
+/// collection itself.
+#[cfg(target_os = "linux")]
+fn is_locked(app: &AppHandle, e: &keyring::Error) -> bool {
+    matches!(e, keyring::Error::NoStorageAccess(_))
+        && app
+            .try_state::<crate::linux_dbus::DbusState>()
+            .and_then(|dbus| dbus.keyring_locked())
+            .unwrap_or(false)
+}
+
+/// Other platforms' stores unlock with the user session.
+#[cfg(not(target_os = "linux"))]
+fn is_locked(_app: &AppHandle, _e: &keyring::Error) -> bool {
+    false
+}
+
+fn store_error(app: &AppHandle, e: keyring::Error) -> String {
+    if is_locked(app, &e) {
+        let _ = app.emit("spectrus://keychain-locked", ());
+        return LOCKED.into();
+    }
+    e.to_string()
+}
+
+/// Store `value` under `key` in whichever store the active profile uses;
+/// the counterpart of `read`.
+pub(crate) fn write(app: &AppHandle, key: &str, value: String) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    if app.state::<Portable>().0.is_some() && !profile.ephemeral {
+        return file_update(&profile, key, Some(value));
+    }
+    if profile.ephemeral {
+        app.state::<MemoryStore>()
+            .0
+            .lock()
+            .unwrap()
+            .insert((profile.keychain_service(), key.to_string()), value);
+        return Ok(());
+    }
+    entry(&profile, key)
         .and_then(|e| e.set_password(&value))
-        .map_err(|e| e.to_string())
+        .map_err(|e| store_error(app, e))
 }
 
-/// Retrieve the value stored under `key`, or `null` if it does not exist.
+/// The OS store entry for `key` in the profile's own namespace, whatever
+/// account is signed in, for secrets that protect the profile's data rather
+/// than an account. Portable and incognito profiles have no such entry.
+fn profile_entry(app: &AppHandle, key: &str) -> Result<Entry, String> {
+    let profile = app.state::<ProfileState>().current();
+    if app.state::<Portable>().0.is_some() || profile.ephemeral {
+        return Err("no OS credential store for this profile".into());
+    }
+    let shared = Profile {
+        account: None,
+        ..profile
+    };
+    entry(&shared, key).map_err(|e| store_error(app, e))
+}
+
+/// Backend access to profile secrets is audited like the commands are,
+/// marked `"backend": true`.
+pub(crate) fn audit_backend(app: &AppHandle, action: Action, key: &str) {
+    audit::record(app, action, json!({ "key": key, "backend": true }));
+}
+
+pub(crate) fn read_profile_secret(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    audit_backend(app, Action::KeychainRead, key);
+    match profile_entry(app, key)?.get_password() {
+        Ok(v) => Ok(Some(v)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(store_error(app, e)),
+    }
+}
+
+pub(crate) fn write_profile_secret(app: &AppHandle, key: &str, value: &str) -> Result<(), String> {
+    audit_backend(app, Action::KeychainWrite, key);
+    profile_entry(app, key)?
+        .set_password(value)
+        .map_err(|e| store_error(app, e))
+}
+
+pub(crate) fn delete_profile_secret(app: &AppHandle, key: &str) -> Result<(), String> {
+    audit_backend(app, Action::KeychainDelete, key);
+    match profile_entry(app, key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(store_error(app, e)),
+    }
+}
+
+/// Where installation-wide secrets go in portable mode: next to the
+/// profiles, travelling with them like the per-profile file store.
+fn app_file_path(app: &AppHandle) -> PathBuf {
+    app.state::<ProfileState>().root().join("keychain.json")
+}
+
+/// A secret of the installation rather than of a profile or account, like
+/// the audit chain key: the OS store under `SERVICE`, memory in incognito
+/// sessions and a file next to the profiles in portable mode. Not audited,
+/// since the audit log itself reads one.
+pub(crate) fn read_app_secret(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    let profile = app.state::<ProfileState>().current();
+    if profile.ephemeral {
+        let memory = app.state::<MemoryStore>();
+        let memory = memory.0.lock().unwrap();
+        return Ok(memory.get(&(SERVICE.to_string(), key.to_string())).cloned());
+    }
+    if app.state::<Portable>().0.is_some() {
+        let store = settings::read_file(&app_file_path(app));
+        return Ok(store.get(key).and_then(Value::as_str).map(str::to_string));
+    }
+    match Entry::new(SERVICE, key).and_then(|e| e.get_password()) {
+        Ok(v) => Ok(Some(v)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(store_error(app, e)),
+    }
+}
+
+pub(crate) fn write_app_secret(app: &AppHandle, key: &str, value: String) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    if profile.ephemeral {
+        app.state::<MemoryStore>()
+            .0
+            .lock()
+            .unwrap()
+            .insert((SERVICE.to_string(), key.to_string()), value);
+        return Ok(());
+    }
+    if app.state::<Portable>().0.is_some() {
+        let path = app_file_path(app);
+        let mut store = settings::read_file(&path);
+        store.insert(key.to_string(), Value::String(value));
+        let json = serde_json::to_vec_pretty(&store).map_err(|e| e.to_string())?;
+        return settings::write_atomic(&path, &json);
+    }
+    Entry::new(SERVICE, key)
+        .and_then(|e| e.set_password(&value))
+        .map_err(|e| store_error(app, e))
+}
+
+/// Store `value` under `key` in the OS credential store.
 #[tauri::command]
-pub fn keychain_get(key: String) -> Result<Option<String>, String> {
-    match Entry::new(SERVICE, &key).and_then(|e| e.get_password()) {
+pub fn keychain_set(app: AppHandle, key: String, value: String) -> Result<(), String> {
+    audit::record(&app, Action::KeychainWrite, json!({ "key": key }));
+    write(&app, &key, value)
+}
+
+/// Read `key` from whichever store the active profile uses. Shared with
+/// backend code that needs a secret without a round trip through the webview.
+pub(crate) fn read(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    let profile = app.state::<ProfileState>().current();
+    if app.state::<Portable>().0.is_some() && !profile.ephemeral {
+        let store = settings::read_file(&file_path(&profile));
+        let value = store.get(&file_key(&profile, key)).and_then(Value::as_str);
+        return Ok(value.map(str::to_string));
+    }
+    if profile.ephemeral {
+        let memory = app.state::<MemoryStore>();
+        let memory = memory.0.lock().unwrap();
+        return Ok(memory
+            .get(&(profile.keychain_service(), key.to_string()))
+            .cloned());
+    }
+    match entry(&profile, key).and_then(|e| e.get_password()) {
         Ok(v) => Ok(Some(v)),
         Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(store_error(app, e)),
     }
 }
 
+/// Retrieve the value stored under `key`, or `null` if it does not exist.
+#[tauri::command]
+pub fn keychain_get(app: AppHandle, key: String) -> Result<Option<String>, String> {
+    audit::record(&app, Action::KeychainRead, json!({ "key": key }));
+    read(&app, &key)
+}
+
 /// Delete the entry stored under `key`. Idempotent — succeeds even if the key
 /// does not exist.
 #[tauri::command]
-pub fn keychain_delete(key: String) -> Result<(), String> {
-    match Entry::new(SERVICE, &key).and_then(|e| e.delete_credential()) {
+pub fn keychain_delete(app: AppHandle, key: String) -> Result<(), String> {
+    audit::record(&app, Action::KeychainDelete, json!({ "key": key }));
+    delete(&app, &key)
+}
+
+/// Remove `key` from whichever store the active profile uses; the
+/// counterpart of `write`.
+pub(crate) fn delete(app: &AppHandle, key: &str) -> Result<(), String> {
+    let profile = app.state::<ProfileState>().current();
+    if app.state::<Portable>().0.is_some() && !profile.ephemeral {
+        return file_update(&profile, key, None);
+    }
+    if profile.ephemeral {
+        app.state::<MemoryStore>()
+            .0
+            .lock()
+            .unwrap()
+            .remove(&(profile.keychain_service(), key.to_string()));
+        return Ok(());
+    }
+    match entry(&profile, key).and_then(|e| e.delete_credential()) {
         Ok(_) => Ok(()),
         Err(keyring::Error::NoEntry) => Ok(()), // already gone — that's fine
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(store_error(app, e)),
+    }
+}
+
+/// Show the OS prompt to unlock the credential store after a `LOCKED` error.
+/// Resolves to whether it is now unlocked; `false` if the user dismissed the
+/// prompt. Only Linux keyrings can be locked on their own.
+#[tauri::command]
+pub async fn keychain_unlock(app: AppHandle) -> Result<bool, String> {
+    #[cfg(target_os = "linux")]
+    {
+        tauri::async_runtime::spawn_blocking(move || {
+            app.try_state::<crate::linux_dbus::DbusState>()
+                .ok_or("no D-Bus session bus")?
+                .keyring_unlock()
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = app;
+        Ok(true)
     }
 }
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+const APP_NAME: &str = "Spectrus";
+
+/// Well-known name and object path of the interface we export.
+const SERVICE_NAME: &str = "org.spectrus.App";
+const SERVICE_PATH: &str = "/org/spectrus/App";
+
+/// Session-bus connection shared by everything in this module. `None` when no
+/// session bus is reachable (headless sessions, some sandboxes); the commands
+/// then fail with a clear error instead of the app refusing to start.
+pub struct DbusState {
+    conn: Option<Connection>,
+    /// Cookie from `org.freedesktop.ScreenSaver.Inhibit`. The inhibition is
+    /// tied to our connection, so it also ends if the process dies.
+    inhibit_cookie: Mutex<Option<u32>>,
+}
+
+#[derive(Deserialize)]
+pub struct NotificationAction {
+    id: String,
+    label: String,
+}
+
+/// Payload of `spectrus://notification-action`.
+#[derive(Clone, Serialize)]
+struct ActionInvoked {
+    notification: u32,
+    action: String,
+}
+
+/// Object exported at `/org/spectrus/App`, e.g.
+/// `gdbus call --session -d org.spectrus.App -o /org/spectrus/App -m org.spectrus.App.Raise`.
+struct AppInterface {
+    app: AppHandle,
+}
+
+#[zbus::interface(name = "org.spectrus.App")]
+impl AppInterface {
+    /// Show and focus the main window.
+    fn raise(&self) {
+        crate::tray::show_main(&self.app);
+    }
+
+    /// Ask the frontend to sync now (`spectrus://sync-requested`).
+    fn sync(&self) {
+        if let Err(e) = self.app.emit("spectrus://sync-requested", ()) {
+            eprintln!("dbus: sync emit error: {e}");
+        }
+    }
+}
+
+impl DbusState {
+    /// Connect to the session bus, export `org.spectrus.App`, and start
+    /// forwarding notification actions to the frontend.
+    pub fn connect(app: &AppHandle) -> DbusState {
+        let conn = match Connection::session() {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                eprintln!("dbus: no session bus: {e}");
+                None
+            }
+        };
+        if let Some(conn) = &conn {
+            export_interface(app, conn);
+            listen_for_actions(app, conn);
+        }
+        DbusState {
+            conn,
+            inhibit_cookie: Mutex::new(None),
+        }
+    }
+
+    /// Whether some process currently owns the well-known bus `name`.
+    pub fn has_owner(&self, name: &str) -> bool {
+        let Some(conn) = &self.conn else {
+            return false;
+        };
+        let Ok(name) = zbus::names::BusName::try_from(name) else {
+            return false;
+        };
+        zbus::blocking::fdo::DBusProxy::new(conn)
+            .ok()
+            .and_then(|bus| bus.name_has_owner(name).ok())
+            .unwrap_or(false)
+    }
+
+    /// KDE's Do Not Disturb switch (`Inhibited` on the notification service).
+    /// `None` where the server doesn't publish it.
+    pub fn notifications_inhibited(&self) -> Option<bool> {
+        let proxy = notifications(self.conn.as_ref()?).ok()?;
+        proxy.get_property("Inhibited").ok()
+    }
+
+    /// Active layout as (short name, display name), from KDE's keyboard
+    /// daemon. `None` outside Plasma.
+    pub fn keyboard_layout(&self) -> Option<(String, String)> {
+        let proxy = Proxy::new(
+            self.conn.as_ref()?,
+            "org.kde.keyboard",
+            "/Layouts",
+            "org.kde.KeyboardLayouts",
+        )
+        .ok()?;
+        let index: u32 = proxy.call("getLayout", &()).ok()?;
+        let layouts: Vec<(String, String, String)> = proxy.call("getLayoutsList", &()).ok()?;
+        let (short, variant, display) = layouts.into_iter().nth(index as usize)?;
+        let id = if variant.is_empty() {
+            short
+        } else {
+            format!("{short}+{variant}")
+        };
+        Some((id, display))
+    }
+
+    /// `ScreenReaderEnabled` on the accessibility bus launcher, which Orca
+    /// sets while it runs. `None` without AT-SPI.
+    pub fn screen_reader_enabled(&self) -> Option<bool> {
+        let proxy = Proxy::new(
+            self.conn.as_ref()?,
+            "org.a11y.Bus",
+            "/org/a11y/bus",
+            "org.a11y.Status",
+        )
+        .ok()?;
+        proxy.get_property("ScreenReaderEnabled").ok()
+    }
+
+    /// Time since the last keyboard or pointer input. Mutter's idle monitor
+    /// on GNOME, the screensaver service elsewhere (KDE reports it in ms).
+    /// `None` where neither answers, e.g. most wlroots compositors.
+    pub fn idle_ms(&self) -> Option<u64> {
+        let conn = self.conn.as_ref()?;
+        let mutter = Proxy::new(
+            conn,
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor",
+        )
+        .and_then(|p| p.call::<_, _, u64>("GetIdletime", &()));
+        if let Ok(ms) = mutter {
+            return Some(ms);
+        }
+        screensaver(conn)
+            .and_then(|p| p.call::<_, _, u32>("GetSessionIdleTime", &()))
+            .ok()
+            .map(u64::from)
+    }
+
+    /// Whether the default Secret Service collection (the login keyring)
+    /// is locked. `None` without a Secret Service or default collection.
+    pub fn keyring_locked(&self) -> Option<bool> {
+        let conn = self.conn.as_ref()?;
+        let collection = default_collection(conn).ok()??;
+        Proxy::new(
+            conn,
+            SECRETS_NAME,
+            collection,
+            "org.freedesktop.Secret.Collection",
+        )
+        .ok()?
+        .get_property("Locked")
+        .ok()
+    }
+
+    /// Ask the Secret Service to unlock the default collection, which shows
+    /// the keyring's password prompt. Blocks until the prompt is answered;
+    /// `Ok(false)` when the user dismissed it.
+    pub fn keyring_unlock(&self) -> Result<bool, String> {
+        let conn = self.conn()?;
+        let collection = default_collection(conn)
+            .map_err(|e| e.to_string())?
+            .ok_or("no default keyring")?;
+        let service = secrets(conn).map_err(|e| e.to_string())?;
+        let (unlocked, prompt): (Vec<OwnedObjectPath>, OwnedObjectPath) = service
+            .call("Unlock", &(vec![collection],))
+            .map_err(|e| e.to_string())?;
+        if prompt.as_str() == "/" {
+            return Ok(!unlocked.is_empty());
+        }
+        let prompt = Proxy::new(conn, SECRETS_NAME, prompt, "org.freedesktop.Secret.Prompt")
+            .map_err(|e| e.to_string())?;
+        // Subscribe before prompting so a quick answer isn't missed.
+        let mut completed = prompt
+            .receive_signal("Completed")
+            .map_err(|e| e.to_string())?;
+        prompt
+            .call::<_, _, ()>("Prompt", &("",))
+            .map_err(|e| e.to_string())?;
+        let signal = completed.next().ok_or("keyring prompt went away")?;
+        let (dismissed, _result): (bool, OwnedValue) =
+            signal.body().deserialize().map_err(|e| e.to_string())?;
+        Ok(!dismissed)
+    }
+
+    fn conn(&self) -> Result<&Connection, String> {
+        self.conn
+            .as_ref()
+            .ok_or_else(|| "no D-Bus session bus".to_string())
+    }
+}
+
+fn export_interface(app: &AppHandle, conn: &Connection) {
+    let iface = AppInterface { app: app.clone() };
+    if let Err(e) = conn.object_server().at(SERVICE_PATH, iface) {
+        eprintln!("dbus: could not export {SERVICE_PATH}: {e}");
+        return;
+    }
+    // A second profile's process finds the name taken; the first keeps it.
+    if let Err(e) = conn.request_name(SERVICE_NAME) {
+        eprintln!("dbus: could not own {SERVICE_NAME}: {e}");
+    }
+}
+
+fn notifications(conn: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        conn,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )
+}
+
+const SECRETS_NAME: &str = "org.freedesktop.secrets";
+
+fn secrets(conn: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        conn,
+        SECRETS_NAME,
+        "/org/freedesktop/secrets",
+        "org.freedesktop.Secret.Service",
+    )
+}
+
+/// Path of the collection behind the "default" alias, where keyring-rs
+/// stores entries. `None` when no collection has that alias.
+fn default_collection(conn: &Connection) -> zbus::Result<Option<OwnedObjectPath>> {
+    let path: OwnedObjectPath = secrets(conn)?.call("ReadAlias", &("default",))?;
+    Ok((path.as_str() != "/").then_some(path))
+}
+
+fn screensaver(conn: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        conn,
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+    )
+}
+
+fn listen_for_actions(app: &AppHandle, conn: &Connection) {
+    let proxy = match notifications(conn) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("dbus: notifications unavailable: {e}");
+            return;
+        }
+    };
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let signals = match proxy.receive_signal("ActionInvoked") {
+            Ok(signals) => signals,
+            Err(e) => {
+                eprintln!("dbus: cannot watch notification actions: {e}");
+                return;
+            }
+        };
+        for signal in signals {
+            let Ok((notification, action)) = signal.body().deserialize::<(u32, String)>() else {
+                continue;
+            };
+            // Clicking the notification body invokes "default".
+            if action == "default" {
+                crate::tray::show_main(&app);
+            }
+            let payload = ActionInvoked {
+                notification,
+                action,
+            };
+            if let Err(e) = app.emit("spectrus://notification-action", payload) {
+                eprintln!("dbus: action emit error: {e}");
+            }
+        }
+    });
+}
+
+/// Show a desktop notification. Clicking one of `actions` emits
+/// `spectrus://notification-action` with the returned id and the action's
+/// `id`; clicking the notification itself reports the action "default".
+/// Pass the id of an earlier notification as `replaces` to update it in place.
+#[tauri::command]
+pub fn linux_notify(
+    dbus: State<'_, DbusState>,
+    title: String,
+    body: String,
+    actions: Option<Vec<NotificationAction>>,
+    replaces: Option<u32>,
+) -> Result<u32, String> {
+    notify(&dbus, &title, &body, &actions.unwrap_or_default(), replaces)
+}
+
+/// `linux_notify` for backend callers.
+pub(crate) fn notify(
+    dbus: &DbusState,
+    title: &str,
+    body: &str,
+    actions: &[NotificationAction],
+    replaces: Option<u32>,
+) -> Result<u32, String> {
+    let proxy = notifications(dbus.conn()?).map_err(|e| e.to_string())?;
+    // The spec flattens actions into [id, label, id, label, ...].
+    let mut flat = vec!["default", ""];
+    for action in actions {
+        flat.push(&action.id);
+        flat.push(&action.label);
+    }
+    let hints: HashMap<&str, Value> = HashMap::new();
+    proxy
+        .call(
+            "Notify",
+            &(
+                APP_NAME,
+                replaces.unwrap_or(0),
+                "",
+                title,
+                body,
+                flat,
+                hints,
+                -1i32,
+            ),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Keep the screensaver and screen lock away, e.g. for the length of a
+/// recording. Calling it again while inhibited is a no-op.
+#[tauri::command]
+pub fn screensaver_inhibit(dbus: State<'_, DbusState>, reason: String) -> Result<(), String> {
+    let mut cookie = dbus.inhibit_cookie.lock().unwrap();
+    if cookie.is_some() {
+        return Ok(());
+    }
+    let proxy = screensaver(dbus.conn()?).map_err(|e| e.to_string())?;
+    let granted: u32 = proxy
+        .call("Inhibit", &(APP_NAME, reason.as_str()))
+        .map_err(|e| e.to_string())?;
+    *cookie = Some(granted);
+    Ok(())
+}
+
+/// Undo `screensaver_inhibit`.
+#[tauri::command]
+pub fn screensaver_uninhibit(dbus: State<'_, DbusState>) -> Result<(), String> {
+    let Some(granted) = dbus.inhibit_cookie.lock().unwrap().take() else {
+        return Ok(());
+    };
+    let proxy = screensaver(dbus.conn()?).map_err(|e| e.to_string())?;
+    proxy
+        .call::<_, _, ()>("UnInhibit", &(granted,))
+        .map_err(|e| e.to_string())
+}
@@ -1,18 +1,198 @@
 // Prevents a console window from appearing on Windows in release builds.
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod a11y;
+mod account;
+mod app_tasks;
+mod archive;
+mod audit;
+mod auto_import;
+mod bandwidth;
+mod ble;
+mod camera;
+mod capture;
+mod cli;
+mod clipboard;
+mod compute;
+mod consent;
+mod deep_link;
+mod dialogs;
+mod discovery;
+mod encryption;
+mod export;
+mod file_read;
+mod focus;
+mod fonts;
+mod fs_scope;
+mod gpu;
+mod handoff;
+mod hash;
+mod i18n;
+mod import;
+mod incognito;
+mod instance;
+mod intl;
+mod jobs;
+mod keyboard;
 mod keychain;
+#[cfg(target_os = "linux")]
+mod linux_dbus;
+mod maintenance;
+mod media;
+mod memory_watchdog;
+mod midi;
+mod monitors;
+mod pdf;
+mod policy;
+mod portable;
+mod power;
+mod print;
+mod privacy;
+mod profile;
+mod profile_transfer;
+mod project_lock;
+mod qr;
+mod recent;
+mod recording;
+mod reminders;
+mod remote_assets;
+mod reports;
+mod retention;
+mod search_index;
+mod serial;
+mod session;
+mod settings;
+mod share;
+mod shred;
+mod spellcheck;
+mod sync;
+mod system_info;
+mod time;
+mod trash_bin;
+mod tray;
+mod unfurl;
+mod update;
+mod virtual_source;
+mod watchdog;
+mod watcher;
+mod webhooks;
+mod zoom;
 
-use tauri::Emitter;
+use tauri::webview::PageLoadEvent;
+use tauri::{DragDropEvent, Manager, RunEvent, WindowEvent};
+
+use profile::ProfileState;
 
 fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_deep_link::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
+    let args = cli::parse();
+    let incognito = args.incognito;
+    let portable_dir = portable::data_dir(args.portable);
+    let mut context = tauri::generate_context!();
+    policy::load(&context.config().identifier);
+    if incognito {
+        incognito::configure(&mut context);
+    }
+    if let Some(dir) = &portable_dir {
+        portable::configure(&mut context, dir);
+    }
+    let gpu_root = gpu::root(&context, portable_dir.as_deref());
+    let gpu_disabled = args.disable_gpu || gpu::fallback_enabled(gpu_root.as_deref());
+    if gpu_disabled {
+        gpu::configure(&mut context);
+    }
+
+    let mut builder = tauri::Builder::default().plugin(tauri_plugin_deep_link::init());
+    // A portable copy is replaced by hand; self-updating would write outside
+    // its directory (installers) or onto a read-only stick.
+    if portable_dir.is_none() {
+        builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
+    }
+    builder
         .plugin(tauri_plugin_process::init())
-        .setup(|app| {
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(zoom::init())
+        .register_asynchronous_uri_scheme_protocol(media::SCHEME, media::handle)
+        .register_asynchronous_uri_scheme_protocol(remote_assets::SCHEME, remote_assets::handle)
+        .manage(keychain::MemoryStore::default())
+        .manage(audit::Audit::default())
+        .manage(watcher::WatcherState::default())
+        .manage(auto_import::AutoImport::default())
+        .manage(fs_scope::FsScope::default())
+        .manage(jobs::Jobs::default())
+        .manage(watchdog::Watchdog::default())
+        .manage(compute::Compute::default())
+        .manage(recording::Recordings::default())
+        .manage(i18n::I18n::default())
+        .manage(consent::Consent::default())
+        .manage(trash_bin::TrashState::default())
+        .manage(remote_assets::RemoteAssets::new())
+        .manage(unfurl::Unfurl::new())
+        .manage(webhooks::Webhooks::new())
+        .manage(bandwidth::Bandwidth::default())
+        .manage(time::TrustedTime::default())
+        .manage(update::Updates::default())
+        .manage(virtual_source::VirtualSources::default())
+        .manage(session::SessionState::default())
+        .manage(reminders::Reminders::default())
+        .manage(focus::Focus::default())
+        .manage(ble::Ble::default())
+        .manage(camera::Camera::default())
+        .manage(capture::Capture::default())
+        .manage(clipboard::ClipHistory::default())
+        .manage(handoff::Handoff::default())
+        .manage(discovery::Discovery::default())
+        .manage(sync::SyncState::default())
+        .manage(encryption::Encryption::default())
+        .manage(privacy::Privacy::default())
+        .manage(project_lock::ProjectLocks::new())
+        .manage(midi::Midi::default())
+        .manage(serial::Serial::default())
+        .manage(gpu::Gpu::new(gpu_disabled, gpu_root))
+        .manage(portable::Portable(portable_dir.clone()))
+        .manage(cli::Startup(args.clone()))
+        .setup(move |app| {
             let handle = app.handle().clone();
 
+            // Open (and lock) the profile before anything touches its data.
+            // `--profile <name>` wins over the last profile used. If another
+            // process already has that profile open, it gets our arguments
+            // and we bow out. Incognito sessions get a throwaway profile and
+            // no tray of their own.
+            if incognito {
+                app.manage(incognito::open_profile()?);
+            } else {
+                let root = match &portable_dir {
+                    Some(dir) => portable::profiles_root(dir),
+                    None => app.path().app_data_dir()?.join("profiles"),
+                };
+                let name = profile::resolve(&root, args.profile.clone());
+                if profile::is_locked_elsewhere(&root.join(&name))
+                    && instance::forward(&root.join(&name), &args)
+                {
+                    std::process::exit(0);
+                }
+                let profiles = ProfileState::open(root, Some(name))?;
+                match instance::listen(app.handle()) {
+                    Ok(instance) => {
+                        instance.announce(&profiles.current().dir);
+                        app.manage(instance);
+                    }
+                    Err(e) => eprintln!("instance: cannot accept forwarded launches: {e}"),
+                }
+                app.manage(profiles);
+                tray::init(app.handle())?;
+                capture::init(app.handle());
+                clipboard::init(app.handle());
+                app_tasks::init(app.handle());
+                #[cfg(target_os = "macos")]
+                share::init_services(app.handle());
+                #[cfg(target_os = "linux")]
+                app.manage(linux_dbus::DbusState::connect(app.handle()));
+            }
+
             // Register the spectrus:// URI-scheme handler.
             // When the OS activates the scheme (because the app is already running),
             // this callback fires and we forward the URL to the webview as a Tauri
@@ -22,22 +202,284 @@ fn main() {
                 use tauri_plugin_deep_link::DeepLinkExt;
                 app.deep_link().on_open_url(move |event| {
                     for url in event.urls() {
-                        handle
-                            .emit("spectrus://deep-link", url.to_string())
-                            .unwrap_or_else(|e| {
-                                eprintln!("deep-link emit error: {e}");
-                            });
+                        deep_link::open(&handle, url.as_str());
                     }
                 });
             }
 
+            memory_watchdog::start(app.handle());
+            maintenance::start(app.handle());
+            power::start(app.handle());
+            privacy::start(app.handle());
+            reminders::start(app.handle());
+            focus::start(app.handle());
+            monitors::start(app.handle());
+            keyboard::start(app.handle());
+            a11y::start(app.handle());
+            time::start(app.handle());
+            encryption::start(app.handle());
+            session::start(app.handle());
+            auto_import::start(app.handle());
+            bandwidth::start(app.handle());
+            discovery::start(app.handle());
+            sync::start(app.handle());
+            project_lock::start(app.handle());
+            webhooks::start(app.handle());
+            watchdog::start(app.handle());
+            spellcheck::start(app.handle());
+            zoom::start(app.handle());
+            cli::apply_window_state(app.handle(), &args);
+            cli::apply(app.handle(), &args);
+
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        // A page that reloads or navigates leaves its calls behind; stop
+        // them rather than let them run for nobody.
+        .on_page_load(|webview, payload| {
+            if payload.event() == PageLoadEvent::Started {
+                webview
+                    .state::<watchdog::Watchdog>()
+                    .release(webview.label());
+            }
+        })
+        .on_window_event(|window, event| match event {
+            // Files the user drops onto a window become readable by that
+            // window's file commands; grants die with the window.
+            WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                let scope = window.state::<fs_scope::FsScope>();
+                for path in paths {
+                    scope.grant(window.label(), path);
+                }
+            }
+            WindowEvent::Destroyed => {
+                window
+                    .state::<fs_scope::FsScope>()
+                    .revoke_window(window.label());
+                window.state::<privacy::Privacy>().forget(window.label());
+                window.state::<watchdog::Watchdog>().release(window.label());
+            }
+            WindowEvent::Focused(focused) => zoom::focus_changed(window.app_handle(), *focused),
+            _ => {}
+        })
+        .invoke_handler(policy::guard(tauri::generate_handler![
+            a11y::a11y_state,
+            a11y::a11y_announce,
+            account::account_current,
+            account::account_switch,
+            app_tasks::app_tasks_set,
+            archive::archive_create,
+            archive::archive_extract,
+            audit::audit_export,
+            audit::audit_query,
+            audit::audit_verify,
+            auto_import::import_rule_delete,
+            auto_import::import_rule_set,
+            auto_import::import_rules_list,
+            bandwidth::bandwidth_set,
+            bandwidth::bandwidth_status,
+            ble::ble_scan_start,
+            ble::ble_scan_stop,
+            ble::ble_devices,
+            ble::ble_connect,
+            ble::ble_disconnect,
+            ble::ble_subscribe,
+            ble::ble_unsubscribe,
+            ble::ble_read,
+            ble::ble_write,
+            camera::camera_list,
+            camera::camera_scan_start,
+            camera::camera_scan_stop,
+            capture::capture_list,
+            capture::capture_shortcut,
+            capture::capture_shortcut_set,
+            capture::capture_submit,
+            cli::startup_args,
+            clipboard::clipboard_copy,
+            clipboard::clipboard_history,
+            clipboard::clipboard_history_clear,
+            clipboard::clipboard_history_enable,
+            clipboard::clipboard_recopy,
+            compute::compute_cache_invalidate,
+            compute::compute_cache_query,
+            compute::compute_peaks,
+            consent::consent_grants,
+            consent::consent_revoke,
+            deep_link::deep_link_create,
+            deep_link::deep_link_verify,
+            dialogs::dialog_open,
+            dialogs::dialog_save,
+            discovery::peers_list,
+            encryption::encryption_disable,
+            encryption::encryption_enable,
+            encryption::encryption_status,
+            export::export_table,
+            file_read::file_size,
+            file_read::file_read_range,
+            file_read::file_read_stream,
+            focus::focus_state,
+            fonts::fonts_list,
+            gpu::gpu_info,
+            gpu::gpu_fallback_set,
+            handoff::handoff_start,
+            handoff::handoff_stop,
+            handoff::handoff_info,
+            hash::file_hash,
+            hash::file_hash_dir,
+            i18n::i18n_catalog,
+            i18n::i18n_language_set,
+            i18n::i18n_languages,
+            i18n::i18n_report_missing,
+            i18n::i18n_update,
+            import::import_files,
+            import::import_formats,
+            incognito::incognito_start,
+            intl::intl_locale,
+            intl::intl_locale_set,
+            intl::intl_number,
+            intl::intl_unit,
+            intl::intl_datetime,
+            intl::intl_relative_time,
+            intl::intl_sort,
+            jobs::job_cancel,
+            jobs::job_list,
+            keyboard::keyboard_layout,
             keychain::keychain_set,
             keychain::keychain_get,
             keychain::keychain_delete,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running Spectrus");
+            keychain::keychain_unlock,
+            #[cfg(target_os = "linux")]
+            linux_dbus::linux_notify,
+            #[cfg(target_os = "linux")]
+            linux_dbus::screensaver_inhibit,
+            #[cfg(target_os = "linux")]
+            linux_dbus::screensaver_uninhibit,
+            maintenance::maintenance_run_now,
+            maintenance::maintenance_report,
+            media::media_url,
+            memory_watchdog::memory_usage,
+            memory_watchdog::ui_snapshot_take,
+            midi::midi_list,
+            midi::midi_open_input,
+            midi::midi_open_output,
+            midi::midi_send,
+            midi::midi_close,
+            monitors::monitor_list,
+            monitors::window_move_to_monitor,
+            pdf::export_pdf,
+            policy::policy_effective,
+            portable::portable_info,
+            print::printer_list,
+            print::print_view,
+            print::print_document,
+            privacy::privacy_policy_set,
+            privacy::privacy_set_sensitive,
+            privacy::privacy_state,
+            profile::profile_list,
+            profile::profile_current,
+            profile::profile_create,
+            profile::profile_switch,
+            profile::profile_delete,
+            profile_transfer::profile_export,
+            profile_transfer::profile_inspect,
+            profile_transfer::profile_import,
+            project_lock::project_lock,
+            project_lock::project_unlock,
+            project_lock::project_lock_status,
+            qr::qr_generate,
+            qr::qr_decode,
+            recent::recent_files_add,
+            recent::recent_files_list,
+            recent::recent_files_open,
+            recent::recent_files_clear,
+            recording::recording_interrupted,
+            recording::recording_recover,
+            recording::recording_start,
+            recording::recording_stop,
+            recording::recording_write,
+            reminders::reminders_schedule,
+            reminders::reminders_list,
+            reminders::reminders_cancel,
+            remote_assets::remote_assets_register,
+            reports::report_templates_list,
+            reports::report_preview,
+            reports::report_generate,
+            retention::retention_policies,
+            retention::retention_policy_set,
+            retention::retention_preview,
+            search_index::search_index_clear,
+            search_index::search_index_remove,
+            search_index::search_index_update,
+            serial::serial_list,
+            serial::serial_open,
+            serial::serial_write,
+            serial::serial_close,
+            session::session_report,
+            session::session_draft_set,
+            session::session_drafts,
+            session::session_restore,
+            session::session_window,
+            settings::settings_get,
+            settings::settings_set,
+            share::export_share,
+            share::export_share_targets,
+            share::share,
+            shred::shred_capability,
+            shred::file_shred,
+            spellcheck::spellcheck_languages,
+            spellcheck::spellcheck_set_languages,
+            spellcheck::spellcheck_words,
+            spellcheck::spellcheck_add_word,
+            spellcheck::spellcheck_remove_word,
+            sync::sync_identity,
+            sync::sync_pull,
+            sync::sync_push,
+            sync::sync_trust,
+            sync::sync_trusted,
+            sync::sync_untrust,
+            system_info::system_info,
+            time::trusted_now,
+            trash_bin::file_trash,
+            trash_bin::trash_restore_last,
+            unfurl::unfurl,
+            update::update_download,
+            update::update_ready,
+            virtual_source::virtual_source_list,
+            virtual_source::virtual_source_add,
+            virtual_source::virtual_source_remove,
+            watchdog::watchdog_calls,
+            watchdog::watchdog_timeouts_set,
+            watcher::watch_start,
+            watcher::watch_stop,
+            watcher::watch_list,
+            webhooks::webhook_delete,
+            webhooks::webhook_set,
+            webhooks::webhook_test,
+            webhooks::webhooks_list,
+            webhooks::webhooks_log,
+            zoom::zoom_get,
+            zoom::zoom_set,
+            zoom::zoom_step,
+        ]))
+        .build(context)
+        .expect("error while running Spectrus")
+        .run(move |app, event| match event {
+            RunEvent::ExitRequested { api, .. } => update::on_exit_requested(app, &api),
+            RunEvent::Exit if incognito => {
+                project_lock::release_all(app);
+                incognito::wipe(app);
+            }
+            RunEvent::Exit => {
+                project_lock::release_all(app);
+                session::end(app, session::Ending::Quit);
+            }
+            // Files opened from the Dock's recent items (or Finder) arrive
+            // here on macOS.
+            #[cfg(target_os = "macos")]
+            RunEvent::Opened { urls } => {
+                for path in urls.iter().filter_map(|u| u.to_file_path().ok()) {
+                    recent::open(app, "main", &path);
+                }
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use walkdir::WalkDir;
+
+use crate::jobs::Job;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::{Call, Scope};
+
+/// How often the scheduler looks at idle time and power.
+const POLL: Duration = Duration::from_secs(5 * 60);
+
+/// No input for this long counts as idle.
+const IDLE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Minimum gap between idle-triggered runs.
+const RUN_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `write_atomic` temp files older than this were left by a crash.
+const TEMP_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    Idle,
+    Manual,
+}
+
+/// Outcome of one housekeeping task.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReport {
+    task: String,
+    removed_files: u64,
+    freed_bytes: u64,
+    error: Option<String>,
+}
+
+/// What a maintenance run did; also kept as `maintenance.json`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    trigger: Trigger,
+    /// Unix ms.
+    started_at: i64,
+    finished_at: i64,
+    tasks: Vec<TaskReport>,
+}
+
+type Task = fn(&AppHandle, &Profile, &mut TaskReport) -> Result<(), String>;
+
+/// Idle-time housekeeping in the order it runs: retention policies (which
+/// include cache pruning), log rotation and orphaned temp files. Each works
+/// on the active profile, the audit log aside, which all profiles share.
+/// Profiles keep no SQLite database or thumbnails, so there's nothing to
+/// VACUUM, ANALYZE or regenerate, and `webhooks-log.json` is capped as it
+/// is written.
+const TASKS: [(&str, Task); 3] = [
+    ("retention", enforce_retention),
+    ("logs", rotate_logs),
+    ("temp", remove_orphaned_temp),
+];
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("maintenance.json")
+}
+
+fn last_report(profile: &Profile) -> Option<MaintenanceReport> {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+}
+
+fn age(modified: SystemTime) -> Duration {
+    SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+}
+
+fn remove(path: &Path, size: u64, report: &mut TaskReport) {
+    if fs::remove_file(path).is_ok() {
+        report.removed_files += 1;
+        report.freed_bytes += size;
+    }
+}
+
+/// Delete whatever the retention policies say has been kept too long or
+/// takes too much room (see `retention`).
+fn enforce_retention(
+    _app: &AppHandle,
+    profile: &Profile,
+    report: &mut TaskReport,
+) -> Result<(), String> {
+    for purge in crate::retention::plan(profile) {
+        if crate::retention::apply(&purge) {
+            report.removed_files += 1;
+            report.freed_bytes += purge.size;
+        }
+    }
+    Ok(())
+}
+
+/// Move a large audit log aside ahead of time (see `audit`); counts the
+/// oldest kept log if that went.
+fn rotate_logs(app: &AppHandle, _profile: &Profile, report: &mut TaskReport) -> Result<(), String> {
+    if let Some(size) = crate::audit::rotate_early(app)? {
+        report.removed_files += 1;
+        report.freed_bytes += size;
+    }
+    Ok(())
+}
+
+/// Delete `*.tmp` files that an interrupted `write_atomic` left in the
+/// profile directory.
+fn remove_orphaned_temp(
+    _app: &AppHandle,
+    profile: &Profile,
+    report: &mut TaskReport,
+) -> Result<(), String> {
+    for entry in WalkDir::new(&profile.dir)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|x| x == "tmp"))
+    {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.modified().is_ok_and(|m| age(m) > TEMP_MAX_AGE) {
+            remove(entry.path(), meta.len(), report);
+        }
+    }
+    Ok(())
+}
+
+/// Run every task as a "maintenance" job, stopping early if it (or the
+/// call in `scope`) is cancelled, and store the report. Emits
+/// `spectrus://maintenance-finished`.
+fn run(
+    app: &AppHandle,
+    trigger: Trigger,
+    scope: Option<Scope>,
+) -> Result<MaintenanceReport, String> {
+    let profile = app.state::<ProfileState>().current();
+    let mut job = Job::start(app, "maintenance").within(scope);
+    let started_at = crate::time::now_ms(app);
+    let mut tasks = Vec::new();
+    for (i, (name, task)) in TASKS.iter().enumerate() {
+        job.check()?;
+        let mut report = TaskReport {
+            task: name.to_string(),
+            ..Default::default()
+        };
+        if let Err(e) = task(app, &profile, &mut report) {
+            report.error = Some(e);
+        }
+        tasks.push(report);
+        job.progress(i as u64 + 1, TASKS.len() as u64);
+    }
+    let report = MaintenanceReport {
+        trigger,
+        started_at,
+        finished_at: crate::time::now_ms(app),
+        tasks,
+    };
+    let json = serde_json::to_vec_pretty(&report).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(&profile), &json)?;
+    let _ = app.emit("spectrus://maintenance-finished", &report);
+    Ok(report)
+}
+
+fn due(app: &AppHandle) -> bool {
+    let profile = app.state::<ProfileState>().current();
+    if profile.ephemeral {
+        return false;
+    }
+    let last = last_report(&profile).map_or(0, |r| r.finished_at);
+    let since = crate::time::now_ms(app) - last;
+    since >= RUN_EVERY.as_millis() as i64
+        && platform::on_ac_power()
+        && platform::idle(app).is_some_and(|idle| idle >= IDLE_AFTER)
+}
+
+/// Run maintenance at most once a day, when nobody is using the machine
+/// and it isn't on battery.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL);
+        if due(&app) {
+            if let Err(e) = run(&app, Trigger::Idle, None) {
+                eprintln!("maintenance: {e}");
+            }
+        }
+    });
+}
+
+/// Run all housekeeping now, regardless of idle time and power.
+#[tauri::command]
+pub async fn maintenance_run_now(app: AppHandle, call: Call) -> Result<MaintenanceReport, String> {
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || run(&app, Trigger::Manual, Some(scope)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// The report of the last run, if any.
+#[tauri::command]
+pub fn maintenance_report(profiles: State<'_, ProfileState>) -> Option<MaintenanceReport> {
+    last_report(&profiles.current())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::time::Duration;
+
+    use tauri::{AppHandle, Manager};
+
+    pub fn idle(app: &AppHandle) -> Option<Duration> {
+        app.try_state::<crate::linux_dbus::DbusState>()?
+            .idle_ms()
+            .map(Duration::from_millis)
+    }
+
+    /// On AC unless a battery reports discharging; desktops have none.
+    pub fn on_ac_power() -> bool {
+        let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+            return true;
+        };
+        !supplies.flatten().any(|supply| {
+            let read =
+                |name: &str| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+            read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::time::Duration;
+
+    use tauri::AppHandle;
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub fn idle(_app: &AppHandle) -> Option<Duration> {
+        let mut info = LASTINPUTINFO {
+            cbSize: size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a properly sized LASTINPUTINFO.
+        unsafe { GetLastInputInfo(&mut info) }.ok().ok()?;
+        // Both are tick counts, so wrapping subtraction survives the
+        // 49-day rollover.
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+    }
+
+    /// `ACLineStatus` is 0 on battery, 1 on AC and 255 when unknown.
+    pub fn on_ac_power() -> bool {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        // SAFETY: `status` is a valid out pointer.
+        if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+            return true;
+        }
+        status.ACLineStatus != 0
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+    use std::time::Duration;
+
+    use tauri::AppHandle;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    pub fn idle(_app: &AppHandle) -> Option<Duration> {
+        // SAFETY: plain query with documented constant arguments.
+        let secs = unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+        };
+        Duration::try_from_secs_f64(secs).ok()
+    }
+
+    /// `pmset -g batt` starts with "Now drawing from 'AC Power'" or
+    /// "'Battery Power'".
+    pub fn on_ac_power() -> bool {
+        Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .map(|out| !String::from_utf8_lossy(&out.stdout).contains("'Battery Power'"))
+            .unwrap_or(true)
+    }
+}
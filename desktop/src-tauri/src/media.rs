@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{Manager, State, UriSchemeContext, UriSchemeResponder, Window, Wry};
+
+use crate::fs_scope::FsScope;
+
+/// URI scheme served by `handle`.
+pub const SCHEME: &str = "spectrus-media";
+
+/// Most bytes returned for one request. Media elements ask for open-ended
+/// ranges ("bytes=0-") and come back for more, so a large recording is never
+/// read into memory whole.
+const MAX_CHUNK: u64 = 4 * 1024 * 1024;
+
+/// Identify common media containers by their magic bytes, falling back to the
+/// extension.
+fn sniff(path: &Path, head: &[u8]) -> &'static str {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    if at(0, b"RIFF") && at(8, b"WAVE") {
+        return "audio/wav";
+    }
+    if at(0, b"fLaC") {
+        return "audio/flac";
+    }
+    if at(0, b"OggS") {
+        return "audio/ogg";
+    }
+    if at(0, b"ID3") || (head.len() > 1 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0) {
+        return "audio/mpeg";
+    }
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        return "video/webm";
+    }
+    if at(4, b"ftyp") {
+        return if at(8, b"M4A ") {
+            "audio/mp4"
+        } else {
+            "video/mp4"
+        };
+    }
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "ogg" | "opus" => "audio/ogg",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" | "mkv" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parse a single `bytes=start-end` / `bytes=start-` / `bytes=-suffix` range
+/// against a file of `len` bytes into an inclusive (start, end). `None` means
+/// the range can't be satisfied.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    // Multi-range requests are answered with the first range only.
+    let spec = spec.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.checked_sub(suffix.min(len))?, len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(len.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+fn status(code: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.as_bytes().to_vec())
+        .unwrap()
+}
+
+fn serve(scope: &FsScope, window: &str, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let uri = request.uri().to_string();
+    let path = url::Url::parse(&uri).ok().and_then(|u| {
+        u.query_pairs()
+            .find(|(k, _)| k == "path")
+            .map(|(_, v)| v.into_owned())
+    });
+    let Some(path) = path else {
+        return status(StatusCode::BAD_REQUEST, "missing path");
+    };
+    let path = match scope.check(window, &path) {
+        Ok(path) => path,
+        Err(e) => return status(StatusCode::FORBIDDEN, &e),
+    };
+    match read_range(&path, request) {
+        Ok(response) => response,
+        Err(e) => status(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+fn read_range(path: &Path, request: &Request<Vec<u8>>) -> std::io::Result<Response<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut head = [0u8; 16];
+    let n = file.read(&mut head)?;
+    let content_type = sniff(path, &head[..n]);
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+    let (start, end, partial) = match range {
+        Some(value) => match parse_range(value, len) {
+            Some((start, end)) => (start, end, true),
+            None => {
+                return Ok(Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                    .body(Vec::new())
+                    .unwrap())
+            }
+        },
+        // Without a Range header a small file is sent whole; a large one gets
+        // its first chunk as a partial response, which makes the element
+        // switch to range requests.
+        None => (0, len.saturating_sub(1), len > MAX_CHUNK),
+    };
+    let end = end.min(start + MAX_CHUNK - 1);
+
+    let mut body = Vec::new();
+    if len > 0 {
+        file.seek(SeekFrom::Start(start))?;
+        file.take(end - start + 1).read_to_end(&mut body)?;
+    }
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_LENGTH, body.len());
+    if partial {
+        response = response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+    }
+    Ok(response.body(body).unwrap())
+}
+
+/// Protocol handler for `spectrus-media://localhost/?path=<file>`. Only files
+/// the requesting window has been granted (see `FsScope`) are served.
+pub fn handle(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    let window = ctx.webview_label().to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let scope = app.state::<FsScope>();
+        responder.respond(serve(&scope, &window, &request));
+    });
+}
+
+/// URL an `<audio>`/`<video>` element can stream `path` from. The scheme's
+/// origin differs per platform (`http://spectrus-media.localhost` on Windows),
+/// so the frontend should always build media URLs through this command.
+#[tauri::command]
+pub fn media_url(
+    window: Window,
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<String, String> {
+    let path = scope.check(window.label(), &path)?;
+    let base = if cfg!(windows) {
+        format!("http://{SCHEME}.localhost/")
+    } else {
+        format!("{SCHEME}://localhost/")
+    };
+    let mut url = url::Url::parse(&base).map_err(|e| e.to_string())?;
+    url.query_pairs_mut()
+        .append_pair("path", &path.to_string_lossy());
+    Ok(url.to_string())
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// How often memory is sampled.
+const INTERVAL: Duration = Duration::from_secs(15);
+
+/// Time the reloaded page gets to settle before the limit is enforced again.
+const COOLDOWN: Duration = Duration::from_secs(120);
+
+/// Settings keys (in MiB) and their defaults. A limit of 0 disables reloads.
+const WARN_KEY: &str = "memory.warn_mb";
+const LIMIT_KEY: &str = "memory.limit_mb";
+const DEFAULT_WARN_MB: u64 = 1536;
+const DEFAULT_LIMIT_MB: u64 = 3072;
+
+/// The frontend registers `window.__spectrusSnapshot = () => state` to have its
+/// UI state kept across a forced reload.
+const SNAPSHOT_JS: &str =
+    "JSON.stringify(typeof window.__spectrusSnapshot === 'function' ? window.__spectrusSnapshot() : null)";
+
+#[derive(Clone, Serialize)]
+pub struct MemoryUsage {
+    /// Resident memory of the app and every process it spawned (webview
+    /// renderers, GPU process). On macOS WebKit's processes belong to launchd
+    /// and only the app process itself is counted.
+    bytes: u64,
+    warn_bytes: u64,
+    limit_bytes: u64,
+}
+
+struct Thresholds {
+    warn: u64,
+    limit: u64,
+}
+
+fn thresholds(profile: &Profile) -> Thresholds {
+    let mb = |key, default| {
+        settings::get(profile, key)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(default)
+            * 1024
+            * 1024
+    };
+    Thresholds {
+        warn: mb(WARN_KEY, DEFAULT_WARN_MB),
+        limit: mb(LIMIT_KEY, DEFAULT_LIMIT_MB),
+    }
+}
+
+/// Resident memory of this process and all of its descendants.
+fn tree_bytes(system: &mut System) -> u64 {
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    let Ok(root) = sysinfo::get_current_pid() else {
+        return 0;
+    };
+    let mut tree: HashSet<Pid> = HashSet::from([root]);
+    // Parents can be listed after their children, so sweep until stable.
+    loop {
+        let before = tree.len();
+        for (pid, process) in system.processes() {
+            if process.parent().is_some_and(|p| tree.contains(&p)) {
+                tree.insert(*pid);
+            }
+        }
+        if tree.len() == before {
+            break;
+        }
+    }
+    tree.iter()
+        .filter_map(|pid| system.process(*pid))
+        .map(|p| p.memory())
+        .sum()
+}
+
+fn snapshot_path(profile: &Profile) -> std::path::PathBuf {
+    profile.dir.join("ui-snapshot.json")
+}
+
+/// Save the main window's UI state, then reload it. Losing the snapshot
+/// doesn't stop the reload: a fresh page beats a white screen.
+fn recover(app: &AppHandle, usage: &MemoryUsage) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let _ = app.emit("spectrus://memory-reload", usage);
+    match crate::pdf::eval(&window, SNAPSHOT_JS) {
+        Ok(state) if state != "null" => {
+            let profile = app.state::<ProfileState>().current();
+            if let Err(e) =
+                crate::encryption::write(app, &snapshot_path(&profile), state.as_bytes())
+            {
+                eprintln!("memory: could not save UI snapshot: {e}");
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("memory: UI snapshot failed: {e}"),
+    }
+    if let Err(e) = window.reload() {
+        eprintln!("memory: reload failed: {e}");
+    }
+}
+
+/// Start sampling memory in the background. Crossing the warning threshold
+/// emits `spectrus://memory-warning` once (re-armed when usage drops back);
+/// crossing the hard limit snapshots the UI and reloads the main window.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut system = System::new();
+        let mut warned = false;
+        let mut last_reload: Option<Instant> = None;
+        loop {
+            thread::sleep(INTERVAL);
+            let Some(profiles) = app.try_state::<ProfileState>() else {
+                continue;
+            };
+            let limits = thresholds(&profiles.current());
+            let usage = MemoryUsage {
+                bytes: tree_bytes(&mut system),
+                warn_bytes: limits.warn,
+                limit_bytes: limits.limit,
+            };
+            if usage.bytes < limits.warn {
+                warned = false;
+            } else if !warned {
+                warned = true;
+                let _ = app.emit("spectrus://memory-warning", &usage);
+            }
+            let cooled = last_reload.is_none_or(|t| t.elapsed() > COOLDOWN);
+            if limits.limit > 0 && usage.bytes >= limits.limit && cooled {
+                recover(&app, &usage);
+                last_reload = Some(Instant::now());
+                warned = false;
+            }
+        }
+    });
+}
+
+/// Current memory use and thresholds (set via settings `memory.warn_mb` and
+/// `memory.limit_mb`).
+#[tauri::command]
+pub async fn memory_usage(profiles: State<'_, ProfileState>) -> Result<MemoryUsage, String> {
+    let limits = thresholds(&profiles.current());
+    let bytes = tauri::async_runtime::spawn_blocking(|| tree_bytes(&mut System::new()))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(MemoryUsage {
+        bytes,
+        warn_bytes: limits.warn,
+        limit_bytes: limits.limit,
+    })
+}
+
+/// UI state saved before the last forced reload, or `null`. Reading it
+/// clears it, so a normal launch afterwards starts clean.
+#[tauri::command]
+pub fn ui_snapshot_take(app: AppHandle, profiles: State<'_, ProfileState>) -> Option<Value> {
+    let path = snapshot_path(&profiles.current());
+    let bytes = crate::encryption::read(&app, &path)?;
+    let _ = fs::remove_file(&path);
+    serde_json::from_slice(&bytes).ok()
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+/// Client name shown by the OS MIDI layer (e.g. in `aconnect -l`).
+const CLIENT: &str = "Spectrus";
+
+#[derive(Serialize)]
+pub struct MidiPort {
+    /// Stable while the device stays connected; pass to the open commands.
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+pub struct MidiPorts {
+    inputs: Vec<MidiPort>,
+    outputs: Vec<MidiPort>,
+}
+
+/// Payload of `spectrus://midi-message`.
+#[derive(Clone, Serialize)]
+struct Message {
+    port: String,
+    /// Microseconds, from a clock whose origin depends on the backend. Only
+    /// differences between messages of the same port are meaningful.
+    timestamp: u64,
+    data: Vec<u8>,
+}
+
+/// Managed state: open connections by port id. Input and output ports of
+/// one device may share an id, so each direction has its own map.
+#[derive(Default)]
+pub struct Midi {
+    inputs: Mutex<HashMap<String, MidiInputConnection<()>>>,
+    outputs: Mutex<HashMap<String, MidiOutputConnection>>,
+}
+
+/// MIDI devices currently connected.
+#[tauri::command]
+pub fn midi_list() -> Result<MidiPorts, String> {
+    let input = MidiInput::new(CLIENT).map_err(|e| e.to_string())?;
+    let output = MidiOutput::new(CLIENT).map_err(|e| e.to_string())?;
+    let inputs = input
+        .ports()
+        .iter()
+        .filter_map(|p| {
+            Some(MidiPort {
+                id: p.id(),
+                name: input.port_name(p).ok()?,
+            })
+        })
+        .collect();
+    let outputs = output
+        .ports()
+        .iter()
+        .filter_map(|p| {
+            Some(MidiPort {
+                id: p.id(),
+                name: output.port_name(p).ok()?,
+            })
+        })
+        .collect();
+    Ok(MidiPorts { inputs, outputs })
+}
+
+/// Start listening on input port `id`. Every message arrives as
+/// `spectrus://midi-message`; clock and active-sensing bytes are dropped, as
+/// controllers send them continuously. Opening an open port is a no-op.
+#[tauri::command]
+pub fn midi_open_input(app: AppHandle, midi: State<'_, Midi>, id: String) -> Result<(), String> {
+    let mut inputs = midi.inputs.lock().unwrap();
+    if inputs.contains_key(&id) {
+        return Ok(());
+    }
+    let mut input = MidiInput::new(CLIENT).map_err(|e| e.to_string())?;
+    input.ignore(Ignore::TimeAndActiveSense);
+    let port = input
+        .find_port_by_id(id.clone())
+        .ok_or_else(|| format!("no MIDI input {id:?}"))?;
+    let port_id = id.clone();
+    let connection = input
+        .connect(
+            &port,
+            "spectrus-in",
+            move |timestamp, data, _| {
+                let _ = app.emit(
+                    "spectrus://midi-message",
+                    Message {
+                        port: port_id.clone(),
+                        timestamp,
+                        data: data.to_vec(),
+                    },
+                );
+            },
+            (),
+        )
+        .map_err(|e| e.to_string())?;
+    inputs.insert(id, connection);
+    Ok(())
+}
+
+/// Open output port `id` for `midi_send`.
+#[tauri::command]
+pub fn midi_open_output(midi: State<'_, Midi>, id: String) -> Result<(), String> {
+    let mut outputs = midi.outputs.lock().unwrap();
+    if outputs.contains_key(&id) {
+        return Ok(());
+    }
+    let output = MidiOutput::new(CLIENT).map_err(|e| e.to_string())?;
+    let port = output
+        .find_port_by_id(id.clone())
+        .ok_or_else(|| format!("no MIDI output {id:?}"))?;
+    let connection = output
+        .connect(&port, "spectrus-out")
+        .map_err(|e| e.to_string())?;
+    outputs.insert(id, connection);
+    Ok(())
+}
+
+/// Send one complete message (status byte first) to an open output.
+#[tauri::command]
+pub fn midi_send(midi: State<'_, Midi>, id: String, data: Vec<u8>) -> Result<(), String> {
+    if data.first().is_none_or(|status| status & 0x80 == 0) {
+        return Err("a MIDI message starts with a status byte".into());
+    }
+    let mut outputs = midi.outputs.lock().unwrap();
+    let connection = outputs
+        .get_mut(&id)
+        .ok_or_else(|| format!("MIDI output {id:?} is not open"))?;
+    connection.send(&data).map_err(|e| e.to_string())
+}
+
+/// Close port `id` in both directions. Closing a port that isn't open is a
+/// no-op.
+#[tauri::command]
+pub fn midi_close(midi: State<'_, Midi>, id: String) {
+    if let Some(connection) = midi.inputs.lock().unwrap().remove(&id) {
+        connection.close();
+    }
+    if let Some(connection) = midi.outputs.lock().unwrap().remove(&id) {
+        connection.close();
+    }
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Monitor, PhysicalPosition, PhysicalSize};
+
+/// Hot-plug isn't reported by the windowing layer, so the monitor list is
+/// polled and compared.
+const POLL: Duration = Duration::from_secs(2);
+
+/// A display, in physical pixels. `work_area` excludes taskbars and docks.
+#[derive(Clone, PartialEq, Serialize)]
+pub struct MonitorInfo {
+    /// Position in the list; stable only until the next hot-plug.
+    index: usize,
+    name: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    work_area: Rect,
+    scale_factor: f64,
+    primary: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && (x - self.x) < self.width as i32
+            && (y - self.y) < self.height as i32
+    }
+}
+
+fn info(index: usize, monitor: &Monitor, primary: Option<&Monitor>) -> MonitorInfo {
+    let area = monitor.work_area();
+    MonitorInfo {
+        index,
+        name: monitor.name().cloned(),
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width,
+        height: monitor.size().height,
+        work_area: Rect {
+            x: area.position.x,
+            y: area.position.y,
+            width: area.size.width,
+            height: area.size.height,
+        },
+        scale_factor: monitor.scale_factor(),
+        primary: primary
+            .is_some_and(|p| p.position() == monitor.position() && p.name() == monitor.name()),
+    }
+}
+
+fn list(app: &AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app.primary_monitor().map_err(|e| e.to_string())?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(i, m)| info(i, m, primary.as_ref()))
+        .collect())
+}
+
+/// Center `window` in `target`'s work area, shrinking it to fit if needed.
+fn place(window: &tauri::WebviewWindow, target: &MonitorInfo) -> Result<(), String> {
+    let area = target.work_area;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let width = size.width.min(area.width);
+    let height = size.height.min(area.height);
+    if (width, height) != (size.width, size.height) {
+        window
+            .set_size(PhysicalSize::new(width, height))
+            .map_err(|e| e.to_string())?;
+    }
+    let x = area.x + (area.width - width) as i32 / 2;
+    let y = area.y + (area.height - height) as i32 / 2;
+    window
+        .set_position(PhysicalPosition::new(x, y))
+        .map_err(|e| e.to_string())
+}
+
+/// Bring back windows whose top-left corner no longer lies on any display,
+/// e.g. a pop-out left on an external monitor that was just unplugged.
+fn rescue_windows(app: &AppHandle, monitors: &[MonitorInfo]) {
+    let Some(home) = monitors.iter().find(|m| m.primary).or(monitors.first()) else {
+        return;
+    };
+    for window in app.webview_windows().into_values() {
+        // Minimized windows report a parking spot off-screen on Windows.
+        if window.is_minimized().unwrap_or(false) || !window.is_visible().unwrap_or(false) {
+            continue;
+        }
+        let Ok(pos) = window.outer_position() else {
+            continue;
+        };
+        let visible = monitors.iter().any(|m| {
+            Rect {
+                x: m.x,
+                y: m.y,
+                width: m.width,
+                height: m.height,
+            }
+            .contains(pos.x, pos.y)
+        });
+        if !visible {
+            if let Err(e) = place(&window, home) {
+                eprintln!("monitors: could not move {}: {e}", window.label());
+            }
+        }
+    }
+}
+
+/// Watch for displays being added or removed. Each change emits
+/// `spectrus://monitors-changed` with the new list and moves stranded windows
+/// onto the primary display.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut last = list(&app).unwrap_or_default();
+        loop {
+            std::thread::sleep(POLL);
+            let Ok(now) = list(&app) else {
+                continue;
+            };
+            if now != last {
+                rescue_windows(&app, &now);
+                let _ = app.emit("spectrus://monitors-changed", &now);
+                last = now;
+            }
+        }
+    });
+}
+
+/// Connected displays with bounds, scale factor and which one is primary.
+#[tauri::command]
+pub async fn monitor_list(app: AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    list(&app)
+}
+
+/// Move window `label` onto the monitor at `index` (from `monitor_list`),
+/// centered in its work area. `maximize` fills the monitor instead.
+#[tauri::command]
+pub async fn window_move_to_monitor(
+    app: AppHandle,
+    label: String,
+    index: usize,
+    maximize: Option<bool>,
+) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("no window {label:?}"))?;
+    let monitors = list(&app)?;
+    let target = monitors
+        .get(index)
+        .ok_or_else(|| format!("no monitor {index}; it may have been unplugged"))?;
+    // A maximized window has to be restored before it can change displays.
+    if window.is_maximized().unwrap_or(false) {
+        window.unmaximize().map_err(|e| e.to_string())?;
+    }
+    place(&window, target)?;
+    if maximize.unwrap_or(false) {
+        window.maximize().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,438 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::Deserialize;
+use tauri::webview::{PageLoadEvent, PlatformWebview};
+use tauri::{AppHandle, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::fs_scope::FsScope;
+use crate::print::Orientation;
+use crate::watchdog::{Call, Scope};
+
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const RENDER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Height of the strip kept free for the header and footer.
+const STRIP_MM: f64 = 8.0;
+
+static NEXT_LABEL: AtomicU64 = AtomicU64::new(1);
+
+/// Evaluates to `true` once the document, its fonts, and (for app routes that
+/// opt in by setting `window.__spectrusPdfReady = false` while loading data)
+/// the page itself are ready.
+const READY_JS: &str = "document.readyState === 'complete' \
+    && document.fonts.status === 'loaded' \
+    && window.__spectrusPdfReady !== false";
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    #[default]
+    A4,
+    A3,
+    Letter,
+    Legal,
+}
+
+impl PageSize {
+    /// Portrait width and height in millimetres.
+    fn mm(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A3 => (297.0, 420.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+        }
+    }
+}
+
+/// A font file to embed, usable from the document as `font-family: <family>`.
+#[derive(Deserialize)]
+pub struct FontFile {
+    family: String,
+    path: String,
+}
+
+#[derive(Deserialize)]
+pub struct PdfOptions {
+    /// Where to write the PDF.
+    path: String,
+    /// Render this HTML instead of an app route.
+    html: Option<String>,
+    #[serde(default)]
+    page_size: PageSize,
+    orientation: Option<Orientation>,
+    margin_mm: Option<f64>,
+    /// Plain text repeated at the top / bottom of every page.
+    header: Option<String>,
+    footer: Option<String>,
+    /// Document title, stored in the PDF metadata.
+    title: Option<String>,
+    #[serde(default)]
+    fonts: Vec<FontFile>,
+}
+
+impl PdfOptions {
+    /// Default A4 layout for a backend-generated HTML document.
+    pub(crate) fn for_html(html: String, title: Option<String>) -> Self {
+        PdfOptions {
+            path: String::new(),
+            html: Some(html),
+            page_size: PageSize::default(),
+            orientation: None,
+            margin_mm: None,
+            header: None,
+            footer: None,
+            title,
+            fonts: Vec::new(),
+        }
+    }
+}
+
+/// Final page geometry handed to the platform renderer, in millimetres.
+struct Page {
+    width: f64,
+    height: f64,
+    margin: f64,
+}
+
+fn font_face(family: &str, path: &Path) -> Result<String, String> {
+    let mime = match path.extension().and_then(|e| e.to_str()) {
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => return Err(format!("{} is not a font file", path.display())),
+    };
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+    let family = serde_json::to_string(family).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "@font-face {{ font-family: {family}; src: url(data:{mime};base64,{data}); }}"
+    ))
+}
+
+/// Script that adds the embedded fonts, header, footer and title to the page.
+fn decorate_script(options: &PdfOptions, fonts: &[String]) -> Result<String, String> {
+    let mut css = fonts.join("\n");
+    css.push_str(&format!(
+        "\n.spectrus-pdf-strip {{ position: fixed; left: 0; right: 0; height: {STRIP_MM}mm; \
+         font: 9pt sans-serif; color: #555; overflow: hidden; white-space: nowrap; \
+         text-overflow: ellipsis; }}\n\
+         .spectrus-pdf-strip.top {{ top: 0; }}\n\
+         .spectrus-pdf-strip.bottom {{ bottom: 0; text-align: right; }}\n"
+    ));
+    if options.header.is_some() {
+        css.push_str(&format!(
+            "body {{ padding-top: {STRIP_MM}mm; box-decoration-break: clone; }}\n"
+        ));
+    }
+    if options.footer.is_some() {
+        css.push_str(&format!(
+            "body {{ padding-bottom: {STRIP_MM}mm; box-decoration-break: clone; }}\n"
+        ));
+    }
+    let json = |v: &Option<String>| serde_json::to_string(v).map_err(|e| e.to_string());
+    Ok(format!(
+        "(() => {{
+            const style = document.createElement('style');
+            style.textContent = {css};
+            document.head.appendChild(style);
+            for (const [cls, text] of [['top', {header}], ['bottom', {footer}]]) {{
+                if (!text) continue;
+                const strip = document.createElement('div');
+                strip.className = 'spectrus-pdf-strip ' + cls;
+                strip.textContent = text;
+                document.body.appendChild(strip);
+            }}
+            const title = {title};
+            if (title) document.title = title;
+            return true;
+        }})()",
+        css = serde_json::to_string(&css).map_err(|e| e.to_string())?,
+        header = json(&options.header)?,
+        footer = json(&options.footer)?,
+        title = json(&options.title)?,
+    ))
+}
+
+/// Run `js` in `page` and wait for its JSON-encoded result.
+pub(crate) fn eval(page: &WebviewWindow, js: &str) -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    page.eval_with_callback(js, move |result| {
+        let _ = tx.send(result);
+    })
+    .map_err(|e| e.to_string())?;
+    rx.recv_timeout(LOAD_TIMEOUT)
+        .map_err(|_| "page stopped responding".to_string())
+}
+
+fn wait_ready(page: &WebviewWindow) -> Result<(), String> {
+    let started = Instant::now();
+    while eval(page, READY_JS)? != "true" {
+        if started.elapsed() > LOAD_TIMEOUT {
+            return Err("page did not finish loading".into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+fn render_page(
+    page: &WebviewWindow,
+    loaded: mpsc::Receiver<()>,
+    options: &PdfOptions,
+    fonts: &[String],
+    geometry: Page,
+    out: PathBuf,
+) -> Result<(), String> {
+    loaded
+        .recv_timeout(LOAD_TIMEOUT)
+        .map_err(|_| "page did not load".to_string())?;
+    if let Some(html) = &options.html {
+        let html = serde_json::to_string(html).map_err(|e| e.to_string())?;
+        eval(
+            page,
+            &format!("document.open(); document.write({html}); document.close(); true"),
+        )?;
+    }
+    wait_ready(page)?;
+    eval(page, &decorate_script(options, fonts)?)?;
+    wait_ready(page)?;
+
+    let (tx, rx) = mpsc::channel();
+    page.with_webview(move |webview| render(webview, &geometry, &out, tx))
+        .map_err(|e| e.to_string())?;
+    rx.recv_timeout(RENDER_TIMEOUT)
+        .map_err(|_| "PDF rendering timed out".to_string())?
+}
+
+#[cfg(windows)]
+fn render(webview: PlatformWebview, page: &Page, out: &Path, done: Sender<Result<(), String>>) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2Environment6, ICoreWebView2_7,
+    };
+    use webview2_com::PrintToPdfCompletedHandler;
+    use windows::core::{Interface, HSTRING};
+
+    let inches = |mm: f64| mm / 25.4;
+    let finished = done.clone();
+    // SAFETY: plain COM calls on interfaces owned by the live webview, made on
+    // the main thread as `with_webview` guarantees.
+    let started = unsafe {
+        (|| -> windows::core::Result<()> {
+            let core = webview
+                .controller()
+                .CoreWebView2()?
+                .cast::<ICoreWebView2_7>()?;
+            let settings = webview
+                .environment()
+                .cast::<ICoreWebView2Environment6>()?
+                .CreatePrintSettings()?;
+            settings.SetPageWidth(inches(page.width))?;
+            settings.SetPageHeight(inches(page.height))?;
+            settings.SetMarginTop(inches(page.margin))?;
+            settings.SetMarginBottom(inches(page.margin))?;
+            settings.SetMarginLeft(inches(page.margin))?;
+            settings.SetMarginRight(inches(page.margin))?;
+            settings.SetShouldPrintBackgrounds(true)?;
+            settings.SetShouldPrintHeaderAndFooter(false)?;
+            let handler = PrintToPdfCompletedHandler::create(Box::new(move |result, ok| {
+                let outcome = match result {
+                    Err(e) => Err(e.to_string()),
+                    Ok(()) if !ok => Err("WebView2 could not write the PDF".into()),
+                    Ok(()) => Ok(()),
+                };
+                let _ = finished.send(outcome);
+                Ok(())
+            }));
+            core.PrintToPdf(&HSTRING::from(out.as_os_str()), &settings, &handler)
+        })()
+    };
+    if let Err(e) = started {
+        let _ = done.send(Err(e.to_string()));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn render(webview: PlatformWebview, page: &Page, out: &Path, done: Sender<Result<(), String>>) {
+    use webkit2gtk::{PrintOperation, PrintOperationExt};
+
+    let Ok(uri) = url::Url::from_file_path(out) else {
+        let _ = done.send(Err(format!("{} is not an absolute path", out.display())));
+        return;
+    };
+    let settings = gtk::PrintSettings::new();
+    settings.set_printer("Print to File");
+    settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+    settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(uri.as_str()));
+
+    let setup = gtk::PageSetup::new();
+    let paper = gtk::PaperSize::new_custom(
+        "spectrus-pdf",
+        "Spectrus PDF",
+        page.width,
+        page.height,
+        gtk::Unit::Mm,
+    );
+    setup.set_paper_size(&paper);
+    setup.set_top_margin(page.margin, gtk::Unit::Mm);
+    setup.set_bottom_margin(page.margin, gtk::Unit::Mm);
+    setup.set_left_margin(page.margin, gtk::Unit::Mm);
+    setup.set_right_margin(page.margin, gtk::Unit::Mm);
+
+    let operation = PrintOperation::new(&webview.inner());
+    operation.set_print_settings(&settings);
+    operation.set_page_setup(&setup);
+    // `failed` is followed by `finished`; the receiver only reads the first.
+    let failed = done.clone();
+    operation.connect_failed(move |_, e| {
+        let _ = failed.send(Err(e.to_string()));
+    });
+    operation.connect_finished(move |_| {
+        let _ = done.send(Ok(()));
+    });
+    operation.print();
+}
+
+#[cfg(target_os = "macos")]
+fn render(webview: PlatformWebview, page: &Page, out: &Path, done: Sender<Result<(), String>>) {
+    use objc2_app_kit::{
+        NSPrintInfo, NSPrintJobDisposition, NSPrintJobSavingURL, NSPrintSaveJob,
+        NSPrintingPaginationMode,
+    };
+    use objc2_foundation::{NSPoint, NSRect, NSSize, NSString, NSURL};
+    use objc2_web_kit::WKWebView;
+
+    let points = |mm: f64| mm / 25.4 * 72.0;
+    // SAFETY: `inner` is the WKWebView backing this window, alive for the
+    // duration of the `with_webview` callback.
+    let view: &WKWebView = unsafe { &*webview.inner().cast() };
+    let url = NSURL::fileURLWithPath(&NSString::from_str(&out.to_string_lossy()));
+
+    let info = NSPrintInfo::new();
+    // SAFETY: both keys take the documented value types (a disposition
+    // string and a file URL).
+    unsafe {
+        let dict = info.dictionary();
+        dict.insert(NSPrintJobDisposition, NSPrintSaveJob);
+        dict.insert(NSPrintJobSavingURL, &url);
+    }
+    info.setPaperSize(NSSize::new(points(page.width), points(page.height)));
+    info.setTopMargin(points(page.margin));
+    info.setBottomMargin(points(page.margin));
+    info.setLeftMargin(points(page.margin));
+    info.setRightMargin(points(page.margin));
+    info.setHorizontalPagination(NSPrintingPaginationMode::Fit);
+    info.setVerticalPagination(NSPrintingPaginationMode::Automatic);
+
+    // SAFETY: `info` is a fully configured print info owned by us.
+    let operation = unsafe { view.printOperationWithPrintInfo(&info) };
+    operation.setShowsPrintPanel(false);
+    operation.setShowsProgressPanel(false);
+    // WKWebView prints blank pages unless the operation's view has a frame.
+    if let Some(print_view) = operation.view() {
+        let width = points(page.width - 2.0 * page.margin);
+        let height = points(page.height - 2.0 * page.margin);
+        print_view.setFrame(NSRect::new(
+            NSPoint::new(0.0, 0.0),
+            NSSize::new(width, height),
+        ));
+    }
+    let outcome = if operation.runOperation() {
+        Ok(())
+    } else {
+        Err("the print system could not write the PDF".into())
+    };
+    let _ = done.send(outcome);
+}
+
+/// Render an app route (e.g. `/reports/42`), or `options.html`, to a PDF file
+/// in a hidden webview. Fonts used by the document are embedded by the
+/// engine; `options.fonts` adds font files that aren't installed. Returns the
+/// written path.
+#[tauri::command]
+pub async fn export_pdf(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    route: Option<String>,
+    options: PdfOptions,
+) -> Result<PathBuf, String> {
+    let out = scope.check_new(call.window(), &options.path)?;
+    let fonts = options
+        .fonts
+        .iter()
+        .map(|f| font_face(&f.family, &scope.check(call.window(), &f.path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    crate::audit::record(
+        &app,
+        crate::audit::Action::Export,
+        serde_json::json!({ "kind": "pdf", "path": out, "route": route }),
+    );
+    render_to_file(&app, route, options, fonts, out, &call.scope()).await
+}
+
+/// Render `route` or `options.html` to `out`; `fonts` are ready-made
+/// `@font-face` rules. `out` must already be cleared with the file scope.
+/// Gives up, closing the hidden webview, once `scope` is cancelled or
+/// times out.
+pub(crate) async fn render_to_file(
+    app: &AppHandle,
+    route: Option<String>,
+    options: PdfOptions,
+    fonts: Vec<String>,
+    out: PathBuf,
+    scope: &Scope,
+) -> Result<PathBuf, String> {
+    let url = match (&route, &options.html) {
+        (Some(route), None) => WebviewUrl::App(route.trim_start_matches('/').into()),
+        (None, Some(_)) => WebviewUrl::External("about:blank".parse().unwrap()),
+        _ => return Err("pass either a route or options.html".into()),
+    };
+
+    let (width, height) = options.page_size.mm();
+    let (width, height) = match options.orientation {
+        Some(Orientation::Landscape) => (height, width),
+        _ => (width, height),
+    };
+    let margin = options.margin_mm.unwrap_or(15.0);
+    if margin < 0.0 || 2.0 * margin >= width.min(height) {
+        return Err(format!("margin of {margin} mm does not fit the page"));
+    }
+    let geometry = Page {
+        width,
+        height,
+        margin,
+    };
+
+    let (loaded_tx, loaded) = mpsc::channel();
+    let label = format!("pdf-export-{}", NEXT_LABEL.fetch_add(1, Ordering::Relaxed));
+    let px = |mm: f64| mm / 25.4 * 96.0;
+    let page = WebviewWindowBuilder::new(app, label, url)
+        .visible(false)
+        .inner_size(px(width - 2.0 * margin), px(height - 2.0 * margin))
+        .on_page_load(move |_, payload| {
+            if payload.event() == PageLoadEvent::Finished {
+                let _ = loaded_tx.send(());
+            }
+        })
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let handle = page.clone();
+    let rendering = async {
+        tauri::async_runtime::spawn_blocking(move || {
+            render_page(&handle, loaded, &options, &fonts, geometry, out.clone()).map(|()| out)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    };
+    let result = scope.run(rendering).await;
+    let _ = page.destroy();
+    result
+}
@@ -0,0 +1,270 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use tauri::ipc::Invoke;
+use tauri::Runtime;
+use url::Url;
+
+/// Settings an administrator manages for every user of the machine. Read
+/// once at startup from, lowest priority first:
+///
+/// - Windows: `HKCU` then `HKLM` `SOFTWARE\Policies\Spectrus`, with pinned
+///   settings as values of its `Settings` subkey
+/// - macOS: the configuration profile payload for the app's identifier in
+///   `/Library/Managed Preferences/<user>/` then `/Library/Managed Preferences/`
+/// - Linux: `/etc/spectrus/policy.json`
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Policy {
+    /// Settings held at a value, e.g. `"analytics.enabled": false`. They read
+    /// as this value and can't be changed.
+    settings: Map<String, Value>,
+    /// Commands the frontend may not call, by name (`keychain_get`).
+    blocked_commands: Vec<String>,
+    /// Proxy for backend HTTP requests (link previews included) and update
+    /// checks, instead of the system's.
+    pub(crate) proxy: Option<Url>,
+    /// Update channel to follow, as the URL of its `latest.json`, instead of
+    /// the public releases.
+    pub(crate) update_endpoint: Option<Url>,
+    #[serde(deserialize_with = "flag")]
+    pub(crate) disable_updates: bool,
+    /// Where the policy was read from; empty when the machine isn't managed.
+    #[serde(skip_deserializing)]
+    sources: Vec<String>,
+}
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Registry policies write booleans as DWORDs.
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => b,
+        Value::Number(n) => n.as_u64().is_some_and(|n| n != 0),
+        _ => false,
+    })
+}
+
+/// Lay `top` over `base`; pinned settings from both are kept.
+fn overlay(base: &mut Map<String, Value>, top: Map<String, Value>) {
+    for (key, value) in top {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(into)), Value::Object(from)) if key == "settings" => {
+                into.extend(from);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn sources(_identifier: &str) -> Vec<(String, Map<String, Value>)> {
+    use windows::Win32::System::Registry::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    const KEY: &str = r"SOFTWARE\Policies\Spectrus";
+    [("HKCU", HKEY_CURRENT_USER), ("HKLM", HKEY_LOCAL_MACHINE)]
+        .into_iter()
+        .filter_map(|(name, root)| {
+            let mut values = registry::values(root, KEY)?;
+            if let Some(settings) = registry::values(root, &format!(r"{KEY}\Settings")) {
+                values.insert("settings".into(), Value::Object(settings));
+            }
+            // Policy values are conventionally PascalCase.
+            let values = values
+                .into_iter()
+                .map(|(k, v)| {
+                    let mut chars = k.chars();
+                    let first = chars.next().map(|c| c.to_ascii_lowercase());
+                    (first.into_iter().chain(chars).collect(), v)
+                })
+                .collect();
+            Some((format!(r"{name}\{KEY}"), values))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+mod registry {
+    use serde_json::{Map, Value};
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::Foundation::ERROR_SUCCESS;
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegEnumValueW, RegOpenKeyExW, HKEY, KEY_READ, REG_DWORD, REG_EXPAND_SZ,
+        REG_MULTI_SZ, REG_QWORD, REG_SZ,
+    };
+
+    fn utf16(data: &[u8]) -> Vec<u16> {
+        data.chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect()
+    }
+
+    /// A string value holding JSON (`true`, `30`, `["a"]`) is read as that
+    /// JSON; anything else as a plain string.
+    fn convert(kind: u32, data: &[u8]) -> Option<Value> {
+        let text = |wide: &[u16]| {
+            String::from_utf16_lossy(wide)
+                .trim_end_matches('\0')
+                .to_string()
+        };
+        Some(match kind {
+            k if k == REG_DWORD.0 => {
+                Value::from(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+            }
+            k if k == REG_QWORD.0 => {
+                Value::from(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+            }
+            k if k == REG_SZ.0 || k == REG_EXPAND_SZ.0 => {
+                let s = text(&utf16(data));
+                serde_json::from_str(&s).unwrap_or(Value::String(s))
+            }
+            k if k == REG_MULTI_SZ.0 => Value::Array(
+                utf16(data)
+                    .split(|&c| c == 0)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Value::String(text(s)))
+                    .collect(),
+            ),
+            _ => return None,
+        })
+    }
+
+    /// Every value under `root\path`, or `None` if the key doesn't exist.
+    pub(super) fn values(root: HKEY, path: &str) -> Option<Map<String, Value>> {
+        let mut key = HKEY::default();
+        let path = HSTRING::from(path);
+        let opened = unsafe { RegOpenKeyExW(root, &path, None, KEY_READ, &mut key) };
+        if opened != ERROR_SUCCESS {
+            return None;
+        }
+        let mut out = Map::new();
+        let mut name = vec![0u16; 16_384];
+        let mut data = vec![0u8; 64 * 1024];
+        for index in 0.. {
+            let mut name_len = name.len() as u32;
+            let mut data_len = data.len() as u32;
+            let mut kind = 0u32;
+            let status = unsafe {
+                RegEnumValueW(
+                    key,
+                    index,
+                    Some(PWSTR(name.as_mut_ptr())),
+                    &mut name_len,
+                    None,
+                    Some(&mut kind),
+                    Some(data.as_mut_ptr()),
+                    Some(&mut data_len),
+                )
+            };
+            if status != ERROR_SUCCESS {
+                break;
+            }
+            let value_name = String::from_utf16_lossy(&name[..name_len as usize]);
+            if let Some(value) = convert(kind, &data[..data_len as usize]) {
+                out.insert(value_name, value);
+            }
+        }
+        unsafe {
+            let _ = RegCloseKey(key);
+        }
+        Some(out)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn sources(identifier: &str) -> Vec<(String, Map<String, Value>)> {
+    let managed = std::path::Path::new("/Library/Managed Preferences");
+    let file = format!("{identifier}.plist");
+    let user = std::env::var("USER").unwrap_or_default();
+    [managed.join(user).join(&file), managed.join(&file)]
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(
+            |path| match plist::from_file::<_, Map<String, Value>>(&path) {
+                Ok(values) => Some((path.display().to_string(), values)),
+                Err(e) => {
+                    eprintln!("policy: {}: {e}", path.display());
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn sources(_identifier: &str) -> Vec<(String, Map<String, Value>)> {
+    let path = std::path::Path::new("/etc/spectrus/policy.json");
+    let Ok(bytes) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(values) => vec![(path.display().to_string(), values)],
+        Err(e) => {
+            eprintln!("policy: {}: {e}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+/// Read the managed policy. Called once, before anything consults it.
+pub fn load(identifier: &str) {
+    let mut merged = Map::new();
+    let mut read = Vec::new();
+    for (source, values) in sources(identifier) {
+        overlay(&mut merged, values);
+        read.push(source);
+    }
+    let mut policy: Policy = serde_json::from_value(Value::Object(merged)).unwrap_or_else(|e| {
+        eprintln!("policy: ignoring malformed policy: {e}");
+        Policy::default()
+    });
+    policy.sources = read;
+    let _ = POLICY.set(policy);
+}
+
+/// The policy in force; empty on unmanaged machines.
+pub(crate) fn current() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+impl Policy {
+    /// The value of a setting the policy holds, if it holds it.
+    pub(crate) fn pinned(&self, key: &str) -> Option<&Value> {
+        self.settings.get(key)
+    }
+
+    /// A reqwest client builder going through the policy's proxy, if any.
+    pub(crate) fn client_builder(&self) -> reqwest::ClientBuilder {
+        let builder = reqwest::Client::builder();
+        match self.proxy.as_ref().map(|p| reqwest::Proxy::all(p.as_str())) {
+            Some(Ok(proxy)) => builder.proxy(proxy),
+            _ => builder,
+        }
+    }
+}
+
+/// Wrap the command handler so commands the policy blocks are rejected
+/// before they run.
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        if current().blocked_commands.iter().any(|c| c == command) {
+            let message = format!("{command} has been disabled by your administrator");
+            invoke.resolver.reject(message);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// The policy in force, so the frontend can disable what it covers: pinned
+/// settings, blocked commands, and whether updates are managed.
+#[tauri::command]
+pub fn policy_effective() -> Policy {
+    current().clone()
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{Context, State};
+
+/// File next to the executable that turns on portable mode for every launch.
+const MARKER: &str = "portable";
+
+/// Directory next to the executable that holds all data in portable mode.
+const DATA_DIR: &str = "SpectrusData";
+
+/// Managed state: the portable data directory, or `None` for a normal install.
+pub struct Portable(pub Option<PathBuf>);
+
+#[derive(Serialize)]
+pub struct PortableInfo {
+    enabled: bool,
+    dir: Option<PathBuf>,
+}
+
+/// Data directory for this launch if running portable, i.e. when started with
+/// `--portable` (`flag`) or when a `portable` file sits beside the executable.
+pub fn data_dir(flag: bool) -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    let requested = flag || exe_dir.join(MARKER).is_file();
+    requested.then(|| exe_dir.join(DATA_DIR))
+}
+
+/// Point every configured window's webview storage (cookies, localStorage,
+/// HTTP cache) into `dir`. macOS WebKit cannot be redirected and keeps its
+/// store in the user's Library.
+pub fn configure(context: &mut Context, dir: &Path) {
+    for window in &mut context.config_mut().app.windows {
+        window.data_directory = Some(dir.join("webview"));
+    }
+}
+
+/// Where profiles live in portable mode.
+pub fn profiles_root(dir: &Path) -> PathBuf {
+    dir.join("profiles")
+}
+
+/// Whether this is a portable launch and where its data lives. The updater is
+/// not available in portable mode; the frontend should hide update prompts
+/// and point users at the release page instead.
+#[tauri::command]
+pub fn portable_info(portable: State<'_, Portable>) -> PortableInfo {
+    PortableInfo {
+        enabled: portable.0.is_some(),
+        dir: portable.0.clone(),
+    }
+}
@@ -0,0 +1,306 @@
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter};
+
+#[derive(Clone, Copy)]
+enum Signal {
+    Suspend,
+    Resume,
+    Locked,
+    Unlocked,
+}
+
+/// Last state reported for (asleep, locked). Several OS sources can report the
+/// same transition, so repeats are dropped.
+static LAST: Mutex<(Option<bool>, Option<bool>)> = Mutex::new((None, None));
+
+fn emit(app: &AppHandle, signal: Signal) {
+    let (event, changed) = {
+        let mut last = LAST.lock().unwrap();
+        let (slot, value, event) = match signal {
+            Signal::Suspend => (&mut last.0, true, "spectrus://system-suspend"),
+            Signal::Resume => (&mut last.0, false, "spectrus://system-resume"),
+            Signal::Locked => (&mut last.1, true, "spectrus://session-locked"),
+            Signal::Unlocked => (&mut last.1, false, "spectrus://session-unlocked"),
+        };
+        (event, slot.replace(value) != Some(value))
+    };
+    if changed {
+        if let Err(e) = app.emit(event, ()) {
+            eprintln!("power: {event} emit error: {e}");
+        }
+    }
+}
+
+/// Start forwarding OS sleep/wake and session lock/unlock as
+/// `spectrus://system-suspend`, `spectrus://system-resume`,
+/// `spectrus://session-locked` and `spectrus://session-unlocked`. Suspend is
+/// delivered shortly before the machine sleeps, so handlers must be quick.
+pub fn start(app: &AppHandle) {
+    platform::start(app);
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::thread;
+
+    use tauri::AppHandle;
+    use zbus::blocking::{Connection, Proxy};
+
+    use super::{emit, Signal};
+
+    /// Screensaver services whose `ActiveChanged` tracks the lock screen.
+    const SCREENSAVERS: [(&str, &str); 2] = [
+        (
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+        ),
+        ("org.gnome.ScreenSaver", "/org/gnome/ScreenSaver"),
+    ];
+
+    pub fn start(app: &AppHandle) {
+        let sleep_app = app.clone();
+        thread::spawn(move || {
+            if let Err(e) = sleep_signals(&sleep_app) {
+                eprintln!("power: sleep events unavailable: {e}");
+            }
+        });
+        for (name, path) in SCREENSAVERS {
+            let app = app.clone();
+            thread::spawn(move || {
+                if let Err(e) = lock_signals(&app, name, path) {
+                    eprintln!("power: {name} unavailable: {e}");
+                }
+            });
+        }
+    }
+
+    /// logind's `PrepareForSleep(true)` before sleeping, `(false)` after.
+    fn sleep_signals(app: &AppHandle) -> zbus::Result<()> {
+        let conn = Connection::system()?;
+        let proxy = Proxy::new(
+            &conn,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )?;
+        for signal in proxy.receive_signal("PrepareForSleep")? {
+            if let Ok(sleeping) = signal.body().deserialize::<bool>() {
+                let signal = if sleeping {
+                    Signal::Suspend
+                } else {
+                    Signal::Resume
+                };
+                emit(app, signal);
+            }
+        }
+        Ok(())
+    }
+
+    fn lock_signals(app: &AppHandle, name: &'static str, path: &'static str) -> zbus::Result<()> {
+        let conn = Connection::session()?;
+        let proxy = Proxy::new(&conn, name, path, name)?;
+        for signal in proxy.receive_signal("ActiveChanged")? {
+            if let Ok(active) = signal.body().deserialize::<bool>() {
+                let signal = if active {
+                    Signal::Locked
+                } else {
+                    Signal::Unlocked
+                };
+                emit(app, signal);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::OnceLock;
+
+    use tauri::AppHandle;
+    use windows::core::w;
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::System::RemoteDesktop::{
+        WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, MSG,
+        PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND, WINDOW_EX_STYLE, WINDOW_STYLE, WM_POWERBROADCAST,
+        WM_WTSSESSION_CHANGE, WNDCLASSW, WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+    };
+
+    use super::{emit, Signal};
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    pub fn start(app: &AppHandle) {
+        let _ = APP.set(app.clone());
+        std::thread::spawn(|| {
+            // SAFETY: the window is created and pumped on this thread only.
+            if let Err(e) = unsafe { run() } {
+                eprintln!("power: event window failed: {e}");
+            }
+        });
+    }
+
+    /// A hidden top-level window of our own: message-only windows don't get
+    /// `WM_POWERBROADCAST`, and the webview's windows belong to the event loop.
+    unsafe fn run() -> windows::core::Result<()> {
+        let instance = GetModuleHandleW(None)?;
+        let class_name = w!("SpectrusPowerEvents");
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+        if RegisterClassW(&class) == 0 {
+            return Err(windows::core::Error::from_thread());
+        }
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            class_name,
+            w!(""),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )?;
+        WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION)?;
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            DispatchMessageW(&msg);
+        }
+        Ok(())
+    }
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        let signal = match (msg, wparam.0 as u32) {
+            (WM_POWERBROADCAST, PBT_APMSUSPEND) => Some(Signal::Suspend),
+            // Sent on every wake; PBT_APMRESUMESUSPEND only follows user input.
+            (WM_POWERBROADCAST, PBT_APMRESUMEAUTOMATIC) => Some(Signal::Resume),
+            (WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK) => Some(Signal::Locked),
+            (WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK) => Some(Signal::Unlocked),
+            _ => None,
+        };
+        if let (Some(signal), Some(app)) = (signal, APP.get()) {
+            emit(app, signal);
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::cell::RefCell;
+    use std::sync::OnceLock;
+
+    use objc2::rc::Retained;
+    use objc2::runtime::NSObject;
+    use objc2::{define_class, msg_send, sel, MainThreadMarker, MainThreadOnly};
+    use objc2_app_kit::{
+        NSWorkspace, NSWorkspaceDidWakeNotification, NSWorkspaceWillSleepNotification,
+    };
+    use objc2_foundation::{ns_string, NSDistributedNotificationCenter, NSNotification};
+    use tauri::AppHandle;
+
+    use super::{emit, Signal};
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    thread_local! {
+        // Notification centers don't retain their observers.
+        static OBSERVER: RefCell<Option<Retained<Observer>>> = const { RefCell::new(None) };
+    }
+
+    fn forward(signal: Signal) {
+        if let Some(app) = APP.get() {
+            emit(app, signal);
+        }
+    }
+
+    define_class!(
+        #[unsafe(super(NSObject))]
+        #[thread_kind = MainThreadOnly]
+        #[name = "SpectrusPowerObserver"]
+        struct Observer;
+
+        impl Observer {
+            #[unsafe(method(willSleep:))]
+            fn will_sleep(&self, _note: &NSNotification) {
+                forward(Signal::Suspend);
+            }
+
+            #[unsafe(method(didWake:))]
+            fn did_wake(&self, _note: &NSNotification) {
+                forward(Signal::Resume);
+            }
+
+            #[unsafe(method(screenLocked:))]
+            fn screen_locked(&self, _note: &NSNotification) {
+                forward(Signal::Locked);
+            }
+
+            #[unsafe(method(screenUnlocked:))]
+            fn screen_unlocked(&self, _note: &NSNotification) {
+                forward(Signal::Unlocked);
+            }
+        }
+    );
+
+    pub fn start(app: &AppHandle) {
+        let _ = APP.set(app.clone());
+        let _ = app.run_on_main_thread(|| {
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            // SAFETY: `init` on a freshly allocated NSObject subclass.
+            let observer: Retained<Observer> = unsafe { msg_send![Observer::alloc(mtm), init] };
+            let workspace = NSWorkspace::sharedWorkspace().notificationCenter();
+            let distributed = NSDistributedNotificationCenter::defaultCenter();
+            // SAFETY: every selector is implemented by `Observer` with the
+            // `(NSNotification *)` signature observers are called with, and
+            // `OBSERVER` keeps it alive for the life of the process.
+            unsafe {
+                workspace.addObserver_selector_name_object(
+                    &observer,
+                    sel!(willSleep:),
+                    Some(NSWorkspaceWillSleepNotification),
+                    None,
+                );
+                workspace.addObserver_selector_name_object(
+                    &observer,
+                    sel!(didWake:),
+                    Some(NSWorkspaceDidWakeNotification),
+                    None,
+                );
+                // Undocumented but long-standing; there is no public API.
+                distributed.addObserver_selector_name_object(
+                    &observer,
+                    sel!(screenLocked:),
+                    Some(ns_string!("com.apple.screenIsLocked")),
+                    None,
+                );
+                distributed.addObserver_selector_name_object(
+                    &observer,
+                    sel!(screenUnlocked:),
+                    Some(ns_string!("com.apple.screenIsUnlocked")),
+                    None,
+                );
+            }
+            OBSERVER.set(Some(observer));
+        });
+    }
+}
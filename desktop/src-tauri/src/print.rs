@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde::{Deserialize, Serialize};
+use tauri::{State, WebviewWindow};
+
+use crate::fs_scope::FsScope;
+use crate::watchdog::Call;
+
+#[derive(Serialize)]
+pub struct PrinterInfo {
+    name: String,
+    default: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct PrintOptions {
+    /// Printer name from `printer_list`; the system default when absent.
+    printer: Option<String>,
+    /// 1-based pages, e.g. "1-3,5".
+    pages: Option<String>,
+    orientation: Option<Orientation>,
+    copies: Option<u32>,
+}
+
+/// Accept only what every spooler understands: comma-separated pages or
+/// `a-b` ranges with `a <= b`.
+fn validate_pages(pages: &str) -> Result<(), String> {
+    let bad = || format!("invalid page range: {pages}");
+    for part in pages.split(',') {
+        let (from, to) = part.trim().split_once('-').unwrap_or((part, part));
+        let from: u32 = from.trim().parse().map_err(|_| bad())?;
+        let to: u32 = to.trim().parse().map_err(|_| bad())?;
+        if from == 0 || from > to {
+            return Err(bad());
+        }
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command) -> Result<Output, String> {
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => output.status.to_string(),
+            msg => msg.to_string(),
+        });
+    }
+    Ok(output)
+}
+
+#[cfg(unix)]
+fn printers() -> Result<Vec<PrinterInfo>, String> {
+    let names = run(Command::new("lpstat").arg("-e").env("LC_ALL", "C"))?;
+    // Fails with "no system default destination" when none is set.
+    let default = Command::new("lpstat")
+        .arg("-d")
+        .env("LC_ALL", "C")
+        .output()
+        .ok()
+        .and_then(|o| {
+            let out = String::from_utf8_lossy(&o.stdout).into_owned();
+            out.split_once(':').map(|(_, name)| name.trim().to_string())
+        });
+    Ok(String::from_utf8_lossy(&names.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|name| PrinterInfo {
+            default: default.as_deref() == Some(name),
+            name: name.to_string(),
+        })
+        .collect())
+}
+
+#[cfg(unix)]
+fn send(path: &Path, options: &PrintOptions) -> Result<(), String> {
+    let mut lp = Command::new("lp");
+    if let Some(printer) = &options.printer {
+        lp.args(["-d", printer]);
+    }
+    if let Some(pages) = &options.pages {
+        lp.args(["-P", pages]);
+    }
+    if let Some(orientation) = options.orientation {
+        let value = match orientation {
+            Orientation::Portrait => "orientation-requested=3",
+            Orientation::Landscape => "orientation-requested=4",
+        };
+        lp.args(["-o", value]);
+    }
+    if let Some(copies) = options.copies {
+        lp.args(["-n", &copies.to_string()]);
+    }
+    run(lp.arg("--").arg(path)).map(|_| ())
+}
+
+#[cfg(windows)]
+pub(crate) fn powershell(script: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+#[cfg(windows)]
+fn printers() -> Result<Vec<PrinterInfo>, String> {
+    let output = run(&mut powershell(
+        "Get-CimInstance Win32_Printer | Select-Object Name,Default | ConvertTo-Json",
+    ))?;
+    let value: serde_json::Value =
+        serde_json::from_slice(&output.stdout).unwrap_or(serde_json::Value::Null);
+    // A single printer comes back as an object rather than an array.
+    let list = match value {
+        serde_json::Value::Array(list) => list,
+        serde_json::Value::Null => Vec::new(),
+        other => vec![other],
+    };
+    Ok(list
+        .iter()
+        .filter_map(|p| {
+            Some(PrinterInfo {
+                name: p.get("Name")?.as_str()?.to_string(),
+                default: p.get("Default").and_then(|d| d.as_bool()).unwrap_or(false),
+            })
+        })
+        .collect())
+}
+
+#[cfg(windows)]
+fn send(path: &Path, options: &PrintOptions) -> Result<(), String> {
+    // The shell's print verbs hand the file to its registered application,
+    // which takes no page, orientation or copy settings.
+    if options.pages.is_some() || options.orientation.is_some() || options.copies.is_some() {
+        return Err("page range, orientation and copies are not supported on Windows".into());
+    }
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let file = quote(&path.to_string_lossy());
+    let script = match &options.printer {
+        Some(printer) => format!(
+            "Start-Process -FilePath {file} -Verb PrintTo -ArgumentList {} -WindowStyle Hidden",
+            quote(&format!("\"{printer}\""))
+        ),
+        None => format!("Start-Process -FilePath {file} -Verb Print -WindowStyle Hidden"),
+    };
+    run(&mut powershell(&script)).map(|_| ())
+}
+
+/// Printers known to the OS spooler.
+#[tauri::command]
+pub async fn printer_list() -> Result<Vec<PrinterInfo>, String> {
+    tauri::async_runtime::spawn_blocking(printers)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Open the native print dialog for this window's current view.
+#[tauri::command]
+pub fn print_view(window: WebviewWindow) -> Result<(), String> {
+    window.print().map_err(|e| e.to_string())
+}
+
+/// Send a backend-rendered document (PDF, image or plain text) straight to a
+/// printer without any dialog.
+#[tauri::command]
+pub async fn print_document(
+    call: Call,
+    scope: State<'_, FsScope>,
+    path: String,
+    options: Option<PrintOptions>,
+) -> Result<(), String> {
+    let path: PathBuf = scope.check(call.window(), &path)?;
+    let options = options.unwrap_or_default();
+    if let Some(pages) = &options.pages {
+        validate_pages(pages)?;
+    }
+    if options.copies == Some(0) {
+        return Err("copies must be at least 1".into());
+    }
+    let sending = async {
+        tauri::async_runtime::spawn_blocking(move || send(&path, &options))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    call.run(sending).await
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Listener, Manager, State, WebviewWindow};
+
+use crate::camera::Camera;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Where the policy is kept, as a `Policy` object.
+const POLICY_SETTING: &str = "privacy.policy";
+
+/// How often screen sharing is checked on platforms that can tell.
+const SHARING_POLL: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// The OS session is locked.
+    Locked,
+    /// The screen is being shared or recorded.
+    Sharing,
+    /// All the time.
+    Always,
+}
+
+/// When each action kicks in; an action applies if any of its triggers
+/// holds. Both default to locked or sharing.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Policy {
+    /// Stop camera scans and tell the frontend to pause recording.
+    pause_capture: Vec<Trigger>,
+    /// Hide windows flagged sensitive from screenshots and capture.
+    protect_windows: Vec<Trigger>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            pause_capture: vec![Trigger::Locked, Trigger::Sharing],
+            protect_windows: vec![Trigger::Locked, Trigger::Sharing],
+        }
+    }
+}
+
+/// Payload of `spectrus://privacy-changed`, also returned by `privacy_state`.
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyState {
+    locked: bool,
+    /// `None` where the platform can't tell.
+    sharing: Option<bool>,
+    /// Recordings and camera scans have been stopped, and new ones are
+    /// refused.
+    capture_paused: bool,
+    /// Sensitive windows are excluded from capture.
+    windows_protected: bool,
+}
+
+/// Managed state: current conditions and actions, and the labels of
+/// windows that flagged themselves sensitive.
+#[derive(Default)]
+pub struct Privacy {
+    state: Mutex<PrivacyState>,
+    sensitive: Mutex<HashSet<String>>,
+}
+
+impl Privacy {
+    /// Drop a closed window's flag so a new window reusing the label
+    /// starts unflagged.
+    pub fn forget(&self, label: &str) {
+        self.sensitive.lock().unwrap().remove(label);
+    }
+}
+
+fn policy(profile: &Profile) -> Policy {
+    settings::get(profile, POLICY_SETTING)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+fn applies(triggers: &[Trigger], state: &PrivacyState) -> bool {
+    triggers.iter().any(|t| match t {
+        Trigger::Locked => state.locked,
+        Trigger::Sharing => state.sharing == Some(true),
+        Trigger::Always => true,
+    })
+}
+
+/// `Err` while the policy has capture paused; recording and camera scans
+/// refuse to start, and recordings to take samples, until it lifts.
+pub(crate) fn check_capture(app: &AppHandle) -> Result<(), String> {
+    if app.state::<Privacy>().state.lock().unwrap().capture_paused {
+        return Err("capture is paused while the screen is locked or shared".into());
+    }
+    Ok(())
+}
+
+fn protect(app: &AppHandle, label: &str, protected: bool) {
+    if let Some(window) = app.get_webview_window(label) {
+        if let Err(e) = window.set_content_protected(protected) {
+            eprintln!("privacy: content protection for {label}: {e}");
+        }
+    }
+}
+
+/// Update the conditions, work out what the policy says, and carry out
+/// whatever changed. Emits `spectrus://privacy-changed` when anything did.
+fn reevaluate(app: &AppHandle, change: impl FnOnce(&mut PrivacyState)) {
+    let policy = policy(&app.state::<ProfileState>().current());
+    let privacy = app.state::<Privacy>();
+    let (before, after) = {
+        let mut state = privacy.state.lock().unwrap();
+        let before = *state;
+        change(&mut state);
+        state.capture_paused = applies(&policy.pause_capture, &state);
+        state.windows_protected = applies(&policy.protect_windows, &state);
+        (before, *state)
+    };
+    if before == after {
+        return;
+    }
+    if after.capture_paused && !before.capture_paused {
+        app.state::<Camera>().stop();
+        crate::recording::stop_all(app);
+    }
+    if after.windows_protected != before.windows_protected {
+        let labels: Vec<String> = privacy.sensitive.lock().unwrap().iter().cloned().collect();
+        for label in labels {
+            protect(app, &label, after.windows_protected);
+        }
+    }
+    let _ = app.emit("spectrus://privacy-changed", after);
+}
+
+/// Follow session lock through the power events and, where the platform
+/// exposes it, screen sharing.
+pub fn start(app: &AppHandle) {
+    for (event, locked) in [
+        ("spectrus://session-locked", true),
+        ("spectrus://session-unlocked", false),
+    ] {
+        let handle = app.clone();
+        app.listen(event, move |_| {
+            reevaluate(&handle, |state| state.locked = locked);
+        });
+    }
+    reevaluate(app, |_| {});
+    if platform::sharing().is_none() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let sharing = platform::sharing();
+        reevaluate(&app, |state| state.sharing = sharing);
+        std::thread::sleep(SHARING_POLL);
+    });
+}
+
+/// Current conditions and what the policy has done about them.
+#[tauri::command]
+pub fn privacy_state(privacy: State<'_, Privacy>) -> PrivacyState {
+    *privacy.state.lock().unwrap()
+}
+
+/// Flag the calling window as showing sensitive content, so the policy
+/// hides it from screen capture when it applies. Where the OS has no
+/// content protection (most Linux desktops) only the event is sent.
+#[tauri::command]
+pub fn privacy_set_sensitive(
+    app: AppHandle,
+    window: WebviewWindow,
+    privacy: State<'_, Privacy>,
+    sensitive: bool,
+) {
+    let label = window.label().to_string();
+    if sensitive {
+        privacy.sensitive.lock().unwrap().insert(label.clone());
+    } else {
+        privacy.forget(&label);
+    }
+    let protected = privacy.state.lock().unwrap().windows_protected;
+    protect(&app, &label, sensitive && protected);
+}
+
+/// Replace the policy and apply it right away.
+#[tauri::command]
+pub fn privacy_policy_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    policy: Policy,
+) -> Result<(), String> {
+    let value = serde_json::to_value(&policy).map_err(|e| e.to_string())?;
+    settings::set(&profiles.current(), POLICY_SETTING, value)?;
+    reevaluate(&app, |_| {});
+    Ok(())
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+
+    /// A Remote Desktop session means someone else is looking at the
+    /// screen. Meeting apps' screen sharing can't be detected.
+    pub fn sharing() -> Option<bool> {
+        // SAFETY: plain query without pointers.
+        Some(unsafe { GetSystemMetrics(SM_REMOTESESSION) } != 0)
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    /// Neither macOS nor the Linux desktops expose whether the screen is
+    /// being captured.
+    pub fn sharing() -> Option<bool> {
+        None
+    }
+}
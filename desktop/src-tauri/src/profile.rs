@@ -0,0 +1,329 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::keychain;
+
+/// Profile used when none is requested and none was used before.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Name of the lock file held open for as long as a profile is in use.
+const LOCK_FILE: &str = ".lock";
+
+/// Remembers the last profile so the next launch reopens it.
+const LAST_PROFILE_FILE: &str = "last-profile";
+
+/// A named profile and the directory that holds all of its data.
+#[derive(Clone, Serialize)]
+pub struct Profile {
+    pub name: String,
+    pub dir: PathBuf,
+    /// Incognito profile living in a temp dir that is wiped on exit.
+    pub ephemeral: bool,
+    /// Signed-in account within this profile (see `account_switch`).
+    pub account: Option<String>,
+}
+
+impl Profile {
+    pub fn settings_path(&self) -> PathBuf {
+        self.dir.join("settings.json")
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.dir.join("cache")
+    }
+
+    /// Keychain service for this profile and account. The default profile and
+    /// no account keep the original namespace so existing installs don't lose
+    /// their tokens.
+    pub fn keychain_service(&self) -> String {
+        let mut service = keychain::SERVICE.to_string();
+        if self.name != DEFAULT_PROFILE {
+            service = format!("{service}.{}", self.name);
+        }
+        if let Some(account) = &self.account {
+            service = format!("{service}.account-{account}");
+        }
+        service
+    }
+}
+
+/// Entry returned by `profile_list`.
+#[derive(Serialize)]
+pub struct ProfileInfo {
+    name: String,
+    active: bool,
+    /// Open in another Spectrus process.
+    locked: bool,
+}
+
+struct Active {
+    profile: Profile,
+    // Dropping the handle releases the OS lock.
+    lock: Option<File>,
+}
+
+/// Managed state tracking the profile this process has open.
+pub struct ProfileState {
+    root: PathBuf,
+    active: Mutex<Active>,
+}
+
+impl ProfileState {
+    /// Open `requested` (or the last used profile) under `root`, failing if
+    /// another process already holds it.
+    pub fn open(root: PathBuf, requested: Option<String>) -> Result<Self, String> {
+        let name = resolve(&root, requested);
+        let active = acquire(&root, &name, false)?;
+        write_last(&root, &name);
+        Ok(Self {
+            root,
+            active: Mutex::new(active),
+        })
+    }
+
+    /// Open a throwaway profile under `root` for an incognito session.
+    pub fn open_ephemeral(root: PathBuf) -> Result<Self, String> {
+        let active = acquire(&root, "incognito", true)?;
+        Ok(Self {
+            root,
+            active: Mutex::new(active),
+        })
+    }
+
+    /// Drop the profile lock ahead of process exit so the directory can be
+    /// removed (Windows refuses to delete open files).
+    pub fn release(&self) {
+        self.active.lock().unwrap().lock = None;
+    }
+
+    /// Snapshot of the active profile.
+    pub fn current(&self) -> Profile {
+        self.active.lock().unwrap().profile.clone()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Names of every profile on disk, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| validate_name(n).is_ok())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Create an empty profile directory. Fails if one already exists.
+    pub fn create(&self, name: &str) -> Result<(), String> {
+        validate_name(name)?;
+        let dir = self.root.join(name);
+        if dir.exists() {
+            return Err(format!("profile {name:?} already exists"));
+        }
+        fs::create_dir_all(dir.join("cache")).map_err(|e| e.to_string())
+    }
+
+    /// Swap the active account, returning the updated profile. Keychain calls
+    /// made after this see only the new account's namespace.
+    pub fn set_account(&self, account: Option<String>) -> Profile {
+        let mut active = self.active.lock().unwrap();
+        active.profile.account = account;
+        active.profile.clone()
+    }
+
+    /// Make `name` the active profile, releasing the previous one.
+    pub fn switch(&self, name: &str) -> Result<Profile, String> {
+        let mut active = self.active.lock().unwrap();
+        if active.profile.ephemeral {
+            return Err("profiles cannot be switched in an incognito session".into());
+        }
+        if active.profile.name == name {
+            return Ok(active.profile.clone());
+        }
+        // Take the new lock before letting go of the old one so a failed
+        // switch leaves the current profile untouched.
+        *active = acquire(&self.root, name, false)?;
+        write_last(&self.root, name);
+        Ok(active.profile.clone())
+    }
+}
+
+/// Profile names double as directory names and keychain suffixes.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    let ok = !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid profile name {name:?}: use 1-32 letters, digits, '-' or '_'"
+        ))
+    }
+}
+
+fn acquire(root: &Path, name: &str, ephemeral: bool) -> Result<Active, String> {
+    validate_name(name)?;
+    let mut profile = Profile {
+        name: name.to_string(),
+        dir: root.join(name),
+        ephemeral,
+        account: None,
+    };
+    profile.account = crate::account::saved(&profile);
+    fs::create_dir_all(profile.cache_dir()).map_err(|e| e.to_string())?;
+    let lock = open_lock(&profile.dir)?;
+    match lock.try_lock() {
+        Ok(()) => Ok(Active {
+            profile,
+            lock: Some(lock),
+        }),
+        Err(TryLockError::WouldBlock) => Err(format!(
+            "profile {name:?} is already open in another Spectrus window"
+        )),
+        Err(TryLockError::Error(e)) => Err(e.to_string()),
+    }
+}
+
+fn open_lock(dir: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))
+        .map_err(|e| e.to_string())
+}
+
+/// Whether another process currently holds `dir`'s lock.
+pub fn is_locked_elsewhere(dir: &Path) -> bool {
+    match open_lock(dir).map(|f| f.try_lock()) {
+        Ok(Ok(())) => false,
+        Ok(Err(TryLockError::WouldBlock)) => true,
+        _ => false,
+    }
+}
+
+fn read_last(root: &Path) -> Option<String> {
+    let name = fs::read_to_string(root.join(LAST_PROFILE_FILE)).ok()?;
+    let name = name.trim().to_string();
+    validate_name(&name).ok().map(|_| name)
+}
+
+fn write_last(root: &Path, name: &str) {
+    if let Err(e) = fs::write(root.join(LAST_PROFILE_FILE), name) {
+        eprintln!("profile: could not remember last profile: {e}");
+    }
+}
+
+/// Name of the profile `open` would pick: `requested`, else the last one used.
+pub fn resolve(root: &Path, requested: Option<String>) -> String {
+    requested
+        .or_else(|| read_last(root))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Activate `name`, tell every window, and refresh the tray menu.
+pub fn switch_to(app: &AppHandle, profiles: &ProfileState, name: &str) -> Result<Profile, String> {
+    let from = profiles.current().name;
+    crate::session::flush(app);
+    let profile = profiles.switch(name)?;
+    if profile.name != from {
+        crate::audit::record(
+            app,
+            crate::audit::Action::ProfileSwitch,
+            serde_json::json!({ "from": from, "to": profile.name }),
+        );
+    }
+    crate::instance::announce(app, &profile.dir);
+    app.emit("spectrus://profile-changed", &profile)
+        .map_err(|e| e.to_string())?;
+    crate::tray::refresh(app);
+    Ok(profile)
+}
+
+/// List all profiles with their active/locked state.
+#[tauri::command]
+pub fn profile_list(profiles: State<'_, ProfileState>) -> Vec<ProfileInfo> {
+    let current = profiles.current().name;
+    profiles
+        .names()
+        .into_iter()
+        .map(|name| ProfileInfo {
+            active: name == current,
+            locked: name != current && is_locked_elsewhere(&profiles.root().join(&name)),
+            name,
+        })
+        .collect()
+}
+
+/// Return the profile this window is running under.
+#[tauri::command]
+pub fn profile_current(profiles: State<'_, ProfileState>) -> Profile {
+    profiles.current()
+}
+
+/// Create an empty profile. Fails if one with that name already exists.
+#[tauri::command]
+pub fn profile_create(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    name: String,
+) -> Result<(), String> {
+    profiles.create(&name)?;
+    crate::tray::refresh(&app);
+    Ok(())
+}
+
+/// Switch this process to another profile. The frontend is expected to reload
+/// its state when `spectrus://profile-changed` fires.
+#[tauri::command]
+pub fn profile_switch(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    name: String,
+) -> Result<Profile, String> {
+    switch_to(&app, &profiles, &name)
+}
+
+/// Delete a profile's data directory and its system search stubs. The active
+/// profile and profiles open in another process cannot be deleted. Keychain
+/// entries are left behind because the OS stores cannot be enumerated; the
+/// frontend should clear the keys it knows about first.
+#[tauri::command]
+pub fn profile_delete(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    name: String,
+) -> Result<(), String> {
+    validate_name(&name)?;
+    if profiles.current().name == name {
+        return Err("cannot delete the active profile".into());
+    }
+    let dir = profiles.root().join(&name);
+    if !dir.is_dir() {
+        return Err(format!("profile {name:?} does not exist"));
+    }
+    let lock = open_lock(&dir)?;
+    if lock.try_lock().is_err() {
+        return Err(format!(
+            "profile {name:?} is open in another Spectrus window"
+        ));
+    }
+    drop(lock);
+    crate::search_index::remove_profile(&app, &name)?;
+    fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    crate::tray::refresh(&app);
+    Ok(())
+}
@@ -0,0 +1,297 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, ZipArchive, ZipWriter};
+
+use crate::fs_scope::FsScope;
+use crate::jobs::Job;
+use crate::profile::{self, ProfileState};
+use crate::settings;
+use crate::watchdog::{Call, Scope};
+
+/// Bumped whenever the bundle layout changes in a way older builds can't read.
+const FORMAT_VERSION: u32 = 1;
+
+/// Always stored unencrypted so compatibility can be checked before asking
+/// for a passphrase.
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    format: u32,
+    app_version: String,
+    profile: String,
+    exported_at: u64,
+    encrypted: bool,
+}
+
+/// What to do when the target profile already exists.
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep existing data; add settings and files the target lacks.
+    Merge,
+    /// Discard the target's data and use the bundle's.
+    Replace,
+}
+
+/// Result of `profile_inspect`.
+#[derive(Serialize)]
+pub struct BundleInfo {
+    manifest: Manifest,
+    compatible: bool,
+}
+
+/// Files under a profile dir that belong in a bundle. The lock and the cache
+/// are machine-local and rebuilt on demand.
+fn bundle_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "cache" && e.file_name() != ".lock")
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> Result<Manifest, String> {
+    let entry = archive
+        .by_name(MANIFEST)
+        .map_err(|_| "not a Spectrus profile bundle".to_string())?;
+    serde_json::from_reader(entry).map_err(|e| format!("corrupt manifest: {e}"))
+}
+
+fn check_compatible(manifest: &Manifest) -> Result<(), String> {
+    if manifest.format > FORMAT_VERSION {
+        return Err(format!(
+            "this bundle was exported by Spectrus {} and needs a newer version to import",
+            manifest.app_version
+        ));
+    }
+    Ok(())
+}
+
+/// Fold a freshly extracted profile into an existing one without overwriting
+/// anything already there.
+fn merge_into(staging: &Path, target: &Path) -> Result<(), String> {
+    for src in bundle_files(staging) {
+        let rel = src.strip_prefix(staging).map_err(|e| e.to_string())?;
+        let dst = target.join(rel);
+        if rel == Path::new("settings.json") && dst.exists() {
+            let mut merged = settings::read_file(&src);
+            merged.extend(settings::read_file(&dst));
+            let json = serde_json::to_vec_pretty(&merged).map_err(|e| e.to_string())?;
+            settings::write_atomic(&dst, &json)?;
+        } else if !dst.exists() {
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::copy(&src, &dst).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Write profile `name` (default: the active one) to a bundle at `dest`.
+/// With a passphrase, everything but the manifest is AES-256 encrypted.
+/// Cancelling the call removes the partial bundle.
+#[tauri::command]
+pub async fn profile_export(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    name: Option<String>,
+    dest: String,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let dest = scope.check_new(call.window(), &dest)?;
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = export(&app, name, &dest, passphrase, &scope);
+        if result.is_err() {
+            let _ = fs::remove_file(&dest);
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn export(
+    app: &AppHandle,
+    name: Option<String>,
+    dest: &Path,
+    passphrase: Option<String>,
+    scope: &Scope,
+) -> Result<(), String> {
+    let profiles = app.state::<ProfileState>();
+    let current = profiles.current();
+    let name = name.unwrap_or(current.name.clone());
+    profile::validate_name(&name)?;
+    let dir = profiles.root().join(&name);
+    if !dir.is_dir() {
+        return Err(format!("profile {name:?} does not exist"));
+    }
+    if name != current.name && profile::is_locked_elsewhere(&dir) {
+        return Err(format!(
+            "profile {name:?} is open in another Spectrus window"
+        ));
+    }
+
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        profile: name,
+        exported_at: (crate::time::now_ms(app) / 1000).max(0) as u64,
+        encrypted: passphrase.is_some(),
+    };
+
+    let mut zip = ZipWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    let plain = SimpleFileOptions::default();
+    let options = match &passphrase {
+        Some(p) => plain.with_aes_encryption(AesMode::Aes256, p),
+        None => plain,
+    };
+    zip.start_file(MANIFEST, plain).map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+
+    for path in bundle_files(&dir) {
+        scope.check()?;
+        let rel = path.strip_prefix(&dir).map_err(|e| e.to_string())?;
+        // Zip entries always use forward slashes.
+        let entry = rel.to_string_lossy().replace('\\', "/");
+        zip.start_file(entry, options).map_err(|e| e.to_string())?;
+        // Bundles carry plaintext: the data key stays in this machine's
+        // keychain, and the passphrase protects the bundle instead.
+        if crate::encryption::is_sealed(&path) {
+            if dir != current.dir {
+                return Err(format!(
+                    "profile {:?} has encrypted data; switch to it to export",
+                    manifest.profile
+                ));
+            }
+            let data = crate::encryption::read(app, &path)
+                .ok_or("cannot decrypt profile data; is the keychain unlocked?")?;
+            zip.write_all(&data).map_err(|e| e.to_string())?;
+            continue;
+        }
+        let mut file = File::open(&path).map_err(|e| e.to_string())?;
+        io::copy(&mut file, &mut zip).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    crate::audit::record(
+        app,
+        crate::audit::Action::Export,
+        serde_json::json!({
+            "kind": "profile",
+            "profile": manifest.profile,
+            "path": dest,
+            "encrypted": manifest.encrypted,
+        }),
+    );
+    Ok(())
+}
+
+/// Read a bundle's manifest so the frontend can show what it contains, whether
+/// it needs a passphrase, and whether this build can import it.
+#[tauri::command]
+pub fn profile_inspect(
+    call: Call,
+    scope: State<'_, FsScope>,
+    src: String,
+) -> Result<BundleInfo, String> {
+    let src = scope.check(call.window(), &src)?;
+    let mut archive =
+        ZipArchive::new(File::open(&src).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    let manifest = read_manifest(&mut archive)?;
+    Ok(BundleInfo {
+        compatible: check_compatible(&manifest).is_ok(),
+        manifest,
+    })
+}
+
+/// Import a bundle as profile `name`. If the profile exists, `mode` decides
+/// whether to merge into it or replace it; replacing the active profile or one
+/// open elsewhere is refused. Runs as a "profile-import" job that stops with
+/// the call.
+#[tauri::command]
+pub async fn profile_import(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    src: String,
+    name: String,
+    mode: ImportMode,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let src = scope.check(call.window(), &src)?;
+    profile::validate_name(&name)?;
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        import(&app, &src, &name, mode, passphrase.as_deref(), scope)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn import(
+    app: &AppHandle,
+    src: &Path,
+    name: &str,
+    mode: ImportMode,
+    passphrase: Option<&str>,
+    scope: Scope,
+) -> Result<(), String> {
+    let profiles = app.state::<ProfileState>();
+    let mut archive =
+        ZipArchive::new(File::open(src).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    let manifest = read_manifest(&mut archive)?;
+    check_compatible(&manifest)?;
+
+    let target = profiles.root().join(name);
+    let exists = target.is_dir();
+    let active = profiles.current().name == name;
+    if exists && mode == ImportMode::Replace {
+        if active {
+            return Err("cannot replace the active profile; switch to another one first".into());
+        }
+        if profile::is_locked_elsewhere(&target) {
+            return Err(format!(
+                "profile {name:?} is open in another Spectrus window"
+            ));
+        }
+    }
+    if !exists {
+        profiles.create(name)?;
+    }
+
+    // Extract next to the target first so a bad passphrase or truncated
+    // bundle never leaves a half-imported profile. The leading dot keeps the
+    // staging dir out of `profile_list`.
+    let staging = profiles.root().join(format!(".import-{name}"));
+    let _ = fs::remove_dir_all(&staging);
+    let mut job = Job::start(app, "profile-import").within(scope);
+    let extracted =
+        crate::archive::extract_archive(&mut job, &mut archive, &staging, passphrase, &[MANIFEST]);
+    let result = extracted.and_then(|_| {
+        if exists && mode == ImportMode::Merge {
+            merge_into(&staging, &target)
+        } else {
+            fs::remove_dir_all(&target)
+                .and_then(|_| fs::rename(&staging, &target))
+                .map_err(|e| e.to_string())
+        }
+    });
+    let _ = fs::remove_dir_all(&staging);
+    if result.is_err() && !exists {
+        let _ = fs::remove_dir_all(&target);
+    }
+    crate::tray::refresh(app);
+    result
+}
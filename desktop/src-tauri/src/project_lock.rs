@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use crate::fs_scope::FsScope;
+use crate::settings;
+
+/// Advisory lock file in the project directory. OS file locks can't be
+/// trusted on SMB and NFS shares, so the file itself is the lock.
+const LOCK_FILE: &str = ".spectrus-lock";
+
+/// How often held locks are refreshed and contested ones looked at again.
+const HEARTBEAT: Duration = Duration::from_secs(20);
+
+/// A lock not refreshed for this long belongs to an instance that crashed
+/// or lost the share, and may be taken over.
+const STALE_AFTER: Duration = Duration::from_secs(90);
+
+/// After taking a lock, wait this long and read it back, in case another
+/// machine took it at the same moment.
+const SETTLE: Duration = Duration::from_millis(750);
+
+/// Contents of the lock file.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Holder {
+    /// Random per running instance.
+    owner: String,
+    host: String,
+    pid: u32,
+    /// Unix ms, by each machine's corrected clock.
+    acquired_at: i64,
+    heartbeat_at: i64,
+}
+
+/// Payload of `spectrus://project-locked`, `spectrus://project-lock-lost`
+/// and `spectrus://project-lock-released`.
+#[derive(Clone, Serialize)]
+struct LockEvent<'a> {
+    path: &'a Path,
+    holder: Option<&'a Holder>,
+}
+
+/// Managed state: this instance's owner id, the projects it holds, and
+/// projects it was refused, watched until they come free.
+pub struct ProjectLocks {
+    owner: String,
+    held: Mutex<HashMap<PathBuf, Holder>>,
+    contested: Mutex<HashMap<PathBuf, Holder>>,
+}
+
+impl ProjectLocks {
+    pub fn new() -> Self {
+        let mut bytes = [0u8; 8];
+        let _ = getrandom::fill(&mut bytes);
+        Self {
+            owner: bytes.iter().map(|b| format!("{b:02x}")).collect(),
+            held: Mutex::default(),
+            contested: Mutex::default(),
+        }
+    }
+}
+
+fn lock_path(project: &Path) -> PathBuf {
+    project.join(LOCK_FILE)
+}
+
+fn read(project: &Path) -> Option<Holder> {
+    fs::read(lock_path(project))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+}
+
+fn write(project: &Path, holder: &Holder) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(holder).map_err(|e| e.to_string())?;
+    settings::write_atomic(&lock_path(project), &bytes)
+}
+
+fn stale(app: &AppHandle, holder: &Holder) -> bool {
+    crate::time::now_ms(app) - holder.heartbeat_at > STALE_AFTER.as_millis() as i64
+}
+
+/// Whether a lock file that can't be read was left half-written long ago,
+/// rather than being written right now.
+fn unreadable_stale(project: &Path) -> bool {
+    fs::metadata(lock_path(project))
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age > STALE_AFTER)
+        })
+        .unwrap_or(true)
+}
+
+/// Create the lock file holding `json`, failing if it exists. The holder is
+/// written to a temp file first and hard-linked into place, so nobody reads
+/// the lock empty. Shares that can't hard-link fall back to creating it in
+/// place; the read-back after `SETTLE` still catches a race there.
+fn create(project: &Path, owner: &str, json: &[u8]) -> std::io::Result<()> {
+    let tmp = project.join(format!("{LOCK_FILE}.{owner}.tmp"));
+    let linked = File::create(&tmp)
+        .and_then(|mut f| f.write_all(json).and_then(|()| f.sync_all()))
+        .and_then(|()| fs::hard_link(&tmp, lock_path(project)));
+    let _ = fs::remove_file(&tmp);
+    match linked {
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::Unsupported | ErrorKind::PermissionDenied
+            ) =>
+        {
+            File::create_new(lock_path(project)).and_then(|mut f| f.write_all(json))
+        }
+        linked => linked,
+    }
+}
+
+/// Wait out `SETTLE` and check the lock is still ours.
+fn settle(project: &Path, ours: Holder) -> Result<Holder, Result<Holder, String>> {
+    std::thread::sleep(SETTLE);
+    match read(project) {
+        Some(current) if current.owner == ours.owner => Ok(ours),
+        Some(current) => Err(Ok(current)),
+        None => Err(Err("the lock file went away while taking it".into())),
+    }
+}
+
+fn emit(app: &AppHandle, event: &str, path: &Path, holder: Option<&Holder>) {
+    let _ = app.emit(event, LockEvent { path, holder });
+}
+
+/// Try to take the lock on `project`. `Err(Ok(holder))` when another
+/// instance has it; `force` takes it anyway.
+fn acquire(app: &AppHandle, project: &Path, force: bool) -> Result<Holder, Result<Holder, String>> {
+    let locks = app.state::<ProjectLocks>();
+    let now = crate::time::now_ms(app);
+    let ours = Holder {
+        owner: locks.owner.clone(),
+        host: gethostname::gethostname().to_string_lossy().into_owned(),
+        pid: std::process::id(),
+        acquired_at: now,
+        heartbeat_at: now,
+    };
+    let json = serde_json::to_vec_pretty(&ours).map_err(|e| Err(e.to_string()))?;
+    if create(project, &locks.owner, &json).is_ok() {
+        return settle(project, ours);
+    }
+    let Some(current) = read(project) else {
+        // Unreadable: stale only if it has stayed that way for a while.
+        if force || unreadable_stale(project) {
+            return take_over(project, ours);
+        }
+        return Err(Err("the project lock is being written; try again".into()));
+    };
+    if current.owner == locks.owner {
+        return Ok(current);
+    }
+    if force || stale(app, &current) {
+        return take_over(project, ours);
+    }
+    Err(Ok(current))
+}
+
+fn take_over(project: &Path, ours: Holder) -> Result<Holder, Result<Holder, String>> {
+    write(project, &ours).map_err(Err)?;
+    settle(project, ours)
+}
+
+/// Refresh every held lock, noticing any another instance took over, and
+/// announce contested projects that came free.
+fn heartbeat(app: &AppHandle) {
+    let locks = app.state::<ProjectLocks>();
+    let now = crate::time::now_ms(app);
+    let held: Vec<(PathBuf, Holder)> = locks
+        .held
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(p, h)| (p.clone(), h.clone()))
+        .collect();
+    for (path, mut holder) in held {
+        match read(&path) {
+            Some(current) if current.owner != holder.owner => {
+                locks.held.lock().unwrap().remove(&path);
+                emit(app, "spectrus://project-lock-lost", &path, Some(&current));
+            }
+            // Unreachable share: keep trying; others will see it go stale
+            // only if this lasts past `STALE_AFTER`.
+            _ if !path.is_dir() => {}
+            _ => {
+                holder.heartbeat_at = now;
+                match write(&path, &holder) {
+                    Ok(()) => {
+                        locks.held.lock().unwrap().insert(path, holder);
+                    }
+                    Err(e) => eprintln!("project lock: {}: {e}", path.display()),
+                }
+            }
+        }
+    }
+
+    let contested: Vec<PathBuf> = locks.contested.lock().unwrap().keys().cloned().collect();
+    for path in contested {
+        let free = match read(&path) {
+            Some(holder) => stale(app, &holder),
+            None => !lock_path(&path).exists() || unreadable_stale(&path),
+        };
+        if free {
+            locks.contested.lock().unwrap().remove(&path);
+            emit(app, "spectrus://project-lock-released", &path, None);
+        }
+    }
+}
+
+/// Keep held locks alive for as long as the app runs.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(HEARTBEAT);
+        heartbeat(&app);
+    });
+}
+
+fn release(app: &AppHandle, project: &Path) {
+    let locks = app.state::<ProjectLocks>();
+    if locks.held.lock().unwrap().remove(project).is_none() {
+        return;
+    }
+    if read(project).is_some_and(|h| h.owner == locks.owner) {
+        let _ = fs::remove_file(lock_path(project));
+    }
+}
+
+/// Let go of every held lock, on the way out.
+pub fn release_all(app: &AppHandle) {
+    let held: Vec<PathBuf> = app
+        .state::<ProjectLocks>()
+        .held
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    for project in held {
+        release(app, &project);
+    }
+}
+
+/// Lock a project directory before opening it, so another machine on the
+/// same share doesn't write to it at the same time. Returns the holder,
+/// this instance on success. When another instance has it, fails with a
+/// message naming the host and emits `spectrus://project-locked`; once it
+/// comes free, `spectrus://project-lock-released` follows. Locks left by a
+/// crashed instance are taken over after 90 seconds without a heartbeat,
+/// or at once with `force`.
+#[tauri::command]
+pub async fn project_lock(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    locks: State<'_, ProjectLocks>,
+    path: String,
+    force: Option<bool>,
+) -> Result<Holder, String> {
+    let project = scope.check(window.label(), &path)?;
+    if !project.is_dir() {
+        return Err(format!("{path} is not a folder"));
+    }
+    let handle = app.clone();
+    let target = project.clone();
+    let acquired = tauri::async_runtime::spawn_blocking(move || {
+        acquire(&handle, &target, force.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    match acquired {
+        Ok(holder) => {
+            locks.contested.lock().unwrap().remove(&project);
+            locks.held.lock().unwrap().insert(project, holder.clone());
+            Ok(holder)
+        }
+        Err(Ok(holder)) => {
+            emit(&app, "spectrus://project-locked", &project, Some(&holder));
+            locks
+                .contested
+                .lock()
+                .unwrap()
+                .insert(project, holder.clone());
+            Err(format!(
+                "the project is open on {} (since {})",
+                holder.host, holder.acquired_at
+            ))
+        }
+        Err(Err(e)) => Err(e),
+    }
+}
+
+/// Release a project's lock when closing it. Stops watching it if it was
+/// only contested.
+#[tauri::command]
+pub fn project_unlock(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    locks: State<'_, ProjectLocks>,
+    path: String,
+) -> Result<(), String> {
+    let project = scope.check(window.label(), &path)?;
+    locks.contested.lock().unwrap().remove(&project);
+    release(&app, &project);
+    Ok(())
+}
+
+/// Who holds a project's lock, if anyone. Stale locks are reported too;
+/// compare `heartbeatAt` with the current time to tell.
+#[tauri::command]
+pub fn project_lock_status(
+    window: Window,
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<Option<Holder>, String> {
+    let project = scope.check(window.label(), &path)?;
+    Ok(read(&project))
+}
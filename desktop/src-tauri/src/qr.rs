@@ -0,0 +1,134 @@
+use std::io::Cursor;
+
+use image::{GrayImage, ImageFormat, Luma};
+use qrcode::{EcLevel, QrCode};
+use serde::Deserialize;
+use tauri::ipc::Response;
+use tauri::State;
+
+use crate::fs_scope::FsScope;
+use crate::watchdog::Call;
+
+/// Side length used when the caller doesn't ask for one.
+const DEFAULT_SIZE: u32 = 256;
+
+/// Upper bound on the rendered image, so a bad option can't allocate gigabytes.
+const MAX_SIZE: u32 = 4096;
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrErrorCorrection {
+    Low,
+    #[default]
+    Medium,
+    Quartile,
+    High,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrOptions {
+    format: QrFormat,
+    /// Minimum side length in pixels; rounded up to a whole number of modules.
+    size: Option<u32>,
+    error_correction: QrErrorCorrection,
+    /// Leave out the four-module white border scanners expect.
+    no_quiet_zone: bool,
+}
+
+/// An image to scan: a file the window has access to, or its encoded bytes.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrSource {
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+/// Every QR code found in `image`, in detection order. Codes that are found
+/// but can't be read (damaged, partly covered) are skipped.
+pub(crate) fn decode_image(image: GrayImage) -> Vec<String> {
+    let mut prepared = rqrr::PreparedImage::prepare(image);
+    prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| grid.decode().ok().map(|(_, content)| content))
+        .collect()
+}
+
+fn generate(data: &str, options: &QrOptions) -> Result<Vec<u8>, String> {
+    let level = match options.error_correction {
+        QrErrorCorrection::Low => EcLevel::L,
+        QrErrorCorrection::Medium => EcLevel::M,
+        QrErrorCorrection::Quartile => EcLevel::Q,
+        QrErrorCorrection::High => EcLevel::H,
+    };
+    let code = QrCode::with_error_correction_level(data, level).map_err(|e| e.to_string())?;
+    let size = options.size.unwrap_or(DEFAULT_SIZE).min(MAX_SIZE);
+    let quiet_zone = !options.no_quiet_zone;
+    match options.format {
+        QrFormat::Png => {
+            let image = code
+                .render::<Luma<u8>>()
+                .quiet_zone(quiet_zone)
+                .min_dimensions(size, size)
+                .max_dimensions(MAX_SIZE, MAX_SIZE)
+                .build();
+            let mut png = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            Ok(png)
+        }
+        QrFormat::Svg => Ok(code
+            .render::<qrcode::render::svg::Color>()
+            .quiet_zone(quiet_zone)
+            .min_dimensions(size, size)
+            .build()
+            .into_bytes()),
+    }
+}
+
+/// Encode `data` as a QR code. The response body is the PNG or the SVG
+/// markup (UTF-8), depending on `options.format`.
+#[tauri::command]
+pub async fn qr_generate(data: String, options: Option<QrOptions>) -> Result<Response, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || generate(&data, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(Response::new)
+}
+
+/// Find and read the QR codes in an image (PNG, JPEG or WebP). Returns an
+/// empty list when there are none.
+#[tauri::command]
+pub async fn qr_decode(
+    call: Call,
+    scope: State<'_, FsScope>,
+    source: QrSource,
+) -> Result<Vec<String>, String> {
+    let bytes = match source {
+        QrSource::Path(path) => {
+            let path = scope.check(call.window(), &path)?;
+            std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?
+        }
+        QrSource::Bytes(bytes) => bytes,
+    };
+    let decoding = async {
+        tauri::async_runtime::spawn_blocking(move || {
+            let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+            Ok(decode_image(image.to_luma8()))
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    };
+    call.run(decoding).await
+}
@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use crate::fs_scope::FsScope;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Longest list kept; older entries fall off the end.
+const MAX_ENTRIES: usize = 20;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Free-form tag from the frontend, e.g. "project" or "recording".
+    pub kind: Option<String>,
+    pub opened_at: u64,
+}
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("recent.json")
+}
+
+fn load(profile: &Profile) -> Vec<RecentFile> {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save(profile: &Profile, entries: &[RecentFile]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(entries).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(profile), &json)
+}
+
+/// Recent entries whose files still exist, newest first. Missing entries are
+/// pruned from disk as a side effect.
+pub fn list(profile: &Profile) -> Vec<RecentFile> {
+    let entries = load(profile);
+    let before = entries.len();
+    let entries: Vec<RecentFile> = entries.into_iter().filter(|e| e.path.exists()).collect();
+    if entries.len() != before {
+        let _ = save(profile, &entries);
+    }
+    entries
+}
+
+/// Grant `window` the recent entry at `path` and tell it to open the file.
+/// Used by the tray and by OS "Open Recent" activations.
+pub fn open(app: &AppHandle, window: &str, path: &Path) {
+    if crate::search_index::open(app, window, path) {
+        return;
+    }
+    app.state::<FsScope>().grant(window, path);
+    if let Err(e) = app.emit_to(window, "spectrus://open-file", path) {
+        eprintln!("recent: open emit error: {e}");
+    }
+}
+
+/// Record that the user opened `path`. Besides the app's own list, the OS
+/// recent-documents store is updated so the file shows up in the Dock / jump
+/// list / desktop "Recent" views.
+#[tauri::command]
+pub fn recent_files_add(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    path: String,
+    kind: Option<String>,
+) -> Result<(), String> {
+    let path = scope.check(window.label(), &path)?;
+    let profile = profiles.current();
+    let mut entries = load(&profile);
+    entries.retain(|e| e.path != path);
+    entries.insert(
+        0,
+        RecentFile {
+            path: path.clone(),
+            kind,
+            opened_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        },
+    );
+    entries.truncate(MAX_ENTRIES);
+    save(&profile, &entries)?;
+    // Incognito sessions must not leave traces in OS-wide history.
+    if !profile.ephemeral {
+        note_os_recent(&app, path);
+    }
+    crate::tray::refresh(&app);
+    Ok(())
+}
+
+/// List recent files for the active profile, newest first, dropping entries
+/// whose paths no longer exist.
+#[tauri::command]
+pub fn recent_files_list(profiles: State<'_, ProfileState>) -> Vec<RecentFile> {
+    list(&profiles.current())
+}
+
+/// Re-open a recent entry: grants this window access to it again. Only paths
+/// already in the list are accepted.
+#[tauri::command]
+pub fn recent_files_open(
+    window: Window,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !list(&profiles.current()).iter().any(|e| e.path == path) {
+        return Err(format!("{} is not a recent file", path.display()));
+    }
+    scope.grant(window.label(), &path);
+    Ok(())
+}
+
+/// Forget all recent files for the active profile.
+#[tauri::command]
+pub fn recent_files_clear(app: AppHandle, profiles: State<'_, ProfileState>) -> Result<(), String> {
+    clear_os_recent(&app);
+    save(&profiles.current(), &[])?;
+    crate::tray::refresh(&app);
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn note_os_recent(app: &AppHandle, path: PathBuf) {
+    let _ = app.run_on_main_thread(move || {
+        use objc2::MainThreadMarker;
+        use objc2_app_kit::NSDocumentController;
+        use objc2_foundation::{NSString, NSURL};
+
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+        NSDocumentController::sharedDocumentController(mtm).noteNewRecentDocumentURL(&url);
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn clear_os_recent(app: &AppHandle) {
+    let _ = app.run_on_main_thread(|| {
+        use objc2::MainThreadMarker;
+        use objc2_app_kit::NSDocumentController;
+
+        if let Some(mtm) = MainThreadMarker::new() {
+            let controller = NSDocumentController::sharedDocumentController(mtm);
+            // SAFETY: `sender` is only used for target/action routing; nil is
+            // what AppKit passes itself.
+            unsafe { controller.clearRecentDocuments(None) };
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn note_os_recent(_app: &AppHandle, path: PathBuf) {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::Win32::UI::Shell::{SHAddToRecentDocs, SHARD_PATHW};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    // SAFETY: `wide` is a NUL-terminated UTF-16 path that outlives the call.
+    unsafe { SHAddToRecentDocs(SHARD_PATHW.0 as u32, Some(wide.as_ptr().cast())) };
+}
+
+#[cfg(target_os = "windows")]
+fn clear_os_recent(_app: &AppHandle) {
+    // SHAddToRecentDocs(NULL) would wipe the user's recent items for every
+    // application, so Windows keeps its history.
+}
+
+#[cfg(target_os = "linux")]
+fn note_os_recent(app: &AppHandle, path: PathBuf) {
+    let _ = app.run_on_main_thread(move || {
+        use gtk::prelude::RecentManagerExt;
+
+        let manager = gtk::RecentManager::default();
+        if let (Some(manager), Ok(uri)) = (manager, url::Url::from_file_path(&path)) {
+            manager.add_item(uri.as_str());
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn clear_os_recent(app: &AppHandle) {
+    // Only remove our own entries; the desktop-wide list belongs to the user.
+    let profile = app.state::<ProfileState>().current();
+    let paths: Vec<PathBuf> = load(&profile).into_iter().map(|e| e.path).collect();
+    let _ = app.run_on_main_thread(move || {
+        use gtk::prelude::RecentManagerExt;
+
+        let Some(manager) = gtk::RecentManager::default() else {
+            return;
+        };
+        for path in paths {
+            if let Ok(uri) = url::Url::from_file_path(&path) {
+                let _ = manager.remove_item(uri.as_str());
+            }
+        }
+    });
+}
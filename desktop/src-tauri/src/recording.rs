@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{InvokeBody, Request};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::import::{self, ImportResult};
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::virtual_source::{self, SourceKind};
+use crate::watchdog::Call;
+
+/// Recording sessions live here in a project until they are stitched into
+/// an import.
+const RECORDINGS: &str = "recordings";
+
+const INDEX: &str = "index.json";
+
+/// A segment is closed, synced and added to the index after this long.
+const SEGMENT: Duration = Duration::from_secs(10);
+
+/// Data written since the last sync is what a crash can lose.
+const SYNC_EVERY: Duration = Duration::from_secs(1);
+
+/// Header `recording_write` takes the session id from.
+const ID_HEADER: &str = "recording-id";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SegmentInfo {
+    file: String,
+    frames: u64,
+}
+
+/// The recovery index, rewritten each time a segment is closed. The
+/// segment being written isn't listed; recovery finds it by name.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Index {
+    id: String,
+    project: String,
+    sample_rate: u32,
+    channels: u16,
+    /// Unix ms.
+    started_at: i64,
+    segments: Vec<SegmentInfo>,
+}
+
+/// A session left on disk by a crash or power loss.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Interrupted {
+    id: String,
+    project: String,
+    started_at: i64,
+    /// What recovery would keep.
+    frames: u64,
+    sample_rate: u32,
+}
+
+/// One segment file and the whole frames it holds.
+pub(crate) struct Segment {
+    pub(crate) path: PathBuf,
+    pub(crate) frames: u64,
+}
+
+/// A session's segments as found on disk, in order, for stitching.
+pub(crate) struct Stored {
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    pub(crate) segments: Vec<Segment>,
+    index: Index,
+}
+
+fn segment_name(n: usize) -> String {
+    format!("segment-{n:06}.f32")
+}
+
+impl Stored {
+    /// Read a session directory. A segment cut off mid-frame by a crash
+    /// keeps its whole frames; listed segments are trusted no further than
+    /// the index says.
+    pub(crate) fn read(dir: &Path) -> Result<Self, String> {
+        let index: Index = serde_json::from_slice(
+            &fs::read(dir.join(INDEX)).map_err(|e| format!("{}: {e}", dir.display()))?,
+        )
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+        let frame_bytes = u64::from(index.channels) * 4;
+        if frame_bytes == 0 {
+            return Err("the recording has no channels".into());
+        }
+        let mut segments = Vec::new();
+        for n in 1.. {
+            let path = dir.join(segment_name(n));
+            let Ok(meta) = fs::metadata(&path) else {
+                break;
+            };
+            let mut frames = meta.len() / frame_bytes;
+            if let Some(listed) = index.segments.get(n - 1) {
+                frames = frames.min(listed.frames);
+            }
+            segments.push(Segment { path, frames });
+        }
+        Ok(Self {
+            sample_rate: index.sample_rate,
+            channels: index.channels,
+            segments,
+            index,
+        })
+    }
+
+    pub(crate) fn frames(&self) -> u64 {
+        self.segments.iter().map(|s| s.frames).sum()
+    }
+}
+
+struct Session {
+    /// Profile the session was started in; it is stitched there even if
+    /// another profile is active by then.
+    profile: Profile,
+    dir: PathBuf,
+    index: Index,
+    file: File,
+    frames_in_segment: u64,
+    frames_per_segment: u64,
+    synced: Instant,
+    /// Set to stop the virtual source feeding the session, if any.
+    feed: Option<Arc<AtomicBool>>,
+}
+
+impl Session {
+    fn frame_bytes(&self) -> usize {
+        usize::from(self.index.channels) * 4
+    }
+
+    fn open_segment(dir: &Path, n: usize) -> Result<File, String> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(segment_name(n)))
+            .map_err(|e| e.to_string())
+    }
+
+    fn save_index(&self) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(&self.index).map_err(|e| e.to_string())?;
+        settings::write_atomic(&self.dir.join(INDEX), &json)
+    }
+
+    /// Close the current segment into the index and start the next.
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file.sync_data().map_err(|e| e.to_string())?;
+        let n = self.index.segments.len() + 1;
+        self.index.segments.push(SegmentInfo {
+            file: segment_name(n),
+            frames: self.frames_in_segment,
+        });
+        self.save_index()?;
+        self.file = Self::open_segment(&self.dir, n + 1)?;
+        self.frames_in_segment = 0;
+        self.synced = Instant::now();
+        Ok(())
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) -> Result<(), String> {
+        let frame_bytes = self.frame_bytes();
+        if !bytes.len().is_multiple_of(frame_bytes) {
+            return Err(format!(
+                "{} bytes isn't a whole number of {}-channel frames",
+                bytes.len(),
+                self.index.channels
+            ));
+        }
+        while !bytes.is_empty() {
+            let room = (self.frames_per_segment - self.frames_in_segment) as usize;
+            let take = bytes.len().min(room * frame_bytes);
+            self.file
+                .write_all(&bytes[..take])
+                .map_err(|e| e.to_string())?;
+            self.frames_in_segment += (take / frame_bytes) as u64;
+            bytes = &bytes[take..];
+            if self.frames_in_segment == self.frames_per_segment {
+                self.rotate()?;
+            }
+        }
+        if self.synced.elapsed() >= SYNC_EVERY {
+            self.file.sync_data().map_err(|e| e.to_string())?;
+            self.synced = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// Managed state: sessions being written, keyed by id.
+#[derive(Default)]
+pub struct Recordings(Mutex<HashMap<String, Arc<Mutex<Session>>>>);
+
+impl Recordings {
+    fn get(&self, id: &str) -> Result<Arc<Mutex<Session>>, String> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("no recording with id {id}"))
+    }
+
+    fn active(&self, id: &str) -> bool {
+        self.0.lock().unwrap().contains_key(id)
+    }
+}
+
+/// Stop feeding `session`, flush it and stitch it into an import.
+fn finish(app: &AppHandle, session: Arc<Mutex<Session>>) -> Result<ImportResult, String> {
+    let feed = session.lock().unwrap().feed.clone();
+    if let Some(stop) = feed {
+        stop.store(true, Ordering::SeqCst);
+    }
+    let session = session.lock().unwrap();
+    session.file.sync_data().map_err(|e| e.to_string())?;
+    Ok(stitch(
+        app,
+        &session.profile,
+        &session.dir,
+        &session.index.project,
+    ))
+}
+
+/// End every recording, for a privacy pause (see `privacy`). Each emits
+/// `spectrus://recording-stopped` with its id, then is stitched like one
+/// ended with `recording_stop`.
+pub(crate) fn stop_all(app: &AppHandle) {
+    let sessions: Vec<(String, Arc<Mutex<Session>>)> = app
+        .state::<Recordings>()
+        .0
+        .lock()
+        .unwrap()
+        .drain()
+        .collect();
+    for (id, session) in sessions {
+        let _ = app.emit("spectrus://recording-stopped", &id);
+        let app = app.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = finish(&app, session) {
+                eprintln!("recording {id}: {e}");
+            }
+        });
+    }
+}
+
+fn recordings_dir(profile: &Profile, project: &str) -> Result<PathBuf, String> {
+    Ok(crate::sync::project_dir(profile, project)?.join(RECORDINGS))
+}
+
+/// Stitch a session into an import in its project and, once that worked,
+/// remove the segments.
+fn stitch(app: &AppHandle, profile: &Profile, dir: &Path, project: &str) -> ImportResult {
+    let imports = match import::imports_dir(profile, project) {
+        Ok(imports) => imports,
+        Err(e) => return import::failed(dir, e),
+    };
+    let result = import::import_recording(app, &imports, dir);
+    if result.import_id().is_some() {
+        let _ = fs::remove_dir_all(dir);
+    }
+    result
+}
+
+/// Sessions on disk that aren't being written, in `project` or in every
+/// project of the profile.
+fn leftovers(app: &AppHandle, profile: &Profile, project: Option<&str>) -> Vec<(PathBuf, Stored)> {
+    let projects: Vec<PathBuf> = match project {
+        Some(project) => crate::sync::project_dir(profile, project)
+            .into_iter()
+            .collect(),
+        None => fs::read_dir(profile.dir.join("projects"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path())
+            .collect(),
+    };
+    let recordings = app.state::<Recordings>();
+    projects
+        .iter()
+        .flat_map(|p| {
+            fs::read_dir(p.join(RECORDINGS))
+                .into_iter()
+                .flatten()
+                .flatten()
+        })
+        .filter(|e| !recordings.active(&e.file_name().to_string_lossy()))
+        .filter_map(|e| Some((e.path(), Stored::read(&e.path()).ok()?)))
+        .collect()
+}
+
+/// Start recording into `project`. Samples are then sent with
+/// `recording_write` and the session ends with `recording_stop`, which
+/// turns it into an import. Until then it is kept as ten-second segments,
+/// synced every second, so a crash loses about a second.
+///
+/// With `source`, a virtual audio source feeds the session in real time
+/// instead, at its own sample rate and channels. Refused while capture is
+/// paused for privacy, which also ends running sessions.
+#[tauri::command]
+pub fn recording_start(
+    app: AppHandle,
+    recordings: State<'_, Recordings>,
+    project: String,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+    source: Option<String>,
+) -> Result<String, String> {
+    crate::privacy::check_capture(&app)?;
+    let stream = source
+        .map(|id| virtual_source::stream(&app, &id, SourceKind::Audio))
+        .transpose()?;
+    let (sample_rate, channels) = match &stream {
+        Some(stream) => (stream.sample_rate, stream.channels),
+        None => (sample_rate.unwrap_or(0), channels.unwrap_or(0)),
+    };
+    if sample_rate == 0 || channels == 0 {
+        return Err("a recording needs a sample rate and at least one channel".into());
+    }
+    let profile = app.state::<ProfileState>().current();
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    let id: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    let dir = recordings_dir(&profile, &project)?.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let session = Session {
+        file: Session::open_segment(&dir, 1)?,
+        index: Index {
+            id: id.clone(),
+            project,
+            sample_rate,
+            channels,
+            started_at: crate::time::now_ms(&app),
+            segments: Vec::new(),
+        },
+        dir,
+        profile,
+        frames_in_segment: 0,
+        frames_per_segment: u64::from(sample_rate) * SEGMENT.as_secs(),
+        synced: Instant::now(),
+        feed: stream.as_ref().map(|_| Arc::default()),
+    };
+    session.save_index()?;
+    let feed = session.feed.clone();
+    let session = Arc::new(Mutex::new(session));
+    recordings
+        .0
+        .lock()
+        .unwrap()
+        .insert(id.clone(), session.clone());
+    if let (Some(stream), Some(stop)) = (stream, feed) {
+        std::thread::spawn(move || {
+            let result = stream.pump(&stop, |frames| {
+                let mut session = session.lock().unwrap();
+                // Stopped while waiting for the lock: the session is being
+                // stitched.
+                if stop.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                let bytes: Vec<u8> = frames.iter().flat_map(|v| v.to_le_bytes()).collect();
+                session.write(&bytes)
+            });
+            if let Err(e) = result {
+                eprintln!("recording: virtual source stopped: {e}");
+            }
+        });
+    }
+    Ok(id)
+}
+
+/// Append samples: the raw request body, interleaved little-endian `f32`
+/// frames, with the session id in the `Recording-Id` header.
+#[tauri::command]
+pub async fn recording_write(
+    app: AppHandle,
+    recordings: State<'_, Recordings>,
+    request: Request<'_>,
+) -> Result<(), String> {
+    crate::privacy::check_capture(&app)?;
+    let id = request
+        .headers()
+        .get(ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or("missing Recording-Id header")?;
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err("send the samples as raw bytes".into());
+    };
+    let session = recordings.get(id)?;
+    let bytes = bytes.clone();
+    tauri::async_runtime::spawn_blocking(move || session.lock().unwrap().write(&bytes))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// End a session and stitch it into an import, reported like any other
+/// (`spectrus://import-finished`). If stitching fails the segments stay for
+/// `recording_recover`.
+#[tauri::command]
+pub async fn recording_stop(app: AppHandle, id: String) -> Result<ImportResult, String> {
+    let session = app
+        .state::<Recordings>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("no recording with id {id}"))?;
+    tauri::async_runtime::spawn_blocking(move || finish(&app, session))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Sessions a crash or power loss left behind, in `project` or in all of
+/// the profile's projects.
+#[tauri::command]
+pub async fn recording_interrupted(
+    app: AppHandle,
+    project: Option<String>,
+) -> Result<Vec<Interrupted>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let profile = app.state::<ProfileState>().current();
+        leftovers(&app, &profile, project.as_deref())
+            .into_iter()
+            .map(|(_, stored)| Interrupted {
+                frames: stored.frames(),
+                sample_rate: stored.sample_rate,
+                id: stored.index.id,
+                project: stored.index.project,
+                started_at: stored.index.started_at,
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Stitch interrupted sessions into imports, keeping every whole frame that
+/// reached the disk. `id` picks one session; otherwise all of them in
+/// `project` (or every project) are recovered. Meant for the next launch
+/// after a crash. Cancelling the call stops before the next session.
+#[tauri::command]
+pub async fn recording_recover(
+    app: AppHandle,
+    call: Call,
+    project: Option<String>,
+    id: Option<String>,
+) -> Result<Vec<ImportResult>, String> {
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let profile = app.state::<ProfileState>().current();
+        let mut recovered = Vec::new();
+        for (dir, stored) in leftovers(&app, &profile, project.as_deref())
+            .into_iter()
+            .filter(|(_, stored)| id.as_ref().is_none_or(|id| *id == stored.index.id))
+        {
+            scope.check()?;
+            recovered.push(stitch(&app, &profile, &dir, &stored.index.project));
+        }
+        Ok(recovered)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::focus::Focus;
+use crate::profile::{Profile, ProfileState};
+
+/// Longest the scheduler sleeps without re-reading the list, so a profile
+/// switch or a clock change is picked up without an explicit wake-up.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    id: String,
+    /// Due time in milliseconds since the Unix epoch.
+    at: u64,
+    title: String,
+    body: String,
+    /// spectrus:// link for the frontend to follow when the reminder fires.
+    url: Option<String>,
+    /// Shown even while OS focus mode is on; others wait for it to end.
+    #[serde(default)]
+    critical: bool,
+}
+
+/// Payload of `spectrus://reminder`.
+#[derive(Clone, Serialize)]
+struct Fired {
+    reminder: Reminder,
+    /// The app wasn't running at the due time; this is a catch-up.
+    late: bool,
+}
+
+/// Managed state. The list itself lives in the profile; this only serialises
+/// access to the file and wakes the scheduler when it changes.
+#[derive(Default)]
+pub struct Reminders {
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+fn path(profile: &Profile) -> PathBuf {
+    profile.dir.join("reminders.json")
+}
+
+fn load(app: &AppHandle, profile: &Profile) -> Vec<Reminder> {
+    crate::encryption::read(app, &path(profile))
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, profile: &Profile, reminders: &[Reminder]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(reminders).map_err(|e| e.to_string())?;
+    crate::encryption::write(app, &path(profile), &json)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn new_id() -> Result<String, String> {
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Show a native notification.
+pub(crate) fn notify(app: &AppHandle, title: &str, body: &str) {
+    // On Linux, go through our own D-Bus client so clicking the notification
+    // raises the window (see `linux_dbus`).
+    #[cfg(target_os = "linux")]
+    if let Some(dbus) = app.try_state::<crate::linux_dbus::DbusState>() {
+        if crate::linux_dbus::notify(&dbus, title, body, &[], None).is_ok() {
+            return;
+        }
+    }
+    use tauri_plugin_notification::NotificationExt;
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("reminders: notification failed: {e}");
+    }
+}
+
+/// Fire everything due, persist what's left, and return when the next one is.
+/// Called with `Reminders::lock` held.
+fn fire_due(app: &AppHandle, profile: &Profile, late: bool) -> Option<u64> {
+    let now = now_ms();
+    let (due, pending): (Vec<_>, Vec<_>) =
+        load(app, profile).into_iter().partition(|r| r.at <= now);
+    if !due.is_empty() {
+        if let Err(e) = save(app, profile, &pending) {
+            eprintln!("reminders: could not save: {e}");
+        }
+    }
+    let focus = app.state::<Focus>();
+    for reminder in due {
+        if reminder.critical || !focus.active() {
+            notify(app, &reminder.title, &reminder.body);
+        } else {
+            focus.defer(&reminder.title, &reminder.body);
+        }
+        let _ = app.emit("spectrus://reminder", Fired { reminder, late });
+    }
+    pending.iter().map(|r| r.at).min()
+}
+
+/// Start the scheduler. Reminders that fell due while the app was closed fire
+/// right away with `late: true`. It runs whether or not a window is open.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut late = true;
+        loop {
+            let Some(profiles) = app.try_state::<ProfileState>() else {
+                std::thread::sleep(MAX_WAIT);
+                continue;
+            };
+            let state = app.state::<Reminders>();
+            // Held until the wait starts, so a change can't slip in between.
+            let guard = state.lock.lock().unwrap();
+            let next = fire_due(&app, &profiles.current(), late);
+            late = false;
+            let wait = next
+                .map(|at| Duration::from_millis(at.saturating_sub(now_ms())))
+                .unwrap_or(MAX_WAIT)
+                .min(MAX_WAIT);
+            let _ = state.changed.wait_timeout(guard, wait).unwrap();
+        }
+    });
+}
+
+/// Schedule a notification at `at` (ms since the Unix epoch). Returns the
+/// stored reminder, whose `id` is used to cancel it. Non-`critical` ones that
+/// fall due during OS focus mode are held and delivered as a digest after it.
+/// The `spectrus://reminder` event fires on time either way.
+#[tauri::command]
+pub fn reminders_schedule(
+    app: AppHandle,
+    reminders: State<'_, Reminders>,
+    at: u64,
+    title: String,
+    body: String,
+    url: Option<String>,
+    critical: Option<bool>,
+) -> Result<Reminder, String> {
+    if url.as_ref().is_some_and(|u| !u.starts_with("spectrus://")) {
+        return Err("reminder links must use spectrus://".into());
+    }
+    let profile = app.state::<ProfileState>().current();
+    let reminder = Reminder {
+        id: new_id()?,
+        at,
+        title,
+        body,
+        url,
+        critical: critical.unwrap_or(false),
+    };
+    {
+        let _guard = reminders.lock.lock().unwrap();
+        let mut list = load(&app, &profile);
+        list.push(reminder.clone());
+        save(&app, &profile, &list)?;
+    }
+    reminders.changed.notify_all();
+    Ok(reminder)
+}
+
+/// Pending reminders, soonest first.
+#[tauri::command]
+pub fn reminders_list(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    reminders: State<'_, Reminders>,
+) -> Vec<Reminder> {
+    let _guard = reminders.lock.lock().unwrap();
+    let mut list = load(&app, &profiles.current());
+    list.sort_by_key(|r| r.at);
+    list
+}
+
+/// Cancel a pending reminder. Cancelling one that already fired is a no-op.
+#[tauri::command]
+pub fn reminders_cancel(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    reminders: State<'_, Reminders>,
+    id: String,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    {
+        let _guard = reminders.lock.lock().unwrap();
+        let mut list = load(&app, &profile);
+        list.retain(|r| r.id != id);
+        save(&app, &profile, &list)?;
+    }
+    reminders.changed.notify_all();
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, State, UriSchemeContext, UriSchemeResponder, Wry};
+use url::Url;
+
+use crate::bandwidth::Subsystem;
+use crate::keychain;
+
+/// URI scheme served by `handle`.
+pub const SCHEME: &str = "spectrus-remote";
+
+/// API paths the protocol may reach. Everything else is refused, so the
+/// scheme can't be used to make arbitrary authenticated API calls.
+const ASSET_PREFIXES: [&str; 3] = ["/assets/", "/attachments/", "/avatars/"];
+
+/// Largest upstream body relayed in one response. Custom protocols answer
+/// with a complete body, so media should be fetched with Range requests,
+/// which are passed through.
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Request headers forwarded upstream and response headers relayed back.
+const REQUEST_HEADERS: [header::HeaderName; 4] = [
+    header::RANGE,
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    header::ACCEPT,
+];
+const RESPONSE_HEADERS: [header::HeaderName; 7] = [
+    header::CONTENT_TYPE,
+    header::CONTENT_RANGE,
+    header::ACCEPT_RANGES,
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::CACHE_CONTROL,
+    header::CONTENT_DISPOSITION,
+];
+
+/// Same shape the frontend stores under `spectrus:tokens:<serverId>`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredTokens {
+    access_token: String,
+}
+
+/// Managed state: API base URL per server id, and one shared HTTP client.
+pub struct RemoteAssets {
+    servers: Mutex<HashMap<String, Url>>,
+    client: Mutex<reqwest::Client>,
+}
+
+fn new_client() -> reqwest::Client {
+    crate::policy::current()
+        .client_builder()
+        .build()
+        .unwrap_or_default()
+}
+
+/// reqwest is built without a bundled crypto provider; use the same one the
+/// updater installs.
+pub(crate) fn install_crypto_provider() {
+    if rustls::crypto::CryptoProvider::get_default().is_none() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+    }
+}
+
+impl RemoteAssets {
+    pub fn new() -> Self {
+        install_crypto_provider();
+        Self {
+            servers: Mutex::new(HashMap::new()),
+            client: Mutex::new(new_client()),
+        }
+    }
+
+    /// API base URLs of the registered servers.
+    pub(crate) fn servers(&self) -> Vec<Url> {
+        self.servers.lock().unwrap().values().cloned().collect()
+    }
+
+    pub(crate) fn client(&self) -> reqwest::Client {
+        self.client.lock().unwrap().clone()
+    }
+
+    /// Forget the registered servers and the pooled connections, for an
+    /// account switch; windows register the new account's servers again.
+    pub(crate) fn reset(&self) {
+        self.servers.lock().unwrap().clear();
+        *self.client.lock().unwrap() = new_client();
+    }
+}
+
+fn status(code: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.as_bytes().to_vec())
+        .unwrap()
+}
+
+/// Map `spectrus-remote://localhost/<serverId>/<path>?<query>` (on Windows
+/// `http://spectrus-remote.localhost/...`) to the server's API URL.
+fn upstream_url(
+    assets: &RemoteAssets,
+    request: &Request<Vec<u8>>,
+) -> Result<(String, Url), String> {
+    let uri = request.uri();
+    let path = uri.path().trim_start_matches('/');
+    let (server, rest) = path.split_once('/').ok_or("missing server id")?;
+    let rest = format!("/{rest}");
+    // URL parsing treats `%2e` as a dot and `\` as a slash in dot segments,
+    // so `.%2e` and `..\` climb out as well.
+    if rest.split(['/', '\\']).any(|s| {
+        let s = percent_encoding::percent_decode_str(s).decode_utf8_lossy();
+        s == ".." || s == "."
+    }) {
+        return Err("invalid asset path".into());
+    }
+    if !ASSET_PREFIXES.iter().any(|p| rest.starts_with(p)) {
+        return Err(format!("{rest}: not an asset endpoint"));
+    }
+    let base = assets
+        .servers
+        .lock()
+        .unwrap()
+        .get(server)
+        .cloned()
+        .ok_or_else(|| format!("unknown server {server}"))?;
+    let mut url = base
+        .join(rest.trim_start_matches('/'))
+        .map_err(|e| e.to_string())?;
+    url.set_query(uri.query());
+    Ok((server.to_string(), url))
+}
+
+async fn proxy(app: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let assets = app.state::<RemoteAssets>();
+    let (server, url) = match upstream_url(&assets, &request) {
+        Ok(target) => target,
+        Err(e) => return status(StatusCode::BAD_REQUEST, &e),
+    };
+    let key = format!("spectrus:tokens:{server}");
+    keychain::audit_backend(app, crate::audit::Action::KeychainRead, &key);
+    let token = match keychain::read(app, &key) {
+        Ok(Some(raw)) => serde_json::from_str::<StoredTokens>(&raw).ok(),
+        Ok(None) => None,
+        Err(e) => return status(StatusCode::INTERNAL_SERVER_ERROR, &e),
+    };
+
+    let mut upstream = assets.client().get(url);
+    for name in REQUEST_HEADERS {
+        if let Some(value) = request.headers().get(&name) {
+            upstream = upstream.header(name, value);
+        }
+    }
+    if let Some(token) = token {
+        upstream = upstream.bearer_auth(token.access_token);
+    }
+    let mut response = match upstream.send().await {
+        Ok(response) => response,
+        Err(e) => return status(StatusCode::BAD_GATEWAY, &e.to_string()),
+    };
+
+    let mut builder = Response::builder().status(response.status().as_u16());
+    for name in RESPONSE_HEADERS {
+        if let Some(value) = response.headers().get(&name) {
+            builder = builder.header(name, value.as_bytes());
+        }
+    }
+    let mut body = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) if body.len() + chunk.len() <= MAX_BODY => {
+                crate::bandwidth::throttle_async(app, Subsystem::Download, chunk.len() as u64)
+                    .await;
+                body.extend_from_slice(&chunk)
+            }
+            Ok(Some(_)) => {
+                return status(StatusCode::BAD_GATEWAY, "asset too large; request a range")
+            }
+            Ok(None) => break,
+            Err(e) => return status(StatusCode::BAD_GATEWAY, &e.to_string()),
+        }
+    }
+    builder.body(body).unwrap()
+}
+
+/// Protocol handler. The bearer token is added here, so it never shows up in
+/// a URL the renderer can see or log. A 401 is passed through; the frontend
+/// refreshes its tokens and retries as it does for API calls. Fetches wait
+/// out a metered connection like other downloads.
+pub fn handle(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    // Fetches die with the page that asked for them, and time out like
+    // commands do (as `remote_assets`).
+    let call = crate::watchdog::track(&app, ctx.webview_label(), "remote_assets");
+    tauri::async_runtime::spawn(async move {
+        let fetched = call.run(async {
+            crate::bandwidth::defer_while_metered(&app, Subsystem::Download, || Ok(())).await?;
+            Ok(proxy(&app, request).await)
+        });
+        let response = match fetched.await {
+            Ok(response) => response,
+            Err(e) => status(StatusCode::GATEWAY_TIMEOUT, &e),
+        };
+        responder.respond(response);
+    });
+}
+
+/// Tell the protocol where `server_id`'s API lives. Only HTTPS is accepted,
+/// plus plain HTTP to loopback for local development.
+#[tauri::command]
+pub fn remote_assets_register(
+    assets: State<'_, RemoteAssets>,
+    server_id: String,
+    base_url: String,
+) -> Result<(), String> {
+    let mut url = Url::parse(&base_url).map_err(|e| e.to_string())?;
+    let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    if url.scheme() != "https" && !(url.scheme() == "http" && loopback) {
+        return Err(format!("{base_url}: assets are only fetched over https"));
+    }
+    if server_id.is_empty() || server_id.contains('/') {
+        return Err(format!("invalid server id {server_id:?}"));
+    }
+    // `join` treats a base without a trailing slash as a file name.
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    assets.servers.lock().unwrap().insert(server_id, url);
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use crate::fs_scope::FsScope;
+use crate::jobs::Job;
+use crate::pdf::{self, PdfOptions};
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::Call;
+
+/// Entry point inside a template version directory. Other `*.hbs` files next
+/// to it are registered as partials under their file stem.
+const MAIN: &str = "template.hbs";
+
+/// Optional metadata next to `template.hbs`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Meta {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TemplateInfo {
+    id: String,
+    version: String,
+    name: String,
+    description: Option<String>,
+}
+
+/// Which template to use; the newest version when `version` is absent.
+#[derive(Deserialize)]
+pub struct TemplateRef {
+    id: String,
+    version: Option<String>,
+}
+
+/// Templates live at `<profile>/report-templates/<id>/<version>/`.
+fn templates_dir(profile: &Profile) -> PathBuf {
+    profile.dir.join("report-templates")
+}
+
+/// Order versions like "1.2" < "1.10", falling back to text comparison for
+/// non-numeric parts.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| -> Vec<String> { v.split('.').map(str::to_string).collect() };
+    for (x, y) in parts(a).iter().zip(parts(b).iter()) {
+        let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.split('.').count().cmp(&b.split('.').count())
+}
+
+fn subdirs(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect()
+}
+
+fn list(profile: &Profile) -> Vec<TemplateInfo> {
+    let root = templates_dir(profile);
+    let mut ids = subdirs(&root);
+    ids.sort();
+    let mut templates = Vec::new();
+    for id in ids {
+        let mut versions = subdirs(&root.join(&id));
+        versions.sort_by(|a, b| compare_versions(b, a));
+        for version in versions {
+            let dir = root.join(&id).join(&version);
+            if !dir.join(MAIN).is_file() {
+                continue;
+            }
+            let meta: Meta = fs::read(dir.join("template.json"))
+                .ok()
+                .and_then(|b| serde_json::from_slice(&b).ok())
+                .unwrap_or_default();
+            templates.push(TemplateInfo {
+                name: meta.name.unwrap_or_else(|| id.clone()),
+                description: meta.description,
+                id: id.clone(),
+                version,
+            });
+        }
+    }
+    templates
+}
+
+/// Resolve `template` to its directory and concrete version.
+fn locate(profile: &Profile, template: &TemplateRef) -> Result<(PathBuf, String), String> {
+    let known = list(profile);
+    let found = known
+        .iter()
+        .find(|t| t.id == template.id && template.version.as_ref().is_none_or(|v| &t.version == v));
+    let Some(found) = found else {
+        return Err(match &template.version {
+            Some(v) => format!("no report template {}@{v}", template.id),
+            None => format!("no report template {}", template.id),
+        });
+    };
+    let dir = templates_dir(profile).join(&found.id).join(&found.version);
+    Ok((dir, found.version.clone()))
+}
+
+/// Merge `data` into the template. The output names the template version it
+/// came from so generated reports stay traceable.
+fn render(profile: &Profile, template: &TemplateRef, data: &Value) -> Result<String, String> {
+    let (dir, version) = locate(profile, template)?;
+    let mut registry = Handlebars::new();
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "hbs") && path.file_name() != Some(MAIN.as_ref()) {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            registry
+                .register_template_file(&name, &path)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    registry
+        .register_template_file("main", dir.join(MAIN))
+        .map_err(|e| e.to_string())?;
+    let html = registry.render("main", data).map_err(|e| e.to_string())?;
+    let marker = format!(
+        "<meta name=\"spectrus-template\" content=\"{}@{version}\">",
+        template.id
+    );
+    Ok(match html.find("<head>") {
+        Some(at) => format!("{}{marker}{}", &html[..at + 6], &html[at + 6..]),
+        None => format!("{marker}\n{html}"),
+    })
+}
+
+/// Installed report templates, every version, newest first within an id.
+#[tauri::command]
+pub fn report_templates_list(profiles: State<'_, ProfileState>) -> Vec<TemplateInfo> {
+    list(&profiles.current())
+}
+
+/// Render a report to HTML without writing anything, for an in-app preview.
+#[tauri::command]
+pub async fn report_preview(
+    profiles: State<'_, ProfileState>,
+    template: TemplateRef,
+    data: Value,
+) -> Result<String, String> {
+    let profile = profiles.current();
+    tauri::async_runtime::spawn_blocking(move || render(&profile, &template, &data))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Generate a report into `path` as a background job ("report-generate").
+/// The output format follows the extension: `.html` or `.pdf`.
+#[tauri::command]
+pub async fn report_generate(
+    app: AppHandle,
+    call: Call,
+    scope: State<'_, FsScope>,
+    profiles: State<'_, ProfileState>,
+    template: TemplateRef,
+    data: Value,
+    path: String,
+) -> Result<PathBuf, String> {
+    let out = scope.check_new(call.window(), &path)?;
+    let pdf_output = match out.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("pdf") => true,
+        Some(e) if e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm") => false,
+        _ => return Err(format!("{}: generate a .html or .pdf file", out.display())),
+    };
+    let profile = profiles.current();
+    let mut job = Job::start(&app, "report-generate").within(call.scope());
+    let title = data
+        .get("title")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let rendering = async {
+        tauri::async_runtime::spawn_blocking(move || render(&profile, &template, &data))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    let rendered = call.run(rendering).await?;
+    job.progress(1, 2);
+    job.check()?;
+
+    if pdf_output {
+        pdf::render_to_file(
+            &app,
+            None,
+            PdfOptions::for_html(rendered, title),
+            Vec::new(),
+            out.clone(),
+            &call.scope(),
+        )
+        .await?;
+    } else {
+        settings::write_atomic(&out, rendered.as_bytes())?;
+    }
+    job.progress(2, 2);
+    Ok(out)
+}
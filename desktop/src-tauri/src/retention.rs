@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use walkdir::WalkDir;
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Policies by category, as an object of `Policy`.
+const POLICIES_SETTING: &str = "retention.policies";
+
+const DAY: u64 = 24 * 60 * 60;
+const MB: u64 = 1024 * 1024;
+
+/// Kinds of data a policy can apply to. The rest of what accumulates is
+/// bounded where it is written, so no policy covers it:
+///
+/// - logs: the backend keeps no log files; diagnostics go to stderr
+/// - the audit log: shared by every profile and hash-chained, so one
+///   profile's policy can't cut it; it rotates past 16 MB and keeps four
+///   old logs (see `audit`)
+/// - webhook deliveries: `webhooks-log.json` keeps the latest 500
+/// - analytics queue: the backend queues no analytics; a frontend that
+///   does is turned off by the `analytics.enabled` setting
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// Imported recordings and logs in `projects/*/imports/`, one per import.
+    Recordings,
+    /// Files received over the LAN, in `inbox/`.
+    Inbox,
+    /// The profile cache: link previews, computed artifacts and the like.
+    Cache,
+}
+
+const CATEGORIES: [Category; 3] = [Category::Recordings, Category::Inbox, Category::Cache];
+
+/// Limits for one category; either may be left out. Past `max_age_days`
+/// items are removed, then the oldest go until the rest fit `max_size_mb`.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Policy {
+    max_age_days: Option<u64>,
+    max_size_mb: Option<u64>,
+}
+
+impl Category {
+    /// Recordings and the inbox are kept until a policy says otherwise; the
+    /// cache is trimmed to a month and 512 MB.
+    fn default_policy(self) -> Policy {
+        match self {
+            Category::Recordings | Category::Inbox => Policy::default(),
+            Category::Cache => Policy {
+                max_age_days: Some(30),
+                max_size_mb: Some(512),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reason {
+    Age,
+    Size,
+}
+
+/// Something a policy would delete.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Purge {
+    category: Category,
+    path: PathBuf,
+    pub(crate) size: u64,
+    /// Unix ms.
+    modified_at: u64,
+    reason: Reason,
+}
+
+struct Item {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Only the policies the user set.
+fn stored(profile: &Profile) -> BTreeMap<Category, Policy> {
+    settings::get(profile, POLICIES_SETTING)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+fn policies(profile: &Profile) -> BTreeMap<Category, Policy> {
+    let stored = stored(profile);
+    CATEGORIES
+        .iter()
+        .map(|&c| (c, stored.get(&c).copied().unwrap_or(c.default_policy())))
+        .collect()
+}
+
+fn files(dir: &Path) -> Vec<Item> {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some(Item {
+                size: meta.len(),
+                modified: meta.modified().unwrap_or(UNIX_EPOCH),
+                path: e.into_path(),
+            })
+        })
+        .collect()
+}
+
+/// Each finished import as a unit, dated by its `meta.json`. Imports still
+/// being written (hidden `.partial` directories) are left alone.
+fn imports(profile: &Profile) -> Vec<Item> {
+    let projects = fs::read_dir(profile.dir.join("projects"))
+        .into_iter()
+        .flatten()
+        .flatten();
+    projects
+        .flat_map(|project| {
+            fs::read_dir(project.path().join("imports"))
+                .into_iter()
+                .flatten()
+                .flatten()
+        })
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| {
+            let path = e.path();
+            let modified = fs::metadata(path.join("meta.json"))
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH);
+            Item {
+                size: files(&path).iter().map(|f| f.size).sum(),
+                modified,
+                path,
+            }
+        })
+        .collect()
+}
+
+fn items(profile: &Profile, category: Category) -> Vec<Item> {
+    match category {
+        Category::Recordings => imports(profile),
+        Category::Inbox => files(&profile.dir.join("inbox")),
+        Category::Cache => files(&profile.cache_dir()),
+    }
+}
+
+fn select(category: Category, policy: Policy, mut items: Vec<Item>, now: SystemTime) -> Vec<Purge> {
+    let purge = |item: &Item, reason| Purge {
+        category,
+        path: item.path.clone(),
+        size: item.size,
+        modified_at: item
+            .modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        reason,
+    };
+    let mut out = Vec::new();
+    if let Some(days) = policy.max_age_days {
+        let max_age = Duration::from_secs(days * DAY);
+        items.retain(|item| {
+            let old = now.duration_since(item.modified).unwrap_or_default() > max_age;
+            if old {
+                out.push(purge(item, Reason::Age));
+            }
+            !old
+        });
+    }
+    if let Some(mb) = policy.max_size_mb {
+        let mut total: u64 = items.iter().map(|i| i.size).sum();
+        items.sort_by_key(|i| i.modified);
+        for item in &items {
+            if total <= mb * MB {
+                break;
+            }
+            out.push(purge(item, Reason::Size));
+            total -= item.size;
+        }
+    }
+    out
+}
+
+/// Everything the active policies would delete right now, oldest first
+/// within each category.
+pub(crate) fn plan(profile: &Profile) -> Vec<Purge> {
+    let now = SystemTime::now();
+    policies(profile)
+        .into_iter()
+        .flat_map(|(category, policy)| select(category, policy, items(profile, category), now))
+        .collect()
+}
+
+/// Delete what `purge` names. `false` if it was already gone or couldn't
+/// be removed.
+pub(crate) fn apply(purge: &Purge) -> bool {
+    let removed = if purge.path.is_dir() {
+        fs::remove_dir_all(&purge.path)
+    } else {
+        fs::remove_file(&purge.path)
+    };
+    removed.is_ok()
+}
+
+/// The policy in force for each category, defaults included.
+#[tauri::command]
+pub fn retention_policies(profiles: State<'_, ProfileState>) -> BTreeMap<Category, Policy> {
+    policies(&profiles.current())
+}
+
+/// Set one category's policy; `null` restores its default. Applied at the
+/// next maintenance run.
+#[tauri::command]
+pub fn retention_policy_set(
+    profiles: State<'_, ProfileState>,
+    category: Category,
+    policy: Option<Policy>,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    let mut stored = stored(&profile);
+    match policy {
+        Some(policy) => stored.insert(category, policy),
+        None => stored.remove(&category),
+    };
+    let value = serde_json::to_value(&stored).map_err(|e| e.to_string())?;
+    settings::set(&profile, POLICIES_SETTING, value)
+}
+
+/// What the next maintenance run would delete under the current policies,
+/// without deleting anything.
+#[tauri::command]
+pub async fn retention_preview(profiles: State<'_, ProfileState>) -> Result<Vec<Purge>, String> {
+    let profile = profiles.current();
+    tauri::async_runtime::spawn_blocking(move || plan(&profile))
+        .await
+        .map_err(|e| e.to_string())
+}
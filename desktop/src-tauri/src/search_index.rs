@@ -0,0 +1,389 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::portable::Portable;
+use crate::profile::ProfileState;
+
+/// Extension of the stubs, associated with the app in `tauri.conf.json` so
+/// opening a search result launches Spectrus with the stub.
+pub const EXTENSION: &str = "spectrus-item";
+
+/// Largest title kept in a stub's file name, which is what search results
+/// show.
+const MAX_TITLE: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemKind {
+    Project,
+    /// An import in a project, `id` naming it.
+    Recording,
+}
+
+/// What the frontend knows about an item, written into its stub.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    kind: ItemKind,
+    project: String,
+    #[serde(default)]
+    id: Option<String>,
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Unix ms.
+    created_at: Option<i64>,
+    modified_at: Option<i64>,
+}
+
+/// Contents of a stub: the item and the profile it belongs to. JSON, so the
+/// system indexers' plain-text filters pick up the title and tags.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stub {
+    profile: String,
+    #[serde(flatten)]
+    item: Item,
+}
+
+/// Payload of `spectrus://open-item`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenItem<'a> {
+    kind: ItemKind,
+    project: &'a str,
+    id: Option<&'a str>,
+}
+
+/// Where stubs go, one folder per profile:
+///
+/// - macOS: `~/Library/Caches/Metadata/<identifier>`, where Spotlight looks
+///   for apps' metadata stubs
+/// - elsewhere: `Documents/Spectrus Search`, in Windows Search's (and
+///   desktop indexers') default scope
+fn root(app: &AppHandle) -> Result<PathBuf, String> {
+    #[cfg(target_os = "macos")]
+    let root = app
+        .path()
+        .home_dir()
+        .map(|home| {
+            home.join("Library/Caches/Metadata")
+                .join(&app.config().identifier)
+        })
+        .map_err(|e| e.to_string())?;
+    #[cfg(not(target_os = "macos"))]
+    let root = app
+        .path()
+        .document_dir()
+        .map(|docs| docs.join("Spectrus Search"))
+        .map_err(|e| e.to_string())?;
+    Ok(root)
+}
+
+/// Nothing reaches system search from incognito or portable sessions.
+fn allowed(app: &AppHandle) -> Result<(), String> {
+    if app.state::<crate::cli::Startup>().0.incognito || app.state::<Portable>().0.is_some() {
+        return Err("search indexing is off in incognito and portable mode".into());
+    }
+    Ok(())
+}
+
+/// The folder holding `item`'s stub, named so it stays put when the title
+/// changes.
+fn item_dir(profile_root: &Path, kind: ItemKind, project: &str, id: Option<&str>) -> PathBuf {
+    match (kind, id) {
+        (ItemKind::Recording, Some(id)) => profile_root.join(format!("recording-{project}-{id}")),
+        _ => profile_root.join(format!("project-{project}")),
+    }
+}
+
+/// A title usable as a file name on every platform.
+fn file_name(title: &str) -> String {
+    let clean: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(MAX_TITLE)
+        .collect();
+    let clean = clean.trim().trim_end_matches('.');
+    let title = if clean.is_empty() { "Untitled" } else { clean };
+    format!("{title}.{EXTENSION}")
+}
+
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("invalid import id {id:?}"));
+    }
+    Ok(())
+}
+
+fn write(app: &AppHandle, profile_root: &Path, profile: &str, item: Item) -> Result<(), String> {
+    let profiles = app.state::<ProfileState>();
+    crate::sync::project_dir(&profiles.current(), &item.project)?;
+    if item.kind == ItemKind::Recording {
+        check_id(
+            item.id
+                .as_deref()
+                .ok_or("a recording needs its import id")?,
+        )?;
+    }
+    let dir = item_dir(profile_root, item.kind, &item.project, item.id.as_deref());
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(file_name(&item.title));
+    let tags = item.tags.clone();
+    let stub = Stub {
+        profile: profile.to_string(),
+        item,
+    };
+    let json = serde_json::to_vec_pretty(&stub).map_err(|e| e.to_string())?;
+    crate::settings::write_atomic(&path, &json)?;
+    platform::annotate(&path, &tags);
+    Ok(())
+}
+
+/// Ask `window` to show `item` with `spectrus://open-item`.
+fn show(app: &AppHandle, window: &str, item: &Item) {
+    let event = OpenItem {
+        kind: item.kind,
+        project: &item.project,
+        id: item.id.as_deref(),
+    };
+    if let Err(e) = app.emit_to(window, "spectrus://open-item", event) {
+        eprintln!("search index: open emit error: {e}");
+    }
+}
+
+/// If `path` is a stub, ask `window` to show its item. Returns whether it
+/// was one; called on the file-association path before anything else
+/// handles the file.
+///
+/// A stub is a plain file anyone can write, so its profile is only switched
+/// to if it already exists, and only once the user agrees.
+pub(crate) fn open(app: &AppHandle, window: &str, path: &Path) -> bool {
+    if path.extension().is_none_or(|x| x != EXTENSION) {
+        return false;
+    }
+    let stub: Stub = match fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|b| serde_json::from_slice(&b).map_err(|e| e.to_string()))
+    {
+        Ok(stub) => stub,
+        Err(e) => {
+            eprintln!("search index: {}: {e}", path.display());
+            return true;
+        }
+    };
+    let profiles = app.state::<ProfileState>();
+    if profiles.current().name == stub.profile {
+        show(app, window, &stub.item);
+        return true;
+    }
+    if !profiles.names().contains(&stub.profile) {
+        eprintln!("search index: {}: no such profile", stub.profile);
+        return true;
+    }
+    // Called on the main thread, so the answer comes back in a callback.
+    let handle = app.clone();
+    let window = window.to_string();
+    app.dialog()
+        .message(format!(
+            "“{}” is in the profile “{}”. Switch to that profile to open it?",
+            stub.item.title, stub.profile
+        ))
+        .title("Switch profile?")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Switch".into(),
+            "Cancel".into(),
+        ))
+        .show(move |switch| {
+            if !switch {
+                return;
+            }
+            let profiles = handle.state::<ProfileState>();
+            match crate::profile::switch_to(&handle, &profiles, &stub.profile) {
+                Ok(_) => show(&handle, &window, &stub.item),
+                Err(e) => eprintln!("search index: {}: {e}", stub.profile),
+            }
+        });
+    true
+}
+
+/// Write or refresh the search stubs for `items` in the active profile, so
+/// they turn up in Spotlight or Windows Search by title and tags. Opening
+/// one brings Spectrus forward with `spectrus://open-item`. Items that fail
+/// don't stop the rest; the first error is returned.
+#[tauri::command]
+pub async fn search_index_update(app: AppHandle, items: Vec<Item>) -> Result<(), String> {
+    allowed(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let profile = app.state::<ProfileState>().current().name;
+        let profile_root = root(&app)?.join(&profile);
+        platform::register();
+        let mut first = None;
+        for item in items {
+            if let Err(e) = write(&app, &profile_root, &profile, item) {
+                first.get_or_insert(e);
+            }
+        }
+        first.map_or(Ok(()), Err)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Take an item out of system search, e.g. once it has been deleted. A
+/// project's recordings go with it.
+#[tauri::command]
+pub fn search_index_remove(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    kind: ItemKind,
+    project: String,
+    id: Option<String>,
+) -> Result<(), String> {
+    crate::sync::project_dir(&profiles.current(), &project)?;
+    if let Some(id) = &id {
+        check_id(id)?;
+    }
+    let profile_root = root(&app)?.join(profiles.current().name);
+    let _ = fs::remove_dir_all(item_dir(&profile_root, kind, &project, id.as_deref()));
+    if kind == ItemKind::Project {
+        let prefix = format!("recording-{project}-");
+        for entry in fs::read_dir(&profile_root).into_iter().flatten().flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove every stub of profile `name`.
+pub(crate) fn remove_profile(app: &AppHandle, name: &str) -> Result<(), String> {
+    let dir = root(app)?.join(name);
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Remove every stub of the active profile.
+#[tauri::command]
+pub fn search_index_clear(app: AppHandle, profiles: State<'_, ProfileState>) -> Result<(), String> {
+    remove_profile(&app, &profiles.current().name)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    extern "C" {
+        fn setxattr(
+            path: *const std::ffi::c_char,
+            name: *const std::ffi::c_char,
+            value: *const std::ffi::c_void,
+            size: usize,
+            position: u32,
+            options: i32,
+        ) -> i32;
+    }
+
+    /// The stub's type is exported as conforming to `public.json`, so
+    /// Spotlight indexes it without an importer of our own.
+    pub fn register() {}
+
+    /// Give the stub the item's tags as Finder tags, which Spotlight
+    /// searches with `tag:`.
+    pub fn annotate(path: &Path, tags: &[String]) {
+        if tags.is_empty() {
+            return;
+        }
+        let mut value = Vec::new();
+        if plist::to_writer_binary(&mut value, &tags).is_err() {
+            return;
+        }
+        let (Ok(path), Ok(name)) = (
+            CString::new(path.as_os_str().as_bytes()),
+            CString::new("com.apple.metadata:_kMDItemUserTags"),
+        ) else {
+            return;
+        };
+        // SAFETY: both strings are NUL-terminated and `value` outlives the call.
+        let status = unsafe {
+            setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+                0,
+            )
+        };
+        if status != 0 {
+            eprintln!("search index: could not tag {}", path.to_string_lossy());
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::path::Path;
+    use std::sync::Once;
+
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::System::Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+
+    /// Windows Search's plain-text filter.
+    const PLAIN_TEXT_HANDLER: &str = "{5e941d80-bf96-11cd-b579-08002b30bfeb}";
+
+    /// Have Windows Search read the stubs' contents with its plain-text
+    /// filter, for the current user.
+    pub fn register() {
+        static REGISTERED: Once = Once::new();
+        REGISTERED.call_once(|| {
+            let key = HSTRING::from(format!(
+                r"Software\Classes\.{}\PersistentHandler",
+                super::EXTENSION
+            ));
+            let value: Vec<u16> = PLAIN_TEXT_HANDLER.encode_utf16().chain([0]).collect();
+            // SAFETY: `value` is a NUL-terminated UTF-16 string of the given size.
+            let status = unsafe {
+                RegSetKeyValueW(
+                    HKEY_CURRENT_USER,
+                    &key,
+                    PCWSTR::null(),
+                    REG_SZ.0,
+                    Some(value.as_ptr().cast()),
+                    (value.len() * 2) as u32,
+                )
+            };
+            if status.is_err() {
+                eprintln!("search index: could not register the search filter: {status:?}");
+            }
+        });
+    }
+
+    /// Tags are in the stub's text, which is what gets indexed.
+    pub fn annotate(_path: &Path, _tags: &[String]) {}
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+
+    /// Desktop indexers read JSON as text without being told.
+    pub fn register() {}
+
+    pub fn annotate(_path: &Path, _tags: &[String]) {}
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serialport::{DataBits, FlowControl, Parity, SerialPort, SerialPortType, StopBits};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::consent::{self, Capability};
+use crate::virtual_source::{self, SourceKind, Stream};
+
+/// Read timeout; also how quickly a close request is noticed.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Pause between attempts to reopen a port that went away.
+const RECONNECT_EVERY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialPortInfo {
+    name: String,
+    /// "usb", "pci", "bluetooth", "virtual" or "unknown".
+    kind: &'static str,
+    vid: Option<u16>,
+    pid: Option<u16>,
+    serial_number: Option<String>,
+    manufacturer: Option<String>,
+    product: Option<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialParity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialFlowControl {
+    None,
+    Software,
+    Hardware,
+}
+
+/// Line settings. Defaults to 9600 8N1 without flow control, which is what
+/// most bench instruments ship with.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SerialConfig {
+    baud_rate: u32,
+    data_bits: u8,
+    parity: SerialParity,
+    stop_bits: u8,
+    flow_control: SerialFlowControl,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: SerialParity::None,
+            stop_bits: 1,
+            flow_control: SerialFlowControl::None,
+        }
+    }
+}
+
+/// Payload of `spectrus://serial-data`.
+#[derive(Clone, Serialize)]
+struct Data {
+    port: String,
+    data: Vec<u8>,
+}
+
+/// Payload of `spectrus://serial-status`.
+#[derive(Clone, Serialize)]
+struct Status {
+    port: String,
+    /// "disconnected" when the device goes away, "connected" once it has
+    /// been reopened.
+    state: &'static str,
+    error: Option<String>,
+}
+
+struct OpenPort {
+    writer: Mutex<Option<Box<dyn SerialPort>>>,
+    closed: AtomicBool,
+}
+
+/// Managed state: open ports by the name they were opened with.
+#[derive(Default)]
+pub struct Serial {
+    ports: Mutex<HashMap<String, Arc<OpenPort>>>,
+}
+
+fn info(port: serialport::SerialPortInfo) -> SerialPortInfo {
+    let mut info = SerialPortInfo {
+        name: port.port_name,
+        kind: "unknown",
+        vid: None,
+        pid: None,
+        serial_number: None,
+        manufacturer: None,
+        product: None,
+    };
+    match port.port_type {
+        SerialPortType::UsbPort(usb) => {
+            info.kind = "usb";
+            info.vid = Some(usb.vid);
+            info.pid = Some(usb.pid);
+            info.serial_number = usb.serial_number;
+            info.manufacturer = usb.manufacturer;
+            info.product = usb.product;
+        }
+        SerialPortType::PciPort => info.kind = "pci",
+        SerialPortType::BluetoothPort => info.kind = "bluetooth",
+        SerialPortType::Unknown => {}
+    }
+    info
+}
+
+fn open(name: &str, config: &SerialConfig) -> Result<Box<dyn SerialPort>, String> {
+    let data_bits = match config.data_bits {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        8 => DataBits::Eight,
+        n => return Err(format!("unsupported data bits {n}")),
+    };
+    let stop_bits = match config.stop_bits {
+        1 => StopBits::One,
+        2 => StopBits::Two,
+        n => return Err(format!("unsupported stop bits {n}")),
+    };
+    let parity = match config.parity {
+        SerialParity::None => Parity::None,
+        SerialParity::Odd => Parity::Odd,
+        SerialParity::Even => Parity::Even,
+    };
+    let flow_control = match config.flow_control {
+        SerialFlowControl::None => FlowControl::None,
+        SerialFlowControl::Software => FlowControl::Software,
+        SerialFlowControl::Hardware => FlowControl::Hardware,
+    };
+    serialport::new(name, config.baud_rate)
+        .data_bits(data_bits)
+        .stop_bits(stop_bits)
+        .parity(parity)
+        .flow_control(flow_control)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|e| format!("{name}: {e}"))
+}
+
+/// Where a USB device with `serial` shows up now. Adapters often come back
+/// under a different name (ttyUSB0 → ttyUSB1, COM3 → COM4) after a replug.
+fn find_by_serial(serial: &str) -> Option<String> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|p| {
+            matches!(&p.port_type,
+                SerialPortType::UsbPort(usb) if usb.serial_number.as_deref() == Some(serial))
+        })
+        .map(|p| p.port_name)
+}
+
+fn usb_serial(name: &str) -> Option<String> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|p| p.port_name == name)
+        .and_then(|p| match p.port_type {
+            SerialPortType::UsbPort(usb) => usb.serial_number,
+            _ => None,
+        })
+}
+
+/// Pump data from `reader` into events until the port is closed. When the
+/// device disappears, keep trying to reopen it with the same settings.
+fn read_loop(
+    app: AppHandle,
+    name: String,
+    config: SerialConfig,
+    port: Arc<OpenPort>,
+    mut reader: Box<dyn SerialPort>,
+) {
+    let usb_serial = usb_serial(&name);
+    let status = |state, error| {
+        let _ = app.emit(
+            "spectrus://serial-status",
+            Status {
+                port: name.clone(),
+                state,
+                error,
+            },
+        );
+    };
+    let mut buf = vec![0u8; 4096];
+    while !port.closed.load(Ordering::Relaxed) {
+        match reader.read(&mut buf) {
+            Ok(0) => {}
+            Ok(n) => {
+                let _ = app.emit(
+                    "spectrus://serial-data",
+                    Data {
+                        port: name.clone(),
+                        data: buf[..n].to_vec(),
+                    },
+                );
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+            Err(e) => {
+                *port.writer.lock().unwrap() = None;
+                status("disconnected", Some(e.to_string()));
+                let reopened = loop {
+                    std::thread::sleep(RECONNECT_EVERY);
+                    if port.closed.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let current = usb_serial
+                        .as_deref()
+                        .and_then(find_by_serial)
+                        .unwrap_or_else(|| name.clone());
+                    if let Ok(reader) = open(&current, &config) {
+                        break reader;
+                    }
+                };
+                *port.writer.lock().unwrap() = reopened.try_clone().ok();
+                reader = reopened;
+                status("connected", None);
+            }
+        }
+    }
+}
+
+/// Send a virtual sensor's frames as CSV lines until the port is closed or
+/// the stream fails, then free the name for the next `serial_open`.
+fn virtual_loop(app: AppHandle, name: String, port: Arc<OpenPort>, stream: Stream) {
+    let channels = usize::from(stream.channels);
+    let result = stream.pump(&port.closed, |frames| {
+        let data: String = frames
+            .chunks_exact(channels)
+            .map(virtual_source::csv_line)
+            .collect();
+        app.emit(
+            "spectrus://serial-data",
+            Data {
+                port: name.clone(),
+                data: data.into_bytes(),
+            },
+        )
+        .map_err(|e| e.to_string())
+    });
+    // `serial_close` has already removed the entry unless the stream failed;
+    // a port opened again under the name since is left alone.
+    {
+        let serial = app.state::<Serial>();
+        let mut ports = serial.ports.lock().unwrap();
+        if ports
+            .get(&name)
+            .is_some_and(|open| Arc::ptr_eq(open, &port))
+        {
+            ports.remove(&name);
+        }
+    }
+    if let Err(e) = result {
+        let _ = app.emit(
+            "spectrus://serial-status",
+            Status {
+                port: name,
+                state: "disconnected",
+                error: Some(e),
+            },
+        );
+    }
+}
+
+/// Serial ports the OS knows about, with USB details where available, then
+/// the virtual sensors.
+#[tauri::command]
+pub fn serial_list(app: AppHandle) -> Result<Vec<SerialPortInfo>, String> {
+    let ports = serialport::available_ports().map_err(|e| e.to_string())?;
+    let mut list: Vec<SerialPortInfo> = ports.into_iter().map(info).collect();
+    list.extend(
+        virtual_source::serial_ports(&app)
+            .into_iter()
+            .map(|(name, product)| SerialPortInfo {
+                name,
+                kind: "virtual",
+                vid: None,
+                pid: None,
+                serial_number: None,
+                manufacturer: Some("Spectrus".into()),
+                product: Some(product),
+            }),
+    );
+    Ok(list)
+}
+
+/// Open `name` and start emitting what it receives as `spectrus://serial-data`.
+/// If the device is unplugged, `spectrus://serial-status` reports it and the
+/// port is reopened automatically when it comes back. The first time a
+/// window opens a given port, the user is asked. Virtual sensors start
+/// sending right away and ignore `config`.
+#[tauri::command]
+pub async fn serial_open(
+    app: AppHandle,
+    window: WebviewWindow,
+    serial: State<'_, Serial>,
+    name: String,
+    config: Option<SerialConfig>,
+) -> Result<(), String> {
+    if let Some(id) = name.strip_prefix(virtual_source::SERIAL_PREFIX) {
+        let stream = virtual_source::stream(&app, id, SourceKind::Sensor)?;
+        let mut ports = serial.ports.lock().unwrap();
+        if ports.contains_key(&name) {
+            return Err(format!("{name} is already open"));
+        }
+        let port = Arc::new(OpenPort {
+            writer: Mutex::new(None),
+            closed: AtomicBool::new(false),
+        });
+        ports.insert(name.clone(), port.clone());
+        std::thread::spawn(move || virtual_loop(app, name, port, stream));
+        return Ok(());
+    }
+    consent::require(&app, &window, Capability::Serial, Some(&name)).await?;
+    let mut ports = serial.ports.lock().unwrap();
+    if ports.contains_key(&name) {
+        return Err(format!("{name} is already open"));
+    }
+    let config = config.unwrap_or_default();
+    let reader = open(&name, &config)?;
+    let writer = reader.try_clone().map_err(|e| e.to_string())?;
+    let port = Arc::new(OpenPort {
+        writer: Mutex::new(Some(writer)),
+        closed: AtomicBool::new(false),
+    });
+    ports.insert(name.clone(), port.clone());
+    std::thread::spawn(move || read_loop(app, name, config, port, reader));
+    Ok(())
+}
+
+/// Write `data` to an open port. Fails while the device is disconnected;
+/// virtual sensors take and drop anything.
+#[tauri::command]
+pub fn serial_write(serial: State<'_, Serial>, name: String, data: Vec<u8>) -> Result<(), String> {
+    let port = serial
+        .ports
+        .lock()
+        .unwrap()
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("{name} is not open"))?;
+    if name.starts_with(virtual_source::SERIAL_PREFIX) {
+        return Ok(());
+    }
+    let mut writer = port.writer.lock().unwrap();
+    let writer = writer
+        .as_mut()
+        .ok_or_else(|| format!("{name} is disconnected"))?;
+    writer.write_all(&data).map_err(|e| e.to_string())?;
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Close a port and stop reconnecting to it.
+#[tauri::command]
+pub fn serial_close(serial: State<'_, Serial>, name: String) {
+    if let Some(port) = serial.ports.lock().unwrap().remove(&name) {
+        port.closed.store(true, Ordering::Relaxed);
+        *port.writer.lock().unwrap() = None;
+    }
+}
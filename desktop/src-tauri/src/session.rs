@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{
+    AppHandle, Listener, Manager, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, Window,
+};
+
+use crate::profile::{Profile, ProfileState};
+
+/// How often the session is written out if anything changed.
+const SNAPSHOT_EVERY: Duration = Duration::from_secs(10);
+
+/// How the previous run ended, as recorded in its last snapshot.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ending {
+    /// Normal quit; nothing is restored.
+    Quit,
+    /// Quit to install an update, which relaunches the app.
+    Update,
+}
+
+/// What a window's frontend last reported through `session_report`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Report {
+    route: Option<String>,
+    /// Scroll positions, zoom and whatever else the view wants back.
+    state: Value,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowSnapshot {
+    label: String,
+    route: Option<String>,
+    state: Value,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    windows: Vec<WindowSnapshot>,
+    /// Unsent drafts by the frontend's own keys.
+    drafts: Map<String, Value>,
+    /// `None` while running, so a snapshot without one means a crash.
+    ended: Option<Ending>,
+}
+
+/// Managed state: what the frontend has reported this run, and the previous
+/// run's session if it should be restored.
+#[derive(Default)]
+pub struct SessionState {
+    reports: Mutex<HashMap<String, Report>>,
+    drafts: Mutex<Drafts>,
+    previous: Mutex<Option<Session>>,
+    restored: AtomicBool,
+    /// Last bytes written, to skip unchanged snapshots.
+    written: Mutex<Vec<u8>>,
+}
+
+/// Drafts and the directory of the profile they were written in, so a
+/// snapshot racing a profile switch can't carry them into the next one.
+#[derive(Default)]
+struct Drafts {
+    dir: PathBuf,
+    items: Map<String, Value>,
+}
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("session.json")
+}
+
+fn load(app: &AppHandle, profile: &Profile) -> Option<Session> {
+    crate::encryption::read(app, &store_path(profile)).and_then(|b| serde_json::from_slice(&b).ok())
+}
+
+fn snapshot_window(window: &WebviewWindow, report: Option<&Report>) -> Option<WindowSnapshot> {
+    // Hidden windows are offscreen helpers (PDF export) or the main window
+    // parked in the tray; neither is part of what the user sees. The
+    // quick-capture popover and clipboard picker are transient.
+    if !window.is_visible().unwrap_or(false)
+        || [crate::capture::LABEL, crate::clipboard::LABEL].contains(&window.label())
+    {
+        return None;
+    }
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    let report = report.cloned().unwrap_or_default();
+    Some(WindowSnapshot {
+        label: window.label().to_string(),
+        route: report.route,
+        state: report.state,
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized().unwrap_or(false),
+    })
+}
+
+/// `None` while the drafts in memory belong to another profile.
+fn current(app: &AppHandle, profile: &Profile, ended: Option<Ending>) -> Option<Session> {
+    let state = app.state::<SessionState>();
+    let drafts = {
+        let drafts = state.drafts.lock().unwrap();
+        if drafts.dir != profile.dir {
+            return None;
+        }
+        drafts.items.clone()
+    };
+    // Window getters run on the main thread, where `session_report` may be
+    // waiting for this lock; don't hold it across them.
+    let reports = state.reports.lock().unwrap().clone();
+    let mut windows: Vec<WindowSnapshot> = app
+        .webview_windows()
+        .values()
+        .filter_map(|w| snapshot_window(w, reports.get(w.label())))
+        .collect();
+    windows.sort_by(|a, b| a.label.cmp(&b.label));
+    Some(Session {
+        windows,
+        drafts,
+        ended,
+    })
+}
+
+fn save(app: &AppHandle, ended: Option<Ending>) {
+    let profile = app.state::<ProfileState>().current();
+    if profile.ephemeral {
+        return;
+    }
+    let Some(session) = current(app, &profile, ended) else {
+        return;
+    };
+    let Ok(json) = serde_json::to_vec_pretty(&session) else {
+        return;
+    };
+    let state = app.state::<SessionState>();
+    let mut written = state.written.lock().unwrap();
+    if *written == json {
+        return;
+    }
+    match crate::encryption::write(app, &store_path(&profile), &json) {
+        Ok(()) => *written = json,
+        Err(e) => eprintln!("session: snapshot failed: {e}"),
+    }
+}
+
+/// Record how this run is ending. Called on exit, and by the updater before
+/// it installs so the relaunched app restores the session.
+pub fn end(app: &AppHandle, ending: Ending) {
+    let previous = load(app, &app.state::<ProfileState>().current()).and_then(|s| s.ended);
+    // The exit after an update install must not downgrade it to a quit.
+    if ending == Ending::Quit && previous == Some(Ending::Update) {
+        return;
+    }
+    save(app, Some(ending));
+}
+
+/// Take the active profile's drafts as the ones being kept.
+fn load_drafts(app: &AppHandle, profile: &Profile, previous: Option<&Session>) {
+    let items = previous.map(|s| s.drafts.clone()).unwrap_or_default();
+    *app.state::<SessionState>().drafts.lock().unwrap() = Drafts {
+        dir: profile.dir.clone(),
+        items,
+    };
+}
+
+/// Write the outgoing profile's snapshot, drafts and all, before a profile
+/// switch.
+pub fn flush(app: &AppHandle) {
+    save(app, None);
+}
+
+/// Pick up the previous run's session and start taking snapshots. Drafts
+/// carry over whatever the ending, since they were never sent. A profile
+/// switched to brings its own drafts.
+pub fn start(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    let previous = load(app, &profile);
+    load_drafts(app, &profile, previous.as_ref());
+    if let Some(previous) = previous.filter(|p| p.ended != Some(Ending::Quit)) {
+        *app.state::<SessionState>().previous.lock().unwrap() = Some(previous);
+    }
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| {
+        let profile = handle.state::<ProfileState>().current();
+        load_drafts(&handle, &profile, load(&handle, &profile).as_ref());
+    });
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SNAPSHOT_EVERY);
+        save(&app, None);
+    });
+}
+
+fn on_screen(app: &AppHandle, x: i32, y: i32) -> bool {
+    app.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .any(|m| {
+            let (pos, size) = (m.position(), m.size());
+            x >= pos.x
+                && y >= pos.y
+                && x < pos.x + size.width as i32
+                && y < pos.y + size.height as i32
+        })
+}
+
+fn place(app: &AppHandle, window: &WebviewWindow, snapshot: &WindowSnapshot) {
+    let _ = window.set_size(PhysicalSize::new(snapshot.width, snapshot.height));
+    if on_screen(app, snapshot.x, snapshot.y) {
+        let _ = window.set_position(PhysicalPosition::new(snapshot.x, snapshot.y));
+    }
+    if snapshot.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Tell the backend where this window is and what it shows. Call on
+/// navigation and, debounced, on scroll or zoom changes.
+#[tauri::command]
+pub fn session_report(
+    window: Window,
+    session: State<'_, SessionState>,
+    route: String,
+    state: Option<Value>,
+) {
+    session.reports.lock().unwrap().insert(
+        window.label().to_string(),
+        Report {
+            route: Some(route),
+            state: state.unwrap_or(Value::Null),
+        },
+    );
+}
+
+/// Keep an unsent draft under `key`, or drop it when `value` is null (sent
+/// or discarded).
+#[tauri::command]
+pub fn session_draft_set(session: State<'_, SessionState>, key: String, value: Option<Value>) {
+    let mut drafts = session.drafts.lock().unwrap();
+    match value {
+        Some(value) if !value.is_null() => drafts.items.insert(key, value),
+        _ => drafts.items.remove(&key),
+    };
+}
+
+/// Drafts kept from this or an earlier run.
+#[tauri::command]
+pub fn session_drafts(session: State<'_, SessionState>) -> Map<String, Value> {
+    session.drafts.lock().unwrap().items.clone()
+}
+
+/// After a crash or an update relaunch, reopen the previous run's windows
+/// at their old size and place and return the session; the main window
+/// applies its own entry, reopened windows fetch theirs with
+/// `session_window`. `None` after a normal quit or once restored.
+#[tauri::command]
+pub async fn session_restore(app: AppHandle) -> Result<Option<Session>, String> {
+    let state = app.state::<SessionState>();
+    if state.restored.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+    let Some(previous) = state.previous.lock().unwrap().clone() else {
+        return Ok(None);
+    };
+    for snapshot in &previous.windows {
+        let window = match app.get_webview_window(&snapshot.label) {
+            Some(window) => window,
+            None => {
+                let route = snapshot.route.clone().unwrap_or_default();
+                WebviewWindowBuilder::new(&app, &snapshot.label, WebviewUrl::App(route.into()))
+                    .title("Spectrus")
+                    .build()
+                    .map_err(|e| e.to_string())?
+            }
+        };
+        place(&app, &window, snapshot);
+    }
+    // Until the reopened windows have reported themselves, their saved
+    // routes stand in for the snapshots.
+    let mut reports = state.reports.lock().unwrap();
+    for snapshot in &previous.windows {
+        reports
+            .entry(snapshot.label.clone())
+            .or_insert_with(|| Report {
+                route: snapshot.route.clone(),
+                state: snapshot.state.clone(),
+            });
+    }
+    Ok(Some(previous))
+}
+
+/// The calling window's entry in the session being restored, taken once.
+#[tauri::command]
+pub fn session_window(window: Window, session: State<'_, SessionState>) -> Option<WindowSnapshot> {
+    let mut previous = session.previous.lock().unwrap();
+    let windows = &mut previous.as_mut()?.windows;
+    let index = windows.iter().position(|w| w.label == window.label())?;
+    Some(windows.remove(index))
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::profile::{Profile, ProfileState};
+
+/// Read the profile's settings file. A missing or unreadable file yields an
+/// empty map so first launches and hand-edited files don't block startup.
+pub fn load(profile: &Profile) -> Map<String, Value> {
+    read_file(&profile.settings_path())
+}
+
+/// Parse a settings file at `path`, tolerating absence and corruption.
+pub(crate) fn read_file(path: &Path) -> Map<String, Value> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Write the settings map atomically (temp file + rename).
+pub fn save(profile: &Profile, settings: &Map<String, Value>) -> Result<(), String> {
+    let path = profile.settings_path();
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    write_atomic(&path, &json)
+}
+
+/// Return a single setting, or `None` if it is unset. Settings held by the
+/// managed policy read as the policy's value.
+pub fn get(profile: &Profile, key: &str) -> Option<Value> {
+    if let Some(pinned) = crate::policy::current().pinned(key) {
+        return Some(pinned.clone());
+    }
+    load(profile).remove(key)
+}
+
+/// Set a single setting; `null` removes it. Fails for settings held by the
+/// managed policy.
+pub fn set(profile: &Profile, key: &str, value: Value) -> Result<(), String> {
+    if crate::policy::current().pinned(key).is_some() {
+        return Err(format!("{key} is managed by your administrator"));
+    }
+    let mut settings = load(profile);
+    if value.is_null() {
+        settings.remove(key);
+    } else {
+        settings.insert(key.to_string(), value);
+    }
+    save(profile, &settings)
+}
+
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Read a setting from the active profile.
+#[tauri::command]
+pub fn settings_get(profiles: State<'_, ProfileState>, key: String) -> Option<Value> {
+    get(&profiles.current(), &key)
+}
+
+/// Write a setting to the active profile. Passing `null` clears it.
+#[tauri::command]
+pub fn settings_set(
+    profiles: State<'_, ProfileState>,
+    key: String,
+    value: Value,
+) -> Result<(), String> {
+    set(&profiles.current(), &key, value)
+}
@@ -0,0 +1,434 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, State, Window};
+
+use crate::fs_scope::FsScope;
+
+/// Somewhere `export_share` can send files.
+#[derive(Clone, Copy, Serialize)]
+pub struct ShareTarget {
+    /// `system` is the OS share sheet, with whatever it offers; the others
+    /// go straight to one service.
+    id: &'static str,
+    name: &'static str,
+}
+
+#[cfg(target_os = "macos")]
+const TARGETS: &[ShareTarget] = &[
+    ShareTarget {
+        id: "system",
+        name: "Share…",
+    },
+    ShareTarget {
+        id: "airdrop",
+        name: "AirDrop",
+    },
+    ShareTarget {
+        id: "email",
+        name: "Mail",
+    },
+    ShareTarget {
+        id: "messages",
+        name: "Messages",
+    },
+];
+
+/// Nearby Sharing, Mail and installed apps are reached through the share
+/// dialog; there's no asking for one of them directly.
+#[cfg(target_os = "windows")]
+const TARGETS: &[ShareTarget] = &[ShareTarget {
+    id: "system",
+    name: "Share…",
+}];
+
+#[cfg(target_os = "linux")]
+const TARGETS: &[ShareTarget] = &[ShareTarget {
+    id: "email",
+    name: "Email",
+}];
+
+/// Open the native share sheet for a file (AirDrop, Mail, Messages, ...),
+/// anchored to the calling window. macOS and Windows.
+#[tauri::command]
+pub fn share(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<(), String> {
+    let path = scope.check(window.label(), &path)?;
+    crate::audit::record(
+        &app,
+        crate::audit::Action::Export,
+        serde_json::json!({ "kind": "share", "target": "sheet", "paths": [path] }),
+    );
+    show_share_sheet(&window, vec![path])
+}
+
+#[cfg(target_os = "macos")]
+fn show_share_sheet(window: &Window, paths: Vec<PathBuf>) -> Result<(), String> {
+    // Raw pointers aren't Send; the view lives as long as the window does.
+    let view = window.ns_view().map_err(|e| e.to_string())? as usize;
+    window
+        .run_on_main_thread(move || macos::share_sheet(view, &paths))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn show_share_sheet(window: &Window, paths: Vec<PathBuf>) -> Result<(), String> {
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?.0 as isize;
+    let title = paths
+        .iter()
+        .filter_map(|p| p.file_name())
+        .map(|n| n.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ");
+    window
+        .run_on_main_thread(move || {
+            if let Err(e) = win::share_ui(hwnd, paths, title) {
+                eprintln!("share: {e}");
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn show_share_sheet(_window: &Window, _paths: Vec<PathBuf>) -> Result<(), String> {
+    Err("there is no share sheet on Linux".into())
+}
+
+#[cfg(target_os = "linux")]
+fn xdg_email() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join("xdg-email"))
+        .find(|p| p.is_file())
+}
+
+fn available(target: &ShareTarget, paths: &[PathBuf]) -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        target.id == "system" || macos::can_perform(target.id, paths)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let _ = paths;
+        target.id == "email" && xdg_email().is_some()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = (target, paths);
+        true
+    }
+}
+
+fn send(window: &Window, target: &str, paths: Vec<PathBuf>) -> Result<(), String> {
+    if target == "system" {
+        return show_share_sheet(window, paths);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let target = target.to_string();
+        window
+            .run_on_main_thread(move || {
+                if !macos::perform(&target, &paths) {
+                    eprintln!("share: {target} refused the files");
+                }
+            })
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let _ = window;
+        let program = xdg_email().ok_or("no email client is set up (xdg-email is missing)")?;
+        let mut command = std::process::Command::new(program);
+        for path in &paths {
+            command.arg("--attach").arg(path);
+        }
+        command.spawn().map(drop).map_err(|e| e.to_string())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = (window, paths);
+        Err(format!("unknown share target: {target}"))
+    }
+}
+
+fn checked(window: &Window, scope: &FsScope, paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    if paths.is_empty() {
+        return Err("nothing to share".into());
+    }
+    paths
+        .iter()
+        .map(|p| {
+            let path = scope.check(window.label(), p)?;
+            if !path.is_file() {
+                return Err(format!("{p} is not a file"));
+            }
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Share targets that can take `paths` on this machine, in the order to
+/// offer them.
+#[tauri::command]
+pub fn export_share_targets(
+    window: Window,
+    scope: State<'_, FsScope>,
+    paths: Vec<String>,
+) -> Result<Vec<ShareTarget>, String> {
+    let paths = checked(&window, &scope, &paths)?;
+    Ok(TARGETS
+        .iter()
+        .filter(|t| available(t, &paths))
+        .copied()
+        .collect())
+}
+
+/// Hand exported files to a share target from `export_share_targets`: the
+/// share sheet, AirDrop, or a new mail with them attached. Returns once the
+/// target has been opened; what the user does there isn't reported.
+#[tauri::command]
+pub fn export_share(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    paths: Vec<String>,
+    target: String,
+) -> Result<(), String> {
+    let paths = checked(&window, &scope, &paths)?;
+    let target = TARGETS
+        .iter()
+        .find(|t| t.id == target && available(t, &paths))
+        .ok_or_else(|| format!("{target} is not available"))?;
+    crate::audit::record(
+        &app,
+        crate::audit::Action::Export,
+        serde_json::json!({ "kind": "share", "target": target.id, "paths": paths }),
+    );
+    send(&window, target.id, paths)
+}
+
+/// Register the "Analyze in Spectrus" Services menu entry declared in
+/// Info.plist (`NSServices`). Must run on the main thread.
+#[cfg(target_os = "macos")]
+pub fn init_services(app: &tauri::AppHandle) {
+    macos::register_provider(app);
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyObject, NSObject};
+    use objc2::{define_class, msg_send, AnyThread, MainThreadMarker, MainThreadOnly};
+    use objc2_app_kit::{
+        NSApplication, NSPasteboard, NSPasteboardTypeFileURL, NSSharingService,
+        NSSharingServiceNameComposeEmail, NSSharingServiceNameComposeMessage,
+        NSSharingServiceNameSendViaAirDrop, NSSharingServicePicker, NSUpdateDynamicServices,
+        NSView,
+    };
+    use objc2_foundation::{NSArray, NSRectEdge, NSString, NSURL};
+    use tauri::{AppHandle, Emitter, Manager};
+
+    use crate::fs_scope::FsScope;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    thread_local! {
+        // AppKit doesn't keep the picker or the provider alive on its own.
+        static PICKER: RefCell<Option<Retained<NSSharingServicePicker>>> =
+            const { RefCell::new(None) };
+        static PROVIDER: RefCell<Option<Retained<Provider>>> = const { RefCell::new(None) };
+    }
+
+    fn items(paths: &[PathBuf]) -> Retained<NSArray> {
+        let urls: Vec<Retained<AnyObject>> = paths
+            .iter()
+            .map(|p| NSURL::fileURLWithPath(&NSString::from_str(&p.to_string_lossy())).into())
+            .collect();
+        NSArray::from_retained_slice(&urls)
+    }
+
+    fn service(id: &str) -> Option<Retained<NSSharingService>> {
+        // SAFETY: reading well-known service name constants.
+        let name = unsafe {
+            match id {
+                "airdrop" => NSSharingServiceNameSendViaAirDrop,
+                "email" => NSSharingServiceNameComposeEmail,
+                "messages" => NSSharingServiceNameComposeMessage,
+                _ => return None,
+            }
+        };
+        NSSharingService::sharingServiceNamed(name)
+    }
+
+    pub fn can_perform(id: &str, paths: &[PathBuf]) -> bool {
+        // SAFETY: file URLs conform to NSPasteboardWriting.
+        service(id).is_some_and(|s| unsafe { s.canPerformWithItems(Some(&items(paths))) })
+    }
+
+    /// Runs on the main thread.
+    pub fn perform(id: &str, paths: &[PathBuf]) -> bool {
+        let Some(service) = service(id) else {
+            return false;
+        };
+        let items = items(paths);
+        // SAFETY: as in `can_perform`.
+        unsafe {
+            if !service.canPerformWithItems(Some(&items)) {
+                return false;
+            }
+            service.performWithItems(&items);
+        }
+        true
+    }
+
+    /// Runs on the main thread.
+    pub fn share_sheet(view: usize, paths: &[PathBuf]) {
+        // SAFETY: `view` is the window's content NSView, passed from
+        // `Window::ns_view` on a live window.
+        let view: &NSView = unsafe { &*(view as *const NSView) };
+        let items = items(paths);
+        // SAFETY: file URLs are one of the item types the picker documents.
+        let picker = unsafe {
+            NSSharingServicePicker::initWithItems(NSSharingServicePicker::alloc(), &items)
+        };
+        picker.showRelativeToRect_ofView_preferredEdge(view.bounds(), view, NSRectEdge::MinY);
+        PICKER.set(Some(picker));
+    }
+
+    define_class!(
+        #[unsafe(super(NSObject))]
+        #[thread_kind = MainThreadOnly]
+        #[name = "SpectrusServicesProvider"]
+        struct Provider;
+
+        impl Provider {
+            /// Handler for the `analyzeFiles` NSMessage in Info.plist.
+            #[unsafe(method(analyzeFiles:userData:error:))]
+            fn analyze_files(
+                &self,
+                pboard: &NSPasteboard,
+                _user_data: Option<&NSString>,
+                _error: *mut *mut NSString,
+            ) {
+                let paths = file_paths(pboard);
+                if let (Some(app), false) = (APP.get(), paths.is_empty()) {
+                    forward(app, &paths);
+                }
+            }
+        }
+    );
+
+    fn file_paths(pboard: &NSPasteboard) -> Vec<PathBuf> {
+        let Some(items) = pboard.pasteboardItems() else {
+            return Vec::new();
+        };
+        items
+            .iter()
+            .filter_map(|item| {
+                // SAFETY: reading a well-known pasteboard type constant.
+                let uri = item.stringForType(unsafe { NSPasteboardTypeFileURL })?;
+                url::Url::parse(&uri.to_string()).ok()?.to_file_path().ok()
+            })
+            .collect()
+    }
+
+    /// Hand the files to the main window through the deep-link path, as a
+    /// `spectrus://import?path=...` link, after granting it access to them.
+    fn forward(app: &AppHandle, paths: &[PathBuf]) {
+        let scope = app.state::<FsScope>();
+        let mut link = url::Url::parse("spectrus://import").expect("static URL");
+        for path in paths {
+            scope.grant("main", path);
+            link.query_pairs_mut()
+                .append_pair("path", &path.to_string_lossy());
+        }
+        crate::tray::show_main(app);
+        if let Err(e) = app.emit("spectrus://deep-link", link.to_string()) {
+            eprintln!("services: deep-link emit error: {e}");
+        }
+    }
+
+    pub fn register_provider(app: &AppHandle) {
+        let _ = APP.set(app.clone());
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        // SAFETY: `init` on a freshly allocated NSObject subclass.
+        let provider: Retained<Provider> = unsafe { msg_send![Provider::alloc(mtm), init] };
+        let app_kit = NSApplication::sharedApplication(mtm);
+        // SAFETY: the provider implements the selector named in Info.plist.
+        unsafe { app_kit.setServicesProvider(Some(&provider)) };
+        NSUpdateDynamicServices();
+        PROVIDER.set(Some(provider));
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod win {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+
+    use windows::core::{Interface, Ref, HSTRING};
+    use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+    use windows::Foundation::TypedEventHandler;
+    use windows::Storage::{IStorageItem, StorageFile};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+    use windows_collections::IIterable;
+
+    thread_local! {
+        // The share dialog asks for its data through this handler; the
+        // previous share's handler is removed before adding the next.
+        static HANDLER: RefCell<Option<(DataTransferManager, i64)>> = const { RefCell::new(None) };
+    }
+
+    fn items(paths: &[PathBuf]) -> windows::core::Result<Vec<Option<IStorageItem>>> {
+        paths
+            .iter()
+            .map(|p| {
+                let file =
+                    StorageFile::GetFileFromPathAsync(&HSTRING::from(p.as_path()))?.join()?;
+                file.cast::<IStorageItem>().map(Some)
+            })
+            .collect()
+    }
+
+    /// Runs on the main thread.
+    pub fn share_ui(hwnd: isize, paths: Vec<PathBuf>, title: String) -> windows::core::Result<()> {
+        let hwnd = HWND(hwnd as *mut _);
+        let interop = windows::core::factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+        // SAFETY: `hwnd` is the calling window's, which is open.
+        let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd)? };
+        HANDLER.with_borrow_mut(|handler| {
+            if let Some((manager, token)) = handler.take() {
+                let _ = manager.RemoveDataRequested(token);
+            }
+        });
+        let handler = TypedEventHandler::new(
+            move |_: Ref<DataTransferManager>, args: Ref<DataRequestedEventArgs>| {
+                let Some(args) = args.as_ref() else {
+                    return Ok(());
+                };
+                let request = args.Request()?;
+                let data = request.Data()?;
+                data.Properties()?.SetTitle(&HSTRING::from(&title))?;
+                match items(&paths) {
+                    Ok(items) => data.SetStorageItemsReadOnly(&IIterable::from(items)),
+                    Err(e) => request.FailWithDisplayText(&HSTRING::from(e.message())),
+                }
+            },
+        );
+        let token = manager.DataRequested(&handler)?;
+        HANDLER.set(Some((manager, token)));
+        // SAFETY: as above.
+        unsafe { interop.ShowShareUIForWindow(hwnd) }
+    }
+}
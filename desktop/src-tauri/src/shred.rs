@@ -0,0 +1,239 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::{State, Window};
+
+use crate::fs_scope::FsScope;
+use crate::watchdog::{Call, Scope};
+
+/// What overwriting can actually promise for a given location.
+#[derive(Serialize)]
+pub struct ShredReport {
+    /// `Some(false)` when the storage is known to keep old copies (SSD wear
+    /// levelling, copy-on-write filesystems); `None` when we can't tell.
+    overwrite_reliable: Option<bool>,
+    filesystem: Option<String>,
+    solid_state: Option<bool>,
+    notes: Vec<String>,
+}
+
+/// Open `path` for writing without following a symlink in its place.
+fn open_no_follow(path: &Path) -> io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        /// `FILE_FLAG_OPEN_REPARSE_POINT`: open the link, not its target.
+        const OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+        options.custom_flags(OPEN_REPARSE_POINT);
+    }
+    options.open(path)
+}
+
+/// Remove a symlink itself; on Windows links to directories go with
+/// `remove_dir`.
+fn unlink(path: &Path) -> io::Result<()> {
+    fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+}
+
+/// Overwrite `path` with random bytes, give it a meaningless name so the
+/// original doesn't linger in the directory entry, then delete it. A
+/// symlink is only unlinked; whatever it points to is left alone.
+pub fn shred_file(path: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.file_type().is_symlink() {
+        return unlink(path);
+    }
+    let len = meta.len();
+    let mut file = open_no_follow(path)?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(buf.len() as u64) as usize;
+        getrandom::fill(&mut buf[..n]).map_err(io::Error::other)?;
+        file.write_all(&buf[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+
+    let mut name = [0u8; 8];
+    getrandom::fill(&mut name).map_err(io::Error::other)?;
+    let scrubbed = path.with_file_name(name.iter().map(|b| format!("{b:02x}")).collect::<String>());
+    fs::rename(path, &scrubbed)?;
+    fs::remove_file(scrubbed)
+}
+
+/// Shred every file under `dir`, then remove the tree. Symlinks are
+/// unlinked, never followed.
+pub fn shred_dir(dir: &Path) -> io::Result<()> {
+    shred_tree(dir, None)
+}
+
+/// `shred_dir`, stopping before the next file once `scope` fails.
+fn shred_tree(dir: &Path, scope: Option<&Scope>) -> io::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let kind = entry.file_type()?;
+        if let Some(scope) = scope {
+            scope.check().map_err(io::Error::other)?;
+        }
+        if kind.is_symlink() {
+            unlink(&path)?;
+        } else if kind.is_dir() {
+            shred_tree(&path, scope)?;
+        } else {
+            shred_file(&path)?;
+        }
+    }
+    fs::remove_dir_all(dir)
+}
+
+#[cfg(target_os = "linux")]
+fn inspect(path: &Path) -> ShredReport {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut notes = Vec::new();
+    let filesystem = linux_fs_type(path);
+    let cow = matches!(filesystem.as_deref(), Some("btrfs" | "zfs" | "bcachefs"));
+    if cow {
+        notes.push("copy-on-write filesystem: overwrites are written to new blocks".into());
+    }
+
+    // Map st_dev to its block device in sysfs; partitions inherit the
+    // parent disk's `queue/rotational`.
+    let solid_state = fs::metadata(path).ok().and_then(|m| {
+        let dev = m.dev();
+        let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+        let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+        let sys = format!("/sys/dev/block/{major}:{minor}");
+        ["queue/rotational", "../queue/rotational"]
+            .iter()
+            .find_map(|rel| fs::read_to_string(format!("{sys}/{rel}")).ok())
+            .map(|v| v.trim() == "0")
+    });
+    if solid_state == Some(true) {
+        notes.push("solid-state storage: wear levelling may keep old copies".into());
+    }
+
+    let overwrite_reliable = match (cow, solid_state) {
+        (true, _) | (_, Some(true)) => Some(false),
+        (false, Some(false)) => Some(true),
+        (false, None) => None,
+    };
+    if overwrite_reliable != Some(true) {
+        notes.push("full-disk encryption is the only dependable protection here".into());
+    }
+    ShredReport {
+        overwrite_reliable,
+        filesystem,
+        solid_state,
+        notes,
+    }
+}
+
+/// Filesystem type of the longest mount point containing `path`.
+#[cfg(target_os = "linux")]
+fn linux_fs_type(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount = fields.next()?.replace("\\040", " ");
+            let fstype = fields.next()?;
+            path.starts_with(&mount)
+                .then(|| (mount.len(), fstype.to_string()))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fstype)| fstype)
+}
+
+#[cfg(target_os = "macos")]
+fn inspect(_path: &Path) -> ShredReport {
+    ShredReport {
+        overwrite_reliable: Some(false),
+        filesystem: Some("apfs".into()),
+        solid_state: None,
+        notes: vec![
+            "APFS is copy-on-write: overwrites are written to new blocks".into(),
+            "FileVault is the only dependable protection here".into(),
+        ],
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn inspect(_path: &Path) -> ShredReport {
+    ShredReport {
+        overwrite_reliable: None,
+        filesystem: None,
+        solid_state: None,
+        notes: vec![
+            "storage type unknown: SSDs may keep old copies despite overwriting".into(),
+            "BitLocker is the only dependable protection on solid-state drives".into(),
+        ],
+    }
+}
+
+/// Report how much overwriting files at `path` can be trusted on this machine.
+#[tauri::command]
+pub fn shred_capability(
+    window: Window,
+    scope: State<'_, FsScope>,
+    path: String,
+) -> Result<ShredReport, String> {
+    let path = scope.check(window.label(), &path)?;
+    Ok(inspect(&path))
+}
+
+/// Overwrite and delete files (or directory trees). Returns the capability
+/// report for the first path so the UI can say honestly whether the data is
+/// really gone. Stops before the next file once the call is cancelled.
+#[tauri::command]
+pub async fn file_shred(
+    call: Call,
+    scope: State<'_, FsScope>,
+    paths: Vec<String>,
+) -> Result<Option<ShredReport>, String> {
+    let paths = paths
+        .iter()
+        .map(|p| scope.check(call.window(), p))
+        .collect::<Result<Vec<_>, _>>()?;
+    let scope = call.scope();
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = paths.first().map(|p| inspect(p));
+        for path in &paths {
+            scope.check()?;
+            let result = if path.is_dir() {
+                shred_tree(path, Some(&scope))
+            } else {
+                shred_file(path)
+            };
+            // A cancelled walk reports why it stopped, not where.
+            result.map_err(|e| {
+                scope
+                    .check()
+                    .err()
+                    .unwrap_or_else(|| format!("{}: {e}", path.display()))
+            })?;
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
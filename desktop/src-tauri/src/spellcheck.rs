@@ -0,0 +1,395 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Spell-check choices of one profile, kept in `spellcheck.json`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Store {
+    /// Languages to check against. `None` follows the system; an empty list
+    /// turns spell checking off.
+    languages: Option<Vec<String>>,
+    /// The user dictionary, sorted.
+    words: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SpellLanguage {
+    /// Dictionary tag as the platform reports it, e.g. `en_US` on Linux and
+    /// macOS, `en-US` on Windows.
+    code: String,
+    enabled: bool,
+}
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("spellcheck.json")
+}
+
+fn load(profile: &Profile) -> Store {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save(profile: &Profile, store: &Store) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(store).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(profile), &json)
+}
+
+/// Dictionaries user words are added to: the enabled ones, or all of them
+/// while following the system.
+fn targets(app: &AppHandle, store: &Store) -> Vec<String> {
+    store
+        .languages
+        .clone()
+        .unwrap_or_else(|| platform::available(app))
+}
+
+/// Push the profile's languages to the webviews and tell the editor, which
+/// sets `spellcheck`/`lang` on its fields from the
+/// `spectrus://spellcheck-changed` payload (`null` means system default).
+fn apply(app: &AppHandle, store: &Store) {
+    platform::apply(app, store.languages.as_deref());
+    let _ = app.emit("spectrus://spellcheck-changed", &store.languages);
+}
+
+/// Apply the active profile's spell-check settings to the webviews. Runs
+/// off the main thread, which the platform calls have to wait for.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let store = load(&app.state::<ProfileState>().current());
+        apply(&app, &store);
+        let targets = targets(&app, &store);
+        for word in &store.words {
+            platform::learn(&app, &targets, word, true);
+        }
+    });
+}
+
+/// Spell-check dictionaries installed on the system, sorted, and whether
+/// each is enabled for the active profile.
+#[tauri::command]
+pub async fn spellcheck_languages(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+) -> Result<Vec<SpellLanguage>, String> {
+    let store = load(&profiles.current());
+    let available = tauri::async_runtime::spawn_blocking(move || platform::available(&app))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(available
+        .into_iter()
+        .map(|code| SpellLanguage {
+            enabled: store.languages.as_ref().is_none_or(|l| l.contains(&code)),
+            code,
+        })
+        .collect())
+}
+
+/// Check spelling against `languages`; an empty list disables spell
+/// checking and `None` goes back to the system default.
+#[tauri::command]
+pub async fn spellcheck_set_languages(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    languages: Option<Vec<String>>,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(languages) = &languages {
+            let available = platform::available(&app);
+            if let Some(missing) = languages.iter().find(|l| !available.contains(l)) {
+                return Err(format!("no {missing} dictionary installed"));
+            }
+        }
+        let mut store = load(&profile);
+        store.languages = languages;
+        save(&profile, &store)?;
+        apply(&app, &store);
+        let targets = targets(&app, &store);
+        for word in &store.words {
+            platform::learn(&app, &targets, word, true);
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Words in the active profile's user dictionary.
+#[tauri::command]
+pub fn spellcheck_words(profiles: State<'_, ProfileState>) -> Vec<String> {
+    load(&profiles.current()).words
+}
+
+/// Accept `word` as correctly spelled. It is also handed to the platform
+/// dictionaries the webview checks against so it stops being underlined.
+#[tauri::command]
+pub async fn spellcheck_add_word(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    word: String,
+) -> Result<(), String> {
+    let word = word.trim().to_string();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err("a dictionary entry is a single word".into());
+    }
+    let profile = profiles.current();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut store = load(&profile);
+        if let Err(at) = store.words.binary_search(&word) {
+            store.words.insert(at, word.clone());
+            save(&profile, &store)?;
+        }
+        platform::learn(&app, &targets(&app, &store), &word, true);
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Take `word` back out of the user dictionary.
+#[tauri::command]
+pub async fn spellcheck_remove_word(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    word: String,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut store = load(&profile);
+        if let Ok(at) = store.words.binary_search(&word) {
+            store.words.remove(at);
+            save(&profile, &store)?;
+        }
+        platform::learn(&app, &targets(&app, &store), &word, false);
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// WebKitGTK checks through Enchant, which reads Hunspell dictionaries and
+/// keeps personal word lists in `~/.config/enchant/<tag>.dic`.
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::{AppHandle, Manager};
+    use webkit2gtk::{WebContextExt, WebViewExt};
+
+    fn dictionary_dirs() -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = std::env::var_os("DICPATH")
+            .map(|p| std::env::split_paths(&p).collect())
+            .unwrap_or_default();
+        dirs.extend(dirs::data_dir().map(|d| d.join("hunspell")));
+        dirs.extend(
+            [
+                "/usr/share/hunspell",
+                "/usr/share/myspell",
+                "/usr/share/myspell/dicts",
+            ]
+            .into_iter()
+            .map(PathBuf::from),
+        );
+        dirs
+    }
+
+    /// A `.dic` only counts with its `.aff`, which also skips hyphenation
+    /// patterns sharing the directories.
+    pub fn available(_app: &AppHandle) -> Vec<String> {
+        let mut codes: Vec<String> = dictionary_dirs()
+            .iter()
+            .flat_map(|dir| fs::read_dir(dir).into_iter().flatten().flatten())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "dic"))
+            .filter(|p| p.with_extension("aff").exists())
+            .filter_map(|p| Some(p.file_stem()?.to_str()?.to_string()))
+            .collect();
+        codes.sort();
+        codes.dedup();
+        codes
+    }
+
+    /// The dictionary matching the session locale (`de_DE.UTF-8` → `de_DE`,
+    /// else any `de_*`).
+    fn system_language(available: &[String]) -> Option<String> {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|v| std::env::var(v).ok().filter(|l| !l.is_empty()))?;
+        let tag = locale.split(['.', '@']).next()?;
+        let language = tag.split('_').next()?;
+        available
+            .iter()
+            .find(|c| *c == tag)
+            .or_else(|| {
+                available
+                    .iter()
+                    .find(|c| c.split('_').next() == Some(language))
+            })
+            .cloned()
+    }
+
+    pub fn apply(app: &AppHandle, languages: Option<&[String]>) {
+        let languages = match languages {
+            Some(languages) => languages.to_vec(),
+            None => system_language(&available(app)).into_iter().collect(),
+        };
+        for window in app.webview_windows().values() {
+            let languages = languages.clone();
+            let _ = window.with_webview(move |webview| {
+                let Some(context) = webview.inner().context() else {
+                    return;
+                };
+                let tags: Vec<&str> = languages.iter().map(String::as_str).collect();
+                context.set_spell_checking_languages(&tags);
+                context.set_spell_checking_enabled(!tags.is_empty());
+            });
+        }
+    }
+
+    pub fn learn(_app: &AppHandle, languages: &[String], word: &str, add: bool) {
+        let Some(dir) = dirs::config_dir().map(|d| d.join("enchant")) else {
+            return;
+        };
+        let _ = fs::create_dir_all(&dir);
+        for language in languages {
+            let path = dir.join(format!("{language}.dic"));
+            let existing = fs::read_to_string(&path).unwrap_or_default();
+            let mut words: Vec<&str> = existing.lines().filter(|w| *w != word).collect();
+            if add {
+                words.push(word);
+            }
+            let mut contents = words.join("\n");
+            contents.push('\n');
+            if contents != existing {
+                let _ = fs::write(&path, contents);
+            }
+        }
+    }
+}
+
+/// WebView2 uses the Windows spell checker, whose per-language user
+/// dictionaries the words are added to. It has no API to pick languages;
+/// the editor does that through the `lang` attribute.
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use windows::core::{Interface, HSTRING, PWSTR};
+    use windows::Win32::Globalization::{
+        ISpellChecker2, ISpellCheckerFactory, SpellCheckerFactory,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_INPROC_SERVER};
+
+    use crate::keyboard::on_main;
+
+    fn factory() -> windows::core::Result<ISpellCheckerFactory> {
+        // SAFETY: COM is initialised on the main thread, where this runs.
+        unsafe { CoCreateInstance(&SpellCheckerFactory, None, CLSCTX_INPROC_SERVER) }
+    }
+
+    pub fn available(app: &AppHandle) -> Vec<String> {
+        on_main(app, || {
+            let mut codes = Vec::new();
+            // SAFETY: each string returned by `Next` is ours to free.
+            unsafe {
+                let Ok(languages) = factory().and_then(|f| f.SupportedLanguages()) else {
+                    return codes;
+                };
+                let mut item = [PWSTR::null()];
+                while languages.Next(&mut item, None).is_ok() && !item[0].is_null() {
+                    codes.extend(item[0].to_string().ok());
+                    CoTaskMemFree(Some(item[0].0 as _));
+                    item[0] = PWSTR::null();
+                }
+            }
+            codes.sort();
+            codes
+        })
+        .unwrap_or_default()
+    }
+
+    pub fn apply(_app: &AppHandle, _languages: Option<&[String]>) {}
+
+    pub fn learn(app: &AppHandle, languages: &[String], word: &str, add: bool) {
+        let (languages, word) = (languages.to_vec(), HSTRING::from(word));
+        on_main(app, move || {
+            let Ok(factory) = factory() else {
+                return;
+            };
+            for language in &languages {
+                // SAFETY: plain COM calls on objects we own.
+                let _ = unsafe {
+                    factory
+                        .CreateSpellChecker(&HSTRING::from(language))
+                        .and_then(|checker| {
+                            if add {
+                                checker.Add(&word)
+                            } else {
+                                checker.cast::<ISpellChecker2>()?.Remove(&word)
+                            }
+                        })
+                };
+            }
+        });
+    }
+}
+
+/// WKWebView checks with the shared NSSpellChecker, which has a single
+/// language or identifies it per paragraph, and one learned-word list.
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2_app_kit::NSSpellChecker;
+    use objc2_foundation::NSString;
+    use tauri::AppHandle;
+
+    use crate::keyboard::on_main;
+
+    pub fn available(app: &AppHandle) -> Vec<String> {
+        on_main(app, || {
+            let mut codes: Vec<String> = NSSpellChecker::sharedSpellChecker()
+                .availableLanguages()
+                .iter()
+                .map(|l| l.to_string())
+                .collect();
+            codes.sort();
+            codes
+        })
+        .unwrap_or_default()
+    }
+
+    pub fn apply(app: &AppHandle, languages: Option<&[String]>) {
+        let single = match languages {
+            Some([language]) => Some(language.clone()),
+            _ => None,
+        };
+        on_main(app, move || {
+            let checker = NSSpellChecker::sharedSpellChecker();
+            checker.setAutomaticallyIdentifiesLanguages(single.is_none());
+            if let Some(language) = single {
+                checker.setLanguage(&NSString::from_str(&language));
+            }
+        });
+    }
+
+    pub fn learn(app: &AppHandle, _languages: &[String], word: &str, add: bool) {
+        let word = word.to_string();
+        on_main(app, move || {
+            let word = NSString::from_str(&word);
+            let checker = NSSpellChecker::sharedSpellChecker();
+            if add {
+                checker.learnWord(&word);
+            } else {
+                checker.unlearnWord(&word);
+            }
+        });
+    }
+}
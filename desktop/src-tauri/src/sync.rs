@@ -0,0 +1,798 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::agreement::{self, EphemeralPrivateKey, X25519};
+use ring::digest::{self, SHA256};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use walkdir::WalkDir;
+
+use crate::bandwidth::Subsystem;
+use crate::jobs::Job;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::{Call, Scope};
+
+/// Bound into every handshake so keys from other protocols, and from
+/// builds that frame messages differently, never verify. v1 sent each
+/// message in a single frame.
+const PROTOCOL: &[u8] = b"spectrus-sync-v2";
+
+/// Largest encrypted frame accepted, before the tag.
+const MAX_FRAME: usize = 1024 * 1024;
+
+/// File data and messages are sent in frames of this size.
+const CHUNK: usize = 256 * 1024;
+
+/// Largest message accepted once its frames are put back together; a
+/// manifest takes about 150 bytes a file.
+const MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+/// Peers served at once; more are turned away until one finishes.
+const MAX_PEERS: usize = 4;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A device this profile exchanges projects with.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedPeer {
+    /// base64url Ed25519 device key.
+    key: String,
+    name: String,
+    added_at: i64,
+}
+
+type Hashes = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Kept as `sync.json`.
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    #[serde(default)]
+    trusted: Vec<TrustedPeer>,
+    /// Each file's hash as of the last sync with a peer, by peer key,
+    /// project and path. A file that differs from it on both sides is a
+    /// conflict.
+    #[serde(default)]
+    base: BTreeMap<String, Hashes>,
+}
+
+/// Managed state: serializes read-modify-write of `sync.json`.
+#[derive(Default)]
+pub struct SyncState(Mutex<()>);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileEntry {
+    size: u64,
+    hash: String,
+}
+
+/// Files offered, by project and `/`-separated path.
+type Manifest = BTreeMap<String, BTreeMap<String, FileEntry>>;
+
+/// A file changed on both sides since the last sync, left as it was.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    project: String,
+    path: String,
+}
+
+/// What the receiving side did with a transfer.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    received_files: u64,
+    received_bytes: u64,
+    /// Already identical on both sides.
+    unchanged: u64,
+    /// Changed only on the receiving side, so kept.
+    kept_newer: u64,
+    /// Changed by the sender while being sent; picked up next time.
+    interrupted: u64,
+    conflicts: Vec<Conflict>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Message {
+    /// The dialer wants these projects sent to it.
+    Pull {
+        projects: Vec<String>,
+    },
+    /// The dialer is about to send its manifest.
+    Push,
+    Manifest {
+        projects: Manifest,
+    },
+    /// (project, path) pairs the receiver needs, each then sent as data
+    /// frames ending with an empty one.
+    Want {
+        files: Vec<(String, String)>,
+    },
+    /// Files now identical on both sides, as (project, path, hash).
+    Done {
+        report: SyncReport,
+        synced: Vec<(String, String, String)>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Payload of `spectrus://sync-finished`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncFinished<'a> {
+    peer: &'a str,
+    /// `"sent"` when the peer pulled, `"received"` when it pushed.
+    direction: &'static str,
+    report: &'a SyncReport,
+}
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("sync.json")
+}
+
+fn load(profile: &Profile) -> Store {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn update(app: &AppHandle, change: impl FnOnce(&mut Store)) -> Result<(), String> {
+    let state = app.state::<SyncState>();
+    let _guard = state.0.lock().unwrap();
+    let profile = app.state::<ProfileState>().current();
+    let mut store = load(&profile);
+    change(&mut store);
+    let json = serde_json::to_vec_pretty(&store).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(&profile), &json)
+}
+
+fn is_trusted(app: &AppHandle, key: &str) -> bool {
+    trusted_name(app, key).is_some()
+}
+
+/// Name the active profile gave the trusted device with base64url `key`.
+pub(crate) fn trusted_name(app: &AppHandle, key: &str) -> Option<String> {
+    let profile = app.state::<ProfileState>().current();
+    load(&profile)
+        .trusted
+        .into_iter()
+        .find(|t| t.key == key)
+        .map(|t| t.name)
+}
+
+fn record_synced(
+    app: &AppHandle,
+    peer: &str,
+    synced: &[(String, String, String)],
+) -> Result<(), String> {
+    update(app, |store| {
+        let base = store.base.entry(peer.to_string()).or_default();
+        for (project, path, hash) in synced {
+            base.entry(project.clone())
+                .or_default()
+                .insert(path.clone(), hash.clone());
+        }
+    })
+}
+
+/// Projects live as directories, attachments included, under the
+/// profile's `projects/`.
+pub(crate) fn project_dir(profile: &Profile, project: &str) -> Result<PathBuf, String> {
+    let valid = !project.is_empty()
+        && project
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("invalid project id: {project}"));
+    }
+    Ok(profile.dir.join("projects").join(project))
+}
+
+/// Resolve a peer-supplied path inside `dir`, refusing anything that
+/// could land outside it.
+fn inside(dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("invalid path from peer: {path}"));
+    }
+    Ok(dir.join(relative))
+}
+
+fn hash_file(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(file).map_err(|e| e.to_string())?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn project_files(profile: &Profile, project: &str) -> Result<BTreeMap<String, FileEntry>, String> {
+    let dir = project_dir(profile, project)?;
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(&dir)
+        .min_depth(1)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_none_or(|x| x != "tmp"))
+    {
+        let Ok(relative) = entry.path().strip_prefix(&dir) else {
+            continue;
+        };
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let size = entry.metadata().map_err(|e| e.to_string())?.len();
+        let hash = hash_file(entry.path())?;
+        files.insert(path, FileEntry { size, hash });
+    }
+    Ok(files)
+}
+
+fn nonce(counter: u64) -> Nonce {
+    let mut bytes = [0u8; 12];
+    bytes[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(bytes)
+}
+
+/// An authenticated, encrypted connection: length-prefixed ChaCha20-Poly1305
+/// frames with a per-direction key and counter nonce.
+struct Channel {
+    stream: TcpStream,
+    send_key: LessSafeKey,
+    recv_key: LessSafeKey,
+    sent: u64,
+    received: u64,
+}
+
+impl Channel {
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        let mut frame = data.to_vec();
+        self.send_key
+            .seal_in_place_append_tag(nonce(self.sent), Aad::empty(), &mut frame)
+            .map_err(|_| "encryption failed")?;
+        self.sent += 1;
+        self.stream
+            .write_all(&(frame.len() as u32).to_be_bytes())
+            .and_then(|()| self.stream.write_all(&frame))
+            .map_err(|e| e.to_string())
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>, String> {
+        let mut len = [0u8; 4];
+        self.stream
+            .read_exact(&mut len)
+            .map_err(|_| "peer closed the connection")?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME + CHACHA20_POLY1305.tag_len() {
+            return Err("frame too large".into());
+        }
+        let mut frame = vec![0u8; len];
+        self.stream
+            .read_exact(&mut frame)
+            .map_err(|_| "peer closed the connection")?;
+        let plain = self
+            .recv_key
+            .open_in_place(nonce(self.received), Aad::empty(), &mut frame)
+            .map_err(|_| "message failed authentication")?
+            .len();
+        self.received += 1;
+        frame.truncate(plain);
+        Ok(frame)
+    }
+
+    /// Send `message` in frames of at most `CHUNK` bytes of JSON, each led
+    /// by a byte saying whether more of it follows, so a large manifest
+    /// never outgrows `MAX_FRAME`.
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        let json = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+        let mut parts = json.chunks(CHUNK).peekable();
+        while let Some(part) = parts.next() {
+            let mut frame = Vec::with_capacity(part.len() + 1);
+            frame.push(u8::from(parts.peek().is_some()));
+            frame.extend_from_slice(part);
+            self.write(&frame)?;
+        }
+        Ok(())
+    }
+
+    fn expect<T: DeserializeOwned>(&mut self) -> Result<T, String> {
+        let mut json = Vec::new();
+        loop {
+            let frame = self.read()?;
+            let (&more, part) = frame.split_first().ok_or("unexpected empty frame")?;
+            if json.len() + part.len() > MAX_MESSAGE {
+                return Err("message too large".into());
+            }
+            json.extend_from_slice(part);
+            if more == 0 {
+                break;
+            }
+        }
+        serde_json::from_slice(&json).map_err(|e| format!("unexpected message: {e}"))
+    }
+}
+
+/// Ephemeral X25519 exchange, with each side signing the transcript with
+/// its device key so both ends know who they are talking to. Returns the
+/// channel and the peer's device key; whether to trust it is the caller's
+/// call.
+fn handshake(
+    mut stream: TcpStream,
+    device: &Ed25519KeyPair,
+    dialer: bool,
+) -> Result<(Channel, Vec<u8>), String> {
+    let closed = |_| "peer closed the connection during the handshake".to_string();
+    let rng = SystemRandom::new();
+    let ephemeral =
+        EphemeralPrivateKey::generate(&X25519, &rng).map_err(|_| "key generation failed")?;
+    let mine = ephemeral
+        .compute_public_key()
+        .map_err(|_| "key generation failed")?;
+    stream.write_all(mine.as_ref()).map_err(|e| e.to_string())?;
+    let mut theirs = [0u8; 32];
+    stream.read_exact(&mut theirs).map_err(closed)?;
+
+    let (dialer_half, listener_half) = if dialer {
+        (mine.as_ref(), &theirs[..])
+    } else {
+        (&theirs[..], mine.as_ref())
+    };
+    let transcript = digest::digest(&SHA256, &[PROTOCOL, dialer_half, listener_half].concat());
+    // The role is signed too, so a signature can't be reflected back.
+    let signed = |dialer: bool| {
+        let role: &[u8] = if dialer { b"dialer" } else { b"listener" };
+        [transcript.as_ref(), role].concat()
+    };
+    let mut hello = device.public_key().as_ref().to_vec();
+    hello.extend_from_slice(device.sign(&signed(dialer)).as_ref());
+    stream.write_all(&hello).map_err(|e| e.to_string())?;
+    let mut peer = [0u8; 32 + 64];
+    stream.read_exact(&mut peer).map_err(closed)?;
+    let (peer_key, signature) = peer.split_at(32);
+    UnparsedPublicKey::new(&ED25519, peer_key)
+        .verify(&signed(!dialer), signature)
+        .map_err(|_| "peer failed to prove its identity")?;
+
+    let (dialer_key, listener_key) = agreement::agree_ephemeral(
+        ephemeral,
+        &agreement::UnparsedPublicKey::new(&X25519, theirs),
+        |shared| {
+            let prk = Salt::new(HKDF_SHA256, transcript.as_ref()).extract(shared);
+            let key = |info: &[u8]| {
+                prk.expand(&[info], &CHACHA20_POLY1305)
+                    .map(|okm| LessSafeKey::new(UnboundKey::from(okm)))
+            };
+            (key(b"dialer"), key(b"listener"))
+        },
+    )
+    .map_err(|_| "key agreement failed")?;
+    let dialer_key = dialer_key.map_err(|_| "key derivation failed")?;
+    let listener_key = listener_key.map_err(|_| "key derivation failed")?;
+    let (send_key, recv_key) = if dialer {
+        (dialer_key, listener_key)
+    } else {
+        (listener_key, dialer_key)
+    };
+    Ok((
+        Channel {
+            stream,
+            send_key,
+            recv_key,
+            sent: 0,
+            received: 0,
+        },
+        peer_key.to_vec(),
+    ))
+}
+
+/// Offer `projects`, send what the peer asks for and return its report.
+fn send_projects(
+    app: &AppHandle,
+    channel: &mut Channel,
+    peer: &str,
+    projects: &[String],
+    scope: Option<Scope>,
+) -> Result<SyncReport, String> {
+    let profile = app.state::<ProfileState>().current();
+    let mut manifest = Manifest::new();
+    for project in projects {
+        if project_dir(&profile, project)?.is_dir() {
+            manifest.insert(project.clone(), project_files(&profile, project)?);
+        }
+    }
+    channel.send(&Message::Manifest {
+        projects: manifest.clone(),
+    })?;
+    let files = match channel.expect()? {
+        Message::Want { files } => files,
+        Message::Error { message } => return Err(message),
+        _ => return Err("unexpected message from peer".into()),
+    };
+
+    let mut entries = Vec::new();
+    for (project, path) in &files {
+        let entry = manifest
+            .get(project)
+            .and_then(|f| f.get(path))
+            .ok_or("peer asked for a file that was not offered")?;
+        entries.push((inside(&project_dir(&profile, project)?, path)?, entry.size));
+    }
+    let total = entries.iter().map(|(_, size)| size).sum();
+    let mut job = Job::start(app, "sync-send").within(scope);
+    let mut done = 0;
+    let mut buf = vec![0u8; CHUNK];
+    for (path, _) in entries {
+        let mut file = File::open(&path).map_err(|e| e.to_string())?;
+        loop {
+            job.check()?;
+            let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            crate::bandwidth::throttle(app, Subsystem::Sync, n as u64);
+            channel.write(&buf[..n])?;
+            done += n as u64;
+            job.progress(done, total);
+        }
+        channel.write(&[])?;
+    }
+    match channel.expect()? {
+        Message::Done { report, synced } => {
+            record_synced(app, peer, &synced)?;
+            Ok(report)
+        }
+        Message::Error { message } => Err(message),
+        _ => Err("unexpected message from peer".into()),
+    }
+}
+
+/// Take the peer's offer, fetch the files that changed only on its side
+/// (or everywhere, with `overwrite`), and report back. Deleted files are
+/// not propagated.
+fn receive_projects(
+    app: &AppHandle,
+    channel: &mut Channel,
+    peer: &str,
+    overwrite: bool,
+    scope: Option<Scope>,
+) -> Result<SyncReport, String> {
+    let offered = match channel.expect()? {
+        Message::Manifest { projects } => projects,
+        Message::Error { message } => return Err(message),
+        _ => return Err("unexpected message from peer".into()),
+    };
+    let profile = app.state::<ProfileState>().current();
+    let base = load(&profile).base.remove(peer).unwrap_or_default();
+    let mut report = SyncReport::default();
+    let mut want = Vec::new();
+    let mut synced = Vec::new();
+    for (project, files) in &offered {
+        let local = project_files(&profile, project)?;
+        let dir = project_dir(&profile, project)?;
+        for (path, entry) in files {
+            inside(&dir, path)?;
+            let ours = local.get(path).map(|e| e.hash.as_str());
+            let common = base
+                .get(project)
+                .and_then(|b| b.get(path))
+                .map(String::as_str);
+            match ours {
+                Some(hash) if hash == entry.hash => {
+                    report.unchanged += 1;
+                    synced.push((project.clone(), path.clone(), entry.hash.clone()));
+                }
+                None => want.push((project.clone(), path.clone())),
+                Some(hash) if common == Some(hash) => want.push((project.clone(), path.clone())),
+                Some(_) if common == Some(entry.hash.as_str()) => report.kept_newer += 1,
+                Some(_) if overwrite => want.push((project.clone(), path.clone())),
+                Some(_) => report.conflicts.push(Conflict {
+                    project: project.clone(),
+                    path: path.clone(),
+                }),
+            }
+        }
+    }
+    channel.send(&Message::Want {
+        files: want.clone(),
+    })?;
+
+    let total = want.iter().map(|(p, f)| offered[p][f].size).sum();
+    let mut job = Job::start(app, "sync-receive").within(scope);
+    let mut done = 0;
+    for (project, path) in want {
+        let entry = &offered[&project][&path];
+        let dest = inside(&project_dir(&profile, &project)?, &path)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let name = dest.file_name().unwrap_or_default().to_string_lossy();
+        let temp = dest.with_file_name(format!("{name}.sync.tmp"));
+        let mut file = File::create(&temp).map_err(|e| e.to_string())?;
+        let mut hasher = blake3::Hasher::new();
+        let mut size = 0;
+        loop {
+            job.check()?;
+            let chunk = channel.read()?;
+            if chunk.is_empty() {
+                break;
+            }
+            crate::bandwidth::throttle(app, Subsystem::Sync, chunk.len() as u64);
+            hasher.update(&chunk);
+            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            size += chunk.len() as u64;
+            done += chunk.len() as u64;
+            job.progress(done, total);
+        }
+        drop(file);
+        if hasher.finalize().to_hex().as_str() != entry.hash {
+            let _ = fs::remove_file(&temp);
+            report.interrupted += 1;
+            continue;
+        }
+        fs::rename(&temp, &dest).map_err(|e| e.to_string())?;
+        report.received_files += 1;
+        report.received_bytes += size;
+        synced.push((project, path, entry.hash.clone()));
+    }
+    record_synced(app, peer, &synced)?;
+    channel.send(&Message::Done {
+        report: report.clone(),
+        synced,
+    })?;
+    Ok(report)
+}
+
+fn serve(app: &AppHandle, device: &Ed25519KeyPair, stream: TcpStream) -> Result<(), String> {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let (mut channel, key) = handshake(stream, device, false)?;
+    let peer = URL_SAFE_NO_PAD.encode(key);
+    // Untrusted devices get nothing past the handshake.
+    if !is_trusted(app, &peer) {
+        return Err("connection from an untrusted device".into());
+    }
+    // The peer can't be kept waiting past `IO_TIMEOUT`, so it is told to
+    // come back later instead.
+    if crate::bandwidth::holding_back(app) {
+        let message = "the device is on a metered connection; try again later".to_string();
+        let _ = channel.send(&Message::Error {
+            message: message.clone(),
+        });
+        return Err(message);
+    }
+    let outcome = match channel.expect()? {
+        Message::Pull { projects } => {
+            send_projects(app, &mut channel, &peer, &projects, None).map(|r| ("sent", r))
+        }
+        Message::Push => {
+            receive_projects(app, &mut channel, &peer, false, None).map(|r| ("received", r))
+        }
+        _ => Err("unexpected message from peer".into()),
+    };
+    match outcome {
+        Ok((direction, report)) => {
+            let _ = app.emit(
+                "spectrus://sync-finished",
+                SyncFinished {
+                    peer: &peer,
+                    direction,
+                    report: &report,
+                },
+            );
+            Ok(())
+        }
+        Err(message) => {
+            let _ = channel.send(&Message::Error {
+                message: message.clone(),
+            });
+            Err(message)
+        }
+    }
+}
+
+/// Accept sync connections on a free port of the LAN interface and
+/// advertise it through discovery, serving up to `MAX_PEERS` at once. Only
+/// trusted devices get past the handshake, which uses the active account's
+/// device key; a finished exchange emits `spectrus://sync-finished`.
+pub fn start(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    if !crate::discovery::enabled(&profile) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let device = match crate::deep_link::device_key(&app) {
+            Ok(device) => device,
+            Err(e) => return eprintln!("sync: no device key: {e}"),
+        };
+        let listener = match TcpListener::bind((crate::handoff::lan_ip(), 0)) {
+            Ok(listener) => listener,
+            Err(e) => return eprintln!("sync: cannot listen: {e}"),
+        };
+        let Ok(port) = listener.local_addr().map(|a| a.port()) else {
+            return;
+        };
+        let key = URL_SAFE_NO_PAD.encode(device.public_key().as_ref());
+        crate::discovery::set_sync(&app, port, key);
+        let serving = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming().flatten() {
+            // Dropping the stream closes it; the peer retries later.
+            if serving.fetch_add(1, Ordering::SeqCst) >= MAX_PEERS {
+                serving.fetch_sub(1, Ordering::SeqCst);
+                eprintln!("sync: turned a connection away; {MAX_PEERS} peers are being served");
+                continue;
+            }
+            let app = app.clone();
+            let serving = serving.clone();
+            std::thread::spawn(move || {
+                let served = crate::deep_link::device_key(&app)
+                    .and_then(|device| serve(&app, &device, stream));
+                serving.fetch_sub(1, Ordering::SeqCst);
+                if let Err(e) = served {
+                    eprintln!("sync: {e}");
+                }
+            });
+        }
+    });
+}
+
+/// Stop exchanges under way and advertise the device key of the account
+/// now active. The key lives in the account's keychain namespace, so peers
+/// see a different device afterwards.
+pub(crate) fn account_changed(app: &AppHandle) {
+    app.state::<crate::jobs::Jobs>()
+        .cancel_kinds(&["sync-send", "sync-receive"]);
+    match crate::deep_link::device_key(app) {
+        Ok(device) => {
+            let key = URL_SAFE_NO_PAD.encode(device.public_key().as_ref());
+            crate::discovery::set_key(app, key);
+        }
+        Err(e) => eprintln!("sync: no device key: {e}"),
+    }
+}
+
+/// Connect to a discovered, trusted peer and authenticate both ends.
+fn dial(app: &AppHandle, peer_id: &str) -> Result<(Channel, String), String> {
+    let peer = crate::discovery::peer_by_id(app, peer_id).ok_or("peer is not on the network")?;
+    let (Some(port), Some(key)) = (peer.sync_port, peer.key) else {
+        return Err("peer does not accept sync".into());
+    };
+    if !is_trusted(app, &key) {
+        return Err(format!("{} is not a trusted device", peer.name));
+    }
+    let stream = peer
+        .addresses
+        .iter()
+        .find_map(|ip| {
+            TcpStream::connect_timeout(&SocketAddr::new(*ip, port), CONNECT_TIMEOUT).ok()
+        })
+        .ok_or_else(|| format!("cannot reach {}", peer.name))?;
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let device = crate::deep_link::device_key(app)?;
+    let (channel, presented) = handshake(stream, &device, true)
+        .map_err(|e| format!("{e}; is this device trusted on {}?", peer.name))?;
+    if URL_SAFE_NO_PAD.encode(presented) != key {
+        return Err(format!("{} presented a different device key", peer.name));
+    }
+    Ok((channel, key))
+}
+
+/// This device's base64url public key, to compare with what a peer shows
+/// before trusting each other.
+#[tauri::command]
+pub fn sync_identity(app: AppHandle) -> Result<String, String> {
+    let device = crate::deep_link::device_key(&app)?;
+    Ok(URL_SAFE_NO_PAD.encode(device.public_key().as_ref()))
+}
+
+/// Allow the device with `key` (as advertised by a peer) to sync with this
+/// profile. Both sides must trust each other.
+#[tauri::command]
+pub fn sync_trust(app: AppHandle, key: String, name: String) -> Result<(), String> {
+    let decoded = URL_SAFE_NO_PAD
+        .decode(&key)
+        .map_err(|_| "malformed device key")?;
+    if decoded.len() != 32 {
+        return Err("malformed device key".into());
+    }
+    let added_at = crate::time::now_ms(&app);
+    update(&app, |store| {
+        store.trusted.retain(|t| t.key != key);
+        store.trusted.push(TrustedPeer {
+            key,
+            name,
+            added_at,
+        });
+    })
+}
+
+/// Stop syncing with a device and forget what was last synced with it.
+#[tauri::command]
+pub fn sync_untrust(app: AppHandle, key: String) -> Result<(), String> {
+    update(&app, |store| {
+        store.trusted.retain(|t| t.key != key);
+        store.base.remove(&key);
+    })
+}
+
+/// Devices this profile syncs with.
+#[tauri::command]
+pub fn sync_trusted(profiles: State<'_, ProfileState>) -> Vec<TrustedPeer> {
+    load(&profiles.current()).trusted
+}
+
+/// Fetch `projects` from the peer with discovery id `peer`. Files changed
+/// on both sides since the last sync are reported as conflicts and left
+/// alone unless `overwrite` is set. Progress comes as a "sync-receive" job.
+/// Held back while the connection is metered (see `bandwidth`).
+#[tauri::command]
+pub async fn sync_pull(
+    app: AppHandle,
+    call: Call,
+    peer: String,
+    projects: Vec<String>,
+    overwrite: Option<bool>,
+) -> Result<SyncReport, String> {
+    let call = call.scope();
+    crate::bandwidth::defer_while_metered(&app, Subsystem::Sync, || call.check()).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (mut channel, key) = dial(&app, &peer)?;
+        channel.send(&Message::Pull { projects })?;
+        receive_projects(
+            &app,
+            &mut channel,
+            &key,
+            overwrite.unwrap_or(false),
+            Some(call),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Send `projects` to the peer with discovery id `peer` and return what it
+/// took. The peer never overwrites its own changes; those come back as
+/// conflicts. Progress comes as a "sync-send" job. Held back while the
+/// connection is metered.
+#[tauri::command]
+pub async fn sync_push(
+    app: AppHandle,
+    call: Call,
+    peer: String,
+    projects: Vec<String>,
+) -> Result<SyncReport, String> {
+    let call = call.scope();
+    crate::bandwidth::defer_while_metered(&app, Subsystem::Sync, || call.check()).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (mut channel, key) = dial(&app, &peer)?;
+        channel.send(&Message::Push)?;
+        send_projects(&app, &mut channel, &key, &projects, Some(call))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::portable::Portable;
+use crate::profile::ProfileState;
+
+#[derive(Serialize)]
+pub struct SystemInfo {
+    os: String,
+    os_version: String,
+    arch: &'static str,
+    /// `XDG_CURRENT_DESKTOP`, e.g. "GNOME" or "KDE". Linux only.
+    desktop: Option<String>,
+    /// "x11" or "wayland". Linux only.
+    session_type: Option<String>,
+    webview: Webview,
+    app_version: String,
+    /// "stable", or the pre-release tag of the version ("beta", "nightly").
+    channel: String,
+    portable: bool,
+    capabilities: Capabilities,
+}
+
+#[derive(Serialize)]
+struct Webview {
+    engine: &'static str,
+    /// `None` if the runtime could not be queried (e.g. WebView2 missing).
+    version: Option<String>,
+}
+
+/// What this build can do on this machine.
+#[derive(Serialize)]
+struct Capabilities {
+    /// Where `keychain_*` stores secrets: "keychain", "credential-manager",
+    /// "secret-service", "file" (portable) or "memory" (incognito).
+    keychain: &'static str,
+    /// `false` on Linux when no Secret Service is running.
+    keychain_available: bool,
+    /// Native notifications with actions (`linux_notify`).
+    notifications: bool,
+    /// The native share sheet (`share`).
+    share_sheet: bool,
+    /// `screensaver_inhibit`.
+    screensaver_inhibit: bool,
+}
+
+const WEBVIEW_ENGINE: &str = if cfg!(windows) {
+    "WebView2"
+} else if cfg!(target_os = "macos") {
+    "WKWebView"
+} else {
+    "WebKitGTK"
+};
+
+fn channel(pre: &str) -> String {
+    match pre {
+        "" => "stable".into(),
+        pre => pre.split('.').next().unwrap_or(pre).to_string(),
+    }
+}
+
+fn os_keychain() -> &'static str {
+    if cfg!(windows) {
+        "credential-manager"
+    } else if cfg!(target_os = "macos") {
+        "keychain"
+    } else {
+        "secret-service"
+    }
+}
+
+fn capabilities(app: &AppHandle, keychain: &'static str) -> Capabilities {
+    #[cfg(target_os = "linux")]
+    {
+        use tauri::Manager;
+        // Incognito sessions don't connect to the bus.
+        let dbus = app.try_state::<crate::linux_dbus::DbusState>();
+        let has = |name: &str| dbus.as_ref().is_some_and(|d| d.has_owner(name));
+        Capabilities {
+            keychain,
+            keychain_available: keychain != "secret-service" || has("org.freedesktop.secrets"),
+            notifications: has("org.freedesktop.Notifications"),
+            share_sheet: false,
+            screensaver_inhibit: has("org.freedesktop.ScreenSaver"),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = app;
+        Capabilities {
+            keychain,
+            keychain_available: true,
+            notifications: false,
+            share_sheet: cfg!(target_os = "macos"),
+            screensaver_inhibit: false,
+        }
+    }
+}
+
+/// Environment and capability report for the About screen and bug reports.
+#[tauri::command]
+pub fn system_info(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    portable: State<'_, Portable>,
+) -> SystemInfo {
+    let os = os_info::get();
+    let linux_env = |name: &str| {
+        cfg!(target_os = "linux")
+            .then(|| std::env::var(name).ok())
+            .flatten()
+            .filter(|v| !v.is_empty())
+    };
+    let keychain = if profiles.current().ephemeral {
+        "memory"
+    } else if portable.0.is_some() {
+        "file"
+    } else {
+        os_keychain()
+    };
+    let version = &app.package_info().version;
+    SystemInfo {
+        os: os.os_type().to_string(),
+        os_version: os.version().to_string(),
+        arch: std::env::consts::ARCH,
+        desktop: linux_env("XDG_CURRENT_DESKTOP"),
+        session_type: linux_env("XDG_SESSION_TYPE"),
+        webview: Webview {
+            engine: WEBVIEW_ENGINE,
+            version: tauri::webview_version().ok(),
+        },
+        app_version: version.to_string(),
+        channel: channel(version.pre.as_str()),
+        portable: portable.0.is_some(),
+        capabilities: capabilities(&app, keychain),
+    }
+}
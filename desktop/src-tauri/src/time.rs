@@ -0,0 +1,229 @@
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+
+use crate::remote_assets::RemoteAssets;
+
+/// Queried in order; the first answer wins.
+const NTP_SERVERS: [&str; 2] = ["time.cloudflare.com:123", "pool.ntp.org:123"];
+const NTP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch.
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+const RECHECK: Duration = Duration::from_secs(30 * 60);
+
+/// Offset beyond which the clock is reported as wrong. Access tokens and
+/// report timestamps tolerate less than this.
+const SKEW_WARNING_MS: i64 = 60_000;
+
+/// Result of one comparison against a reference clock.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSync {
+    /// Reference time minus local time.
+    offset_ms: i64,
+    /// NTP server or API origin the offset came from.
+    source: String,
+    round_trip_ms: u64,
+    skewed: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedNow {
+    /// Milliseconds since the Unix epoch, corrected by the last offset.
+    now: i64,
+    offset_ms: i64,
+    /// `None` until a reference clock has answered; `now` is then the
+    /// local clock.
+    source: Option<String>,
+}
+
+/// Managed state: the last successful comparison.
+#[derive(Default)]
+pub struct TrustedTime {
+    last: Mutex<Option<ClockSync>>,
+}
+
+impl TrustedTime {
+    fn offset_ms(&self) -> i64 {
+        self.last
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |s| s.offset_ms)
+    }
+}
+
+fn local_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// Corrected current time in Unix milliseconds, for code that stamps or
+/// checks expiry.
+pub fn now_ms(app: &AppHandle) -> i64 {
+    local_ms() + app.state::<TrustedTime>().offset_ms()
+}
+
+/// NTP timestamp (32.32 fixed point seconds since 1900) in Unix ms.
+fn from_ntp(bytes: &[u8]) -> i64 {
+    let secs = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as i64;
+    let frac = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as i64;
+    (secs - NTP_UNIX_OFFSET) * 1000 + ((frac * 1000) >> 32)
+}
+
+fn to_ntp(ms: i64) -> [u8; 8] {
+    let secs = (ms.div_euclid(1000) + NTP_UNIX_OFFSET) as u32;
+    let frac = ((ms.rem_euclid(1000) << 32) / 1000) as u32;
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&secs.to_be_bytes());
+    out[4..].copy_from_slice(&frac.to_be_bytes());
+    out
+}
+
+/// One SNTP exchange (RFC 4330), returning (offset, round trip).
+fn ntp(server: &str) -> Result<(i64, u64), String> {
+    let addr = server
+        .to_socket_addrs()
+        .map_err(|e| format!("{server}: {e}"))?
+        .next()
+        .ok_or_else(|| format!("{server}: no address"))?;
+    let bind = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+    socket
+        .set_read_timeout(Some(NTP_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+
+    // Version 4, client mode. Our transmit time comes back as the
+    // originate time, which ties the answer to this request.
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    let sent = local_ms();
+    let stamp = to_ntp(sent);
+    request[40..].copy_from_slice(&stamp);
+    socket.send_to(&request, addr).map_err(|e| e.to_string())?;
+    let mut reply = [0u8; 48];
+    let (n, from) = socket
+        .recv_from(&mut reply)
+        .map_err(|e| format!("{server}: {e}"))?;
+    let received = local_ms();
+    if n < 48 || from != addr || reply[24..32] != stamp {
+        return Err(format!("{server}: unexpected reply"));
+    }
+    // Mode 4 is a server answer; stratum 0 is a "kiss of death".
+    if reply[0] & 0x07 != 4 || reply[1] == 0 {
+        return Err(format!("{server}: refused"));
+    }
+    let server_received = from_ntp(&reply[32..40]);
+    let server_sent = from_ntp(&reply[40..48]);
+    let offset = ((server_received - sent) + (server_sent - received)) / 2;
+    let round_trip = (received - sent) - (server_sent - server_received);
+    Ok((offset, round_trip.max(0) as u64))
+}
+
+/// Fall back to the `Date` header of our own API when NTP is blocked, as it
+/// often is on corporate networks. It only has second resolution.
+async fn api(app: &AppHandle) -> Result<(i64, u64, String), String> {
+    let assets = app.state::<RemoteAssets>();
+    let client = assets.client();
+    for base in assets.servers() {
+        let started = Instant::now();
+        let sent = local_ms();
+        let Ok(response) = client.head(base.clone()).send().await else {
+            continue;
+        };
+        let round_trip = started.elapsed().as_millis() as u64;
+        let Some(date) = response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|d| d.to_str().ok())
+        else {
+            continue;
+        };
+        let Ok(server) = jiff::fmt::rfc2822::DateTimeParser::new().parse_timestamp(date) else {
+            continue;
+        };
+        // The header is truncated to the second; assume the middle of it.
+        let server = server.as_millisecond() + 500;
+        let offset = server - (sent + round_trip as i64 / 2);
+        return Ok((offset, round_trip, base.origin().ascii_serialization()));
+    }
+    Err("no reference clock reachable".into())
+}
+
+/// Compare against NTP, else the API, and remember the result. Emits
+/// `spectrus://clock-skew` with the `ClockSync` whenever the clock goes
+/// from fine to skewed or back.
+async fn check(app: &AppHandle) -> Result<ClockSync, String> {
+    let from_ntp = tauri::async_runtime::spawn_blocking(|| {
+        NTP_SERVERS
+            .iter()
+            .find_map(|s| ntp(s).ok().map(|(o, rtt)| (o, rtt, s.to_string())))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let (offset_ms, round_trip_ms, source) = match from_ntp {
+        Some(result) => result,
+        None => api(app).await?,
+    };
+    let sync = ClockSync {
+        offset_ms,
+        source,
+        round_trip_ms,
+        skewed: offset_ms.abs() >= SKEW_WARNING_MS,
+    };
+    let state = app.state::<TrustedTime>();
+    let was_skewed = state
+        .last
+        .lock()
+        .unwrap()
+        .replace(sync.clone())
+        .is_some_and(|s| s.skewed);
+    if sync.skewed != was_skewed {
+        let _ = app.emit("spectrus://clock-skew", &sync);
+    }
+    Ok(sync)
+}
+
+/// Check the clock now, every `RECHECK`, and after the machine wakes up
+/// (sleep is when clocks without network time drift the most).
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen("spectrus://system-resume", move |_| {
+        let app = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = check(&app).await {
+                eprintln!("time: {e}");
+            }
+        });
+    });
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        if let Err(e) = tauri::async_runtime::block_on(check(&app)) {
+            eprintln!("time: {e}");
+        }
+        std::thread::sleep(RECHECK);
+    });
+}
+
+/// Current time corrected for the local clock's measured offset.
+#[tauri::command]
+pub fn trusted_now(time: State<'_, TrustedTime>) -> TrustedNow {
+    let last = time.last.lock().unwrap().clone();
+    let offset_ms = last.as_ref().map_or(0, |s| s.offset_ms);
+    TrustedNow {
+        now: local_ms() + offset_ms,
+        offset_ms,
+        source: last.map(|s| s.source),
+    }
+}
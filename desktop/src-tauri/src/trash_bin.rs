@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, State, Window};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::fs_scope::FsScope;
+
+/// Paths moved to the trash by the most recent `file_trash` call.
+#[derive(Default)]
+pub struct TrashState(Mutex<Vec<PathBuf>>);
+
+/// Outcome of `file_trash`, per input path.
+#[derive(Serialize, Default)]
+pub struct TrashResult {
+    trashed: Vec<PathBuf>,
+    /// Permanently deleted after the user confirmed, because they couldn't be
+    /// moved to a trash (network shares, volumes without a trash folder).
+    deleted: Vec<PathBuf>,
+    /// Left in place because the user declined permanent deletion.
+    kept: Vec<PathBuf>,
+}
+
+fn confirm_permanent_delete(app: &AppHandle, count: usize) -> bool {
+    let what = if count == 1 {
+        "This item".to_string()
+    } else {
+        format!("{count} items")
+    };
+    app.dialog()
+        .message(format!(
+            "{what} can't be moved to the Trash. Delete permanently? This can't be undone."
+        ))
+        .title("Delete permanently?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Delete".into(),
+            "Cancel".into(),
+        ))
+        .blocking_show()
+}
+
+/// Move files or directories to the OS trash. Anything that can't be trashed
+/// is only deleted after a native confirmation prompt.
+#[tauri::command]
+pub async fn file_trash(
+    app: AppHandle,
+    window: Window,
+    scope: State<'_, FsScope>,
+    last: State<'_, TrashState>,
+    paths: Vec<String>,
+) -> Result<TrashResult, String> {
+    let paths = paths
+        .iter()
+        .map(|p| scope.check(window.label(), p))
+        .collect::<Result<Vec<_>, _>>()?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut result = TrashResult::default();
+        let mut untrashable = Vec::new();
+        for path in paths {
+            match trash::delete(&path) {
+                Ok(()) => result.trashed.push(path),
+                Err(e) => {
+                    eprintln!("trash: {}: {e}", path.display());
+                    untrashable.push(path);
+                }
+            }
+        }
+        if untrashable.is_empty() {
+            return Ok(result);
+        }
+        if !confirm_permanent_delete(&app, untrashable.len()) {
+            result.kept = untrashable;
+            return Ok(result);
+        }
+        for path in untrashable {
+            let removed = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            removed.map_err(|e| format!("{}: {e}", path.display()))?;
+            result.deleted.push(path);
+        }
+        Ok::<_, String>(result)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if !result.trashed.is_empty() {
+        *last.0.lock().unwrap() = result.trashed.clone();
+    }
+    Ok(result)
+}
+
+/// Put back everything the last `file_trash` call moved to the trash.
+/// Returns the restored paths. Not available on macOS, where Finder owns the
+/// Trash; users restore from there with "Put Back".
+#[tauri::command]
+pub async fn trash_restore_last(last: State<'_, TrashState>) -> Result<Vec<PathBuf>, String> {
+    let wanted = last.0.lock().unwrap().clone();
+    if wanted.is_empty() {
+        return Ok(wanted);
+    }
+    let restored = tauri::async_runtime::spawn_blocking(move || restore(wanted))
+        .await
+        .map_err(|e| e.to_string())??;
+    last.0.lock().unwrap().clear();
+    Ok(restored)
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn restore(wanted: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    use trash::os_limited;
+
+    // Several trashed items can share an original path; the newest one is
+    // the copy this session put there.
+    let mut items = os_limited::list().map_err(|e| e.to_string())?;
+    items.sort_by_key(|i| std::cmp::Reverse(i.time_deleted));
+    let mut picked = Vec::new();
+    for path in &wanted {
+        if let Some(item) = items.iter().find(|i| &i.original_path() == path) {
+            picked.push(item.clone());
+        }
+    }
+    let restored = picked.iter().map(|i| i.original_path()).collect();
+    os_limited::restore_all(picked).map_err(|e| e.to_string())?;
+    Ok(restored)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn restore(_wanted: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    Err("restore the items from the Trash in Finder with \"Put Back\"".into())
+}
@@ -0,0 +1,139 @@
+use tauri::menu::{
+    CheckMenuItem, IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu,
+};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::i18n::text;
+use crate::profile::{self, ProfileState};
+use crate::recent;
+
+const TRAY_ID: &str = "main";
+
+/// Menu ids for profile entries are `profile:<name>`.
+const PROFILE_PREFIX: &str = "profile:";
+
+/// Menu ids for recent files are `recent:<index into recent::list>`.
+const RECENT_PREFIX: &str = "recent:";
+
+/// Create the tray icon and its menu.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Spectrus")
+        .menu(&build_menu(app)?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(on_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu after profiles are created, deleted, or switched.
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = build_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        eprintln!("tray: menu refresh failed: {e}");
+    }
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let profiles = app.state::<ProfileState>();
+    let current = profiles.current().name;
+    let items = profiles
+        .names()
+        .into_iter()
+        .map(|name| {
+            CheckMenuItem::with_id(
+                app,
+                format!("{PROFILE_PREFIX}{name}"),
+                &name,
+                true,
+                name == current,
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let items: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|i| i as _).collect();
+    let profiles_menu = Submenu::with_items(app, text(app, "tray.profiles"), true, &items)?;
+
+    let recent = recent::list(&profiles.current())
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let label = entry
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| entry.path.display().to_string());
+            MenuItem::with_id(
+                app,
+                format!("{RECENT_PREFIX}{i}"),
+                label,
+                true,
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let recent: Vec<&dyn IsMenuItem<Wry>> = recent.iter().map(|i| i as _).collect();
+    let recent_menu =
+        Submenu::with_items(app, text(app, "tray.recent"), !recent.is_empty(), &recent)?;
+
+    Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(app, "show", text(app, "tray.show"), true, None::<&str>)?,
+            &MenuItem::with_id(
+                app,
+                "capture",
+                text(app, "tray.capture"),
+                true,
+                None::<&str>,
+            )?,
+            &recent_menu,
+            &profiles_menu,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "quit", text(app, "tray.quit"), true, None::<&str>)?,
+        ],
+    )
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        "show" => show_main(app),
+        "capture" => crate::capture::toggle(app),
+        "quit" => app.exit(0),
+        id => {
+            if let Some(index) = id.strip_prefix(RECENT_PREFIX) {
+                let profile = app.state::<ProfileState>().current();
+                let entry = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| recent::list(&profile).into_iter().nth(i));
+                if let Some(entry) = entry {
+                    show_main(app);
+                    recent::open(app, "main", &entry.path);
+                }
+                return;
+            }
+            if let Some(name) = id.strip_prefix(PROFILE_PREFIX) {
+                let profiles = app.state::<ProfileState>();
+                if let Err(e) = profile::switch_to(app, &profiles, name) {
+                    eprintln!("tray: profile switch failed: {e}");
+                    // Restore the check marks to reflect the unchanged profile.
+                    refresh(app);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn show_main(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
@@ -0,0 +1,482 @@
+use std::fs;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use url::Url;
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::Call;
+
+/// Most of a page that is read; the metadata lives in `<head>`.
+const MAX_HTML: usize = 512 * 1024;
+const MAX_IMAGE: usize = 1024 * 1024;
+const MAX_ICON: usize = 64 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+
+/// How long a preview is served from the cache before it is fetched again.
+const CACHE_TTL: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPreview {
+    /// Where the link ended up after redirects.
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    site_name: Option<String>,
+    /// The preview image and favicon as `data:` URLs, so the renderer
+    /// never contacts the site itself.
+    image: Option<String>,
+    favicon: Option<String>,
+    fetched_at: u64,
+}
+
+/// Resolver that drops loopback, private and link-local addresses, so a
+/// public name pointing inside the network can't be used to reach it.
+struct PublicOnly;
+
+impl Resolve for PublicOnly {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolve_public(host).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Managed state: the HTTP client used for previews, through the policy's
+/// proxy when there is one. It follows no redirects by itself; `fetch`
+/// checks every hop.
+pub struct Unfurl {
+    client: reqwest::Client,
+}
+
+impl Unfurl {
+    pub fn new() -> Self {
+        crate::remote_assets::install_crypto_provider();
+        let client = crate::policy::current()
+            .client_builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(TIMEOUT)
+            .user_agent(concat!(
+                "Spectrus/",
+                env!("CARGO_PKG_VERSION"),
+                " (link preview)"
+            ))
+            .dns_resolver(PublicOnly)
+            .build()
+            .expect("link preview HTTP client");
+        Self { client }
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT and benchmarking ranges.
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || first == 0x2001 && v6.segments()[1] == 0x0db8)
+        }
+    }
+}
+
+async fn resolve_public(host: String) -> Result<Vec<SocketAddr>, String> {
+    let lookup = host.clone();
+    let addrs: Vec<SocketAddr> =
+        tauri::async_runtime::spawn_blocking(move || (lookup.as_str(), 0).to_socket_addrs())
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("{host}: {e}"))?
+            .collect();
+    if addrs.is_empty() || !addrs.iter().all(|a| is_public(a.ip())) {
+        return Err(format!("{host} is not a public address"));
+    }
+    Ok(addrs)
+}
+
+/// Only http(s) on the default ports to hosts that resolve to public
+/// addresses. Names are also checked here because requests through a proxy
+/// bypass `PublicOnly`.
+async fn check(url: &Url, allowed: &[String]) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{url}: only http and https links are previewed"));
+    }
+    if url.port().is_some_and(|p| p != 80 && p != 443) {
+        return Err(format!("{url}: non-standard port"));
+    }
+    let host = url.host_str().ok_or_else(|| format!("{url}: no host"))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return if is_public(ip) {
+            Ok(())
+        } else {
+            Err(format!("{host} is not a public address"))
+        };
+    }
+    let lower = host.to_ascii_lowercase();
+    if lower == "localhost" || lower.ends_with(".localhost") || lower.ends_with(".local") {
+        return Err(format!("{host} is not a public address"));
+    }
+    if !allowed.is_empty()
+        && !allowed
+            .iter()
+            .any(|d| lower == *d || lower.ends_with(&format!(".{d}")))
+    {
+        return Err(format!("{host} is not on the link preview allowlist"));
+    }
+    resolve_public(lower).await.map(drop)
+}
+
+/// Optional `unfurl.allowedDomains` setting: when non-empty, only these
+/// domains and their subdomains are fetched.
+fn allowlist(profile: &Profile) -> Vec<String> {
+    settings::get(profile, "unfurl.allowedDomains")
+        .and_then(|v| serde_json::from_value::<Vec<String>>(v).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|d| d.trim_start_matches('.').to_ascii_lowercase())
+        .collect()
+}
+
+/// GET `url`, following redirects after checking each target, and read at
+/// most `limit` bytes of the body (the rest is dropped, not an error).
+async fn fetch(
+    unfurl: &Unfurl,
+    allowed: &[String],
+    mut url: Url,
+    accept: &str,
+    limit: usize,
+) -> Result<(Url, String, Vec<u8>), String> {
+    for _ in 0..=MAX_REDIRECTS {
+        check(&url, allowed).await?;
+        let mut response = unfurl
+            .client
+            .get(url.clone())
+            .header(header::ACCEPT, accept)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .ok_or("redirect without a location")?;
+            url = url.join(location).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return Err(format!("{url}: HTTP {}", response.status()));
+        }
+        let mime = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|t| t.to_str().ok())
+            .and_then(|t| t.split(';').next())
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= limit {
+                body.truncate(limit);
+                break;
+            }
+        }
+        return Ok((url, mime, body));
+    }
+    Err(format!("{url}: too many redirects"))
+}
+
+/// The image at `url` as a `data:` URL, or `None` if it can't be had
+/// within `limit`.
+async fn data_url(unfurl: &Unfurl, allowed: &[String], url: Url, limit: usize) -> Option<String> {
+    let (_, mime, body) = fetch(unfurl, allowed, url, "image/*", limit + 1)
+        .await
+        .ok()?;
+    if !mime.starts_with("image/") || body.is_empty() || body.len() > limit {
+        return None;
+    }
+    let data = base64::engine::general_purpose::STANDARD.encode(body);
+    Some(format!("data:{mime};base64,{data}"))
+}
+
+/// Undo the entity escapes that show up in titles and descriptions.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.bytes().take(12).position(|b| b == b';') else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Attributes of a start tag body such as ` property="og:title" content=x`.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace() || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (v, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let inner = &after[1..];
+                    let end = inner.find(q).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = unescape(v);
+            rest = remaining.trim_start();
+        } else if name.is_empty() {
+            rest = rest.get(1..).unwrap_or("").trim_start();
+        }
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+    }
+    attrs
+}
+
+#[derive(Default)]
+struct Meta {
+    title: Option<String>,
+    og_title: Option<String>,
+    description: Option<String>,
+    og_description: Option<String>,
+    site_name: Option<String>,
+    image: Option<String>,
+    icon: Option<String>,
+}
+
+/// Pull the preview fields out of a page's `<head>`. This is a tag scanner,
+/// not an HTML parser; it only has to cope with `<meta>`, `<link>` and
+/// `<title>`.
+fn parse(html: &str) -> Meta {
+    let mut meta = Meta::default();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..close];
+        rest = &rest[close + 1..];
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        match name.as_str() {
+            "title" if meta.title.is_none() => {
+                let end = rest.to_ascii_lowercase().find("</title").unwrap_or(0);
+                meta.title = Some(unescape(&rest[..end])).filter(|t| !t.is_empty());
+            }
+            "meta" => {
+                let attrs = attributes(&tag[name_end..]);
+                let get = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+                let Some(content) = get("content").filter(|c| !c.is_empty()).cloned() else {
+                    continue;
+                };
+                let key = get("property")
+                    .or_else(|| get("name"))
+                    .map(|k| k.to_ascii_lowercase())
+                    .unwrap_or_default();
+                let slot = match key.as_str() {
+                    "og:title" | "twitter:title" => &mut meta.og_title,
+                    "og:description" | "twitter:description" => &mut meta.og_description,
+                    "description" => &mut meta.description,
+                    "og:site_name" => &mut meta.site_name,
+                    "og:image" | "og:image:url" | "og:image:secure_url" | "twitter:image" => {
+                        &mut meta.image
+                    }
+                    _ => continue,
+                };
+                slot.get_or_insert(content);
+            }
+            "link" => {
+                let attrs = attributes(&tag[name_end..]);
+                let get = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+                let rel = get("rel")
+                    .map(|r| r.to_ascii_lowercase())
+                    .unwrap_or_default();
+                if rel.split_whitespace().any(|r| r == "icon") {
+                    if let Some(href) = get("href") {
+                        meta.icon.get_or_insert(href.clone());
+                    }
+                }
+            }
+            "body" | "/head" => break,
+            _ => {}
+        }
+    }
+    meta
+}
+
+fn cache_path(profile: &Profile, url: &str) -> PathBuf {
+    let key = blake3::hash(url.as_bytes()).to_hex();
+    profile
+        .cache_dir()
+        .join("unfurl")
+        .join(format!("{key}.json"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+async fn build(unfurl: &Unfurl, allowed: &[String], url: Url) -> Result<LinkPreview, String> {
+    let (final_url, mime, body) = fetch(
+        unfurl,
+        allowed,
+        url,
+        "text/html,application/xhtml+xml;q=0.9,*/*;q=0.5",
+        MAX_HTML,
+    )
+    .await?;
+    let mut preview = LinkPreview {
+        url: final_url.to_string(),
+        fetched_at: now(),
+        ..Default::default()
+    };
+    if mime.starts_with("image/") {
+        // A direct image link: the image is the preview.
+        preview.title = final_url
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        preview.image = data_url(unfurl, allowed, final_url, MAX_IMAGE).await;
+        return Ok(preview);
+    }
+    if mime != "text/html" && mime != "application/xhtml+xml" {
+        return Err(format!("{final_url}: not a web page ({mime})"));
+    }
+    let meta = parse(&String::from_utf8_lossy(&body));
+    preview.title = meta.og_title.or(meta.title);
+    preview.description = meta.og_description.or(meta.description);
+    preview.site_name = meta.site_name;
+    let image = meta.image.and_then(|i| final_url.join(&i).ok());
+    let icon = meta
+        .icon
+        .and_then(|i| final_url.join(&i).ok())
+        .or_else(|| final_url.join("/favicon.ico").ok());
+    if let Some(image) = image {
+        preview.image = data_url(unfurl, allowed, image, MAX_IMAGE).await;
+    }
+    if let Some(icon) = icon {
+        preview.favicon = data_url(unfurl, allowed, icon, MAX_ICON).await;
+    }
+    Ok(preview)
+}
+
+/// Fetch title, description, image and favicon for a link. Results are
+/// cached in the profile for a day; `refresh` skips the cache. Links to
+/// loopback, private or link-local addresses are refused.
+#[tauri::command]
+pub async fn unfurl(
+    call: Call,
+    unfurl: State<'_, Unfurl>,
+    profiles: State<'_, ProfileState>,
+    url: String,
+    refresh: Option<bool>,
+) -> Result<LinkPreview, String> {
+    let parsed = Url::parse(&url).map_err(|e| format!("{url}: {e}"))?;
+    let profile = profiles.current();
+    let path = cache_path(&profile, parsed.as_str());
+    if !refresh.unwrap_or(false) {
+        let cached = fs::read(&path)
+            .ok()
+            .and_then(|b| serde_json::from_slice::<LinkPreview>(&b).ok())
+            .filter(|p| now().saturating_sub(p.fetched_at) < CACHE_TTL);
+        if let Some(preview) = cached {
+            return Ok(preview);
+        }
+    }
+    let preview = call
+        .run(build(&unfurl, &allowlist(&profile), parsed))
+        .await?;
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(json) = serde_json::to_vec(&preview) {
+        let _ = settings::write_atomic(&path, &json);
+    }
+    Ok(preview)
+}
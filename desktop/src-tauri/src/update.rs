@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, ExitRequestApi, Manager, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::bandwidth::Subsystem;
+use crate::jobs::{Job, Jobs};
+use crate::portable::Portable;
+use crate::profile::ProfileState;
+use crate::settings;
+use crate::watchdog::Call;
+
+/// Setting that opts into installing a downloaded update on quit.
+const INSTALL_ON_QUIT: &str = "updates.installOnQuit";
+
+/// How often a quit that is waiting on jobs checks again.
+const JOB_POLL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    version: String,
+    current_version: String,
+    notes: Option<String>,
+}
+
+impl From<&Update> for UpdateInfo {
+    fn from(update: &Update) -> Self {
+        Self {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            notes: update.body.clone(),
+        }
+    }
+}
+
+/// A package that has been downloaded and passed signature verification.
+struct Downloaded {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+/// Managed state: the update waiting to be installed, if any.
+#[derive(Default)]
+pub struct Updates {
+    ready: Mutex<Option<Downloaded>>,
+    /// Set once quitting has turned into installing, so the exit that
+    /// follows is let through.
+    installing: AtomicBool,
+}
+
+fn install_on_quit(app: &AppHandle) -> bool {
+    let profile = app.state::<ProfileState>().current();
+    settings::get(&profile, INSTALL_ON_QUIT)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Check for an update and download it in the background, reporting
+/// progress as an "update-download" job. Returns `None` when up to date.
+/// Emits `spectrus://update-ready` with the `UpdateInfo` once verified.
+#[tauri::command]
+pub async fn update_download(app: AppHandle, call: Call) -> Result<Option<UpdateInfo>, String> {
+    if app.state::<Portable>().0.is_some() {
+        return Err("updates are not available in portable mode".into());
+    }
+    let policy = crate::policy::current();
+    if policy.disable_updates {
+        return Err("updates are managed by your administrator".into());
+    }
+    let mut builder = app.updater_builder();
+    if let Some(proxy) = &policy.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    if let Some(endpoint) = &policy.update_endpoint {
+        builder = builder
+            .endpoints(vec![endpoint.clone()])
+            .map_err(|e| e.to_string())?;
+    }
+    let updater = builder.build().map_err(|e| e.to_string())?;
+    let checked = call.run(async { updater.check().await.map_err(|e| e.to_string()) });
+    let Some(update) = checked.await? else {
+        return Ok(None);
+    };
+    let mut job = Job::start(&app, "update-download").within(call.scope());
+    crate::bandwidth::defer_while_metered(&app, Subsystem::Download, || job.check()).await?;
+    let mut done = 0u64;
+    // Dropped, and the download with it, if the window goes away.
+    let download = update.download(
+        |chunk, total| {
+            // The updater calls this on the async runtime.
+            tokio::task::block_in_place(|| {
+                crate::bandwidth::throttle(&app, Subsystem::Download, chunk as u64)
+            });
+            done += chunk as u64;
+            job.progress(done, total.unwrap_or(0));
+        },
+        || {},
+    );
+    let bytes = call
+        .run(async { download.await.map_err(|e| e.to_string()) })
+        .await?;
+    drop(job);
+    let info = UpdateInfo::from(&update);
+    *app.state::<Updates>().ready.lock().unwrap() = Some(Downloaded { update, bytes });
+    let _ = app.emit("spectrus://update-ready", &info);
+    Ok(Some(info))
+}
+
+/// The downloaded update waiting to be installed, if any.
+#[tauri::command]
+pub fn update_ready(updates: State<'_, Updates>) -> Option<UpdateInfo> {
+    updates
+        .ready
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|d| UpdateInfo::from(&d.update))
+}
+
+/// Called for `RunEvent::ExitRequested`. With `updates.installOnQuit` on and
+/// an update downloaded, holds the exit until running jobs (exports above
+/// all) have finished, installs without prompting, then exits.
+/// `spectrus://update-waiting` lists the jobs being waited for.
+pub fn on_exit_requested(app: &AppHandle, api: &ExitRequestApi) {
+    let updates = app.state::<Updates>();
+    if updates.installing.load(Ordering::SeqCst)
+        || updates.ready.lock().unwrap().is_none()
+        || !install_on_quit(app)
+    {
+        return;
+    }
+    updates.installing.store(true, Ordering::SeqCst);
+    api.prevent_exit();
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut announced = false;
+        loop {
+            let running = app.state::<Jobs>().list();
+            if running.is_empty() {
+                break;
+            }
+            if !announced {
+                let _ = app.emit("spectrus://update-waiting", &running);
+                announced = true;
+            }
+            std::thread::sleep(JOB_POLL);
+        }
+        let ready = app.state::<Updates>().ready.lock().unwrap().take();
+        if let Some(Downloaded { update, bytes }) = ready {
+            crate::audit::record(
+                &app,
+                crate::audit::Action::UpdateInstall,
+                serde_json::json!({
+                    "from": update.current_version,
+                    "to": update.version,
+                }),
+            );
+            crate::session::end(&app, crate::session::Ending::Update);
+            // On Windows this launches the installer and exits the process.
+            if let Err(e) = update.restart_after_install(false).install(bytes) {
+                eprintln!("update: install on quit failed: {e}");
+            }
+        }
+        app.exit(0);
+    });
+}
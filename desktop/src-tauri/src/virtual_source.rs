@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::profile::ProfileState;
+
+/// Virtual sensors show up among the serial ports under this prefix.
+pub const SERIAL_PREFIX: &str = "virtual:";
+
+/// How often a running source produces the frames due.
+const TICK: Duration = Duration::from_millis(20);
+
+/// Highest rate a source may run at, the top of what audio interfaces offer.
+const MAX_SAMPLE_RATE: u32 = 384_000;
+
+fn check_rate(rate: u32) -> Result<u32, String> {
+    if (1..=MAX_SAMPLE_RATE).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!(
+            "sample rate {rate} is outside 1..={MAX_SAMPLE_RATE}"
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// Selectable in `recording_start`.
+    Audio,
+    /// Selectable in `serial_list` / `serial_open`, sending a CSV line per
+    /// frame like a bench instrument.
+    Sensor,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseColor {
+    #[default]
+    White,
+    Pink,
+    Brown,
+}
+
+/// What a source produces.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Signal {
+    /// Logarithmic sweep from `from` to `to` Hz over `period` seconds, over
+    /// and over.
+    #[serde(rename_all = "camelCase")]
+    Sweep { from: f64, to: f64, period: f64 },
+    Noise {
+        #[serde(default)]
+        color: NoiseColor,
+    },
+    /// An import of a project played back in a loop, `speed` times as fast.
+    /// Its sample rate and channels replace the source's.
+    #[serde(rename_all = "camelCase")]
+    Replay {
+        project: String,
+        import: String,
+        #[serde(default = "one")]
+        speed: f64,
+    },
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// A source as configured with `virtual_source_add`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceConfig {
+    name: String,
+    kind: SourceKind,
+    signal: Signal,
+    /// Frames per second; 48000 for audio and 10 for sensors if left out.
+    sample_rate: Option<u32>,
+    #[serde(default = "mono")]
+    channels: u16,
+    #[serde(default = "half")]
+    amplitude: f32,
+}
+
+fn mono() -> u16 {
+    1
+}
+
+fn half() -> f32 {
+    0.5
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualSource {
+    id: String,
+    #[serde(flatten)]
+    config: SourceConfig,
+}
+
+/// Managed state: the sources defined in this run.
+#[derive(Default)]
+pub struct VirtualSources(Mutex<HashMap<String, SourceConfig>>);
+
+/// Xorshift; plenty for noise.
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let mut seed = [0u8; 8];
+        let _ = getrandom::fill(&mut seed);
+        Self(u64::from_le_bytes(seed) | 1)
+    }
+
+    /// Uniform in -1.0..1.0.
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+enum Generator {
+    Sweep {
+        from: f64,
+        ratio: f64,
+        period: f64,
+        phase: f64,
+        t: f64,
+    },
+    Noise {
+        color: NoiseColor,
+        rng: Rng,
+        /// Pink filter taps, or the brown walk in `state[0]`.
+        state: [f32; 7],
+    },
+    Replay {
+        file: BufReader<File>,
+        frames: u64,
+        speed: f64,
+        position: f64,
+        next: u64,
+        frame: Vec<f32>,
+    },
+}
+
+/// A configured source ready to produce frames.
+pub(crate) struct Stream {
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    amplitude: f32,
+    generator: Generator,
+}
+
+impl Stream {
+    fn open(app: &AppHandle, config: &SourceConfig) -> Result<Self, String> {
+        let default_rate = match config.kind {
+            SourceKind::Audio => 48_000,
+            SourceKind::Sensor => 10,
+        };
+        let mut sample_rate = check_rate(config.sample_rate.unwrap_or(default_rate))?;
+        let mut channels = config.channels.max(1);
+        let generator = match &config.signal {
+            Signal::Sweep { from, to, period } => {
+                if *from <= 0.0 || *to <= 0.0 || *period <= 0.0 {
+                    return Err("a sweep needs positive frequencies and period".into());
+                }
+                Generator::Sweep {
+                    from: *from,
+                    ratio: to / from,
+                    period: *period,
+                    phase: 0.0,
+                    t: 0.0,
+                }
+            }
+            Signal::Noise { color } => Generator::Noise {
+                color: *color,
+                rng: Rng::new(),
+                state: [0.0; 7],
+            },
+            Signal::Replay {
+                project,
+                import,
+                speed,
+            } => {
+                if import.is_empty() || !import.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("invalid import id: {import}"));
+                }
+                let profile = app.state::<ProfileState>().current();
+                let dir = crate::sync::project_dir(&profile, project)?
+                    .join("imports")
+                    .join(import);
+                let meta: Value = serde_json::from_slice(
+                    &std::fs::read(dir.join("meta.json")).map_err(|e| format!("{import}: {e}"))?,
+                )
+                .map_err(|e| e.to_string())?;
+                channels = meta["channels"].as_array().map_or(0, |c| c.len() as u16);
+                let frames = meta["frames"].as_u64().unwrap_or(0);
+                if channels == 0 || frames == 0 {
+                    return Err("the import has no samples".into());
+                }
+                if let Some(rate) = meta["sampleRate"].as_u64() {
+                    sample_rate = check_rate(u32::try_from(rate).unwrap_or(u32::MAX))?;
+                }
+                let file = File::open(dir.join("samples.f32")).map_err(|e| e.to_string())?;
+                Generator::Replay {
+                    file: BufReader::new(file),
+                    frames,
+                    speed: speed.clamp(0.01, 100.0),
+                    position: 0.0,
+                    next: 0,
+                    frame: vec![0.0; channels.into()],
+                }
+            }
+        };
+        Ok(Self {
+            sample_rate,
+            channels,
+            amplitude: config.amplitude.clamp(0.0, 1.0),
+            generator,
+        })
+    }
+
+    /// Append `count` interleaved frames to `out`.
+    pub(crate) fn fill(&mut self, count: usize, out: &mut Vec<f32>) -> Result<(), String> {
+        let channels = usize::from(self.channels);
+        let dt = 1.0 / f64::from(self.sample_rate);
+        for _ in 0..count {
+            match &mut self.generator {
+                Generator::Sweep {
+                    from,
+                    ratio,
+                    period,
+                    phase,
+                    t,
+                } => {
+                    let frequency = *from * ratio.powf(*t / *period);
+                    *phase = (*phase + TAU * frequency * dt) % TAU;
+                    *t = (*t + dt) % *period;
+                    let value = phase.sin() as f32 * self.amplitude;
+                    out.extend(std::iter::repeat_n(value, channels));
+                }
+                Generator::Noise { color, rng, state } => {
+                    for _ in 0..channels {
+                        let white = rng.next();
+                        let value = match color {
+                            NoiseColor::White => white,
+                            // Paul Kellet's refined pink filter.
+                            NoiseColor::Pink => {
+                                state[0] = 0.99886 * state[0] + white * 0.0555179;
+                                state[1] = 0.99332 * state[1] + white * 0.0750759;
+                                state[2] = 0.96900 * state[2] + white * 0.153852;
+                                state[3] = 0.86650 * state[3] + white * 0.3104856;
+                                state[4] = 0.55000 * state[4] + white * 0.5329522;
+                                state[5] = -0.7616 * state[5] - white * 0.0168980;
+                                let pink =
+                                    state[..6].iter().sum::<f32>() + state[6] + white * 0.5362;
+                                state[6] = white * 0.115926;
+                                pink * 0.11
+                            }
+                            NoiseColor::Brown => {
+                                state[0] = (state[0] + white * 0.02) * 0.998;
+                                state[0] * 3.5
+                            }
+                        };
+                        out.push(value.clamp(-1.0, 1.0) * self.amplitude);
+                    }
+                }
+                Generator::Replay {
+                    file,
+                    frames,
+                    speed,
+                    position,
+                    next,
+                    frame,
+                } => {
+                    *position += *speed;
+                    while (*next as f64) < *position {
+                        if *next % *frames == 0 {
+                            file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+                        }
+                        let mut bytes = vec![0u8; channels * 4];
+                        file.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+                        for (value, chunk) in frame.iter_mut().zip(bytes.chunks_exact(4)) {
+                            *value = f32::from_le_bytes(chunk.try_into().expect("4-byte chunk"));
+                        }
+                        *next += 1;
+                    }
+                    out.extend_from_slice(frame);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce frames in real time, handing each batch to `sink`, until
+    /// `stop` is set or `sink` fails.
+    pub(crate) fn pump(
+        mut self,
+        stop: &AtomicBool,
+        mut sink: impl FnMut(&[f32]) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let started = Instant::now();
+        let mut produced = 0u64;
+        let mut batch = Vec::new();
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(TICK);
+            let due = (started.elapsed().as_secs_f64() * f64::from(self.sample_rate)) as u64;
+            if due <= produced {
+                continue;
+            }
+            batch.clear();
+            self.fill((due - produced) as usize, &mut batch)?;
+            produced = due;
+            sink(&batch)?;
+        }
+        Ok(())
+    }
+}
+
+/// The source `id` of `kind`, opened.
+pub(crate) fn stream(app: &AppHandle, id: &str, kind: SourceKind) -> Result<Stream, String> {
+    let config = app
+        .state::<VirtualSources>()
+        .0
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .filter(|c| c.kind == kind)
+        .ok_or_else(|| format!("no virtual source {id}"))?;
+    Stream::open(app, &config)
+}
+
+/// Virtual sensors as (port name, source name), for `serial_list`.
+pub(crate) fn serial_ports(app: &AppHandle) -> Vec<(String, String)> {
+    let sources = app.state::<VirtualSources>();
+    let sources = sources.0.lock().unwrap();
+    let mut ports: Vec<(String, String)> = sources
+        .iter()
+        .filter(|(_, c)| c.kind == SourceKind::Sensor)
+        .map(|(id, c)| (format!("{SERIAL_PREFIX}{id}"), c.name.clone()))
+        .collect();
+    ports.sort();
+    ports
+}
+
+/// A frame as a CSV line, the way serial instruments report readings.
+pub(crate) fn csv_line(frame: &[f32]) -> String {
+    let mut line = frame
+        .iter()
+        .map(|v| format!("{v:.5}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Virtual sources defined in this run.
+#[tauri::command]
+pub fn virtual_source_list(sources: State<'_, VirtualSources>) -> Vec<VirtualSource> {
+    let mut list: Vec<VirtualSource> = sources
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, config)| VirtualSource {
+            id: id.clone(),
+            config: config.clone(),
+        })
+        .collect();
+    list.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+    list
+}
+
+/// Define a synthetic source for demos and tests. Sensors then show up in
+/// `serial_list` as `virtual:<id>`; audio sources are picked with
+/// `recording_start`'s `source`. Either way they run through the same
+/// events and recording path as real hardware. Lasts until quit.
+#[tauri::command]
+pub fn virtual_source_add(
+    app: AppHandle,
+    sources: State<'_, VirtualSources>,
+    config: SourceConfig,
+) -> Result<String, String> {
+    // Fail now rather than when the source is opened.
+    Stream::open(&app, &config)?;
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    let id: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    sources.0.lock().unwrap().insert(id.clone(), config);
+    Ok(id)
+}
+
+/// Remove a source. Streams already running from it go on until closed.
+#[tauri::command]
+pub fn virtual_source_remove(sources: State<'_, VirtualSources>, id: String) {
+    sources.0.lock().unwrap().remove(&id);
+}
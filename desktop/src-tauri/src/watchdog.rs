@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::ipc::{CommandArg, CommandItem, InvokeError};
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime, State};
+use tokio::sync::Notify;
+
+use crate::jobs::CANCELLED;
+use crate::profile::ProfileState;
+use crate::settings;
+
+/// Setting holding the timeout, in seconds, for commands without one of
+/// their own; 0 turns it off.
+const DEFAULT_SETTING: &str = "commands.timeout";
+
+/// Setting holding per-command timeouts in seconds, `{"unfurl": 10}`; 0
+/// turns one off.
+const TIMEOUTS_SETTING: &str = "commands.timeouts";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Commands that take as long as their work does. They still stop when
+/// their window goes away, and their jobs with `job_cancel`.
+const UNTIMED: &[&str] = &[
+    "archive_create",
+    "archive_extract",
+    "compute_peaks",
+    "export_table",
+    "file_hash",
+    "file_hash_dir",
+    "file_read_stream",
+    "file_shred",
+    "import_files",
+    "maintenance_run_now",
+    "profile_export",
+    "profile_import",
+    "recording_recover",
+    "sync_pull",
+    "sync_push",
+    "update_download",
+];
+
+/// How often overdue calls are looked for.
+const SCAN: Duration = Duration::from_secs(1);
+
+/// Cancelled when its window reloads or closes; the next call from the
+/// window gets a fresh one.
+#[derive(Default)]
+struct Token {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl Token {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !self.cancelled.load(Ordering::SeqCst) {
+            notified.await;
+        }
+    }
+}
+
+struct InFlight {
+    window: String,
+    command: &'static str,
+    started: Instant,
+    deadline: Option<Instant>,
+    reported: bool,
+}
+
+#[derive(Default)]
+struct Timeouts {
+    default: Option<Duration>,
+    commands: HashMap<String, Option<Duration>>,
+}
+
+impl Timeouts {
+    fn of(&self, command: &str) -> Option<Duration> {
+        match self.commands.get(command) {
+            Some(timeout) => *timeout,
+            None if UNTIMED.contains(&command) => None,
+            None => self.default,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    next_id: AtomicU64,
+    windows: Mutex<HashMap<String, Arc<Token>>>,
+    calls: Mutex<HashMap<u64, InFlight>>,
+    timeouts: Mutex<Timeouts>,
+}
+
+/// Managed state: a cancellation token per window, the calls in flight and
+/// the configured timeouts.
+#[derive(Default)]
+pub struct Watchdog(Arc<Shared>);
+
+impl Watchdog {
+    /// Cancel everything `window` has in flight. Called when it starts
+    /// loading a page and when it is destroyed.
+    pub fn release(&self, window: &str) {
+        if let Some(token) = self.0.windows.lock().unwrap().remove(window) {
+            token.cancel();
+        }
+    }
+}
+
+/// What a call's work checks between units: whether its window is still
+/// there and its deadline hasn't passed. Cheap to clone into threads.
+#[derive(Clone)]
+pub struct Scope {
+    command: &'static str,
+    token: Arc<Token>,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+}
+
+impl Scope {
+    fn timed_out(&self) -> String {
+        let secs = self.timeout.map_or(0, |t| t.as_secs());
+        format!("{} timed out after {secs}s", self.command)
+    }
+
+    /// `Err` once the calling window has gone away (`"cancelled"`) or the
+    /// command's timeout has passed; meant to be `?`-ed between units of
+    /// work.
+    pub fn check(&self) -> Result<(), String> {
+        if self.token.cancelled.load(Ordering::SeqCst) {
+            return Err(CANCELLED.into());
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(self.timed_out());
+        }
+        Ok(())
+    }
+
+    /// Run `work` until it finishes, the window goes away or the deadline
+    /// passes, whichever comes first. `work` is dropped in the last two
+    /// cases, which ends requests in flight.
+    pub async fn run<T>(&self, work: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        let deadline = async {
+            match self.deadline {
+                Some(at) => tokio::time::sleep_until(at.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            result = work => result,
+            () = self.token.cancelled() => Err(CANCELLED.into()),
+            () = deadline => Err(self.timed_out()),
+        }
+    }
+}
+
+/// A command argument tying the call to its window: add `call: Call` to a
+/// command and check it or run its work through it. Dropped, with the
+/// command's future, once the call has been answered.
+///
+/// Commands that wait on the network, a device or long file work take one
+/// (imports, exports, PDFs and reports, archives, profile bundles, hashing,
+/// shredding, QR decoding, printing, recovery, maintenance, sync, updates,
+/// unfurling, catalog updates, webhook tests, Bluetooth), and so do remote
+/// asset fetches through `track`. Quick local commands don't, and aren't
+/// timed.
+pub struct Call {
+    id: u64,
+    window: String,
+    scope: Scope,
+    shared: Arc<Shared>,
+}
+
+impl Call {
+    pub fn scope(&self) -> Scope {
+        self.scope.clone()
+    }
+
+    /// Label of the calling window.
+    pub fn window(&self) -> &str {
+        &self.window
+    }
+
+    pub async fn run<T>(&self, work: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        self.scope.run(work).await
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.shared.calls.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Call {
+    fn new(shared: Arc<Shared>, window: String, command: &'static str) -> Self {
+        let token = shared
+            .windows
+            .lock()
+            .unwrap()
+            .entry(window.clone())
+            .or_default()
+            .clone();
+        let timeout = shared.timeouts.lock().unwrap().of(command);
+        let started = Instant::now();
+        let deadline = timeout.map(|t| started + t);
+        let id = shared.next_id.fetch_add(1, Ordering::Relaxed);
+        shared.calls.lock().unwrap().insert(
+            id,
+            InFlight {
+                window: window.clone(),
+                command,
+                started,
+                deadline,
+                reported: false,
+            },
+        );
+        Self {
+            id,
+            window,
+            scope: Scope {
+                command,
+                token,
+                deadline,
+                timeout,
+            },
+            shared,
+        }
+    }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for Call {
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        let webview = command.message.webview_ref();
+        let shared = webview.state::<Watchdog>().0.clone();
+        Ok(Call::new(shared, webview.label().to_string(), command.name))
+    }
+}
+
+/// A `Call` for work on behalf of `window` that doesn't come in as a
+/// command, like protocol requests. `name` picks its timeout as a command
+/// name would.
+pub(crate) fn track(app: &AppHandle, window: &str, name: &'static str) -> Call {
+    let shared = app.state::<Watchdog>().0.clone();
+    Call::new(shared, window.to_string(), name)
+}
+
+/// Payload of `spectrus://command-timeout`, and an entry of
+/// `watchdog_calls`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallInfo {
+    window: String,
+    command: &'static str,
+    elapsed_ms: u64,
+    timeout_ms: Option<u64>,
+}
+
+impl InFlight {
+    fn info(&self, now: Instant) -> CallInfo {
+        CallInfo {
+            window: self.window.clone(),
+            command: self.command,
+            elapsed_ms: (now - self.started).as_millis() as u64,
+            timeout_ms: self.deadline.map(|d| (d - self.started).as_millis() as u64),
+        }
+    }
+}
+
+fn seconds(value: &Value) -> Option<Option<Duration>> {
+    let secs = value.as_u64()?;
+    Some((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+fn reload(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    let default = settings::get(&profile, DEFAULT_SETTING)
+        .and_then(|v| seconds(&v))
+        .unwrap_or(Some(DEFAULT_TIMEOUT));
+    let commands = settings::get(&profile, TIMEOUTS_SETTING)
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(name, v)| Some((name, seconds(&v)?)))
+        .collect();
+    *app.state::<Watchdog>().0.timeouts.lock().unwrap() = Timeouts { default, commands };
+}
+
+/// Tell windows about calls that overran their timeout, once each. The
+/// work itself stops at its next check.
+fn scan(app: &AppHandle) {
+    let now = Instant::now();
+    let overdue: Vec<CallInfo> = app
+        .state::<Watchdog>()
+        .0
+        .calls
+        .lock()
+        .unwrap()
+        .values_mut()
+        .filter(|c| !c.reported && c.deadline.is_some_and(|d| now >= d))
+        .map(|c| {
+            c.reported = true;
+            c.info(now)
+        })
+        .collect();
+    for call in overdue {
+        eprintln!(
+            "watchdog: {} from {} timed out after {} ms",
+            call.command, call.window, call.elapsed_ms
+        );
+        let _ = app.emit_to(
+            call.window.as_str(),
+            "spectrus://command-timeout",
+            call.clone(),
+        );
+    }
+}
+
+/// Load the timeouts, follow profile switches and watch for overdue calls.
+pub fn start(app: &AppHandle) {
+    reload(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| reload(&handle));
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SCAN);
+        scan(&app);
+    });
+}
+
+/// Timeouts in seconds, as stored; `0` turns one off.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutSettings {
+    default: Option<u64>,
+    #[serde(default)]
+    commands: HashMap<String, u64>,
+}
+
+/// Calls in flight that take a `Call`, oldest first.
+#[tauri::command]
+pub fn watchdog_calls(watchdog: State<'_, Watchdog>) -> Vec<CallInfo> {
+    let now = Instant::now();
+    let calls = watchdog.0.calls.lock().unwrap();
+    let mut list: Vec<(Instant, CallInfo)> =
+        calls.values().map(|c| (c.started, c.info(now))).collect();
+    list.sort_by_key(|(started, _)| *started);
+    list.into_iter().map(|(_, info)| info).collect()
+}
+
+/// Set the default timeout (`None` restores two minutes) and per-command
+/// overrides, replacing earlier ones. Applies to calls made from now on.
+#[tauri::command]
+pub fn watchdog_timeouts_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    timeouts: TimeoutSettings,
+) -> Result<(), String> {
+    let profile = profiles.current();
+    settings::set(
+        &profile,
+        DEFAULT_SETTING,
+        timeouts.default.map_or(Value::Null, Value::from),
+    )?;
+    let commands = serde_json::to_value(timeouts.commands).map_err(|e| e.to_string())?;
+    settings::set(&profile, TIMEOUTS_SETTING, commands)?;
+    reload(&app);
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::consent::{self, Capability};
+use crate::fs_scope::FsScope;
+
+/// Quiet period used when the caller doesn't pick one. Long enough to ride out
+/// an instrument flushing a file in several writes.
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// Shorter quiet periods are raised to this; at 0 the loop would spin.
+const MIN_DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// One coalesced change to a single path.
+#[derive(Clone, Serialize)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+/// Payload of `spectrus://fs-change`: everything that settled in one burst.
+#[derive(Clone, Serialize)]
+struct ChangeBatch {
+    watch_id: u64,
+    changes: Vec<Change>,
+}
+
+/// Description of an active watch returned by `watch_list`.
+#[derive(Clone, Serialize)]
+pub struct WatchInfo {
+    id: u64,
+    path: PathBuf,
+    recursive: bool,
+    patterns: Vec<String>,
+}
+
+/// A running watch. Dropping it stops the OS watcher, which in turn ends the
+/// debounce thread once it has flushed.
+pub struct Watch {
+    info: WatchInfo,
+    _watcher: RecommendedWatcher,
+}
+
+#[derive(Default)]
+pub struct WatcherState {
+    next_id: AtomicU64,
+    watches: Mutex<HashMap<u64, Watch>>,
+}
+
+/// Fold a new raw event into whatever is already pending for the path, so a
+/// create-then-write burst reports one `created` and a temp file that comes
+/// and goes reports nothing.
+fn coalesce(prev: Option<ChangeKind>, next: ChangeKind) -> Option<ChangeKind> {
+    use ChangeKind::*;
+    match (prev, next) {
+        (None, k) => Some(k),
+        (Some(Created), Modified) => Some(Created),
+        (Some(Created), Removed) => None,
+        (Some(Removed), Created) => Some(Modified),
+        (Some(_), k) => Some(k),
+    }
+}
+
+fn classify(event: &notify::Event) -> Vec<(PathBuf, ChangeKind)> {
+    use ChangeKind::*;
+    let all = |kind| event.paths.iter().map(|p| (p.clone(), kind)).collect();
+    match event.kind {
+        EventKind::Create(_) => all(Created),
+        EventKind::Remove(_) => all(Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => all(Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => all(Created),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => vec![
+            (event.paths[0].clone(), Removed),
+            (event.paths[1].clone(), Created),
+        ],
+        EventKind::Modify(_) | EventKind::Any => all(Modified),
+        EventKind::Access(_) | EventKind::Other => Vec::new(),
+    }
+}
+
+fn build_globs(patterns: &[String]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for p in patterns {
+        builder.add(Glob::new(p).map_err(|e| format!("bad pattern {p:?}: {e}"))?);
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
+
+/// Watch `path` and call `on_batch` with coalesced changes once a burst has
+/// been quiet for `debounce` (at least `MIN_DEBOUNCE`). `patterns` are globs
+/// matched against the path relative to `path`; empty means everything.
+pub fn spawn<F>(
+    id: u64,
+    path: &Path,
+    recursive: bool,
+    patterns: Vec<String>,
+    debounce: Duration,
+    on_batch: F,
+) -> Result<Watch, String>
+where
+    F: Fn(Vec<Change>) + Send + 'static,
+{
+    let debounce = debounce.max(MIN_DEBOUNCE);
+    let globs = build_globs(&patterns)?;
+    let root = path.to_path_buf();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(path, mode).map_err(|e| e.to_string())?;
+
+    let filter_root = root.clone();
+    thread::spawn(move || {
+        let mut pending: HashMap<PathBuf, (ChangeKind, Instant)> = HashMap::new();
+        loop {
+            let disconnected = match rx.recv_timeout(debounce) {
+                Ok(Ok(event)) => {
+                    for (path, kind) in classify(&event) {
+                        let matches = match (&globs, path.strip_prefix(&filter_root)) {
+                            (None, _) => true,
+                            (Some(g), Ok(rel)) => g.is_match(rel),
+                            (Some(_), Err(_)) => false,
+                        };
+                        if !matches {
+                            continue;
+                        }
+                        let prev = pending.get(&path).map(|(k, _)| *k);
+                        match coalesce(prev, kind) {
+                            Some(k) => {
+                                pending.insert(path, (k, Instant::now()));
+                            }
+                            None => {
+                                pending.remove(&path);
+                            }
+                        }
+                    }
+                    false
+                }
+                Ok(Err(e)) => {
+                    eprintln!("watcher {id}: {e}");
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+
+            let now = Instant::now();
+            let mut ready = Vec::new();
+            pending.retain(|path, (kind, at)| {
+                if disconnected || now.duration_since(*at) >= debounce {
+                    ready.push(Change {
+                        path: path.clone(),
+                        kind: *kind,
+                    });
+                    false
+                } else {
+                    true
+                }
+            });
+            if !ready.is_empty() {
+                on_batch(ready);
+            }
+            if disconnected {
+                break;
+            }
+        }
+    });
+
+    Ok(Watch {
+        info: WatchInfo {
+            id,
+            path: root,
+            recursive,
+            patterns,
+        },
+        _watcher: watcher,
+    })
+}
+
+impl WatcherState {
+    pub fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn insert(&self, watch: Watch) {
+        self.watches.lock().unwrap().insert(watch.info.id, watch);
+    }
+
+    pub fn remove(&self, id: u64) -> bool {
+        self.watches.lock().unwrap().remove(&id).is_some()
+    }
+}
+
+/// Start watching `path` and emit `spectrus://fs-change` batches. Returns the
+/// watch id used by `watch_stop`. Folders the window wasn't given through a
+/// dialog or drop need the user's consent.
+#[tauri::command]
+pub async fn watch_start(
+    app: AppHandle,
+    window: WebviewWindow,
+    watchers: State<'_, WatcherState>,
+    path: String,
+    recursive: Option<bool>,
+    patterns: Option<Vec<String>>,
+    debounce_ms: Option<u64>,
+) -> Result<u64, String> {
+    let scoped = window
+        .state::<FsScope>()
+        .check(window.label(), &path)
+        .is_ok();
+    if !scoped {
+        consent::require(&app, &window, Capability::Watch, Some(&path)).await?;
+    }
+    let id = watchers.next_id();
+    let debounce = Duration::from_millis(debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+    let watch = spawn(
+        id,
+        Path::new(&path),
+        recursive.unwrap_or(true),
+        patterns.unwrap_or_default(),
+        debounce,
+        move |changes| {
+            let batch = ChangeBatch {
+                watch_id: id,
+                changes,
+            };
+            if let Err(e) = app.emit("spectrus://fs-change", batch) {
+                eprintln!("watcher {id}: emit error: {e}");
+            }
+        },
+    )?;
+    watchers.insert(watch);
+    Ok(id)
+}
+
+/// Stop a watch. Idempotent — unknown ids are ignored.
+#[tauri::command]
+pub fn watch_stop(watchers: State<'_, WatcherState>, id: u64) {
+    watchers.remove(id);
+}
+
+/// List active watches.
+#[tauri::command]
+pub fn watch_list(watchers: State<'_, WatcherState>) -> Vec<WatchInfo> {
+    let watches = watchers.watches.lock().unwrap();
+    let mut list: Vec<WatchInfo> = watches.values().map(|w| w.info.clone()).collect();
+    list.sort_by_key(|w| w.id);
+    list
+}
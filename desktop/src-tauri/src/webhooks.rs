@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Listener, Manager, State};
+use url::Url;
+
+use crate::audit::Action;
+use crate::bandwidth::Subsystem;
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+use crate::watchdog::Call;
+
+/// `true` turns forwarding on. Off by default.
+const ENABLED_SETTING: &str = "webhooks.enabled";
+
+/// The configured hooks, as an array of `Hook`.
+const HOOKS_SETTING: &str = "webhooks.hooks";
+
+/// Events that may be forwarded, without the `spectrus://` prefix.
+const FORWARDABLE: &[&str] = &[
+    "job-finished",
+    "import-finished",
+    "auto-imported",
+    "sync-finished",
+    "maintenance-finished",
+    "capture-added",
+    "handoff-received",
+    "reminder",
+];
+
+/// Delivery is given up after this many tries, waiting twice as long after
+/// each failure.
+const ATTEMPTS: u32 = 5;
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The audit log keeps this many of the latest deliveries.
+const LOG_ENTRIES: usize = 500;
+
+/// A URL to POST selected events to.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hook {
+    id: String,
+    url: Url,
+    /// Names from `FORWARDABLE`.
+    events: Vec<String>,
+    /// Handlebars template for the JSON body, with `event`, `timestamp` and
+    /// `payload` available; `{{json payload}}` inserts a value as JSON. The
+    /// default body is `{"event", "timestamp", "payload"}`.
+    #[serde(default)]
+    template: Option<String>,
+    /// With a secret, each request carries `X-Spectrus-Signature`: the
+    /// hex HMAC-SHA256 of the body. Write-only: `webhook_set` moves it to
+    /// the keychain and an empty one removes it.
+    #[serde(default, skip_serializing)]
+    secret: Option<String>,
+    /// Whether the keychain holds a secret for the hook.
+    #[serde(default)]
+    has_secret: bool,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// One delivery in `webhooks-log.json`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    hook: String,
+    event: String,
+    url: String,
+    /// Unix ms of the last attempt.
+    at: i64,
+    attempts: u32,
+    status: Option<u16>,
+    error: Option<String>,
+}
+
+/// Managed state: the HTTP client, and a lock so log writes don't race.
+pub struct Webhooks {
+    client: reqwest::Client,
+    log: Mutex<()>,
+}
+
+impl Webhooks {
+    pub fn new() -> Self {
+        crate::remote_assets::install_crypto_provider();
+        Self {
+            client: crate::policy::current()
+                .client_builder()
+                .timeout(TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            log: Mutex::new(()),
+        }
+    }
+}
+
+fn hooks(profile: &Profile) -> Vec<Hook> {
+    settings::get(profile, HOOKS_SETTING)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+fn save(profile: &Profile, hooks: &[Hook]) -> Result<(), String> {
+    let value = serde_json::to_value(hooks).map_err(|e| e.to_string())?;
+    settings::set(profile, HOOKS_SETTING, value)
+}
+
+/// Keychain entry holding hook `id`'s signing secret.
+fn secret_key(id: &str) -> String {
+    format!("spectrus:webhook-secret:{id}")
+}
+
+/// Put a secret given with `hook` in the keychain, or remove it if empty.
+fn store_secret(app: &AppHandle, hook: &mut Hook) -> Result<(), String> {
+    match hook.secret.take() {
+        Some(secret) if secret.is_empty() => {
+            crate::keychain::audit_backend(app, Action::KeychainDelete, &secret_key(&hook.id));
+            crate::keychain::delete(app, &secret_key(&hook.id))?;
+            hook.has_secret = false;
+        }
+        Some(secret) => {
+            crate::keychain::audit_backend(app, Action::KeychainWrite, &secret_key(&hook.id));
+            crate::keychain::write(app, &secret_key(&hook.id), secret)?;
+            hook.has_secret = true;
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Move secrets that older versions kept in `settings.json` to the
+/// keychain.
+fn migrate_secrets(app: &AppHandle) {
+    let profile = app.state::<ProfileState>().current();
+    let mut all = hooks(&profile);
+    if all.iter().all(|h| h.secret.is_none()) {
+        return;
+    }
+    let moved = all
+        .iter_mut()
+        .try_for_each(|hook| store_secret(app, hook))
+        .and_then(|()| save(&profile, &all));
+    if let Err(e) = moved {
+        eprintln!("webhooks: could not move secrets to the keychain: {e}");
+    }
+}
+
+fn log_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("webhooks-log.json")
+}
+
+fn read_log(profile: &Profile) -> VecDeque<Delivery> {
+    fs::read(log_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn record(app: &AppHandle, delivery: Delivery) {
+    let profile = app.state::<ProfileState>().current();
+    let webhooks = app.state::<Webhooks>();
+    let _guard = webhooks.log.lock().unwrap();
+    let mut log = read_log(&profile);
+    log.push_back(delivery);
+    while log.len() > LOG_ENTRIES {
+        log.pop_front();
+    }
+    let written = serde_json::to_vec(&log)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| settings::write_atomic(&log_path(&profile), &bytes));
+    if let Err(e) = written {
+        eprintln!("webhooks: could not write the log: {e}");
+    }
+}
+
+handlebars_helper!(json_helper: |value: Json| value.to_string());
+
+/// The request body for `event`, checked to be JSON.
+fn body(hook: &Hook, event: &str, timestamp: i64, payload: &Value) -> Result<Vec<u8>, String> {
+    let data = json!({ "event": event, "timestamp": timestamp, "payload": payload });
+    let Some(template) = &hook.template else {
+        return serde_json::to_vec(&data).map_err(|e| e.to_string());
+    };
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry.register_helper("json", Box::new(json_helper));
+    let rendered = registry
+        .render_template(template, &data)
+        .map_err(|e| format!("template: {e}"))?;
+    serde_json::from_str::<Value>(&rendered)
+        .map_err(|e| format!("template doesn't produce JSON: {e}"))?;
+    Ok(rendered.into_bytes())
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::sign(&key, body)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// POST `body`, retrying network errors, 429 and 5xx responses.
+async fn deliver(app: &AppHandle, hook: &Hook, event: &str, body: Vec<u8>) {
+    let client = app.state::<Webhooks>().client.clone();
+    let mut delivery = Delivery {
+        hook: hook.id.clone(),
+        event: event.to_string(),
+        url: hook.url.to_string(),
+        at: 0,
+        attempts: 0,
+        status: None,
+        error: None,
+    };
+    // Unsigned requests would be rejected by a receiver expecting a
+    // signature, so a secret that can't be read fails the delivery.
+    let secret = if hook.has_secret {
+        crate::keychain::audit_backend(app, Action::KeychainRead, &secret_key(&hook.id));
+        match crate::keychain::read(app, &secret_key(&hook.id)) {
+            Ok(Some(secret)) => Some(secret),
+            Ok(None) => {
+                delivery.error = Some("the signing secret is missing".into());
+                None
+            }
+            Err(e) => {
+                delivery.error = Some(format!("the signing secret is unavailable: {e}"));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if delivery.error.is_some() {
+        delivery.at = crate::time::now_ms(app);
+        record(app, delivery);
+        return;
+    }
+    let mut wait = FIRST_RETRY;
+    while delivery.attempts < ATTEMPTS {
+        if delivery.attempts > 0 {
+            tokio::time::sleep(wait).await;
+            wait *= 2;
+        }
+        delivery.attempts += 1;
+        delivery.at = crate::time::now_ms(app);
+        let mut request = client
+            .post(hook.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Spectrus-Event", event);
+        if let Some(secret) = &secret {
+            request = request.header("X-Spectrus-Signature", signature(secret, &body));
+        }
+        crate::bandwidth::throttle_async(app, Subsystem::Upload, body.len() as u64).await;
+        match request.body(body.clone()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                delivery.status = Some(status.as_u16());
+                delivery.error = None;
+                if !(status.is_server_error() || status.as_u16() == 429) {
+                    break;
+                }
+            }
+            Err(e) => {
+                delivery.status = None;
+                delivery.error = Some(e.to_string());
+            }
+        }
+    }
+    record(app, delivery);
+}
+
+/// Send `event` to every enabled hook subscribed to it.
+fn dispatch(app: &AppHandle, event: &str, payload: &str) {
+    let profile = app.state::<ProfileState>().current();
+    if settings::get(&profile, ENABLED_SETTING) != Some(Value::Bool(true)) {
+        return;
+    }
+    let payload: Value = serde_json::from_str(payload).unwrap_or(Value::Null);
+    let timestamp = crate::time::now_ms(app);
+    for hook in hooks(&profile) {
+        if !hook.enabled || !hook.events.iter().any(|e| e == event) {
+            continue;
+        }
+        match body(&hook, event, timestamp, &payload) {
+            Ok(body) => {
+                let (app, event) = (app.clone(), event.to_string());
+                tauri::async_runtime::spawn(async move {
+                    deliver(&app, &hook, &event, body).await;
+                });
+            }
+            Err(e) => record(
+                app,
+                Delivery {
+                    hook: hook.id,
+                    event: event.to_string(),
+                    url: hook.url.to_string(),
+                    at: timestamp,
+                    attempts: 0,
+                    status: None,
+                    error: Some(e),
+                },
+            ),
+        }
+    }
+}
+
+/// Listen for the forwardable events, whichever window they are sent to.
+/// Nothing is sent unless `webhooks.enabled` is on and some hook asks for
+/// the event.
+pub fn start(app: &AppHandle) {
+    migrate_secrets(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| {
+        migrate_secrets(&handle)
+    });
+    for event in FORWARDABLE {
+        let handle = app.clone();
+        app.listen_any(format!("spectrus://{event}"), move |e| {
+            dispatch(&handle, event, e.payload());
+        });
+    }
+}
+
+/// The configured hooks, and which events they can subscribe to. Secrets
+/// are never returned, only `hasSecret`.
+#[tauri::command]
+pub fn webhooks_list(profiles: State<'_, ProfileState>) -> Value {
+    json!({ "hooks": hooks(&profiles.current()), "events": FORWARDABLE })
+}
+
+/// Add a hook, or replace the one with the same id. An empty id makes a new
+/// hook.
+#[tauri::command]
+pub fn webhook_set(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    mut hook: Hook,
+) -> Result<Hook, String> {
+    if !matches!(hook.url.scheme(), "http" | "https") {
+        return Err("webhook URLs must be http or https".into());
+    }
+    if let Some(unknown) = hook
+        .events
+        .iter()
+        .find(|e| !FORWARDABLE.contains(&e.as_str()))
+    {
+        return Err(format!("unknown event: {unknown}"));
+    }
+    if let Some(template) = &hook.template {
+        handlebars::Template::compile(template).map_err(|e| format!("template: {e}"))?;
+    }
+    if hook.id.is_empty() {
+        let mut bytes = [0u8; 8];
+        getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+        hook.id = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    }
+    let profile = profiles.current();
+    let mut all = hooks(&profile);
+    hook.has_secret = all.iter().any(|h| h.id == hook.id && h.has_secret);
+    store_secret(&app, &mut hook)?;
+    match all.iter_mut().find(|h| h.id == hook.id) {
+        Some(existing) => *existing = hook.clone(),
+        None => all.push(hook.clone()),
+    }
+    save(&profile, &all)?;
+    Ok(hook)
+}
+
+#[tauri::command]
+pub fn webhook_delete(
+    app: AppHandle,
+    profiles: State<'_, ProfileState>,
+    id: String,
+) -> Result<(), String> {
+    crate::keychain::audit_backend(&app, Action::KeychainDelete, &secret_key(&id));
+    crate::keychain::delete(&app, &secret_key(&id))?;
+    let profile = profiles.current();
+    let mut all = hooks(&profile);
+    all.retain(|h| h.id != id);
+    save(&profile, &all)
+}
+
+/// Send a `test` event to one hook right away, whether or not forwarding
+/// is on, and return how the delivery went.
+#[tauri::command]
+pub async fn webhook_test(app: AppHandle, call: Call, id: String) -> Result<Delivery, String> {
+    let profile = app.state::<ProfileState>().current();
+    let hook = hooks(&profile)
+        .into_iter()
+        .find(|h| h.id == id)
+        .ok_or("no such webhook")?;
+    let body = body(&hook, "test", crate::time::now_ms(&app), &json!({}))?;
+    call.run(async {
+        deliver(&app, &hook, "test", body).await;
+        Ok(())
+    })
+    .await?;
+    read_log(&profile)
+        .into_iter()
+        .rev()
+        .find(|d| d.hook == id && d.event == "test")
+        .ok_or_else(|| "the delivery wasn't logged".into())
+}
+
+/// The audit log of deliveries, newest first.
+#[tauri::command]
+pub fn webhooks_log(profiles: State<'_, ProfileState>, limit: Option<usize>) -> Vec<Delivery> {
+    read_log(&profiles.current())
+        .into_iter()
+        .rev()
+        .take(limit.unwrap_or(LOG_ENTRIES))
+        .collect()
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime, State, WebviewWindow, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::profile::{Profile, ProfileState};
+use crate::settings;
+
+/// Browser-like zoom steps for the keyboard shortcuts.
+const STEPS: [f64; 13] = [
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+const MIN: f64 = 0.5;
+const MAX: f64 = 3.0;
+
+/// Ctrl/Cmd with +, − and 0, and the step each takes. Registered only
+/// while one of our windows has focus, so other apps keep the keys.
+const SHORTCUTS: [(&str, i32); 6] = [
+    ("CmdOrCtrl+Equal", 1),
+    ("CmdOrCtrl+Shift+Equal", 1),
+    ("CmdOrCtrl+NumpadAdd", 1),
+    ("CmdOrCtrl+Minus", -1),
+    ("CmdOrCtrl+NumpadSubtract", -1),
+    ("CmdOrCtrl+Digit0", 0),
+];
+
+/// Payload of `spectrus://zoom-changed`.
+#[derive(Clone, Serialize)]
+struct ZoomChanged<'a> {
+    label: &'a str,
+    level: f64,
+}
+
+fn store_path(profile: &Profile) -> PathBuf {
+    profile.dir.join("zoom.json")
+}
+
+fn load(profile: &Profile) -> HashMap<String, f64> {
+    fs::read(store_path(profile))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn level(profile: &Profile, label: &str) -> f64 {
+    load(profile).get(label).copied().unwrap_or(1.0)
+}
+
+fn apply(app: &AppHandle, window: &WebviewWindow, level: f64) -> Result<f64, String> {
+    let level = level.clamp(MIN, MAX);
+    window.set_zoom(level).map_err(|e| e.to_string())?;
+    let profile = app.state::<ProfileState>().current();
+    let mut levels = load(&profile);
+    // 100% is the default; don't keep an entry for it.
+    if level == 1.0 {
+        levels.remove(window.label());
+    } else {
+        levels.insert(window.label().to_string(), level);
+    }
+    let json = serde_json::to_vec_pretty(&levels).map_err(|e| e.to_string())?;
+    settings::write_atomic(&store_path(&profile), &json)?;
+    let _ = window.emit(
+        "spectrus://zoom-changed",
+        ZoomChanged {
+            label: window.label(),
+            level,
+        },
+    );
+    Ok(level)
+}
+
+/// Plugin that restores each window's saved level when its webview is
+/// created.
+pub fn init() -> TauriPlugin<Wry> {
+    Builder::new("zoom")
+        .on_webview_ready(|webview| restore(&webview))
+        .build()
+}
+
+/// Windows created before the profile is open are caught up by `start`.
+fn restore<R: Runtime>(webview: &tauri::Webview<R>) {
+    let Some(profiles) = webview.try_state::<ProfileState>() else {
+        return;
+    };
+    let _ = webview.set_zoom(level(&profiles.current(), webview.window().label()));
+}
+
+fn restore_all(app: &AppHandle) {
+    for window in app.webview_windows().values() {
+        restore(window.as_ref());
+    }
+}
+
+/// Apply the saved levels to the windows that already exist, which includes
+/// the main window, and again whenever the profile changes.
+pub fn start(app: &AppHandle) {
+    restore_all(app);
+    let handle = app.clone();
+    app.listen("spectrus://profile-changed", move |_| restore_all(&handle));
+}
+
+/// Take the zoom shortcuts while one of our windows has focus and give
+/// them back when it loses it. Called from the window event handler.
+pub fn focus_changed(app: &AppHandle, focused: bool) {
+    let shortcuts = app.global_shortcut();
+    for (shortcut, step) in SHORTCUTS {
+        if !focused {
+            let _ = shortcuts.unregister(shortcut);
+            continue;
+        }
+        if shortcuts.is_registered(shortcut) {
+            continue;
+        }
+        let registered = shortcuts.on_shortcut(shortcut, move |app, _, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            let focused = app
+                .webview_windows()
+                .into_values()
+                .find(|w| w.is_focused().unwrap_or(false));
+            if let Some(window) = focused {
+                if let Err(e) = zoom_step(app.clone(), window, step) {
+                    eprintln!("zoom: {e}");
+                }
+            }
+        });
+        if let Err(e) = registered {
+            eprintln!("zoom: shortcut {shortcut}: {e}");
+        }
+    }
+}
+
+/// The calling window's zoom factor (1.0 is 100%).
+#[tauri::command]
+pub fn zoom_get(window: WebviewWindow, profiles: State<'_, ProfileState>) -> f64 {
+    level(&profiles.current(), window.label())
+}
+
+/// Set the calling window's zoom factor, clamped to 50–300%, and remember
+/// it for that window. Resolves to the level applied. Emits
+/// `spectrus://zoom-changed` to the window.
+#[tauri::command]
+pub fn zoom_set(app: AppHandle, window: WebviewWindow, level: f64) -> Result<f64, String> {
+    if !level.is_finite() {
+        return Err("zoom level must be a number".into());
+    }
+    apply(&app, &window, level)
+}
+
+/// One step in (`1`) or out (`-1`) from the current level, or back to 100%
+/// (`0`). Bound to Ctrl/Cmd +, − and 0 while a window has focus.
+#[tauri::command]
+pub fn zoom_step(app: AppHandle, window: WebviewWindow, step: i32) -> Result<f64, String> {
+    let current = level(&app.state::<ProfileState>().current(), window.label());
+    let next = match step.signum() {
+        0 => Some(1.0),
+        1 => STEPS.iter().copied().find(|s| *s > current + 1e-3),
+        _ => STEPS.iter().rev().copied().find(|s| *s < current - 1e-3),
+    }
+    .unwrap_or(current);
+    apply(&app, &window, next)
+}